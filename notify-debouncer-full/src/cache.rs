@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     path::{Path, PathBuf},
 };
 
@@ -30,13 +30,25 @@ pub trait FileIdCache {
     ///
     /// This will be called if the notification back-end has dropped events.
     /// The root paths are passed as argument, so the implementer doesn't have to store them.
-    /// 
+    ///
     /// The default implementation calls `add_path` for each root path.
     fn rescan(&mut self, root_paths: &[(PathBuf, RecursiveMode)]) {
         for (path, recursive_mode) in root_paths {
             self.add_path(path, *recursive_mode);
         }
     }
+
+    /// Replace the cached file IDs below `root` with the ones currently on disk.
+    ///
+    /// This allows matching renames of files that existed before the debouncer was started, or
+    /// that changed while it wasn't watching. Unlike `add_path`, paths below `root` that no longer
+    /// exist are dropped from the cache. Files whose ID can't be read are skipped.
+    ///
+    /// The default implementation calls `remove_path` and then `add_path`.
+    fn warm(&mut self, root: &Path, recursive_mode: RecursiveMode) {
+        self.remove_path(root);
+        self.add_path(root, recursive_mode);
+    }
 }
 
 /// A cache to hold the file system IDs of all watched files.
@@ -90,6 +102,97 @@ impl FileIdCache for FileIdMap {
     }
 }
 
+/// A file ID cache holding at most a fixed number of paths.
+///
+/// Once the capacity is reached, the least recently added or updated path is evicted. Renames
+/// involving an evicted path can't be matched by file ID anymore and fall back to the rename
+/// matching of the notification back-end. This bounds the memory used on large trees.
+#[derive(Debug, Clone)]
+pub struct LruFileIdCache {
+    capacity: usize,
+    /// Path -> (file ID, tick of the last use)
+    paths: HashMap<PathBuf, (FileId, u64)>,
+    /// Tick of the last use -> path
+    order: BTreeMap<u64, PathBuf>,
+    tick: u64,
+}
+
+impl LruFileIdCache {
+    /// Construct an empty cache holding at most `capacity` paths.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            paths: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+
+    /// The maximum number of cached paths.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The number of cached paths.
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    /// Whether no paths are cached.
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    fn insert(&mut self, path: PathBuf, file_id: FileId) {
+        self.tick += 1;
+        if let Some((_, tick)) = self.paths.insert(path.clone(), (file_id, self.tick)) {
+            self.order.remove(&tick);
+        }
+        self.order.insert(self.tick, path);
+
+        while self.paths.len() > self.capacity {
+            let Some((_, evicted)) = self.order.pop_first() else {
+                break;
+            };
+            self.paths.remove(&evicted);
+        }
+    }
+}
+
+impl FileIdCache for LruFileIdCache {
+    fn cached_file_id(&self, path: &Path) -> Option<&FileId> {
+        self.paths.get(path).map(|(file_id, _)| file_id)
+    }
+
+    fn add_path(&mut self, path: &Path, recursive_mode: RecursiveMode) {
+        let is_recursive = recursive_mode == RecursiveMode::Recursive;
+
+        for (path, file_id) in WalkDir::new(path)
+            .follow_links(true)
+            .max_depth(FileIdMap::dir_scan_depth(is_recursive))
+            .into_iter()
+            .filter_map(|entry| {
+                let path = entry.ok()?.into_path();
+                let file_id = get_file_id(&path).ok()?;
+                Some((path, file_id))
+            })
+        {
+            self.insert(path, file_id);
+        }
+    }
+
+    fn remove_path(&mut self, path: &Path) {
+        let order = &mut self.order;
+        self.paths.retain(|p, (_, tick)| {
+            let keep = !p.starts_with(path);
+            if !keep {
+                order.remove(tick);
+            }
+            keep
+        });
+    }
+}
+
 /// An implementation of the `FileIdCache` trait that doesn't hold any data.
 ///
 /// This pseudo cache can be used to disable the file tracking using file system IDs.
@@ -119,3 +222,36 @@ pub type RecommendedCache = NoCache;
 /// The recommended file ID cache implementation for the current platform
 #[cfg(not(any(target_os = "linux", target_os = "android")))]
 pub type RecommendedCache = FileIdMap;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn lru_cache_evicts_the_least_recently_added_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let [a, b, c] = ["a", "b", "c"].map(|name| dir.path().join(name));
+        for path in [&a, &b, &c] {
+            fs::write(path, b"").unwrap();
+        }
+
+        let mut cache = LruFileIdCache::new(2);
+        cache.add_path(&a, RecursiveMode::NonRecursive);
+        cache.add_path(&b, RecursiveMode::NonRecursive);
+        cache.add_path(&a, RecursiveMode::NonRecursive);
+        cache.add_path(&c, RecursiveMode::NonRecursive);
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.cached_file_id(&a), Some(&get_file_id(&a).unwrap()));
+        assert_eq!(cache.cached_file_id(&b), None);
+        assert_eq!(cache.cached_file_id(&c), Some(&get_file_id(&c).unwrap()));
+
+        cache.add_path(dir.path(), RecursiveMode::Recursive);
+        assert_eq!(cache.len(), 2);
+
+        cache.remove_path(dir.path());
+        assert!(cache.is_empty());
+        assert!(cache.order.is_empty());
+    }
+}
@@ -0,0 +1,162 @@
+use std::time::Duration;
+
+use notify::{Error, ErrorKind};
+
+use crate::{FileIdCache, RecommendedCache};
+
+/// Configuration of the debouncer, see [`new_debouncer_with_config`](crate::new_debouncer_with_config).
+///
+/// The configuration is validated when the debouncer is created.
+///
+/// ```rust,no_run
+/// # use std::path::Path;
+/// # use std::time::Duration;
+/// use notify_debouncer_full::{
+///     new_debouncer_with_config, notify::*, DebounceEventResult, DebouncerConfig, FileIdMap,
+/// };
+///
+/// let config = DebouncerConfig::default()
+///     .timeout(Duration::from_secs(2))
+///     .tick_rate(Duration::from_millis(250))
+///     .file_id_cache(FileIdMap::new())
+///     .notify_config(Config::default().with_follow_symlinks(false));
+///
+/// let mut debouncer = new_debouncer_with_config::<_, RecommendedWatcher, _>(
+///     config,
+///     |result: DebounceEventResult| println!("{result:?}"),
+/// )
+/// .unwrap();
+/// debouncer.watch(".", RecursiveMode::Recursive).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct DebouncerConfig<C = RecommendedCache> {
+    pub(crate) timeout: Duration,
+    pub(crate) tick_rate: Option<Duration>,
+    pub(crate) file_id_cache: C,
+    pub(crate) notify_config: notify::Config,
+    pub(crate) forward_pathless_events: bool,
+    pub(crate) rename_match_window: Option<Duration>,
+    pub(crate) keep_modify_after_create: bool,
+    pub(crate) keep_rename_chains: bool,
+    pub(crate) heartbeat: Option<u32>,
+}
+
+impl Default for DebouncerConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_millis(500),
+            tick_rate: None,
+            file_id_cache: RecommendedCache::new(),
+            notify_config: notify::Config::default(),
+            forward_pathless_events: false,
+            rename_match_window: None,
+            keep_modify_after_create: false,
+            keep_rename_chains: false,
+            heartbeat: None,
+        }
+    }
+}
+
+impl<C: FileIdCache> DebouncerConfig<C> {
+    /// Set timeout
+    ///
+    /// Timeout is the amount of time after which a debounced event is emitted.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the interval at which the debouncer checks for due events
+    ///
+    /// Must not be larger than the timeout. Defaults to 1/4 of the timeout.
+    pub fn tick_rate(mut self, tick_rate: Duration) -> Self {
+        self.tick_rate = Some(tick_rate);
+        self
+    }
+
+    /// Set the [`FileIdCache`] used to match rename events
+    ///
+    /// Defaults to the [`RecommendedCache`] of the platform.
+    pub fn file_id_cache<D: FileIdCache>(self, file_id_cache: D) -> DebouncerConfig<D> {
+        DebouncerConfig {
+            timeout: self.timeout,
+            tick_rate: self.tick_rate,
+            file_id_cache,
+            notify_config: self.notify_config,
+            forward_pathless_events: self.forward_pathless_events,
+            rename_match_window: self.rename_match_window,
+            keep_modify_after_create: self.keep_modify_after_create,
+            keep_rename_chains: self.keep_rename_chains,
+            heartbeat: self.heartbeat,
+        }
+    }
+
+    /// Set [`notify::Config`] for the backend
+    pub fn notify_config(mut self, notify_config: notify::Config) -> Self {
+        self.notify_config = notify_config;
+        self
+    }
+
+    /// Forward events without any paths instead of dropping them
+    ///
+    /// See [`Debouncer::set_forward_pathless_events`](crate::Debouncer::set_forward_pathless_events).
+    pub fn forward_pathless_events(mut self, forward_pathless_events: bool) -> Self {
+        self.forward_pathless_events = forward_pathless_events;
+        self
+    }
+
+    /// Set the maximum time between a rename `From` event and its `To` event
+    ///
+    /// See [`Debouncer::set_rename_match_window`](crate::Debouncer::set_rename_match_window).
+    pub fn rename_match_window(mut self, rename_match_window: Duration) -> Self {
+        self.rename_match_window = Some(rename_match_window);
+        self
+    }
+
+    /// Keep `Modify` events that follow a `Create` event of the same path
+    ///
+    /// See [`Debouncer::set_keep_modify_after_create`](crate::Debouncer::set_keep_modify_after_create).
+    pub fn keep_modify_after_create(mut self, keep_modify_after_create: bool) -> Self {
+        self.keep_modify_after_create = keep_modify_after_create;
+        self
+    }
+
+    /// Keep every rename of a chain of renames as an event of its own
+    ///
+    /// See [`Debouncer::set_keep_rename_chains`](crate::Debouncer::set_keep_rename_chains).
+    pub fn keep_rename_chains(mut self, keep_rename_chains: bool) -> Self {
+        self.keep_rename_chains = keep_rename_chains;
+        self
+    }
+
+    /// Call the event handler with an empty list of events after `ticks` ticks without events
+    ///
+    /// This allows to tell that the debouncer is still running during quiet periods. Without it,
+    /// the event handler is only called with events or errors.
+    pub fn heartbeat(mut self, ticks: u32) -> Self {
+        self.heartbeat = Some(ticks.max(1));
+        self
+    }
+
+    /// Returns the effective tick rate, or an error if the tick rate exceeds the timeout.
+    pub(crate) fn validated_tick_rate(&self) -> Result<Duration, Error> {
+        let tick_div = 4;
+        match self.tick_rate {
+            Some(v) => {
+                if v > self.timeout {
+                    return Err(Error::new(ErrorKind::Generic(format!(
+                        "Invalid tick_rate, tick rate {:?} > {:?} timeout!",
+                        v, self.timeout
+                    ))));
+                }
+                Ok(v)
+            }
+            None => self.timeout.checked_div(tick_div).ok_or_else(|| {
+                Error::new(ErrorKind::Generic(format!(
+                    "Failed to calculate tick as {:?}/{}!",
+                    self.timeout, tick_div
+                )))
+            }),
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use file_id::{get_file_id, FileId};
+use notify::{Event, EventHandler, EventKind};
+
+use crate::time::now;
+
+/// An [`EventHandler`] adapter that collapses events for files reachable through multiple hard
+/// links.
+///
+/// Events of the same kind that refer to the same file ID within `window` are forwarded only
+/// once, the first event wins. If the file ID of a path can't be read, e.g. because the file was
+/// deleted, the event is passed through unchanged. Errors are always passed through.
+///
+/// File IDs are cached per path. The cache entry of a path is refreshed when it is created and
+/// dropped when it is removed or renamed.
+///
+/// ```rust,no_run
+/// # use std::path::Path;
+/// # use std::time::Duration;
+/// use notify_debouncer_full::{notify::*, FileIdDedup};
+///
+/// let handler = FileIdDedup::new(
+///     |event: Result<Event>| println!("{event:?}"),
+///     Duration::from_millis(100),
+/// );
+/// let mut watcher = recommended_watcher(handler).unwrap();
+/// watcher.watch(Path::new("."), RecursiveMode::Recursive).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct FileIdDedup<F> {
+    inner: F,
+    window: Duration,
+    file_ids: HashMap<PathBuf, FileId>,
+    seen: HashMap<(FileId, EventKind), Instant>,
+}
+
+impl<F: EventHandler> FileIdDedup<F> {
+    /// Create a new adapter forwarding deduplicated events to `inner`.
+    pub fn new(inner: F, window: Duration) -> Self {
+        Self {
+            inner,
+            window,
+            file_ids: HashMap::new(),
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Returns `true` if the event refers to a file that was already reported within the window.
+    fn is_duplicate(&mut self, event: &Event) -> bool {
+        let Some(path) = event.paths.first() else {
+            return false;
+        };
+
+        let file_id = self.file_id(path, &event.kind);
+
+        // the path no longer refers to the same file
+        if event.kind.is_remove() || event.kind.is_rename() {
+            for path in &event.paths {
+                self.file_ids.remove(path);
+            }
+        }
+
+        let Some(file_id) = file_id else {
+            return false;
+        };
+
+        let now = now();
+        let window = self.window;
+        self.seen
+            .retain(|_, time| now.saturating_duration_since(*time) < window);
+
+        match self.seen.get(&(file_id, event.kind)) {
+            Some(_) => true,
+            None => {
+                self.seen.insert((file_id, event.kind), now);
+                false
+            }
+        }
+    }
+
+    fn file_id(&mut self, path: &Path, kind: &EventKind) -> Option<FileId> {
+        // a new file may have been created at a known path
+        if kind.is_create() {
+            self.file_ids.remove(path);
+        }
+
+        if let Some(file_id) = self.file_ids.get(path) {
+            return Some(*file_id);
+        }
+
+        let file_id = get_file_id(path).ok()?;
+        self.file_ids.insert(path.to_path_buf(), file_id);
+        Some(file_id)
+    }
+}
+
+impl<F: EventHandler> EventHandler for FileIdDedup<F> {
+    fn handle_event(&mut self, event: notify::Result<Event>) {
+        if let Ok(event) = &event {
+            if self.is_duplicate(event) {
+                log::trace!("skipping duplicate event for the same file id: {event:?}");
+                return;
+            }
+        }
+
+        self.inner.handle_event(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        sync::{Arc, Mutex},
+    };
+
+    use notify::event::{DataChange, ModifyKind, RemoveKind};
+
+    use super::*;
+    use crate::time::MockTime;
+
+    fn collecting_handler() -> (Arc<Mutex<Vec<Event>>>, impl EventHandler) {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let collected = events.clone();
+        let handler = move |event: notify::Result<Event>| {
+            collected.lock().unwrap().push(event.unwrap());
+        };
+        (events, handler)
+    }
+
+    #[test]
+    fn hard_links_are_reported_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file");
+        let link = dir.path().join("link");
+        fs::write(&file, b"Lorem ipsum").unwrap();
+        fs::hard_link(&file, &link).unwrap();
+
+        let time = Instant::now();
+        MockTime::set_time(time);
+
+        let (events, handler) = collecting_handler();
+        let mut dedup = FileIdDedup::new(handler, Duration::from_millis(50));
+
+        let kind = EventKind::Modify(ModifyKind::Data(DataChange::Content));
+        dedup.handle_event(Ok(Event::new(kind).add_path(file.clone())));
+        dedup.handle_event(Ok(Event::new(kind).add_path(link.clone())));
+        assert_eq!(events.lock().unwrap().len(), 1);
+
+        MockTime::advance(Duration::from_millis(50));
+        dedup.handle_event(Ok(Event::new(kind).add_path(link.clone())));
+        assert_eq!(events.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn deleted_files_pass_through() {
+        let dir = tempfile::tempdir().unwrap();
+        let gone = dir.path().join("gone");
+
+        MockTime::set_time(Instant::now());
+
+        let (events, handler) = collecting_handler();
+        let mut dedup = FileIdDedup::new(handler, Duration::from_millis(50));
+
+        let kind = EventKind::Remove(RemoveKind::File);
+        dedup.handle_event(Ok(Event::new(kind).add_path(gone.clone())));
+        dedup.handle_event(Ok(Event::new(kind).add_path(gone.clone())));
+        assert_eq!(events.lock().unwrap().len(), 2);
+    }
+}
@@ -6,7 +6,7 @@
 //! * Optionally keeps track of the file system IDs all files and stitches rename events together (macOS FS Events, Windows)
 //! * Emits only one `Remove` event when deleting a directory (inotify)
 //! * Doesn't emit duplicate create events
-//! * Doesn't emit `Modify` events after a `Create` event
+//! * Doesn't emit `Modify` events after a `Create` event, unless configured otherwise
 //!
 //! # Installation
 //!
@@ -57,6 +57,9 @@
 //! As all file events are sourced from notify, the [known problems](https://docs.rs/notify/latest/notify/#known-problems) section applies here too.
 
 mod cache;
+mod config;
+mod dedup;
+mod rename;
 mod time;
 
 #[cfg(test)]
@@ -65,6 +68,7 @@ mod testing;
 use std::{
     cmp::Reverse,
     collections::{BinaryHeap, HashMap, VecDeque},
+    panic,
     path::{Path, PathBuf},
     sync::{
         atomic::{AtomicBool, Ordering},
@@ -75,16 +79,19 @@ use std::{
 
 use time::now;
 
-pub use cache::{FileIdCache, FileIdMap, NoCache, RecommendedCache};
+pub use cache::{FileIdCache, FileIdMap, LruFileIdCache, NoCache, RecommendedCache};
+pub use config::DebouncerConfig;
+pub use dedup::FileIdDedup;
+pub use rename::RenamePairs;
 
 pub use file_id;
 pub use notify;
-pub use notify_types::debouncer_full::DebouncedEvent;
+pub use notify_types::debouncer_full::{relative_paths, DebouncedEvent};
 
 use file_id::FileId;
 use notify::{
     event::{ModifyKind, RemoveKind, RenameMode},
-    Error, ErrorKind, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher, WatcherKind,
+    Error, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher, WatcherKind,
 };
 
 /// The set of requirements for watcher debounce event handling functions.
@@ -178,6 +185,10 @@ pub(crate) struct DebounceDataInner<T> {
     rescan_event: Option<DebouncedEvent>,
     errors: Vec<Error>,
     timeout: Duration,
+    forward_pathless_events: bool,
+    rename_match_window: Duration,
+    keep_modify_after_create: bool,
+    keep_rename_chains: bool,
 }
 
 impl<T: FileIdCache> DebounceDataInner<T> {
@@ -190,6 +201,10 @@ impl<T: FileIdCache> DebounceDataInner<T> {
             rescan_event: None,
             errors: Vec::new(),
             timeout,
+            forward_pathless_events: false,
+            rename_match_window: timeout,
+            keep_modify_after_create: false,
+            keep_rename_chains: false,
         }
     }
 
@@ -199,6 +214,8 @@ impl<T: FileIdCache> DebounceDataInner<T> {
         let mut events_expired = Vec::with_capacity(self.queues.len());
         let mut queues_remaining = HashMap::with_capacity(self.queues.len());
 
+        self.expire_rename_event();
+
         if let Some(event) = self.rescan_event.take() {
             if now.saturating_duration_since(event.time) >= self.timeout {
                 log::trace!("debounced event: {event:?}");
@@ -250,6 +267,35 @@ impl<T: FileIdCache> DebounceDataInner<T> {
         std::mem::take(&mut self.errors)
     }
 
+    /// Returns the point in time at which the next event expires
+    ///
+    /// Stored errors are due immediately.
+    pub fn next_tick(&self) -> Option<Instant> {
+        if !self.errors.is_empty() {
+            return Some(now());
+        }
+
+        self.rescan_event
+            .iter()
+            .chain(self.queues.values().flat_map(|queue| queue.events.iter()))
+            .map(|event| event.time + self.timeout)
+            .min()
+    }
+
+    /// Number of events waiting to be debounced
+    pub fn pending_event_count(&self) -> usize {
+        self.queues
+            .values()
+            .map(|queue| queue.events.len())
+            .sum::<usize>()
+            + usize::from(self.rescan_event.is_some())
+    }
+
+    /// Retrieve all expired events and stored errors
+    pub fn tick(&mut self) -> (Vec<DebouncedEvent>, Vec<Error>) {
+        (self.debounced_events(), self.errors())
+    }
+
     /// Add an error entry to re-send later on
     pub fn add_error(&mut self, error: Error) {
         log::trace!("raw error: {error:?}");
@@ -267,6 +313,21 @@ impl<T: FileIdCache> DebounceDataInner<T> {
             return;
         }
 
+        if event.paths.is_empty() {
+            if self.forward_pathless_events {
+                // path-less events share a queue with an empty path, so they are debounced
+                // like any other event
+                self.queues
+                    .entry(PathBuf::new())
+                    .or_default()
+                    .events
+                    .push_back(DebouncedEvent::new(event, now()));
+            } else {
+                log::trace!("skipping event with no paths");
+            }
+            return;
+        }
+
         let path = &event.paths[0];
 
         match &event.kind {
@@ -331,6 +392,18 @@ impl<T: FileIdCache> DebounceDataInner<T> {
             .unwrap_or(RecursiveMode::NonRecursive)
     }
 
+    /// Forget the rename `from` event if it's too old to be matched with a rename `to` event.
+    fn expire_rename_event(&mut self) {
+        let expired = self.rename_event.as_ref().is_some_and(|(event, _)| {
+            now().saturating_duration_since(event.time) > self.rename_match_window
+        });
+
+        if expired {
+            log::trace!("rename event expired: {:?}", self.rename_event);
+            self.rename_event = None;
+        }
+    }
+
     fn handle_rename_from(&mut self, event: Event) {
         let time = now();
         let path = &event.paths[0];
@@ -345,34 +418,27 @@ impl<T: FileIdCache> DebounceDataInner<T> {
     }
 
     fn handle_rename_to(&mut self, event: Event) {
+        // a `to` event arriving too late is treated as a move in
+        self.expire_rename_event();
+
         let recursive_mode = self.recursive_mode(&event.paths[0]);
 
         self.cache.add_path(&event.paths[0], recursive_mode);
 
-        let trackers_match = self
+        let is_pair = self
             .rename_event
             .as_ref()
-            .and_then(|(e, _)| e.tracker())
-            .and_then(|from_tracker| {
-                event
-                    .attrs
-                    .tracker()
-                    .map(|to_tracker| from_tracker == to_tracker)
-            })
-            .unwrap_or_default();
-
-        let file_ids_match = self
-            .rename_event
-            .as_ref()
-            .and_then(|(_, id)| id.as_ref())
-            .and_then(|from_file_id| {
-                self.cache
-                    .cached_file_id(&event.paths[0])
-                    .map(|to_file_id| from_file_id == to_file_id)
-            })
-            .unwrap_or_default();
+            .is_some_and(|(from, from_file_id)| {
+                rename::is_rename_pair(
+                    (from.tracker(), from_file_id.as_ref()),
+                    (
+                        event.attrs.tracker(),
+                        self.cache.cached_file_id(&event.paths[0]),
+                    ),
+                )
+            });
 
-        if trackers_match || file_ids_match {
+        if is_pair {
             // connect rename
             let (mut rename_event, _) = self.rename_event.take().unwrap(); // unwrap is safe because `rename_event` must be set at this point
             let path = rename_event.paths.remove(0);
@@ -394,11 +460,13 @@ impl<T: FileIdCache> DebounceDataInner<T> {
         // remove rename `from` event
         source_queue.events.pop_back();
 
-        // remove existing rename event
+        // remove existing rename event, so that a chain of renames results in a single one
+        let keep_rename_chains = self.keep_rename_chains;
         let (remove_index, original_path, original_time) = source_queue
             .events
             .iter()
             .enumerate()
+            .filter(|_| !keep_rename_chains)
             .find_map(|(index, e)| {
                 if matches!(
                     e.kind,
@@ -427,21 +495,32 @@ impl<T: FileIdCache> DebounceDataInner<T> {
             );
         }
 
-        // update paths
+        // update paths, except those of kept rename events
         for e in &mut source_queue.events {
-            e.paths = vec![event.paths[0].clone()];
+            if !e.kind.is_rename() {
+                e.paths = vec![event.paths[0].clone()];
+            }
         }
 
-        // insert rename event at the front, unless the file was just created
-        if !source_queue.was_created() {
-            source_queue.events.push_front(DebouncedEvent {
-                event: Event {
-                    kind: EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
-                    paths: vec![original_path, event.paths[0].clone()],
-                    attrs: event.attrs,
+        // insert rename event after the kept ones, unless the file was just created or renamed
+        // back to its original path
+        if !source_queue.was_created() && original_path != event.paths[0] {
+            let index = source_queue
+                .events
+                .iter()
+                .take_while(|e| e.kind.is_rename())
+                .count();
+            source_queue.events.insert(
+                index,
+                DebouncedEvent {
+                    event: Event {
+                        kind: EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+                        paths: vec![original_path, event.paths[0].clone()],
+                        attrs: event.attrs,
+                    },
+                    time: original_time,
                 },
-                time: original_time,
-            });
+            );
         }
 
         if let Some(target_queue) = self.queues.get_mut(&event.paths[0]) {
@@ -489,12 +568,15 @@ impl<T: FileIdCache> DebounceDataInner<T> {
 
     fn push_event(&mut self, event: Event, time: Instant) {
         let path = &event.paths[0];
+        let keep_modify_after_create = self.keep_modify_after_create;
 
         if let Some(queue) = self.queues.get_mut(path) {
             // skip duplicate create events and modifications right after creation
             if match event.kind {
-                EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Metadata(_))
-                | EventKind::Create(_) => !queue.was_created(),
+                EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Metadata(_)) => {
+                    keep_modify_after_create || !queue.was_created()
+                }
+                EventKind::Create(_) => !queue.was_created(),
                 _ => true,
             } {
                 queue.events.push_back(DebouncedEvent::new(event, time));
@@ -538,6 +620,73 @@ impl<T: Watcher, C: FileIdCache> Debouncer<T, C> {
         self.stop.store(true, Ordering::Relaxed);
     }
 
+    /// Returns the point in time at which the next debounced event is due, if any.
+    ///
+    /// Together with [`Debouncer::tick`] this allows driving the debouncer from a custom event
+    /// loop.
+    pub fn next_tick(&self) -> Option<Instant> {
+        self.data.lock().unwrap().next_tick()
+    }
+
+    /// Returns the number of events waiting to be debounced.
+    ///
+    /// A growing number means that events arrive faster than they are debounced, e.g. because
+    /// files are modified continuously.
+    pub fn pending_event_count(&self) -> usize {
+        self.data.lock().unwrap().pending_event_count()
+    }
+
+    /// Forward events without any paths instead of dropping them.
+    ///
+    /// Such events are emitted once after the timeout, consecutive events of the same kind are
+    /// merged. Events flagged with [`Flag::Rescan`](notify::event::Flag::Rescan) are always
+    /// forwarded.
+    ///
+    /// Off by default.
+    pub fn set_forward_pathless_events(&mut self, forward_pathless_events: bool) {
+        self.data.lock().unwrap().forward_pathless_events = forward_pathless_events;
+    }
+
+    /// Keep `Modify` events that follow a `Create` event of the same path.
+    ///
+    /// By default, a created file is reported with a single `Create` event, even if its contents
+    /// were written afterwards. As the `Create` event may be emitted before the file has any
+    /// content, this allows to be notified about the write as well.
+    ///
+    /// Off by default.
+    pub fn set_keep_modify_after_create(&mut self, keep_modify_after_create: bool) {
+        self.data.lock().unwrap().keep_modify_after_create = keep_modify_after_create;
+    }
+
+    /// Keep every rename of a chain of renames as an event of its own.
+    ///
+    /// By default, a path that is renamed several times within the timeout is reported with a
+    /// single `Modify(Name(Both))` event from its original to its final path, e.g. renaming `a` to
+    /// `b` and then to `c` results in one event with the paths `[a, c]`. A path renamed back to
+    /// its original path isn't reported as renamed at all. This reports each rename instead.
+    ///
+    /// Off by default.
+    pub fn set_keep_rename_chains(&mut self, keep_rename_chains: bool) {
+        self.data.lock().unwrap().keep_rename_chains = keep_rename_chains;
+    }
+
+    /// Set the maximum time between a rename `From` event and its `To` event.
+    ///
+    /// A `To` event arriving later is not stitched together with the `From` event, but reported
+    /// as a file that was moved into the watched tree.
+    ///
+    /// Defaults to the debounce timeout.
+    pub fn set_rename_match_window(&mut self, rename_match_window: Duration) {
+        self.data.lock().unwrap().rename_match_window = rename_match_window;
+    }
+
+    /// Returns all events that are due and all errors that occurred since the last tick.
+    ///
+    /// These events and errors are not passed to the event handler anymore.
+    pub fn tick(&self) -> (Vec<DebouncedEvent>, Vec<Error>) {
+        self.data.lock().unwrap().tick()
+    }
+
     #[deprecated = "`Debouncer` provides all methods from `Watcher` itself now. Remove `.watcher()` and use those methods directly."]
     pub fn watcher(&mut self) {}
 
@@ -577,6 +726,17 @@ impl<T: Watcher, C: FileIdCache> Debouncer<T, C> {
         Ok(())
     }
 
+    /// Returns whether changes to `path` are reported under the current watches, see
+    /// [`notify::covers`].
+    pub fn covers(&self, path: impl AsRef<Path>) -> bool {
+        let data = self.data.lock().unwrap();
+        let watches = data
+            .roots
+            .iter()
+            .map(|(root, mode)| (root.as_path(), *mode));
+        notify::covers(watches, path.as_ref())
+    }
+
     pub fn unwatch(&mut self, path: impl AsRef<Path>) -> notify::Result<()> {
         self.watcher.unwatch(path.as_ref())?;
         self.remove_root(path);
@@ -606,72 +766,83 @@ impl<T: Watcher, C: FileIdCache> Drop for Debouncer<T, C> {
 /// Timeout is the amount of time after which a debounced event is emitted.
 ///
 /// If `tick_rate` is `None`, notify will select a tick rate that is 1/4 of the provided timeout.
+///
+/// See also [`new_debouncer_with_config`].
 pub fn new_debouncer_opt<F: DebounceEventHandler, T: Watcher, C: FileIdCache + Send + 'static>(
     timeout: Duration,
     tick_rate: Option<Duration>,
-    mut event_handler: F,
+    event_handler: F,
     file_id_cache: C,
     config: notify::Config,
 ) -> Result<Debouncer<T, C>, Error> {
-    let data = Arc::new(Mutex::new(DebounceDataInner::new(file_id_cache, timeout)));
-    let stop = Arc::new(AtomicBool::new(false));
-
-    let tick_div = 4;
-    let tick = match tick_rate {
-        Some(v) => {
-            if v > timeout {
-                return Err(Error::new(ErrorKind::Generic(format!(
-                    "Invalid tick_rate, tick rate {:?} > {:?} timeout!",
-                    v, timeout
-                ))));
-            }
-            v
-        }
-        None => timeout.checked_div(tick_div).ok_or_else(|| {
-            Error::new(ErrorKind::Generic(format!(
-                "Failed to calculate tick as {:?}/{}!",
-                timeout, tick_div
-            )))
-        })?,
+    let config = DebouncerConfig {
+        tick_rate,
+        ..DebouncerConfig::default()
+            .timeout(timeout)
+            .file_id_cache(file_id_cache)
+            .notify_config(config)
     };
+    new_debouncer_with_config(config, event_handler)
+}
+
+/// Creates a new debounced watcher from a [`DebouncerConfig`].
+///
+/// Returns an error if the configuration is invalid, e.g. if the tick rate is larger than the
+/// timeout.
+pub fn new_debouncer_with_config<
+    F: DebounceEventHandler,
+    T: Watcher,
+    C: FileIdCache + Send + 'static,
+>(
+    config: DebouncerConfig<C>,
+    mut event_handler: F,
+) -> Result<Debouncer<T, C>, Error> {
+    let tick = config.validated_tick_rate()?;
+
+    let mut inner = DebounceDataInner::new(config.file_id_cache, config.timeout);
+    inner.forward_pathless_events = config.forward_pathless_events;
+    inner.keep_modify_after_create = config.keep_modify_after_create;
+    inner.keep_rename_chains = config.keep_rename_chains;
+    if let Some(rename_match_window) = config.rename_match_window {
+        inner.rename_match_window = rename_match_window;
+    }
+    let data = Arc::new(Mutex::new(inner));
+    let stop = Arc::new(AtomicBool::new(false));
 
     let data_c = data.clone();
     let stop_c = stop.clone();
+    let heartbeat = config.heartbeat;
     let thread = std::thread::Builder::new()
         .name("notify-rs debouncer loop".to_string())
-        .spawn(move || loop {
-            if stop_c.load(Ordering::Acquire) {
-                break;
-            }
-            std::thread::sleep(tick);
-            let send_data;
-            let errors;
-            {
-                let mut lock = data_c.lock().unwrap();
-                send_data = lock.debounced_events();
-                errors = lock.errors();
-            }
-            if !send_data.is_empty() {
-                event_handler.handle_event(Ok(send_data));
-            }
-            if !errors.is_empty() {
-                event_handler.handle_event(Err(errors));
+        .spawn(move || {
+            let mut quiet_ticks = 0;
+            let debounce = panic::AssertUnwindSafe(|| loop {
+                if stop_c.load(Ordering::Acquire) {
+                    break;
+                }
+                std::thread::sleep(tick);
+                let (send_data, errors) = data_c.lock().unwrap().tick();
+                let quiet = send_data.is_empty() && errors.is_empty();
+                if !send_data.is_empty() {
+                    event_handler.handle_event(Ok(send_data));
+                }
+                if !errors.is_empty() {
+                    event_handler.handle_event(Err(errors));
+                }
+                if let Some(heartbeat) = heartbeat {
+                    quiet_ticks = if quiet { quiet_ticks + 1 } else { 0 };
+                    if quiet_ticks >= heartbeat {
+                        quiet_ticks = 0;
+                        event_handler.handle_event(Ok(Vec::new()));
+                    }
+                }
+            });
+            if let Err(payload) = panic::catch_unwind(debounce) {
+                event_handler.handle_event(Err(vec![Error::watcher_panicked(payload)]));
             }
         })?;
 
-    let data_c = data.clone();
-    let watcher = T::new(
-        move |e: Result<Event, Error>| {
-            let mut lock = data_c.lock().unwrap();
-
-            match e {
-                Ok(e) => lock.add_event(e),
-                // can't have multiple TX, so we need to pipe that through our debouncer
-                Err(e) => lock.add_error(e),
-            }
-        },
-        config,
-    )?;
+    let watcher = new_watcher(data.clone(), config.notify_config)?;
 
     let guard = Debouncer {
         watcher,
@@ -683,6 +854,50 @@ pub fn new_debouncer_opt<F: DebounceEventHandler, T: Watcher, C: FileIdCache + S
     Ok(guard)
 }
 
+/// Creates a new debounced watcher with custom configuration that doesn't spawn a thread.
+///
+/// Timeout is the amount of time after which a debounced event is emitted.
+///
+/// Events are not passed to an event handler, instead [`Debouncer::tick`] has to be called to
+/// retrieve them. [`Debouncer::next_tick`] returns the point in time at which the next events are
+/// due.
+pub fn new_debouncer_opt_manual<T: Watcher, C: FileIdCache + Send + 'static>(
+    timeout: Duration,
+    file_id_cache: C,
+    config: notify::Config,
+) -> Result<Debouncer<T, C>, Error> {
+    let data = Arc::new(Mutex::new(DebounceDataInner::new(file_id_cache, timeout)));
+
+    let watcher = new_watcher(data.clone(), config)?;
+
+    let guard = Debouncer {
+        watcher,
+        debouncer_thread: None,
+        data,
+        stop: Arc::new(AtomicBool::new(false)),
+    };
+
+    Ok(guard)
+}
+
+fn new_watcher<T: Watcher, C: FileIdCache + Send + 'static>(
+    data: DebounceData<C>,
+    config: notify::Config,
+) -> Result<T, Error> {
+    T::new(
+        move |e: Result<Event, Error>| {
+            let mut lock = data.lock().unwrap();
+
+            match e {
+                Ok(e) => lock.add_event(e),
+                // can't have multiple TX, so we need to pipe that through our debouncer
+                Err(e) => lock.add_error(e),
+            }
+        },
+        config,
+    )
+}
+
 /// Short function to create a new debounced watcher with the recommended debouncer and the built-in file ID cache.
 ///
 /// Timeout is the amount of time after which a debounced event is emitted.
@@ -764,6 +979,7 @@ mod tests {
             "add_create_event_after_remove_event",
             "add_create_dir_event_twice",
             "add_modify_content_event_after_create_event",
+            "add_modify_content_event_after_create_event_kept",
             "add_rename_from_event",
             "add_rename_from_event_after_create_event",
             "add_rename_from_event_after_modify_event",
@@ -774,11 +990,15 @@ mod tests {
             "add_rename_from_and_to_event",
             "add_rename_from_and_to_event_after_create",
             "add_rename_from_and_to_event_after_rename",
+            "add_rename_from_and_to_event_after_rename_kept",
+            "add_rename_from_and_to_event_chain",
+            "add_rename_from_and_to_event_round_trip",
             "add_rename_from_and_to_event_after_modify_content",
             "add_rename_from_and_to_event_override_created",
             "add_rename_from_and_to_event_override_modified",
             "add_rename_from_and_to_event_override_removed",
             "add_rename_from_and_to_event_with_file_ids",
+            "add_rename_from_and_to_event_after_window",
             "add_rename_from_and_to_event_with_different_file_ids",
             "add_rename_from_and_to_event_with_different_tracker",
             "add_rename_both_event",
@@ -788,6 +1008,8 @@ mod tests {
             "add_remove_event_after_create_and_modify_event",
             "add_remove_parent_event_after_remove_child_event",
             "add_errors",
+            "add_pathless_event",
+            "add_pathless_event_forwarded",
             "emit_continuous_modify_content_events",
             "emit_events_in_chronological_order",
             "emit_events_with_a_prepended_rename_event",
@@ -889,6 +1111,57 @@ mod tests {
         }
     }
 
+    #[test]
+    fn next_tick_and_tick() {
+        let time = now();
+        MockTime::set_time(time);
+
+        let timeout = Duration::from_millis(50);
+        let mut state = DebounceDataInner::new(NoCache, timeout);
+        assert_eq!(state.next_tick(), None);
+
+        let event = Event::new(EventKind::Create(notify::event::CreateKind::File))
+            .add_path(PathBuf::from("/file"));
+        state.add_event(event);
+        assert_eq!(state.next_tick(), Some(time + timeout));
+
+        MockTime::advance(Duration::from_millis(10));
+        let (events, errors) = state.tick();
+        assert!(events.is_empty());
+        assert!(errors.is_empty());
+        assert_eq!(state.pending_event_count(), 1);
+
+        MockTime::advance(Duration::from_millis(40));
+        let (events, errors) = state.tick();
+        assert_eq!(events.len(), 1);
+        assert!(errors.is_empty());
+        assert_eq!(state.next_tick(), None);
+        assert_eq!(state.pending_event_count(), 0);
+    }
+
+    #[test]
+    fn warm_file_id_cache() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+        let file_path = dir.path().join("sub").join("file.txt");
+        fs::create_dir(dir.path().join("sub"))?;
+        fs::write(&file_path, b"Lorem ipsum")?;
+
+        let mut cache = FileIdMap::new();
+        cache.warm(dir.path(), RecursiveMode::Recursive);
+        assert!(cache.cached_file_id(&file_path).is_some());
+
+        fs::remove_file(&file_path)?;
+        cache.add_path(dir.path(), RecursiveMode::Recursive);
+        assert!(cache.cached_file_id(&file_path).is_some());
+
+        // unlike adding the path, warming up drops the stale IDs
+        cache.warm(dir.path(), RecursiveMode::Recursive);
+        assert!(cache.cached_file_id(&file_path).is_none());
+        assert!(cache.cached_file_id(&dir.path().join("sub")).is_some());
+
+        Ok(())
+    }
+
     #[test]
     fn integration() -> Result<(), Box<dyn std::error::Error>> {
         let dir = tempdir()?;
@@ -910,4 +1183,83 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn integration_manual() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+
+        let mut debouncer = new_debouncer_opt_manual::<RecommendedWatcher, _>(
+            Duration::from_millis(10),
+            RecommendedCache::new(),
+            notify::Config::default(),
+        )?;
+
+        debouncer.watch(dir.path(), RecursiveMode::Recursive)?;
+
+        fs::write(dir.path().join("file.txt"), b"Lorem ipsum")?;
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            assert!(Instant::now() < deadline, "no events received");
+
+            if let Some(next_tick) = debouncer.next_tick() {
+                std::thread::sleep(next_tick.saturating_duration_since(Instant::now()));
+            } else {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+
+            let (events, errors) = debouncer.tick();
+            assert!(errors.is_empty(), "received an error");
+
+            if !events.is_empty() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn config_is_validated_and_applied() {
+        let config = DebouncerConfig::default()
+            .timeout(Duration::from_millis(10))
+            .tick_rate(Duration::from_millis(20));
+        let result = new_debouncer_with_config::<_, RecommendedWatcher, _>(
+            config,
+            |_: DebounceEventResult| {},
+        );
+        assert!(
+            result.is_err(),
+            "tick rate larger than the timeout was accepted"
+        );
+
+        let config = DebouncerConfig::default()
+            .timeout(Duration::from_millis(10))
+            .file_id_cache(NoCache::new())
+            .forward_pathless_events(true)
+            .rename_match_window(Duration::from_millis(50));
+        let debouncer = new_debouncer_with_config::<_, RecommendedWatcher, _>(
+            config,
+            |_: DebounceEventResult| {},
+        )
+        .unwrap();
+        let data = debouncer.data.lock().unwrap();
+        assert!(data.forward_pathless_events);
+        assert_eq!(data.rename_match_window, Duration::from_millis(50));
+    }
+
+    #[test]
+    fn heartbeat_is_emitted_without_events() {
+        let config = DebouncerConfig::default()
+            .timeout(Duration::from_millis(40))
+            .tick_rate(Duration::from_millis(10))
+            .heartbeat(3);
+        let (tx, rx) = std::sync::mpsc::channel();
+        let _debouncer = new_debouncer_with_config::<_, RecommendedWatcher, _>(config, tx).unwrap();
+
+        for _ in 0..2 {
+            let events = rx.recv_timeout(Duration::from_secs(1)).unwrap().unwrap();
+            assert!(events.is_empty());
+        }
+    }
 }
@@ -0,0 +1,229 @@
+use std::{
+    sync::{Arc, Mutex, Weak},
+    thread,
+    time::{Duration, Instant},
+};
+
+use file_id::FileId;
+use notify::{
+    event::{ModifyKind, RenameMode},
+    Event, EventHandler, EventKind,
+};
+
+use crate::time::now;
+
+/// Returns `true` if a rename `From` and a rename `To` event belong to the same rename, i.e. if
+/// their trackers or their file IDs match.
+pub(crate) fn is_rename_pair(
+    from: (Option<usize>, Option<&FileId>),
+    to: (Option<usize>, Option<&FileId>),
+) -> bool {
+    let trackers_match = from
+        .0
+        .zip(to.0)
+        .is_some_and(|(from_tracker, to_tracker)| from_tracker == to_tracker);
+    let file_ids_match = from
+        .1
+        .zip(to.1)
+        .is_some_and(|(from_file_id, to_file_id)| from_file_id == to_file_id);
+
+    trackers_match || file_ids_match
+}
+
+/// An [`EventHandler`] adapter that stitches rename `From` and `To` events together into a single
+/// rename `Both` event, while passing all other events through immediately.
+///
+/// A rename `From` event is held back for at most `window`, waiting for its `To` event. Events are
+/// matched by their tracker or by their file ID attribute, see
+/// [`Config::with_event_file_ids`](notify::Config::with_event_file_ids). A `From` event that isn't
+/// matched in time is forwarded unchanged, a `To` event without a `From` event is forwarded right
+/// away. Pending `From` events are forwarded when the adapter is dropped. Errors are always
+/// passed through.
+///
+/// As only rename events are delayed, other events may be delivered before the rename that
+/// preceded them.
+///
+/// ```rust,no_run
+/// # use std::path::Path;
+/// # use std::time::Duration;
+/// use notify_debouncer_full::{notify::*, RenamePairs};
+///
+/// let handler = RenamePairs::new(
+///     |event: Result<Event>| println!("{event:?}"),
+///     Duration::from_millis(50),
+/// )
+/// .unwrap();
+/// let mut watcher = recommended_watcher(handler).unwrap();
+/// watcher.watch(Path::new("."), RecursiveMode::Recursive).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct RenamePairs<F: EventHandler> {
+    inner: Arc<Mutex<RenamePairsInner<F>>>,
+}
+
+#[derive(Debug)]
+struct RenamePairsInner<F: EventHandler> {
+    event_handler: F,
+    window: Duration,
+    /// Rename `From` events waiting for their `To` event
+    pending: Vec<(Event, Instant)>,
+}
+
+impl<F: EventHandler> RenamePairs<F> {
+    /// Create a new adapter forwarding events to `event_handler`, waiting up to `window` for the
+    /// `To` half of a rename.
+    ///
+    /// Unmatched `From` events are forwarded from a background thread.
+    pub fn new(event_handler: F, window: Duration) -> notify::Result<Self> {
+        let inner = Arc::new(Mutex::new(RenamePairsInner {
+            event_handler,
+            window,
+            pending: Vec::new(),
+        }));
+
+        let weak = Arc::downgrade(&inner);
+        thread::Builder::new()
+            .name("notify-rs rename pairs".to_string())
+            .spawn(move || expire_loop(weak, window))?;
+
+        Ok(Self { inner })
+    }
+}
+
+impl<F: EventHandler> RenamePairsInner<F> {
+    /// Forward the `From` events that weren't matched within the window.
+    fn expire(&mut self) {
+        let now = now();
+        let window = self.window;
+        let (expired, pending) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|(_, time)| now.saturating_duration_since(*time) >= window);
+        self.pending = pending;
+
+        for (event, _) in expired {
+            log::trace!("rename event expired: {event:?}");
+            self.event_handler.handle_event(Ok(event));
+        }
+    }
+
+    fn handle_rename_to(&mut self, mut event: Event) {
+        let to = (event.attrs.tracker(), event.attrs.file_id());
+        let matched = self.pending.iter().position(|(from, _)| {
+            let from_file_id = from.attrs.file_id();
+            is_rename_pair(
+                (from.attrs.tracker(), from_file_id.as_ref()),
+                (to.0, to.1.as_ref()),
+            )
+        });
+
+        if let Some(index) = matched {
+            let (mut from, _) = self.pending.remove(index);
+            event.kind = EventKind::Modify(ModifyKind::Name(RenameMode::Both));
+            event.paths.splice(0..0, from.paths.drain(..));
+        }
+
+        self.event_handler.handle_event(Ok(event));
+    }
+}
+
+impl<F: EventHandler> EventHandler for RenamePairs<F> {
+    fn handle_event(&mut self, event: notify::Result<Event>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.expire();
+
+        match event {
+            Ok(event) if event.kind == EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                inner.pending.push((event, now()));
+            }
+            Ok(event) if event.kind == EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                inner.handle_rename_to(event);
+            }
+            event => inner.event_handler.handle_event(event),
+        }
+    }
+}
+
+impl<F: EventHandler> Drop for RenamePairsInner<F> {
+    fn drop(&mut self) {
+        for (event, _) in self.pending.drain(..) {
+            self.event_handler.handle_event(Ok(event));
+        }
+    }
+}
+
+/// Periodically forwards expired `From` events until the adapter is dropped.
+fn expire_loop<F: EventHandler>(inner: Weak<Mutex<RenamePairsInner<F>>>, window: Duration) {
+    loop {
+        thread::sleep(window);
+        let Some(inner) = inner.upgrade() else {
+            break;
+        };
+        inner.lock().unwrap().expire();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, sync::mpsc};
+
+    use notify::event::{DataChange, RemoveKind};
+
+    use super::*;
+
+    fn rename(mode: RenameMode, path: &str, tracker: usize) -> notify::Result<Event> {
+        Ok(Event::new(EventKind::Modify(ModifyKind::Name(mode)))
+            .add_path(PathBuf::from(path))
+            .set_tracker(tracker))
+    }
+
+    #[test]
+    fn rename_halves_are_joined_and_other_events_pass_through() {
+        let (tx, rx) = mpsc::channel();
+        let mut handler = RenamePairs::new(tx, Duration::from_secs(60)).unwrap();
+
+        handler.handle_event(rename(RenameMode::From, "/from", 1));
+        handler.handle_event(Ok(Event::new(EventKind::Modify(ModifyKind::Data(
+            DataChange::Any,
+        )))
+        .add_path(PathBuf::from("/other"))));
+
+        let modify = rx.try_recv().unwrap().unwrap();
+        assert_eq!(modify.paths, vec![PathBuf::from("/other")]);
+
+        handler.handle_event(rename(RenameMode::To, "/to", 1));
+
+        let both = rx.try_recv().unwrap().unwrap();
+        assert_eq!(
+            both.kind,
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both))
+        );
+        assert_eq!(
+            both.paths,
+            vec![PathBuf::from("/from"), PathBuf::from("/to")]
+        );
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn unmatched_rename_halves_are_forwarded() {
+        let (tx, rx) = mpsc::channel();
+        let mut handler = RenamePairs::new(tx, Duration::from_millis(20)).unwrap();
+
+        handler.handle_event(rename(RenameMode::From, "/from", 1));
+        handler.handle_event(rename(RenameMode::To, "/to", 2));
+
+        let to = rx.try_recv().unwrap().unwrap();
+        assert_eq!(to.kind, EventKind::Modify(ModifyKind::Name(RenameMode::To)));
+
+        let from = rx.recv_timeout(Duration::from_secs(5)).unwrap().unwrap();
+        assert_eq!(
+            from.kind,
+            EventKind::Modify(ModifyKind::Name(RenameMode::From))
+        );
+
+        handler.handle_event(Ok(
+            Event::new(EventKind::Remove(RemoveKind::Any)).add_path(PathBuf::from("/to"))
+        ));
+        assert!(rx.try_recv().unwrap().unwrap().kind.is_remove());
+    }
+}
@@ -91,6 +91,29 @@ mod schema {
         /// Current rescan event
         pub rescan_event: Option<Event>,
 
+        /// Whether events without paths are forwarded
+        ///
+        /// Only used for the initial state.
+        #[serde(default)]
+        pub forward_pathless_events: bool,
+
+        /// Whether modify events after a create event are kept
+        ///
+        /// Only used for the initial state.
+        #[serde(default)]
+        pub keep_modify_after_create: bool,
+
+        /// Whether each rename of a chain of renames is kept
+        ///
+        /// Only used for the initial state.
+        #[serde(default)]
+        pub keep_rename_chains: bool,
+
+        /// Maximum time between a rename from and to event, defaults to the timeout
+        ///
+        /// Only used for the initial state.
+        pub rename_match_window: Option<u64>,
+
         /// Debounced events
         ///
         /// Only used for the expected state.
@@ -273,6 +296,12 @@ impl schema::State {
             rescan_event,
             errors: Vec::new(),
             timeout: Duration::from_millis(self.timeout.unwrap_or(50)),
+            forward_pathless_events: self.forward_pathless_events,
+            keep_modify_after_create: self.keep_modify_after_create,
+            keep_rename_chains: self.keep_rename_chains,
+            rename_match_window: Duration::from_millis(
+                self.rename_match_window.or(self.timeout).unwrap_or(50),
+            ),
         }
     }
 }
@@ -54,13 +54,18 @@
 //! As all file events are sourced from notify, the [known problems](https://docs.rs/notify/latest/notify/#known-problems) section applies here too.
 use std::{
     collections::HashMap,
+    panic,
     path::PathBuf,
-    sync::mpsc::{RecvTimeoutError, Sender},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{RecvTimeoutError, Sender},
+        Arc,
+    },
     time::{Duration, Instant},
 };
 
 pub use notify;
-pub use notify_types::debouncer_mini::{DebouncedEvent, DebouncedEventKind};
+pub use notify_types::debouncer_mini::{relative_paths, DebouncedEvent, DebouncedEventKind};
 
 use notify::{Error, Event, RecommendedWatcher, Watcher};
 
@@ -307,6 +312,7 @@ impl DebounceDataInner {
 pub struct Debouncer<T: Watcher> {
     watcher: T,
     stop_channel: Sender<InnerEvent>,
+    pending: Arc<AtomicUsize>,
 }
 
 impl<T: Watcher> Debouncer<T> {
@@ -314,6 +320,13 @@ impl<T: Watcher> Debouncer<T> {
     pub fn watcher(&mut self) -> &mut dyn Watcher {
         &mut self.watcher
     }
+
+    /// Returns the number of paths with events waiting to be debounced.
+    ///
+    /// A growing number means that events arrive faster than they are debounced.
+    pub fn pending_event_count(&self) -> usize {
+        self.pending.load(Ordering::Relaxed)
+    }
 }
 
 impl<T: Watcher> Drop for Debouncer<T> {
@@ -329,42 +342,50 @@ pub fn new_debouncer_opt<F: DebounceEventHandler, T: Watcher>(
     mut event_handler: F,
 ) -> Result<Debouncer<T>, Error> {
     let (tx, rx) = std::sync::mpsc::channel();
+    let pending = Arc::new(AtomicUsize::new(0));
+    let pending_c = pending.clone();
 
     std::thread::Builder::new()
         .name("notify-rs debouncer loop".to_string())
         .spawn(move || {
             let mut data = DebounceDataInner::new(config.timeout, config.batch_mode);
             let mut run = true;
-            while run {
-                match data.next_tick() {
-                    Some(timeout) => {
-                        // wait for wakeup
-                        match rx.recv_timeout(timeout) {
-                            Ok(InnerEvent::NotifyEvent(event_result)) => match event_result {
-                                Ok(event) => data.add_event(event),
-                                Err(err) => event_handler.handle_event(Err(err)),
-                            },
-                            Err(RecvTimeoutError::Timeout) => {
-                                let send_data = data.debounced_events();
-                                if !send_data.is_empty() {
-                                    event_handler.handle_event(Ok(send_data));
+            let debounce = panic::AssertUnwindSafe(|| {
+                while run {
+                    pending_c.store(data.event_map.len(), Ordering::Relaxed);
+                    match data.next_tick() {
+                        Some(timeout) => {
+                            // wait for wakeup
+                            match rx.recv_timeout(timeout) {
+                                Ok(InnerEvent::NotifyEvent(event_result)) => match event_result {
+                                    Ok(event) => data.add_event(event),
+                                    Err(err) => event_handler.handle_event(Err(err)),
+                                },
+                                Err(RecvTimeoutError::Timeout) => {
+                                    let send_data = data.debounced_events();
+                                    if !send_data.is_empty() {
+                                        event_handler.handle_event(Ok(send_data));
+                                    }
+                                }
+                                Ok(InnerEvent::Shutdown) | Err(RecvTimeoutError::Disconnected) => {
+                                    run = false
                                 }
-                            }
-                            Ok(InnerEvent::Shutdown) | Err(RecvTimeoutError::Disconnected) => {
-                                run = false
                             }
                         }
-                    }
-                    None => match rx.recv() {
-                        // no timeout, wait for event
-                        Ok(InnerEvent::NotifyEvent(e)) => match e {
-                            Ok(event) => data.add_event(event),
-                            Err(err) => event_handler.handle_event(Err(err)),
+                        None => match rx.recv() {
+                            // no timeout, wait for event
+                            Ok(InnerEvent::NotifyEvent(e)) => match e {
+                                Ok(event) => data.add_event(event),
+                                Err(err) => event_handler.handle_event(Err(err)),
+                            },
+                            Ok(InnerEvent::Shutdown) => run = false,
+                            Err(_) => run = false,
                         },
-                        Ok(InnerEvent::Shutdown) => run = false,
-                        Err(_) => run = false,
-                    },
+                    }
                 }
+            });
+            if let Err(payload) = panic::catch_unwind(debounce) {
+                event_handler.handle_event(Err(Error::watcher_panicked(payload)));
             }
         })?;
 
@@ -381,6 +402,7 @@ pub fn new_debouncer_opt<F: DebounceEventHandler, T: Watcher>(
     let guard = Debouncer {
         watcher,
         stop_channel: tx,
+        pending,
     };
 
     Ok(guard)
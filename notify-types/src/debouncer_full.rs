@@ -1,8 +1,9 @@
 use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
 
 use web_time::Instant;
 
-use crate::event::Event;
+use crate::event::{Event, RelativeRoot};
 
 /// A debounced event is emitted after a short delay.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -18,6 +19,23 @@ impl DebouncedEvent {
     pub fn new(event: Event, time: Instant) -> Self {
         Self { event, time }
     }
+
+    /// Returns the original event, dropping the time.
+    pub fn into_event(self) -> Event {
+        self.event
+    }
+}
+
+impl From<DebouncedEvent> for Event {
+    fn from(debounced: DebouncedEvent) -> Self {
+        debounced.event
+    }
+}
+
+impl AsRef<Event> for DebouncedEvent {
+    fn as_ref(&self) -> &Event {
+        &self.event
+    }
 }
 
 impl Deref for DebouncedEvent {
@@ -33,3 +51,14 @@ impl DerefMut for DebouncedEvent {
         &mut self.event
     }
 }
+
+/// The paths of the `events` relative to `root`, like [`Event::relative_to`] for each event.
+///
+/// `root` is canonicalized once for all events.
+pub fn relative_paths(events: &[DebouncedEvent], root: &Path) -> Vec<Option<PathBuf>> {
+    let root = RelativeRoot::new(root);
+    events
+        .iter()
+        .map(|event| event.paths.iter().find_map(|path| root.relative(path)))
+        .collect()
+}
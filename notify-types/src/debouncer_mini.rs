@@ -1,4 +1,6 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use crate::event::RelativeRoot;
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -31,4 +33,22 @@ impl DebouncedEvent {
     pub fn new(path: PathBuf, kind: DebouncedEventKind) -> Self {
         Self { path, kind }
     }
+
+    /// The path of the event relative to `root`, see
+    /// [`Event::relative_to`](crate::event::Event::relative_to).
+    pub fn relative_to(&self, root: &Path) -> Option<PathBuf> {
+        RelativeRoot::new(root).relative(&self.path)
+    }
+}
+
+/// The paths of the `events` relative to `root`, like [`DebouncedEvent::relative_to`] for each
+/// event.
+///
+/// `root` is canonicalized once for all events.
+pub fn relative_paths(events: &[DebouncedEvent], root: &Path) -> Vec<Option<PathBuf>> {
+    let root = RelativeRoot::new(root);
+    events
+        .iter()
+        .map(|event| root.relative(&event.path))
+        .collect()
 }
@@ -1,11 +1,22 @@
 //! The `Event` type and the hierarchical `EventKind` descriptor.
 
-use std::{
+#[cfg(not(feature = "std"))]
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+};
+use core::{
     fmt,
     hash::{Hash, Hasher},
-    path::PathBuf,
+};
+#[cfg(feature = "std")]
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
 };
 
+#[cfg(feature = "std")]
+pub use file_id::FileId;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
@@ -270,12 +281,86 @@ impl EventKind {
     pub fn is_other(&self) -> bool {
         matches!(self, EventKind::Other)
     }
+
+    /// Indicates whether an event is a rename, regardless of its [`RenameMode`].
+    pub fn is_rename(&self) -> bool {
+        matches!(self, EventKind::Modify(ModifyKind::Name(_)))
+    }
+
+    /// Returns the [`RenameMode`] if the event is a rename.
+    pub fn rename_mode(&self) -> Option<RenameMode> {
+        match self {
+            EventKind::Modify(ModifyKind::Name(mode)) => Some(*mode),
+            _ => None,
+        }
+    }
+
+    /// Returns the [`DataChange`] if the event is a data modification.
+    pub fn data_change(&self) -> Option<DataChange> {
+        match self {
+            EventKind::Modify(ModifyKind::Data(change)) => Some(*change),
+            _ => None,
+        }
+    }
+}
+
+/// Coarse classification of an [`EventKind`], ignoring the details that vary between backends
+/// and between events of the same change, see `Event::dedup_key`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum EventKindTag {
+    /// [`EventKind::Any`]
+    Any,
+    /// Any [`EventKind::Access`]
+    Access,
+    /// Any [`EventKind::Create`]
+    Create,
+    /// [`ModifyKind::Data`]
+    ModifyData,
+    /// [`ModifyKind::Metadata`]
+    ModifyMetadata,
+    /// [`ModifyKind::Name`]
+    ModifyName,
+    /// [`ModifyKind::Any`] and [`ModifyKind::Other`]
+    Modify,
+    /// Any [`EventKind::Remove`]
+    Remove,
+    /// [`EventKind::Other`]
+    Other,
+}
+
+impl From<EventKind> for EventKindTag {
+    fn from(kind: EventKind) -> Self {
+        match kind {
+            EventKind::Any => EventKindTag::Any,
+            EventKind::Access(_) => EventKindTag::Access,
+            EventKind::Create(_) => EventKindTag::Create,
+            EventKind::Modify(ModifyKind::Data(_)) => EventKindTag::ModifyData,
+            EventKind::Modify(ModifyKind::Metadata(_)) => EventKindTag::ModifyMetadata,
+            EventKind::Modify(ModifyKind::Name(_)) => EventKindTag::ModifyName,
+            EventKind::Modify(ModifyKind::Any | ModifyKind::Other) => EventKindTag::Modify,
+            EventKind::Remove(_) => EventKindTag::Remove,
+            EventKind::Other => EventKindTag::Other,
+        }
+    }
+}
+
+/// Key of the logical change an [`Event`] represents, see [`Event::dedup_key`].
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct DedupKey {
+    /// The path the change is about
+    pub path: Option<PathBuf>,
+    /// The kind of the change
+    pub tag: EventKindTag,
 }
 
 /// Notify event.
 ///
 /// You might want to check [`Event::need_rescan`] to make sure no event was missed before you
 /// received this one.
+///
+/// Without the `std` feature, the event has no paths, and the attributes holding a time or a
+/// file ID are not available.
 #[derive(Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Event {
@@ -319,6 +404,7 @@ pub struct Event {
     ///
     /// The order of the paths is likely to be significant! For example, renames where both ends of
     /// the name change are known will have the "source" path first, and the "target" path last.
+    #[cfg(feature = "std")]
     pub paths: Vec<PathBuf>,
 
     // "What should be in the struct" and "what can go in the attrs" is an interesting question.
@@ -352,6 +438,10 @@ pub struct Event {
 }
 
 /// Additional attributes of the event.
+///
+/// Only the tracker, flag, info, source and source kind are part of the eq and hash
+/// representations. The other attributes, like the time or the sequence number, describe a single
+/// occurrence of an event, so events that only differ in those compare equal.
 #[derive(Clone, Default, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct EventAttributes {
@@ -417,6 +507,67 @@ struct EventAttributesInner {
         serde(default, skip_serializing, skip_deserializing)
     )]
     process_id: Option<u32>,
+
+    /// The time the event was received by the backend.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    #[cfg(feature = "std")]
+    time: Option<SystemTime>,
+
+    /// The kind of watcher that emitted the event.
+    ///
+    /// Useful to tell events apart when several watchers share an event handler.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    source_kind: Option<WatcherKind>,
+
+    /// Sequence number of the event within the watcher that emitted it.
+    ///
+    /// Strictly increasing in the order the events were emitted.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    seq: Option<u64>,
+
+    /// The ID of the file the event is about, read when the event was emitted.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    #[cfg(feature = "std")]
+    file_id: Option<FileId>,
+
+    /// Number of times the watcher's event queue overflowed so far, including this time.
+    ///
+    /// Set on the rescan events emitted for an overflow.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    overflow_count: Option<u64>,
+
+    /// The last modification time of the affected file, as reported by the backend.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    #[cfg(feature = "std")]
+    modified: Option<SystemTime>,
+
+    /// The ID of the device holding the affected file, as reported by the backend.
+    ///
+    /// Allows to tell apart files on different filesystems below the same watched directory, e.g.
+    /// behind bind mounts.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    device_id: Option<u64>,
 }
 
 impl EventAttributes {
@@ -425,6 +576,22 @@ impl EventAttributes {
         Self { inner: None }
     }
 
+    /// Returns a builder to create `EventAttributes` with the given attributes.
+    ///
+    /// ```
+    /// # use notify_types::event::{EventAttributes, Flag};
+    /// let attrs = EventAttributes::builder()
+    ///     .tracker(1)
+    ///     .flag(Flag::Rescan)
+    ///     .info("example")
+    ///     .build();
+    /// assert_eq!(attrs.tracker(), Some(1));
+    /// assert_eq!(attrs.info(), Some("example"));
+    /// ```
+    pub fn builder() -> EventAttributesBuilder {
+        EventAttributesBuilder::default()
+    }
+
     /// Retrieves the tracker ID for an event directly, if present.
     pub fn tracker(&self) -> Option<usize> {
         self.inner.as_ref().and_then(|inner| inner.tracker)
@@ -455,6 +622,44 @@ impl EventAttributes {
         self.inner.as_ref().and_then(|inner| inner.process_id)
     }
 
+    #[cfg(feature = "std")]
+    /// Retrieves the time the event was received by the backend, if present.
+    pub fn time(&self) -> Option<SystemTime> {
+        self.inner.as_ref().and_then(|inner| inner.time)
+    }
+
+    /// Retrieves the kind of watcher that emitted the event, if present.
+    pub fn source_kind(&self) -> Option<WatcherKind> {
+        self.inner.as_ref().and_then(|inner| inner.source_kind)
+    }
+
+    /// Retrieves the sequence number of the event, if present.
+    pub fn seq(&self) -> Option<u64> {
+        self.inner.as_ref().and_then(|inner| inner.seq)
+    }
+
+    #[cfg(feature = "std")]
+    /// Retrieves the ID of the file the event is about, if present.
+    pub fn file_id(&self) -> Option<FileId> {
+        self.inner.as_ref().and_then(|inner| inner.file_id)
+    }
+
+    /// Retrieves the number of queue overflows of the watcher so far, if present.
+    pub fn overflow_count(&self) -> Option<u64> {
+        self.inner.as_ref().and_then(|inner| inner.overflow_count)
+    }
+
+    #[cfg(feature = "std")]
+    /// Retrieves the last modification time of the affected file, if present.
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.inner.as_ref().and_then(|inner| inner.modified)
+    }
+
+    /// Retrieves the ID of the device holding the affected file, if present.
+    pub fn device_id(&self) -> Option<u64> {
+        self.inner.as_ref().and_then(|inner| inner.device_id)
+    }
+
     /// Sets the tracker.
     pub fn set_tracker(&mut self, tracker: usize) {
         self.inner_mut().tracker = Some(tracker);
@@ -470,16 +675,205 @@ impl EventAttributes {
         self.inner_mut().info = Some(info.to_string());
     }
 
+    /// Sets the source onto the event.
+    pub fn set_source(&mut self, source: &str) {
+        self.inner_mut().source = Some(source.to_string());
+    }
+
     /// Sets the process id onto the event.
     pub fn set_process_id(&mut self, process_id: u32) {
         self.inner_mut().process_id = Some(process_id)
     }
 
+    #[cfg(feature = "std")]
+    /// Sets the time the event was received by the backend.
+    pub fn set_time(&mut self, time: SystemTime) {
+        self.inner_mut().time = Some(time)
+    }
+
+    /// Sets the kind of watcher that emitted the event.
+    pub fn set_source_kind(&mut self, source_kind: WatcherKind) {
+        self.inner_mut().source_kind = Some(source_kind)
+    }
+
+    /// Sets the sequence number of the event.
+    pub fn set_seq(&mut self, seq: u64) {
+        self.inner_mut().seq = Some(seq)
+    }
+
+    #[cfg(feature = "std")]
+    /// Sets the ID of the file the event is about.
+    pub fn set_file_id(&mut self, file_id: FileId) {
+        self.inner_mut().file_id = Some(file_id)
+    }
+
+    /// Sets the number of queue overflows of the watcher so far.
+    pub fn set_overflow_count(&mut self, overflow_count: u64) {
+        self.inner_mut().overflow_count = Some(overflow_count)
+    }
+
+    #[cfg(feature = "std")]
+    /// Sets the last modification time of the affected file.
+    pub fn set_modified(&mut self, modified: SystemTime) {
+        self.inner_mut().modified = Some(modified)
+    }
+
+    /// Sets the ID of the device holding the affected file.
+    pub fn set_device_id(&mut self, device_id: u64) {
+        self.inner_mut().device_id = Some(device_id)
+    }
+
     fn inner_mut(&mut self) -> &mut EventAttributesInner {
         self.inner.get_or_insert_with(Box::default)
     }
 }
 
+/// Like for [`Event`], only the tracker, flag, info, source and source kind are compared.
+impl Eq for EventAttributes {}
+impl PartialEq for EventAttributes {
+    fn eq(&self, other: &Self) -> bool {
+        self.tracker().eq(&other.tracker())
+            && self.flag().eq(&other.flag())
+            && self.info().eq(&other.info())
+            && self.source().eq(&other.source())
+            && self.source_kind().eq(&other.source_kind())
+    }
+}
+
+impl Hash for EventAttributes {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.tracker().hash(state);
+        self.flag().hash(state);
+        self.info().hash(state);
+        self.source().hash(state);
+        self.source_kind().hash(state);
+    }
+}
+
+/// Builder for [`EventAttributes`], see [`EventAttributes::builder`].
+#[derive(Clone, Default, Debug)]
+pub struct EventAttributesBuilder {
+    attrs: EventAttributes,
+}
+
+impl EventAttributesBuilder {
+    /// Sets the tracker.
+    pub fn tracker(mut self, tracker: usize) -> Self {
+        self.attrs.set_tracker(tracker);
+        self
+    }
+
+    /// Sets the Notify flag.
+    pub fn flag(mut self, flag: Flag) -> Self {
+        self.attrs.set_flag(flag);
+        self
+    }
+
+    /// Sets the additional info.
+    pub fn info(mut self, info: &str) -> Self {
+        self.attrs.set_info(info);
+        self
+    }
+
+    /// Sets the source.
+    pub fn source(mut self, source: &str) -> Self {
+        self.attrs.set_source(source);
+        self
+    }
+
+    /// Sets the process id.
+    pub fn process_id(mut self, process_id: u32) -> Self {
+        self.attrs.set_process_id(process_id);
+        self
+    }
+
+    #[cfg(feature = "std")]
+    /// Sets the time the event was received by the backend.
+    pub fn time(mut self, time: SystemTime) -> Self {
+        self.attrs.set_time(time);
+        self
+    }
+
+    /// Sets the kind of watcher that emitted the event.
+    pub fn source_kind(mut self, source_kind: WatcherKind) -> Self {
+        self.attrs.set_source_kind(source_kind);
+        self
+    }
+
+    /// Sets the sequence number of the event.
+    pub fn seq(mut self, seq: u64) -> Self {
+        self.attrs.set_seq(seq);
+        self
+    }
+
+    #[cfg(feature = "std")]
+    /// Sets the ID of the file the event is about.
+    pub fn file_id(mut self, file_id: FileId) -> Self {
+        self.attrs.set_file_id(file_id);
+        self
+    }
+
+    /// Sets the number of queue overflows of the watcher so far.
+    pub fn overflow_count(mut self, overflow_count: u64) -> Self {
+        self.attrs.set_overflow_count(overflow_count);
+        self
+    }
+
+    #[cfg(feature = "std")]
+    /// Sets the last modification time of the affected file.
+    pub fn modified(mut self, modified: SystemTime) -> Self {
+        self.attrs.set_modified(modified);
+        self
+    }
+
+    /// Sets the ID of the device holding the affected file.
+    pub fn device_id(mut self, device_id: u64) -> Self {
+        self.attrs.set_device_id(device_id);
+        self
+    }
+
+    /// Creates the [`EventAttributes`].
+    pub fn build(self) -> EventAttributes {
+        self.attrs
+    }
+}
+
+/// Turns paths into paths relative to a root, see [`Event::relative_to`].
+#[cfg(feature = "std")]
+pub(crate) struct RelativeRoot<'a> {
+    root: &'a Path,
+    canonical: Option<PathBuf>,
+}
+
+#[cfg(feature = "std")]
+impl<'a> RelativeRoot<'a> {
+    pub(crate) fn new(root: &'a Path) -> Self {
+        Self {
+            root,
+            canonical: root.canonicalize().ok(),
+        }
+    }
+
+    pub(crate) fn relative(&self, path: &Path) -> Option<PathBuf> {
+        if let Some(relative) = self.strip(path) {
+            return Some(relative);
+        }
+        let canonical = match path.canonicalize() {
+            Ok(canonical) => canonical,
+            // a removed path
+            Err(_) => path.parent()?.canonicalize().ok()?.join(path.file_name()?),
+        };
+        self.strip(&canonical)
+    }
+
+    fn strip(&self, path: &Path) -> Option<PathBuf> {
+        path.strip_prefix(self.root)
+            .ok()
+            .or_else(|| path.strip_prefix(self.canonical.as_ref()?).ok())
+            .map(Path::to_path_buf)
+    }
+}
+
 /// Special Notify flag on the event.
 ///
 /// This attribute is used to flag certain kinds of events that Notify either marks or generates in
@@ -499,6 +893,33 @@ pub enum Flag {
     /// that keeps an in-memory representation of the filesystem will need to care, and will need
     /// to refresh that representation directly from the filesystem.
     Rescan,
+
+    /// Resync events are emitted by `Watcher::resync` for every path that exists below the
+    /// watched paths, as `Create` events.
+    ///
+    /// They don't mean that the path was just created, but allow an application to rebuild its
+    /// in-memory representation of the filesystem, e.g. after a [`Flag::Rescan`] notice.
+    Resync,
+}
+
+/// Watcher kind enumeration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+#[non_exhaustive]
+pub enum WatcherKind {
+    /// inotify backend (linux)
+    Inotify,
+    /// FS-Event backend (mac)
+    Fsevent,
+    /// KQueue backend (bsd,optionally mac)
+    Kqueue,
+    /// Polling based backend (fallback)
+    PollWatcher,
+    /// Windows backend
+    ReadDirectoryChangesWatcher,
+    /// Fake watcher for testing
+    NullWatcher,
 }
 
 impl Event {
@@ -509,6 +930,89 @@ impl Event {
     pub fn need_rescan(&self) -> bool {
         matches!(self.flag(), Some(Flag::Rescan))
     }
+
+    /// Returns whether the event reports an existing path on request rather than a change.
+    ///
+    /// See [`Flag::Resync`] for more information.
+    pub fn is_resync(&self) -> bool {
+        matches!(self.flag(), Some(Flag::Resync))
+    }
+
+    /// Indicates whether the watch of the event's path stopped working.
+    ///
+    /// Such events are of kind [`EventKind::Other`] and carry the `Info` attribute
+    /// `"watch-invalidated"`. They are emitted for the watched path itself, e.g. when it is
+    /// deleted or its filesystem is unmounted. No further events are delivered for it until it is
+    /// watched again.
+    pub fn is_watch_invalidated(&self) -> bool {
+        self.kind == EventKind::Other && self.info() == Some("watch-invalidated")
+    }
+
+    /// Indicates whether the filesystem containing the event's path was unmounted.
+    ///
+    /// Such events are of kind [`RemoveKind::Other`] and carry the `Info` attribute `"unmount"`,
+    /// which tells them apart from a deletion of the path. They are emitted for watched paths
+    /// where the platform reports the unmount, which are `IN_UNMOUNT` with inotify and
+    /// `NOTE_REVOKE` with kqueue. The latter is also reported if access to the path was revoked.
+    pub fn is_unmount(&self) -> bool {
+        self.kind == EventKind::Remove(RemoveKind::Other) && self.info() == Some("unmount")
+    }
+
+    /// Indicates whether a watched path was replaced by one of a different type.
+    ///
+    /// Such events are of kind [`EventKind::Create`] with the [`CreateKind`] of the new path and
+    /// carry the `Info` attribute `"type-changed"`. They are only emitted by the `RearmWatcher` of
+    /// the notify crate, once it watches a root again that was replaced by a path of the other
+    /// type, i.e. a file by a directory or the other way around. The backends don't report type
+    /// changes, a replaced path shows up as a removal followed by a creation there.
+    pub fn is_type_changed(&self) -> bool {
+        matches!(self.kind, EventKind::Create(_)) && self.info() == Some("type-changed")
+    }
+
+    /// Indicates whether the watch of the event's path has been set up completely.
+    ///
+    /// Such events are of kind [`EventKind::Other`] and carry the `Info` attribute
+    /// `"watch-ready"`. They are emitted by watchers that set up watches in the background, once
+    /// all watches for the event's path are installed.
+    pub fn is_watch_ready(&self) -> bool {
+        self.kind == EventKind::Other && self.info() == Some("watch-ready")
+    }
+
+    /// Indicates whether a directory was not descended into because it is too deep.
+    ///
+    /// Such events are of kind [`EventKind::Other`] and carry the `Info` attribute
+    /// `"max-depth-exceeded"`. They are emitted for each directory beyond the maximum depth of a
+    /// recursive watch, whose contents are not watched.
+    pub fn is_max_depth_exceeded(&self) -> bool {
+        self.kind == EventKind::Other && self.info() == Some("max-depth-exceeded")
+    }
+
+    /// Indicates whether the event queue of the watcher is likely to overflow.
+    ///
+    /// Such events are of kind [`EventKind::Other`] and carry the `Info` attribute
+    /// `"overflow-risk"`. They are emitted for a watched path whose tree is large enough that a
+    /// change of the whole tree, like removing it, may report more events than the queue holds.
+    /// Events lost to an overflow are reported by an event for which [`Event::need_rescan`]
+    /// returns `true`.
+    pub fn is_overflow_risk(&self) -> bool {
+        self.kind == EventKind::Other && self.info() == Some("overflow-risk")
+    }
+
+    /// Indicates whether the event is a rename, see [`EventKind::is_rename`].
+    pub fn is_rename(&self) -> bool {
+        self.kind.is_rename()
+    }
+
+    /// Retrieves the [`RenameMode`] of the event, see [`EventKind::rename_mode`].
+    pub fn rename_mode(&self) -> Option<RenameMode> {
+        self.kind.rename_mode()
+    }
+
+    /// Retrieves the [`DataChange`] of the event, see [`EventKind::data_change`].
+    pub fn data_change(&self) -> Option<DataChange> {
+        self.kind.data_change()
+    }
+
     /// Retrieves the tracker ID for an event directly, if present.
     pub fn tracker(&self) -> Option<usize> {
         self.attrs.tracker()
@@ -529,10 +1033,92 @@ impl Event {
         self.attrs.source()
     }
 
+    #[cfg(feature = "std")]
+    /// Retrieves the time the event was received by the backend, if present.
+    pub fn time(&self) -> Option<SystemTime> {
+        self.attrs.time()
+    }
+
+    /// Retrieves the kind of watcher that emitted the event, if present.
+    pub fn source_kind(&self) -> Option<WatcherKind> {
+        self.attrs.source_kind()
+    }
+
+    /// Retrieves the sequence number of the event, if present.
+    pub fn seq(&self) -> Option<u64> {
+        self.attrs.seq()
+    }
+
+    #[cfg(feature = "std")]
+    /// Retrieves the ID of the file the event is about, if present.
+    pub fn file_id(&self) -> Option<FileId> {
+        self.attrs.file_id()
+    }
+
+    /// Retrieves the number of queue overflows of the watcher so far, if present.
+    pub fn overflow_count(&self) -> Option<u64> {
+        self.attrs.overflow_count()
+    }
+
+    #[cfg(feature = "std")]
+    /// Retrieves the last modification time of the affected file, if present.
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.attrs.modified()
+    }
+
+    /// Retrieves the ID of the device holding the affected file, if present.
+    pub fn device_id(&self) -> Option<u64> {
+        self.attrs.device_id()
+    }
+
+    #[cfg(feature = "std")]
+    /// The first path of the event below `root`, relative to `root`.
+    ///
+    /// Event paths may be canonicalized by the backend while `root` isn't, or the other way
+    /// round, e.g. on macOS where temporary directories are behind a symbolic link. If the path
+    /// isn't below `root` as is, both are compared canonicalized. Paths that no longer exist are
+    /// canonicalized through their parent directory.
+    ///
+    /// Returns `None` if none of the paths is below `root`.
+    pub fn relative_to(&self, root: &Path) -> Option<PathBuf> {
+        let root = RelativeRoot::new(root);
+        self.paths.iter().find_map(|path| root.relative(path))
+    }
+
+    #[cfg(feature = "std")]
+    /// Returns a key that is equal for events representing the same logical change, e.g. to
+    /// coalesce events in a `HashSet`.
+    ///
+    /// The key consists of the path and the [`EventKindTag`], so the details of the kind and the
+    /// attributes are ignored. A rename with both paths is keyed on its destination, all other
+    /// events on their first path.
+    ///
+    /// ```
+    /// # use notify_types::event::{DataChange, Event, EventKind, ModifyKind};
+    /// # use std::path::PathBuf;
+    /// let write = |change| {
+    ///     Event::new(EventKind::Modify(ModifyKind::Data(change))).add_path(PathBuf::from("/a"))
+    /// };
+    /// assert_eq!(write(DataChange::Any).dedup_key(), write(DataChange::Content).dedup_key());
+    /// ```
+    pub fn dedup_key(&self) -> DedupKey {
+        let path = match self.kind {
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if self.paths.len() > 1 => {
+                self.paths.last()
+            }
+            _ => self.paths.first(),
+        };
+        DedupKey {
+            path: path.cloned(),
+            tag: self.kind.into(),
+        }
+    }
+
     /// Creates a new `Event` given a kind.
     pub fn new(kind: EventKind) -> Self {
         Self {
             kind,
+            #[cfg(feature = "std")]
             paths: Vec::new(),
             attrs: EventAttributes::new(),
         }
@@ -544,12 +1130,14 @@ impl Event {
         self
     }
 
+    #[cfg(feature = "std")]
     /// Adds a path to the event.
     pub fn add_path(mut self, path: PathBuf) -> Self {
         self.paths.push(path);
         self
     }
 
+    #[cfg(feature = "std")]
     /// Adds a path to the event if the argument is Some.
     pub fn add_some_path(self, path: Option<PathBuf>) -> Self {
         if let Some(path) = path {
@@ -582,54 +1170,254 @@ impl Event {
         self.attrs.set_process_id(process_id);
         self
     }
+
+    #[cfg(feature = "std")]
+    /// Sets the time the event was received by the backend.
+    pub fn set_time(mut self, time: SystemTime) -> Self {
+        self.attrs.set_time(time);
+        self
+    }
+
+    /// Sets the kind of watcher that emitted the event.
+    pub fn set_source_kind(mut self, source_kind: WatcherKind) -> Self {
+        self.attrs.set_source_kind(source_kind);
+        self
+    }
+
+    /// Sets the sequence number of the event.
+    pub fn set_seq(mut self, seq: u64) -> Self {
+        self.attrs.set_seq(seq);
+        self
+    }
+
+    #[cfg(feature = "std")]
+    /// Sets the ID of the file the event is about.
+    pub fn set_file_id(mut self, file_id: FileId) -> Self {
+        self.attrs.set_file_id(file_id);
+        self
+    }
+
+    /// Sets the number of queue overflows of the watcher so far.
+    pub fn set_overflow_count(mut self, overflow_count: u64) -> Self {
+        self.attrs.set_overflow_count(overflow_count);
+        self
+    }
+
+    #[cfg(feature = "std")]
+    /// Sets the last modification time of the affected file.
+    pub fn set_modified(mut self, modified: SystemTime) -> Self {
+        self.attrs.set_modified(modified);
+        self
+    }
+
+    /// Sets the ID of the device holding the affected file.
+    pub fn set_device_id(mut self, device_id: u64) -> Self {
+        self.attrs.set_device_id(device_id);
+        self
+    }
 }
 
 impl fmt::Debug for Event {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_struct("Event")
-            .field("kind", &self.kind)
-            .field("paths", &self.paths)
+        let mut debug = f.debug_struct("Event");
+        debug.field("kind", &self.kind);
+        #[cfg(feature = "std")]
+        debug.field("paths", &self.paths);
+        debug
             .field("attr:tracker", &self.tracker())
             .field("attr:flag", &self.flag())
             .field("attr:info", &self.info())
-            .field("attr:source", &self.source())
-            .finish()
+            .field("attr:source", &self.source());
+        #[cfg(feature = "std")]
+        debug.field("attr:time", &self.time());
+        debug
+            .field("attr:source_kind", &self.source_kind())
+            .field("attr:seq", &self.seq());
+        #[cfg(feature = "std")]
+        debug.field("attr:file_id", &self.file_id());
+        debug.field("attr:overflow_count", &self.overflow_count());
+        #[cfg(feature = "std")]
+        debug.field("attr:modified", &self.modified());
+        debug.field("attr:device_id", &self.device_id()).finish()
     }
 }
 impl Default for Event {
     fn default() -> Self {
-        Self {
-            kind: EventKind::default(),
-            paths: Vec::new(),
-            attrs: EventAttributes::new(),
-        }
+        Self::new(EventKind::default())
     }
 }
 
 impl Eq for Event {}
 impl PartialEq for Event {
     fn eq(&self, other: &Self) -> bool {
-        self.kind.eq(&other.kind)
-            && self.paths.eq(&other.paths)
-            && self.tracker().eq(&other.tracker())
-            && self.flag().eq(&other.flag())
-            && self.info().eq(&other.info())
-            && self.source().eq(&other.source())
+        #[cfg(feature = "std")]
+        if self.paths != other.paths {
+            return false;
+        }
+        self.kind.eq(&other.kind) && self.attrs.eq(&other.attrs)
     }
 }
 
 impl Hash for Event {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.kind.hash(state);
+        #[cfg(feature = "std")]
         self.paths.hash(state);
-        self.tracker().hash(state);
-        self.flag().hash(state);
-        self.info().hash(state);
-        self.source().hash(state);
+        self.attrs.hash(state);
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod std_tests {
+    use super::*;
+
+    use rstest::rstest;
+
+    #[test]
+    fn event_attributes_round_trip() {
+        let time = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1);
+        let attrs = EventAttributes::builder()
+            .tracker(1)
+            .flag(Flag::Rescan)
+            .info("info")
+            .source("source")
+            .process_id(2)
+            .time(time)
+            .source_kind(WatcherKind::Inotify)
+            .seq(3)
+            .file_id(FileId::new_inode(4, 5))
+            .overflow_count(6)
+            .modified(time)
+            .device_id(7)
+            .build();
+
+        assert_eq!(attrs.tracker(), Some(1));
+        assert_eq!(attrs.flag(), Some(Flag::Rescan));
+        assert_eq!(attrs.info(), Some("info"));
+        assert_eq!(attrs.source(), Some("source"));
+        assert_eq!(attrs.process_id(), Some(2));
+        assert_eq!(attrs.time(), Some(time));
+        assert_eq!(attrs.source_kind(), Some(WatcherKind::Inotify));
+        assert_eq!(attrs.seq(), Some(3));
+        assert_eq!(attrs.file_id(), Some(FileId::new_inode(4, 5)));
+        assert_eq!(attrs.overflow_count(), Some(6));
+        assert_eq!(attrs.modified(), Some(time));
+        assert_eq!(attrs.device_id(), Some(7));
+
+        assert_eq!(attrs.clone(), attrs);
+        assert_ne!(EventAttributes::builder().tracker(2).build(), attrs);
+        assert_eq!(EventAttributes::builder().build(), EventAttributes::new());
+    }
+
+    #[test]
+    fn dedup_key_collapses_sub_kinds() {
+        let event = |kind, paths: &[&str]| {
+            paths
+                .iter()
+                .fold(Event::new(kind), |event, path| event.add_path(path.into()))
+        };
+        let data = |change| EventKind::Modify(ModifyKind::Data(change));
+
+        let write = event(data(DataChange::Any), &["/a"]).set_seq(1);
+        assert_eq!(
+            write.dedup_key(),
+            event(data(DataChange::Size), &["/a"]).dedup_key()
+        );
+        assert_ne!(
+            write.dedup_key(),
+            event(data(DataChange::Any), &["/b"]).dedup_key()
+        );
+        assert_ne!(
+            write.dedup_key(),
+            event(
+                EventKind::Modify(ModifyKind::Metadata(MetadataKind::Any)),
+                &["/a"]
+            )
+            .dedup_key()
+        );
+        assert_eq!(
+            event(EventKind::Create(CreateKind::File), &["/a"]).dedup_key(),
+            event(EventKind::Create(CreateKind::Any), &["/a"]).dedup_key()
+        );
+
+        let both = event(
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+            &["/from", "/to"],
+        );
+        let to = event(
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)),
+            &["/to"],
+        );
+        assert_eq!(both.dedup_key(), to.dedup_key());
+        assert_eq!(both.dedup_key().path, Some(PathBuf::from("/to")));
+
+        assert_eq!(
+            Event::new(EventKind::Other).dedup_key(),
+            DedupKey {
+                path: None,
+                tag: EventKindTag::Other
+            }
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn relative_to_handles_canonicalization() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("root");
+        let link = dir.path().join("link");
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(root.join("sub/file"), b"").unwrap();
+        std::os::unix::fs::symlink(&root, &link).unwrap();
+
+        let canonical = root.canonicalize().unwrap();
+        let event = |path: PathBuf| Event::new(EventKind::Any).add_path(path);
+        let relative = Some(PathBuf::from("sub").join("file"));
+        assert_eq!(event(root.join("sub/file")).relative_to(&root), relative);
+        assert_eq!(
+            event(canonical.join("sub/file")).relative_to(&link),
+            relative
+        );
+        assert_eq!(event(link.join("sub/file")).relative_to(&root), relative);
+        assert_eq!(
+            event(link.join("sub/removed")).relative_to(&canonical),
+            Some(PathBuf::from("sub").join("removed"))
+        );
+        assert_eq!(event(dir.path().join("other")).relative_to(&root), None);
+    }
+
+    #[rstest]
+    fn is_rename(
+        #[values(
+            RenameMode::Any,
+            RenameMode::To,
+            RenameMode::From,
+            RenameMode::Both,
+            RenameMode::Other
+        )]
+        mode: RenameMode,
+    ) {
+        let event = Event::new(EventKind::Modify(ModifyKind::Name(mode)));
+        assert!(event.is_rename());
+        assert_eq!(event.rename_mode(), Some(mode));
+        assert_eq!(event.data_change(), None);
+    }
+
+    #[test]
+    fn data_change() {
+        let kind = EventKind::Modify(ModifyKind::Data(DataChange::Content));
+        assert!(!kind.is_rename());
+        assert_eq!(kind.rename_mode(), None);
+        assert_eq!(kind.data_change(), Some(DataChange::Content));
     }
 }
 
-#[cfg(all(test, feature = "serde", not(feature = "serialization-compat-6")))]
+#[cfg(all(
+    test,
+    feature = "std",
+    feature = "serde",
+    not(feature = "serialization-compat-6")
+))]
 mod tests {
     use super::*;
 
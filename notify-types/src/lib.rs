@@ -1,8 +1,25 @@
+//! Types used by the notify crate
+//!
+//! Without the default `std` feature, the crate is `no_std` and requires `alloc`. The [`event`]
+//! module then provides [`Event`](event::Event) without its paths, [`EventAttributes`] without
+//! the time, modification time and file ID attributes, and the enums like
+//! [`EventKind`](event::EventKind). The debouncer types and the path helpers of `Event`, like
+//! `Event::dedup_key`, require `std`.
+//!
+//! [`EventAttributes`]: event::EventAttributes
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+
+#[cfg(feature = "std")]
 pub mod debouncer_full;
+#[cfg(feature = "std")]
 pub mod debouncer_mini;
 pub mod event;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
 
@@ -27,6 +44,7 @@ mod tests {
         assert_debug_impl!(event::RenameMode);
         assert_debug_impl!(event::Event);
         assert_debug_impl!(event::EventKind);
+        assert_debug_impl!(event::WatcherKind);
         assert_debug_impl!(debouncer_mini::DebouncedEvent);
         assert_debug_impl!(debouncer_mini::DebouncedEventKind);
         assert_debug_impl!(debouncer_full::DebouncedEvent);
@@ -0,0 +1,106 @@
+//! Watcher wrapper buffering events for polling them
+//!
+//! Applications driven by their own loop, e.g. games or GUIs, often can't block on a channel.
+//! The [`BufferedWatcher`] collects the events of its watcher and hands them out with the
+//! non-blocking [`BufferedWatcher::next_event`].
+
+use crate::{Config, Event, Result, Watcher};
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+type Events = Arc<Mutex<VecDeque<Result<Event>>>>;
+
+/// [`Watcher`] wrapper storing the events in an internal queue until they are taken with
+/// [`next_event`](BufferedWatcher::next_event).
+///
+/// The queue is unbounded, so events should be drained regularly.
+///
+/// ```no_run
+/// # use notify::{BufferedWatcher, Config, RecommendedWatcher, RecursiveMode, Result, Watcher};
+/// # use std::path::Path;
+/// # fn main() -> Result<()> {
+/// let mut watcher = BufferedWatcher::<RecommendedWatcher>::new(Config::default())?;
+/// watcher.watcher().watch(Path::new("."), RecursiveMode::Recursive)?;
+///
+/// // once per frame
+/// while let Some(event) = watcher.next_event() {
+///     println!("{event:?}");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct BufferedWatcher<W> {
+    watcher: W,
+    events: Events,
+}
+
+impl<W: Watcher> BufferedWatcher<W> {
+    /// Create a new [`BufferedWatcher`] with a watcher of type `W` configured by `config`.
+    pub fn new(config: Config) -> Result<Self> {
+        let events = Events::default();
+        let watcher = {
+            let events = events.clone();
+            W::new(
+                move |res: Result<Event>| events.lock().unwrap().push_back(res),
+                config,
+            )?
+        };
+        Ok(Self { watcher, events })
+    }
+
+    /// Take the oldest buffered event, returns `None` right away if there is none.
+    pub fn next_event(&mut self) -> Option<Result<Event>> {
+        self.events.lock().unwrap().pop_front()
+    }
+
+    /// Access the wrapped watcher, e.g. to add and remove watches.
+    pub fn watcher(&mut self) -> &mut W {
+        &mut self.watcher
+    }
+}
+
+impl<W: fmt::Debug> fmt::Debug for BufferedWatcher<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BufferedWatcher")
+            .field("watcher", &self.watcher)
+            .field("buffered", &self.events.lock().unwrap().len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RecommendedWatcher, RecursiveMode};
+    use std::fs;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn buffered_events_are_drained_without_blocking() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut watcher = BufferedWatcher::<RecommendedWatcher>::new(Config::default()).unwrap();
+        assert!(watcher.next_event().is_none());
+
+        watcher
+            .watcher()
+            .watch(dir.path(), RecursiveMode::Recursive)
+            .unwrap();
+        fs::write(dir.path().join("file"), b"data").unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut seen = false;
+        while !seen && Instant::now() < deadline {
+            while let Some(event) = watcher.next_event() {
+                seen |= event
+                    .unwrap()
+                    .paths
+                    .iter()
+                    .any(|path| path.ends_with("file"));
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert!(seen);
+    }
+}
@@ -0,0 +1,162 @@
+//! Normalizing the case of event paths, see [`Config::with_normalize_path_case`](crate::Config::with_normalize_path_case)
+
+use std::env;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// The watched paths on case-insensitive filesystems, whose case is applied to the event paths
+/// below them.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CaseRoots {
+    roots: Arc<Mutex<Vec<PathBuf>>>,
+}
+
+impl CaseRoots {
+    /// Remembers the case of `root` if it is on a case-insensitive filesystem.
+    pub(crate) fn add(&self, root: &Path) {
+        let root = absolute(root);
+        if !is_case_insensitive(&root) {
+            return;
+        }
+        let mut roots = self.roots.lock().unwrap();
+        if !roots.contains(&root) {
+            roots.push(root);
+            // the longest root wins
+            roots.sort_by_key(|root| std::cmp::Reverse(root.components().count()));
+        }
+    }
+
+    /// Forgets `root`, or all roots starting with it if `is_prefix` is set.
+    pub(crate) fn remove(&self, root: &Path, is_prefix: bool) {
+        let root = absolute(root);
+        self.roots
+            .lock()
+            .unwrap()
+            .retain(|r| !(r == &root || is_prefix && r.starts_with(&root)));
+    }
+
+    /// Replaces the start of `path` with the root it matches, ignoring case.
+    pub(crate) fn normalize(&self, path: &mut PathBuf) {
+        let roots = self.roots.lock().unwrap();
+        for root in roots.iter() {
+            if path.starts_with(root) {
+                return;
+            }
+            if let Some(rest) = strip_prefix_ignoring_case(path, root) {
+                *path = root.join(rest);
+                return;
+            }
+        }
+    }
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    match env::current_dir() {
+        Ok(dir) if path.is_relative() => dir.join(path),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// The rest of `path` if it starts with `prefix`, ignoring the case of the components.
+fn strip_prefix_ignoring_case<'a>(path: &'a Path, prefix: &Path) -> Option<&'a Path> {
+    let mut components = path.components();
+    for expected in prefix.components() {
+        let component = components.next()?;
+        if !eq_ignoring_case(component, expected) {
+            return None;
+        }
+    }
+    Some(components.as_path())
+}
+
+fn eq_ignoring_case(a: Component, b: Component) -> bool {
+    match (a.as_os_str().to_str(), b.as_os_str().to_str()) {
+        (Some(a), Some(b)) => a.to_lowercase() == b.to_lowercase(),
+        _ => a == b,
+    }
+}
+
+/// Whether the filesystem holding `path` ignores the case of file names.
+///
+/// Looks up the closest ancestor whose name has a case with the case swapped and checks whether
+/// it refers to the same file.
+fn is_case_insensitive(path: &Path) -> bool {
+    for ancestor in path.ancestors() {
+        let Some(name) = ancestor.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let swapped: String = name
+            .chars()
+            .map(|c| {
+                if c.is_uppercase() {
+                    c.to_lowercase().next().unwrap_or(c)
+                } else {
+                    c.to_uppercase().next().unwrap_or(c)
+                }
+            })
+            .collect();
+        if swapped == name {
+            continue;
+        }
+        return is_same_file(ancestor, &ancestor.with_file_name(swapped));
+    }
+    false
+}
+
+#[cfg(unix)]
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    // resolves the case stored on disk
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_paths_take_the_case_of_the_root() {
+        let case_roots = CaseRoots::default();
+        case_roots
+            .roots
+            .lock()
+            .unwrap()
+            .push(PathBuf::from("/Users/me/Project"));
+
+        let mut path = PathBuf::from("/users/me/project/src/Main.rs");
+        case_roots.normalize(&mut path);
+        assert_eq!(path, Path::new("/Users/me/Project/src/Main.rs"));
+
+        let mut other = PathBuf::from("/users/me/other");
+        case_roots.normalize(&mut other);
+        assert_eq!(other, Path::new("/users/me/other"));
+    }
+
+    #[test]
+    fn case_sensitive_roots_are_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("Root");
+        fs::create_dir(&root).unwrap();
+
+        let case_roots = CaseRoots::default();
+        case_roots.add(&root);
+        let is_insensitive = dir.path().join("ROOT").exists();
+        assert_eq!(
+            case_roots.roots.lock().unwrap().len(),
+            is_insensitive as usize
+        );
+    }
+}
@@ -1,5 +1,7 @@
 //! Configuration types
 
+use std::fmt;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 /// Indicates whether only the provided directory or its sub-directories as well should be watched
@@ -13,7 +15,23 @@ pub enum RecursiveMode {
 }
 
 impl RecursiveMode {
-    pub(crate) fn is_recursive(&self) -> bool {
+    /// Returns the mode for watching recursively if `recursive` is `true`.
+    ///
+    /// ```
+    /// # use notify::RecursiveMode;
+    /// assert_eq!(RecursiveMode::from_bool(true), RecursiveMode::Recursive);
+    /// assert_eq!(RecursiveMode::from_bool(false).to_string(), "non-recursive");
+    /// ```
+    pub const fn from_bool(recursive: bool) -> Self {
+        if recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        }
+    }
+
+    /// Returns whether sub-directories are watched as well.
+    pub const fn is_recursive(&self) -> bool {
         match *self {
             RecursiveMode::Recursive => true,
             RecursiveMode::NonRecursive => false,
@@ -21,6 +39,162 @@ impl RecursiveMode {
     }
 }
 
+impl fmt::Display for RecursiveMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RecursiveMode::Recursive => "recursive",
+            RecursiveMode::NonRecursive => "non-recursive",
+        })
+    }
+}
+
+/// How the [`PollWatcher`](crate::PollWatcher) decides whether a file changed, see
+/// [`Config::with_compare`].
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug, Hash)]
+pub enum CompareMode {
+    /// Compare the modification time only
+    #[default]
+    MetadataOnly,
+
+    /// Compare the modification time and the size
+    ///
+    /// This catches changes within the resolution of the modification time, as long as they
+    /// change the size of the file.
+    SizeAndMtime,
+
+    /// Compare the modification time and a hash of the contents
+    ///
+    /// See [`Config::with_compare_contents`].
+    Contents,
+}
+
+/// Coarse category of [`EventKind`](crate::EventKind)s, see [`Config::with_event_kinds`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum EventKindMask {
+    /// `Create` events
+    Create,
+
+    /// `Remove` events
+    Remove,
+
+    /// `Modify` events, including renames
+    Modify,
+
+    /// `Access` events
+    Access,
+}
+
+impl EventKindMask {
+    const ALL: [EventKindMask; 4] = [
+        EventKindMask::Create,
+        EventKindMask::Remove,
+        EventKindMask::Modify,
+        EventKindMask::Access,
+    ];
+
+    const fn bit(self) -> u8 {
+        1 << self as u8
+    }
+
+    /// Returns whether `kind` belongs to this category.
+    ///
+    /// ```
+    /// # use notify::{EventKind, EventKindMask};
+    /// # use notify::event::CreateKind;
+    /// assert!(EventKindMask::Create.matches(&EventKind::Create(CreateKind::File)));
+    /// assert!(!EventKindMask::Modify.matches(&EventKind::Create(CreateKind::File)));
+    /// ```
+    pub fn matches(&self, kind: &crate::EventKind) -> bool {
+        use crate::EventKind;
+        matches!(
+            (self, kind),
+            (EventKindMask::Create, EventKind::Create(_))
+                | (EventKindMask::Remove, EventKind::Remove(_))
+                | (EventKindMask::Modify, EventKind::Modify(_))
+                | (EventKindMask::Access, EventKind::Access(_))
+        )
+    }
+}
+
+/// Which files events are delivered for, depending on whether they are hidden, see
+/// [`Config::with_hidden_files`].
+///
+/// Files are hidden if their name starts with a dot on Unix, and if they have the hidden
+/// attribute on Windows.
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug, Hash)]
+pub enum HiddenFiles {
+    /// Deliver events for all files
+    #[default]
+    Include,
+
+    /// Don't deliver events for hidden files
+    Exclude,
+
+    /// Only deliver events for hidden files
+    Only,
+}
+
+impl HiddenFiles {
+    /// Whether an event with the given `paths` is delivered. Events without paths are always
+    /// delivered, an event with several paths, e.g. a rename, if any of them is.
+    pub(crate) fn allows(&self, paths: &[PathBuf]) -> bool {
+        paths.is_empty() || paths.iter().any(|path| self.allows_path(path))
+    }
+
+    /// Whether events for the file at `path` are delivered.
+    pub(crate) fn allows_path(&self, path: &Path) -> bool {
+        match self {
+            HiddenFiles::Include => true,
+            HiddenFiles::Exclude => !is_hidden(path),
+            HiddenFiles::Only => is_hidden(path),
+        }
+    }
+}
+
+/// Whether the file at `path` is hidden, see [`HiddenFiles`].
+#[cfg(not(windows))]
+pub(crate) fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.as_encoded_bytes().starts_with(b"."))
+}
+
+/// Whether the file at `path` is hidden, see [`HiddenFiles`].
+///
+/// Files that no longer exist are not hidden, as their attributes can't be read.
+#[cfg(windows)]
+pub(crate) fn is_hidden(path: &Path) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    use windows_sys::Win32::Storage::FileSystem::FILE_ATTRIBUTE_HIDDEN;
+
+    std::fs::symlink_metadata(path)
+        .is_ok_and(|metadata| metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0)
+}
+
+/// Returns whether changes to `path` are reported by at least one of `watches`.
+///
+/// A watch covers its own path and, for directories, their immediate children. Recursive
+/// watches additionally cover everything below their path. Paths are compared as given, without
+/// resolving symbolic links or relative paths.
+///
+/// ```
+/// # use notify::RecursiveMode;
+/// # use std::path::Path;
+/// let watches = [(Path::new("/project"), RecursiveMode::NonRecursive)];
+/// assert!(notify::covers(watches, Path::new("/project/Cargo.toml")));
+/// assert!(!notify::covers(watches, Path::new("/project/src/lib.rs")));
+/// ```
+pub fn covers<'a, I>(watches: I, path: &Path) -> bool
+where
+    I: IntoIterator<Item = (&'a Path, RecursiveMode)>,
+{
+    watches
+        .into_iter()
+        .any(|(root, recursive_mode)| match recursive_mode {
+            RecursiveMode::Recursive => path.starts_with(root),
+            RecursiveMode::NonRecursive => path == root || path.parent() == Some(root),
+        })
+}
+
 /// Watcher Backend configuration
 ///
 /// This contains multiple settings that may relate to only one specific backend,
@@ -40,10 +214,100 @@ pub struct Config {
     /// See [Config::with_poll_interval]
     poll_interval: Option<Duration>,
 
-    /// See [Config::with_compare_contents]
-    compare_contents: bool,
+    /// See [Config::with_compare]
+    compare: CompareMode,
 
     follow_symlinks: bool,
+
+    /// See [Config::with_report_self_events]
+    report_self_events: bool,
+
+    /// See [Config::with_prefer_close_write]
+    prefer_close_write: Option<Duration>,
+
+    /// See [Config::with_wait_granularity]
+    wait_granularity: Duration,
+
+    /// See [Config::with_max_tracked_paths]
+    max_tracked_paths: Option<usize>,
+
+    /// See [Config::with_rename_pair_window]
+    rename_pair_window: Option<Duration>,
+
+    /// See [Config::with_event_timestamps]
+    event_timestamps: bool,
+
+    /// See [Config::with_report_dir_modify_events]
+    report_dir_modify_events: bool,
+
+    /// See [Config::with_fsevent_watch_root]
+    fsevent_watch_root: bool,
+
+    /// See [Config::with_event_source_kind]
+    event_source_kind: bool,
+
+    /// See [Config::with_kqueue_diff_directories]
+    kqueue_diff_directories: bool,
+
+    /// See [Config::with_event_sequence_numbers]
+    event_sequence_numbers: bool,
+
+    /// See [Config::with_buffer_paused_events]
+    buffer_paused_events: bool,
+
+    /// See [Config::with_event_file_ids]
+    event_file_ids: bool,
+
+    /// See [Config::with_standard_windows_reader]
+    standard_windows_reader: bool,
+
+    /// See [Config::with_fsevent_drop_leading_create]
+    fsevent_drop_leading_create: bool,
+
+    /// See [Config::with_event_device_ids]
+    event_device_ids: bool,
+
+    /// See [Config::with_trash_detection]
+    trash_detection: bool,
+
+    /// See [Config::with_watch_symlinks_as_links]
+    watch_symlinks_as_links: bool,
+
+    /// See [Config::with_min_create_age]
+    min_create_age: Option<Duration>,
+
+    /// See [Config::with_coalesce_tree_removal]
+    coalesce_tree_removal: bool,
+
+    /// See [Config::with_event_buffer_capacity]
+    event_buffer_capacity: Option<usize>,
+
+    /// See [Config::with_fsevent_device_stream]
+    fsevent_device_stream: bool,
+
+    /// See [Config::with_event_kinds]
+    event_kinds: Option<u8>,
+
+    /// See [Config::with_channel_capacity]
+    channel_capacity: Option<usize>,
+
+    /// See [Config::with_hidden_files]
+    hidden_files: HiddenFiles,
+
+    /// See [Config::with_auto_watch_limit]
+    auto_watch_limit: Option<(usize, Duration)>,
+
+    /// See [Config::with_normalize_path_case]
+    normalize_path_case: bool,
+
+    /// See [Config::with_precise_create_kinds]
+    precise_create_kinds: bool,
+
+    /// See [Config::with_precise_metadata_kinds]
+    precise_metadata_kinds: bool,
+
+    /// See [Config::with_max_watch_depth]
+    max_watch_depth: Option<usize>,
 }
 
 impl Config {
@@ -55,6 +319,10 @@ impl Config {
     /// The default poll frequency is 30 seconds.
     ///
     /// This will enable automatic polling, overwriting [`with_manual_polling()`](Config::with_manual_polling).
+    ///
+    /// The interval is read by [`PollWatcher::new`](crate::PollWatcher::new), so it also applies
+    /// to the [`RecommendedWatcher`](crate::RecommendedWatcher) on platforms without a native
+    /// backend.
     pub fn with_poll_interval(mut self, dur: Duration) -> Self {
         // TODO: v7.0 break signature to option
         self.poll_interval = Some(dur);
@@ -87,20 +355,53 @@ impl Config {
     /// need to be read and hashed at each `poll_interval`.
     ///
     /// This can't be changed during runtime. Off by default.
+    ///
+    /// Shorthand for [`with_compare`](Config::with_compare) with [`CompareMode::Contents`], or
+    /// with the default [`CompareMode::MetadataOnly`] when disabled.
     pub fn with_compare_contents(mut self, compare_contents: bool) -> Self {
-        self.compare_contents = compare_contents;
+        self.compare = if compare_contents {
+            CompareMode::Contents
+        } else {
+            CompareMode::MetadataOnly
+        };
         self
     }
 
     /// Returns current setting
     pub fn compare_contents(&self) -> bool {
-        self.compare_contents
+        self.compare == CompareMode::Contents
+    }
+
+    /// For the [`PollWatcher`](crate::PollWatcher) backend.
+    ///
+    /// Select which properties of a file are compared to detect a change, see [`CompareMode`].
+    /// The modification time has a resolution of one second, so [`CompareMode::MetadataOnly`]
+    /// misses repeated changes within the same second, while [`CompareMode::SizeAndMtime`]
+    /// catches those that change the size without reading the file.
+    ///
+    /// This can't be changed during runtime. [`CompareMode::MetadataOnly`] by default.
+    pub fn with_compare(mut self, compare: CompareMode) -> Self {
+        self.compare = compare;
+        self
+    }
+
+    /// Returns current setting
+    pub fn compare(&self) -> CompareMode {
+        self.compare
     }
 
     /// For the [INotifyWatcher](crate::INotifyWatcher), [KqueueWatcher](crate::KqueueWatcher),
     /// and [PollWatcher](crate::PollWatcher).
     ///
-    /// Determine if sybolic links should be followed when recursively watching a directory.
+    /// Determine if symbolic links should be followed when recursively watching a directory.
+    ///
+    /// When disabled, no watches are added and no metadata is read below symbolic links to
+    /// directories, and a watched path that is a symbolic link is watched itself instead of its
+    /// target. The inotify backend uses `IN_DONT_FOLLOW` for this.
+    ///
+    /// The [FsEventWatcher](crate::FsEventWatcher) and the
+    /// [ReadDirectoryChangesWatcher](crate::ReadDirectoryChangesWatcher) never follow symbolic
+    /// links below a watched directory, changes in linked directories are not reported by them.
     ///
     /// This can't be changed during runtime. On by default.
     pub fn with_follow_symlinks(mut self, follow_symlinks: bool) -> Self {
@@ -112,14 +413,668 @@ impl Config {
     pub fn follow_symlinks(&self) -> bool {
         self.follow_symlinks
     }
+
+    /// For all backends.
+    ///
+    /// Determine if events about the watched path itself, such as it being removed or renamed,
+    /// or its watch being [invalidated](crate::Event::is_watch_invalidated), should be reported.
+    /// Events about the children of a watched directory are not affected.
+    ///
+    /// The watch is still cleaned up internally when the watched path goes away, only the
+    /// event is suppressed.
+    ///
+    /// This can't be changed during runtime. On by default.
+    pub fn with_report_self_events(mut self, report_self_events: bool) -> Self {
+        self.report_self_events = report_self_events;
+        self
+    }
+
+    /// Returns current setting
+    pub fn report_self_events(&self) -> bool {
+        self.report_self_events
+    }
+
+    /// For the [INotifyWatcher](crate::INotifyWatcher) backend.
+    ///
+    /// Treat `Access(Close(Write))` as the definitive signal that a file was modified. The
+    /// `Modify(Data)` events for a path are held back until its close-write arrives, at which
+    /// point they are dropped and only the close-write event is emitted.
+    ///
+    /// If a file is written to but not closed, a single held back `Modify(Data)` event is
+    /// emitted once `timeout` has passed, or as soon as any other event arrives, apart from
+    /// further writes to the same file. It is always emitted before the newer event.
+    ///
+    /// This can't be changed during runtime. Off by default.
+    pub fn with_prefer_close_write(mut self, timeout: Duration) -> Self {
+        self.prefer_close_write = Some(timeout);
+        self
+    }
+
+    /// Returns current setting
+    pub fn prefer_close_write(&self) -> Option<Duration> {
+        self.prefer_close_write
+    }
+
+    /// For the [ReadDirectoryChangesWatcher](crate::ReadDirectoryChangesWatcher) backend.
+    ///
+    /// How long the background thread waits for an I/O completion before it checks for new
+    /// control actions (watch, unwatch, ...). Lower values reduce the latency of those actions
+    /// at the cost of more frequent wakeups.
+    ///
+    /// Must be at least 1ms, creating the watcher fails with
+    /// [ErrorKind::InvalidConfig](crate::ErrorKind::InvalidConfig) otherwise.
+    ///
+    /// This can't be changed during runtime. The default is 100ms.
+    pub fn with_wait_granularity(mut self, wait_granularity: Duration) -> Self {
+        self.wait_granularity = wait_granularity;
+        self
+    }
+
+    /// Returns current setting
+    pub fn wait_granularity(&self) -> Duration {
+        self.wait_granularity
+    }
+
+    /// For the [`PollWatcher`](crate::PollWatcher) backend.
+    ///
+    /// Maximum number of paths tracked across all watches. Once the limit is reached, paths that
+    /// aren't tracked yet are ignored, while the tracked ones keep being compared. An error of
+    /// kind [ErrorKind::MaxFilesWatch](crate::ErrorKind::MaxFilesWatch) is emitted once per
+    /// watched path reaching the limit.
+    ///
+    /// This can't be changed during runtime. Unlimited by default.
+    pub fn with_max_tracked_paths(mut self, max_tracked_paths: usize) -> Self {
+        self.max_tracked_paths = Some(max_tracked_paths);
+        self
+    }
+
+    /// Returns current setting
+    pub fn max_tracked_paths(&self) -> Option<usize> {
+        self.max_tracked_paths
+    }
+
+    /// For the [ReadDirectoryChangesWatcher](crate::ReadDirectoryChangesWatcher) backend.
+    ///
+    /// Pair the old and new name of a rename into a single `Modify(Name(Both))` event, even if
+    /// they are reported in separate completions. An unmatched old name is held back for up to
+    /// `window`, after which it is emitted as `Modify(Name(From))`. The window is checked with
+    /// the precision of [`with_wait_granularity`](Config::with_wait_granularity).
+    ///
+    /// This can't be changed during runtime. Off by default, old and new names are reported
+    /// as separate `From` and `To` events.
+    pub fn with_rename_pair_window(mut self, window: Duration) -> Self {
+        self.rename_pair_window = Some(window);
+        self
+    }
+
+    /// Returns current setting
+    pub fn rename_pair_window(&self) -> Option<Duration> {
+        self.rename_pair_window
+    }
+
+    /// For all backends.
+    ///
+    /// Stamp every event with the time it was emitted by the backend, available through
+    /// [`Event::time`](crate::Event::time). This is closer to the time the change happened than
+    /// stamping events on arrival in the event handler, especially when events queue up.
+    ///
+    /// This can't be changed during runtime. Off by default.
+    pub fn with_event_timestamps(mut self, event_timestamps: bool) -> Self {
+        self.event_timestamps = event_timestamps;
+        self
+    }
+
+    /// Returns current setting
+    pub fn event_timestamps(&self) -> bool {
+        self.event_timestamps
+    }
+
+    /// For the [`PollWatcher`](crate::PollWatcher) backend.
+    ///
+    /// Determine if modify events should be reported for directories. The modification time of
+    /// a directory changes whenever an entry is added to or removed from it, so creating a file
+    /// usually results in a modify event for its parent directory as well. The events for the
+    /// entries themselves are not affected.
+    ///
+    /// This can't be changed during runtime. On by default.
+    pub fn with_report_dir_modify_events(mut self, report_dir_modify_events: bool) -> Self {
+        self.report_dir_modify_events = report_dir_modify_events;
+        self
+    }
+
+    /// Returns current setting
+    pub fn report_dir_modify_events(&self) -> bool {
+        self.report_dir_modify_events
+    }
+
+    /// For the [`FsEventWatcher`](crate::FsEventWatcher) backend.
+    ///
+    /// Request notifications about changes to the path leading up to the watched roots, by
+    /// creating the stream with `kFSEventStreamCreateFlagWatchRoot`. Moving or deleting a
+    /// watched directory is then reported as `Modify(Name(From))` event with the info
+    /// `root changed`. Without it, FSEvents doesn't report these changes at all.
+    ///
+    /// This can't be changed during runtime. Off by default.
+    pub fn with_fsevent_watch_root(mut self, fsevent_watch_root: bool) -> Self {
+        self.fsevent_watch_root = fsevent_watch_root;
+        self
+    }
+
+    /// Returns current setting
+    pub fn fsevent_watch_root(&self) -> bool {
+        self.fsevent_watch_root
+    }
+
+    /// For all backends.
+    ///
+    /// Tag every event with the [`WatcherKind`](crate::WatcherKind) of the watcher that emitted
+    /// it, available through [`Event::source_kind`](crate::Event::source_kind). This allows
+    /// telling events apart when several watchers share an event handler.
+    ///
+    /// This can't be changed during runtime. Off by default.
+    pub fn with_event_source_kind(mut self, event_source_kind: bool) -> Self {
+        self.event_source_kind = event_source_kind;
+        self
+    }
+
+    /// Returns current setting
+    pub fn event_source_kind(&self) -> bool {
+        self.event_source_kind
+    }
+
+    /// For the [`KqueueWatcher`](crate::KqueueWatcher) backend.
+    ///
+    /// kqueue only reports that a directory was written to, not which entries changed. With
+    /// this option the entries of every watched directory are remembered and compared on each
+    /// write, and `Create` and `Remove` events are emitted for the added and removed entries,
+    /// similar to the [`PollWatcher`](crate::PollWatcher). This costs a directory listing for
+    /// every write and memory for the entries of all watched directories.
+    ///
+    /// This can't be changed during runtime. Off by default.
+    pub fn with_kqueue_diff_directories(mut self, kqueue_diff_directories: bool) -> Self {
+        self.kqueue_diff_directories = kqueue_diff_directories;
+        self
+    }
+
+    /// Returns current setting
+    pub fn kqueue_diff_directories(&self) -> bool {
+        self.kqueue_diff_directories
+    }
+
+    /// For all backends.
+    ///
+    /// Number every event with a strictly increasing sequence number, available through
+    /// [`Event::seq`](crate::Event::seq). The numbers start at 0 for every watcher and reflect
+    /// the order in which the watcher emitted the events, so it can be restored after the events
+    /// were passed through several channels or threads. The
+    /// [`HybridWatcher`](crate::HybridWatcher) numbers native and polled events separately.
+    ///
+    /// This can't be changed during runtime. Off by default.
+    pub fn with_event_sequence_numbers(mut self, event_sequence_numbers: bool) -> Self {
+        self.event_sequence_numbers = event_sequence_numbers;
+        self
+    }
+
+    /// Returns current setting
+    pub fn event_sequence_numbers(&self) -> bool {
+        self.event_sequence_numbers
+    }
+
+    /// For all backends.
+    ///
+    /// Keep the events emitted while the watcher is [paused](crate::Watcher::pause) and deliver
+    /// them on [resume](crate::Watcher::resume). By default they are discarded and a single
+    /// rescan event is delivered on resume instead. Buffered events are held in memory without
+    /// a limit. Their [timestamps](Config::with_event_timestamps) are taken when they are
+    /// emitted, not on resume.
+    ///
+    /// This can't be changed during runtime. Off by default.
+    pub fn with_buffer_paused_events(mut self, buffer_paused_events: bool) -> Self {
+        self.buffer_paused_events = buffer_paused_events;
+        self
+    }
+
+    /// Returns current setting
+    pub fn buffer_paused_events(&self) -> bool {
+        self.buffer_paused_events
+    }
+
+    /// For the [`INotifyWatcher`](crate::INotifyWatcher) and
+    /// [`ReadDirectoryChangesWatcher`](crate::ReadDirectoryChangesWatcher) backends.
+    ///
+    /// Attach the ID of the affected file, i.e. its device and inode number, to every event,
+    /// available through [`Event::file_id`](crate::Event::file_id). This allows matching
+    /// renames across directories without reading the IDs separately. The ID is read with an
+    /// extra `stat` call per event, so it is missing if the file no longer exists, e.g. for
+    /// remove events. For renames, the ID is read from the new path.
+    ///
+    /// On Windows, the events are read with `ReadDirectoryChangesExW` instead, which reports the
+    /// file ID of every entry, including removed files, as well as its last modification time,
+    /// available through [`Event::modified`](crate::Event::modified). This requires Windows 10
+    /// version 1709 or later, see also [`Config::with_standard_windows_reader`].
+    ///
+    /// This can't be changed during runtime. Off by default.
+    pub fn with_event_file_ids(mut self, event_file_ids: bool) -> Self {
+        self.event_file_ids = event_file_ids;
+        self
+    }
+
+    /// Returns current setting
+    pub fn event_file_ids(&self) -> bool {
+        self.event_file_ids
+    }
+
+    /// For the [`ReadDirectoryChangesWatcher`](crate::ReadDirectoryChangesWatcher) backend.
+    ///
+    /// Always read events with the standard `ReadDirectoryChangesW`, even if
+    /// [`Config::with_event_file_ids`] asks for the extended `ReadDirectoryChangesExW` reader.
+    /// This is an escape hatch for environments where the extended reader misbehaves, e.g. Wine or
+    /// some filesystem drivers. Events then carry no file IDs and modification times. Where the
+    /// extended reader isn't supported at all, the standard one is used anyway.
+    ///
+    /// This can't be changed during runtime. Off by default.
+    pub fn with_standard_windows_reader(mut self, standard_windows_reader: bool) -> Self {
+        self.standard_windows_reader = standard_windows_reader;
+        self
+    }
+
+    /// Returns current setting
+    pub fn standard_windows_reader(&self) -> bool {
+        self.standard_windows_reader
+    }
+
+    /// For the [`FsEventWatcher`](crate::FsEventWatcher) backend.
+    ///
+    /// Drop a `Create` event that is directly followed by a `Modify(Name)` or `Remove` event for
+    /// the same path within the events FSEvents delivers at once. FSEvents coalesces the history
+    /// of a path into its flags, so a path that was created at some point keeps reporting the
+    /// created flag with every later change, and a rename or removal is preceded by a spurious
+    /// `Create` event. With this setting, the output matches the other backends more closely.
+    ///
+    /// A file that actually is created and renamed or removed right away is then only reported
+    /// by the latter event.
+    ///
+    /// This can't be changed during runtime. Off by default.
+    pub fn with_fsevent_drop_leading_create(mut self, fsevent_drop_leading_create: bool) -> Self {
+        self.fsevent_drop_leading_create = fsevent_drop_leading_create;
+        self
+    }
+
+    /// Returns current setting
+    pub fn fsevent_drop_leading_create(&self) -> bool {
+        self.fsevent_drop_leading_create
+    }
+
+    /// For the [`INotifyWatcher`](crate::INotifyWatcher) backend.
+    ///
+    /// Attach the ID of the device holding the affected file to every event, available through
+    /// [`Event::device_id`](crate::Event::device_id). This tells apart files on different
+    /// filesystems below the same watched directory, e.g. behind bind mounts. The device is read
+    /// once when a directory is watched, so events carry the device of their directory's
+    /// filesystem.
+    ///
+    /// This can't be changed during runtime. Off by default.
+    pub fn with_event_device_ids(mut self, event_device_ids: bool) -> Self {
+        self.event_device_ids = event_device_ids;
+        self
+    }
+
+    /// Returns current setting
+    pub fn event_device_ids(&self) -> bool {
+        self.event_device_ids
+    }
+
+    /// For all backends.
+    ///
+    /// Report files moved to the trash, or recycle bin, as `Remove(Other)` events with the info
+    /// `trash`, with the original path of the file. Such moves are otherwise reported as renames
+    /// or, when the trash isn't watched, as removals. Detection is best-effort: a rename is
+    /// recognized by its destination, a removal or a rename without destination by looking for
+    /// the file in the trash of the current user.
+    ///
+    /// The lookup runs on the backend's thread, for each removal and rename without destination.
+    /// It reads the metadata of the path and of the trash, and the trash's record for a file of
+    /// the same name. The listing of the trash is only needed when the trash renamed the file,
+    /// and is read at most once a second. On Windows, the recycle bin is listed again whenever it
+    /// changed, reading only the new records. With many such events, e.g. when deleting a large
+    /// tree, this slows down the backend and makes an overflow of its event queue more likely.
+    ///
+    /// This can't be changed during runtime. Off by default.
+    pub fn with_trash_detection(mut self, trash_detection: bool) -> Self {
+        self.trash_detection = trash_detection;
+        self
+    }
+
+    /// Returns current setting
+    pub fn trash_detection(&self) -> bool {
+        self.trash_detection
+    }
+
+    /// For the [`INotifyWatcher`](crate::INotifyWatcher) backend.
+    ///
+    /// Watch a symbolic link passed to [`Watcher::watch`](crate::Watcher::watch) as the link
+    /// itself instead of its target. Changes of the target aren't reported then, but the link
+    /// being removed or replaced, e.g. when it is repointed, is. A link watched this way is never
+    /// watched recursively. Symbolic links found below a recursively watched directory are still
+    /// handled according to [`with_follow_symlinks`](Config::with_follow_symlinks).
+    ///
+    /// This can't be changed during runtime. Off by default.
+    pub fn with_watch_symlinks_as_links(mut self, watch_symlinks_as_links: bool) -> Self {
+        self.watch_symlinks_as_links = watch_symlinks_as_links;
+        self
+    }
+
+    /// Returns current setting
+    pub fn watch_symlinks_as_links(&self) -> bool {
+        self.watch_symlinks_as_links
+    }
+
+    /// For all backends.
+    ///
+    /// Hold back the `Create` event of a file until its size and modification time stayed the
+    /// same for `min_age`, so a file that is still being written isn't reported yet. Other events
+    /// of the file are dropped until then. See [`Settle`](crate::Settle) for details.
+    ///
+    /// This can't be changed during runtime. Off by default.
+    pub fn with_min_create_age(mut self, min_age: Duration) -> Self {
+        self.min_create_age = Some(min_age);
+        self
+    }
+
+    /// Returns current setting
+    pub fn min_create_age(&self) -> Option<Duration> {
+        self.min_create_age
+    }
+
+    /// For the [`INotifyWatcher`](crate::INotifyWatcher) backend.
+    ///
+    /// Report a recursively removed directory with a single `Remove` event. inotify reports the
+    /// removal of every file and directory below it as well, these events are dropped if they are
+    /// read together with the removal of the directory. Coalescing works within a single read of
+    /// the inotify queue only: the removal of a large tree spans several reads, and the removals
+    /// read before the one of the directory are still reported.
+    ///
+    /// This can't be changed during runtime. Off by default.
+    pub fn with_coalesce_tree_removal(mut self, coalesce_tree_removal: bool) -> Self {
+        self.coalesce_tree_removal = coalesce_tree_removal;
+        self
+    }
+
+    /// Returns current setting
+    pub fn coalesce_tree_removal(&self) -> bool {
+        self.coalesce_tree_removal
+    }
+
+    /// For the [ReadDirectoryChangesWatcher](crate::ReadDirectoryChangesWatcher) backend.
+    ///
+    /// Buffer up to `capacity` events between reading them and passing them to the event handler,
+    /// which is then called from a separate thread. A slow event handler doesn't hold up reading
+    /// the events, so the kernel buffer doesn't overflow as easily. If the buffer itself is full,
+    /// events are dropped and an event with the [`Rescan`](crate::event::Flag::Rescan) flag is
+    /// emitted once there is room again, unlike with [`Config::with_channel_capacity`], which
+    /// holds up the backend instead.
+    ///
+    /// Must be at least 1, creating the watcher fails with
+    /// [ErrorKind::InvalidConfig](crate::ErrorKind::InvalidConfig) otherwise.
+    ///
+    /// This can't be changed during runtime. Off by default, the event handler is called while
+    /// the events are read.
+    pub fn with_event_buffer_capacity(mut self, capacity: usize) -> Self {
+        self.event_buffer_capacity = Some(capacity);
+        self
+    }
+
+    /// Returns current setting
+    pub fn event_buffer_capacity(&self) -> Option<usize> {
+        self.event_buffer_capacity
+    }
+
+    /// For the [`FsEventWatcher`](crate::FsEventWatcher) backend.
+    ///
+    /// Scope the stream to the device holding the watched paths, by creating it with
+    /// `FSEventStreamCreateRelativeToDevice`. The stream then isn't woken up by changes on other
+    /// volumes, which helps when watching e.g. an external drive. If the watched paths are on
+    /// different devices, a regular stream is used.
+    ///
+    /// This can't be changed during runtime. Off by default.
+    pub fn with_fsevent_device_stream(mut self, fsevent_device_stream: bool) -> Self {
+        self.fsevent_device_stream = fsevent_device_stream;
+        self
+    }
+
+    /// Returns current setting
+    pub fn fsevent_device_stream(&self) -> bool {
+        self.fsevent_device_stream
+    }
+
+    /// For all backends.
+    ///
+    /// Only deliver events of the given `kinds`, other events are dropped before they reach the
+    /// event handler. Errors and events of kind `Any` or `Other`, e.g. rescan requests, are
+    /// always delivered. The [`INotifyWatcher`](crate::INotifyWatcher) and
+    /// [`ReadDirectoryChangesWatcher`](crate::ReadDirectoryChangesWatcher) backends also narrow
+    /// what they ask the operating system for, so unwanted changes don't wake them up.
+    ///
+    /// ```
+    /// # use notify::{Config, EventKindMask};
+    /// let config = Config::default().with_event_kinds(&[EventKindMask::Create, EventKindMask::Remove]);
+    /// ```
+    ///
+    /// This can't be changed during runtime. Off by default.
+    pub fn with_event_kinds(mut self, kinds: &[EventKindMask]) -> Self {
+        self.event_kinds = Some(kinds.iter().fold(0, |mask, kind| mask | kind.bit()));
+        self
+    }
+
+    /// Returns current setting
+    pub fn event_kinds(&self) -> Option<Vec<EventKindMask>> {
+        self.event_kinds.map(|mask| {
+            EventKindMask::ALL
+                .into_iter()
+                .filter(|kind| mask & kind.bit() != 0)
+                .collect()
+        })
+    }
+
+    /// For all backends.
+    ///
+    /// Deliver events to the event handler from a dedicated thread, through a queue holding up to
+    /// `capacity` events. Once the queue is full, the backend waits for the event handler, so a
+    /// slow handler bounds the memory used instead of letting events pile up; the operating
+    /// system's own queue may overflow meanwhile. A `capacity` of 0 makes the queue unbounded,
+    /// so the backend never waits. [`Watcher::stop`](crate::Watcher::stop) waits for the queued
+    /// events to be delivered.
+    ///
+    /// This can't be changed during runtime. Off by default, the event handler is called from
+    /// the backend's thread.
+    pub fn with_channel_capacity(mut self, capacity: usize) -> Self {
+        self.channel_capacity = Some(capacity);
+        self
+    }
+
+    /// Returns current setting
+    pub fn channel_capacity(&self) -> Option<usize> {
+        self.channel_capacity
+    }
+
+    /// For all backends.
+    ///
+    /// Whether events are delivered for hidden files, see [`HiddenFiles`]. Only the last
+    /// component of the paths is considered, so files in hidden directories are not hidden
+    /// themselves. The [`PollWatcher`](crate::PollWatcher) doesn't track excluded files at all.
+    ///
+    /// This can't be changed during runtime. All files are included by default.
+    pub fn with_hidden_files(mut self, hidden_files: HiddenFiles) -> Self {
+        self.hidden_files = hidden_files;
+        self
+    }
+
+    /// Returns current setting
+    pub fn hidden_files(&self) -> HiddenFiles {
+        self.hidden_files
+    }
+
+    /// For the [`INotifyWatcher`](crate::INotifyWatcher) backend.
+    ///
+    /// Add at most `max_watches` watches per `interval` for directories created below a
+    /// recursively watched path. Watching a large tree that appeared at once, e.g. an extracted
+    /// archive, then doesn't hold up the event loop or use up the watch limit. Once the limit is
+    /// reached, no further watches are added in that interval and an
+    /// [`EventKind::Other`](crate::EventKind::Other) event with the
+    /// [`Rescan`](crate::event::Flag::Rescan) flag and the info `auto-watch-limit` is emitted for
+    /// each directory that isn't watched completely, so it can be handled otherwise.
+    ///
+    /// This can't be changed during runtime. Off by default.
+    pub fn with_auto_watch_limit(mut self, max_watches: usize, interval: Duration) -> Self {
+        self.auto_watch_limit = Some((max_watches, interval));
+        self
+    }
+
+    /// Returns current setting
+    pub fn auto_watch_limit(&self) -> Option<(usize, Duration)> {
+        self.auto_watch_limit
+    }
+
+    /// For all backends.
+    ///
+    /// Spell the part of event paths matching a watched path the way the watched path was passed
+    /// to [`Watcher::watch`](crate::Watcher::watch). On case-insensitive filesystems, some
+    /// backends report paths in the case stored on disk, so events for `/Users/me/Project` may
+    /// arrive as `/users/me/project/file` when watching it as `/Users/me/Project`. Paths on
+    /// case-sensitive filesystems are left alone.
+    ///
+    /// This can't be changed during runtime. Off by default.
+    pub fn with_normalize_path_case(mut self, normalize_path_case: bool) -> Self {
+        self.normalize_path_case = normalize_path_case;
+        self
+    }
+
+    /// Returns current setting
+    pub fn normalize_path_case(&self) -> bool {
+        self.normalize_path_case
+    }
+
+    /// For all backends.
+    ///
+    /// Report [`CreateKind::File`](crate::event::CreateKind::File) or
+    /// [`CreateKind::Folder`](crate::event::CreateKind::Folder) instead of
+    /// [`CreateKind::Any`](crate::event::CreateKind::Any) for created paths. Backends that can't
+    /// tell from the change itself, e.g. the
+    /// [`ReadDirectoryChangesWatcher`](crate::ReadDirectoryChangesWatcher) and the
+    /// [`PollWatcher`](crate::PollWatcher), then look up the type of the path, which costs a
+    /// `stat` call for the former. Paths that are gone by then are reported as files.
+    ///
+    /// This can't be changed during runtime. Off by default.
+    pub fn with_precise_create_kinds(mut self, precise_create_kinds: bool) -> Self {
+        self.precise_create_kinds = precise_create_kinds;
+        self
+    }
+
+    /// Returns current setting
+    pub fn precise_create_kinds(&self) -> bool {
+        self.precise_create_kinds
+    }
+
+    /// For the [`INotifyWatcher`](crate::INotifyWatcher) backend.
+    ///
+    /// Tell apart the attribute changes inotify reports alike, e.g. to distinguish changes of
+    /// extended attributes from changes of the permissions. The attributes of a changed path are
+    /// looked up and compared to the ones seen before, which costs a `stat` call per change:
+    /// changed permissions, ownership, write or access times are reported as such, an otherwise
+    /// unchanged path as [`MetadataKind::Extended`](crate::event::MetadataKind::Extended). The
+    /// first change of a path that was neither watched itself nor created while watched is
+    /// reported as [`MetadataKind::Any`](crate::event::MetadataKind::Any), as its previous
+    /// attributes are unknown.
+    ///
+    /// This can't be changed during runtime. Off by default.
+    pub fn with_precise_metadata_kinds(mut self, precise_metadata_kinds: bool) -> Self {
+        self.precise_metadata_kinds = precise_metadata_kinds;
+        self
+    }
+
+    /// Returns current setting
+    pub fn precise_metadata_kinds(&self) -> bool {
+        self.precise_metadata_kinds
+    }
+
+    /// For the [`INotifyWatcher`](crate::INotifyWatcher) and [`PollWatcher`](crate::PollWatcher)
+    /// backends.
+    ///
+    /// Descend at most `max_depth` directory levels below a recursively watched path, as a
+    /// safety bound against pathologically deep trees. Directories beyond it are reported with
+    /// an [`EventKind::Other`](crate::EventKind::Other) event with the info `max-depth-exceeded`,
+    /// see [`Event::is_max_depth_exceeded`](crate::Event::is_max_depth_exceeded), and their
+    /// contents are not watched. The [`INotifyWatcher`](crate::INotifyWatcher) reports them when
+    /// the watch is set up and when they are created later on, the
+    /// [`PollWatcher`](crate::PollWatcher) when the watch is set up.
+    ///
+    /// Unlike [`Traversal::with_max_depth`](crate::Traversal::with_max_depth), which prunes the
+    /// watched tree silently, exceeding this bound is reported.
+    ///
+    /// This can't be changed during runtime. Off by default.
+    pub fn with_max_watch_depth(mut self, max_depth: usize) -> Self {
+        self.max_watch_depth = Some(max_depth);
+        self
+    }
+
+    /// Returns current setting
+    pub fn max_watch_depth(&self) -> Option<usize> {
+        self.max_watch_depth
+    }
+
+    /// Whether events of the category `kind` are delivered, see [Config::with_event_kinds].
+    pub(crate) fn allows_kind(&self, kind: EventKindMask) -> bool {
+        self.event_kinds.map_or(true, |mask| mask & kind.bit() != 0)
+    }
+
+    /// Whether an event of `kind` is delivered, see [Config::with_event_kinds].
+    pub(crate) fn allows_event(&self, kind: &crate::EventKind) -> bool {
+        match EventKindMask::ALL
+            .into_iter()
+            .find(|mask| mask.matches(kind))
+        {
+            Some(mask) => self.allows_kind(mask),
+            None => true,
+        }
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             poll_interval: Some(Duration::from_secs(30)),
-            compare_contents: false,
+            compare: CompareMode::MetadataOnly,
             follow_symlinks: true,
+            report_self_events: true,
+            prefer_close_write: None,
+            wait_granularity: Duration::from_millis(100),
+            max_tracked_paths: None,
+            rename_pair_window: None,
+            event_timestamps: false,
+            report_dir_modify_events: true,
+            fsevent_watch_root: false,
+            event_source_kind: false,
+            kqueue_diff_directories: false,
+            event_sequence_numbers: false,
+            buffer_paused_events: false,
+            event_file_ids: false,
+            standard_windows_reader: false,
+            fsevent_drop_leading_create: false,
+            event_device_ids: false,
+            trash_detection: false,
+            watch_symlinks_as_links: false,
+            min_create_age: None,
+            coalesce_tree_removal: false,
+            event_buffer_capacity: None,
+            fsevent_device_stream: false,
+            event_kinds: None,
+            channel_capacity: None,
+            hidden_files: HiddenFiles::Include,
+            auto_watch_limit: None,
+            normalize_path_case: false,
+            precise_create_kinds: false,
+            precise_metadata_kinds: false,
+            max_watch_depth: None,
         }
     }
 }
@@ -0,0 +1,366 @@
+//! Delivering events from a dedicated thread through a queue
+
+use crate::event::{EventKind, Flag};
+use crate::pause::Flush;
+use crate::{Config, Error, Event, EventHandler, Result};
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread;
+
+/// Wraps `event_handler` in a queue to a dispatch thread if enabled in the `config`, see
+/// [`Config::with_channel_capacity`].
+pub(crate) fn from_config<F: EventHandler>(
+    event_handler: F,
+    config: &Config,
+) -> Result<DispatchHandler<F>> {
+    Ok(match config.channel_capacity() {
+        Some(capacity) => DispatchHandler::Queued(Dispatcher::start(
+            "notify-rs dispatch loop",
+            (capacity > 0).then_some(capacity),
+            Overflow::Wait,
+            event_handler,
+        )?),
+        None => DispatchHandler::Direct(event_handler),
+    })
+}
+
+/// What happens to events queued while the queue is full
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Overflow {
+    /// The backend waits for room, see [`Config::with_channel_capacity`]
+    Wait,
+    /// The events are dropped and an event with the [`Rescan`](Flag::Rescan) flag is queued once
+    /// there is room again, see [`Config::with_event_buffer_capacity`]
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    Rescan,
+}
+
+/// A thread passing the events of a [`Queue`] to an event handler.
+///
+/// Dropping the dispatcher closes the queue, the thread ends once it passed on the queued events.
+pub(crate) struct Dispatcher {
+    queue: Queue,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+struct State {
+    batches: VecDeque<Vec<Result<Event>>>,
+    /// Number of queued events
+    len: usize,
+    /// Maximum number of queued events, unbounded if `None`
+    capacity: Option<usize>,
+    overflow: Overflow,
+    /// Events were dropped since the last rescan event
+    overflowed: bool,
+    /// The thread is passing on a batch
+    busy: bool,
+    /// No more events are queued
+    closed: bool,
+    /// The thread ended
+    finished: bool,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    /// Notified whenever the state changes
+    changed: Condvar,
+}
+
+/// [`EventHandler`] queueing the events for a [`Dispatcher`].
+#[derive(Clone)]
+pub(crate) struct Queue(Arc<Shared>);
+
+impl Dispatcher {
+    /// Starts the thread named `name`, passing the queued events to `event_handler`.
+    pub(crate) fn start<F: EventHandler>(
+        name: &str,
+        capacity: Option<usize>,
+        overflow: Overflow,
+        mut event_handler: F,
+    ) -> Result<Self> {
+        let queue = Queue(Arc::new(Shared {
+            state: Mutex::new(State {
+                batches: VecDeque::new(),
+                len: 0,
+                capacity,
+                overflow,
+                overflowed: false,
+                busy: false,
+                closed: false,
+                finished: false,
+            }),
+            changed: Condvar::new(),
+        }));
+        let shared = queue.clone();
+        let thread = thread::Builder::new()
+            .name(name.to_string())
+            .spawn(move || {
+                if let Err(error) = Error::catch_panic(|| shared.drain(&mut event_handler)) {
+                    event_handler.handle_event(Err(error));
+                }
+                shared.lock().finished = true;
+                shared.0.changed.notify_all();
+            })?;
+        Ok(Self {
+            queue,
+            thread: Some(thread),
+        })
+    }
+
+    /// Returns the [`EventHandler`] queueing the events.
+    pub(crate) fn queue(&self) -> Queue {
+        self.queue.clone()
+    }
+
+    /// Waits for the queued events to be passed on and stops the thread.
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    pub(crate) fn close(mut self) {
+        self.queue.close();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Dispatcher {
+    fn drop(&mut self) {
+        self.queue.close();
+    }
+}
+
+impl Queue {
+    /// Locks the state, the event handler isn't called while it is locked.
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.0.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn wait<'a>(&self, guard: MutexGuard<'a, State>) -> MutexGuard<'a, State> {
+        self.0
+            .changed
+            .wait(guard)
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn close(&self) {
+        self.lock().closed = true;
+        self.0.changed.notify_all();
+    }
+
+    /// Waits until the events queued so far were passed on.
+    pub(crate) fn flush(&self) {
+        let mut state = self.lock();
+        while !state.finished && (state.len > 0 || state.busy) {
+            state = self.wait(state);
+        }
+    }
+
+    /// Passes the queued events to `event_handler` until the queue is closed and empty.
+    fn drain<F: EventHandler>(&self, event_handler: &mut F) {
+        let mut state = self.lock();
+        loop {
+            match state.batches.pop_front() {
+                Some(batch) => {
+                    state.len -= batch.len();
+                    state.busy = true;
+                    drop(state);
+                    self.0.changed.notify_all();
+                    event_handler.handle_events(batch);
+                    state = self.lock();
+                    state.busy = false;
+                    self.0.changed.notify_all();
+                }
+                None if state.closed => return,
+                None => state = self.wait(state),
+            }
+        }
+    }
+}
+
+impl EventHandler for Queue {
+    fn handle_event(&mut self, event: Result<Event>) {
+        self.handle_events(vec![event]);
+    }
+
+    fn handle_events(&mut self, mut events: Vec<Result<Event>>) {
+        let mut state = self.lock();
+        if let Some(capacity) = state.capacity {
+            match state.overflow {
+                // a batch larger than the queue waits for the queue to be empty
+                Overflow::Wait => {
+                    while !state.finished && state.len > 0 && state.len + events.len() > capacity {
+                        state = self.wait(state);
+                    }
+                }
+                Overflow::Rescan => {
+                    if state.overflowed && state.len < capacity {
+                        state.overflowed = false;
+                        state.len += 1;
+                        let rescan = Event::new(EventKind::Other).set_flag(Flag::Rescan);
+                        state.batches.push_back(vec![Ok(rescan)]);
+                    }
+                    let room = capacity.saturating_sub(state.len);
+                    if events.len() > room {
+                        events.truncate(room);
+                        state.overflowed = true;
+                    }
+                }
+            }
+        }
+        // the thread is gone if the event handler panicked
+        if !events.is_empty() && !state.finished {
+            state.len += events.len();
+            state.batches.push_back(events);
+            self.0.changed.notify_all();
+        }
+    }
+}
+
+/// [`EventHandler`] created by [`from_config`].
+pub(crate) enum DispatchHandler<F> {
+    Direct(F),
+    Queued(Dispatcher),
+}
+
+#[cfg_attr(
+    not(any(target_os = "linux", target_os = "android", target_os = "windows")),
+    allow(dead_code)
+)]
+impl<F> DispatchHandler<F> {
+    /// Returns a [`Flush`] waiting for the queued events to be delivered, if enabled.
+    pub(crate) fn flush(&self) -> Option<Flush> {
+        match self {
+            DispatchHandler::Direct(_) => None,
+            DispatchHandler::Queued(dispatcher) => {
+                let queue = dispatcher.queue();
+                Some(Box::new(move || queue.flush()))
+            }
+        }
+    }
+}
+
+impl<F: EventHandler> EventHandler for DispatchHandler<F> {
+    fn handle_event(&mut self, event: Result<Event>) {
+        match self {
+            DispatchHandler::Direct(event_handler) => event_handler.handle_event(event),
+            DispatchHandler::Queued(dispatcher) => dispatcher.queue.handle_event(event),
+        }
+    }
+
+    fn handle_events(&mut self, events: Vec<Result<Event>>) {
+        match self {
+            DispatchHandler::Direct(event_handler) => event_handler.handle_events(events),
+            DispatchHandler::Queued(dispatcher) => dispatcher.queue.handle_events(events),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    fn event(seq: u64) -> Result<Event> {
+        Ok(Event::default().set_seq(seq))
+    }
+
+    /// An event handler waiting for a permit before handling each event.
+    fn gated(
+        capacity: usize,
+    ) -> (
+        DispatchHandler<impl EventHandler>,
+        mpsc::Sender<()>,
+        mpsc::Receiver<u64>,
+    ) {
+        let (permit_tx, permit_rx) = mpsc::channel::<()>();
+        let (out_tx, out_rx) = mpsc::channel();
+        let event_handler = move |res: Result<Event>| {
+            permit_rx.recv().unwrap();
+            out_tx.send(res.unwrap().seq().unwrap()).unwrap();
+        };
+        let config = Config::default().with_channel_capacity(capacity);
+        let handler = from_config(event_handler, &config).unwrap();
+        (handler, permit_tx, out_rx)
+    }
+
+    #[test]
+    fn full_channel_blocks_the_backend() {
+        let (mut handler, permit_tx, out_rx) = gated(1);
+        let (sent_tx, sent_rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for seq in 0..3 {
+                handler.handle_event(event(seq));
+                sent_tx.send(seq).unwrap();
+            }
+        });
+
+        // the first event is being handled, the second one fills the channel
+        assert_eq!(sent_rx.recv_timeout(Duration::from_secs(1)), Ok(0));
+        assert_eq!(sent_rx.recv_timeout(Duration::from_secs(1)), Ok(1));
+        assert!(sent_rx.recv_timeout(Duration::from_millis(100)).is_err());
+
+        for _ in 0..3 {
+            permit_tx.send(()).unwrap();
+        }
+        assert_eq!(sent_rx.recv_timeout(Duration::from_secs(1)), Ok(2));
+        let delivered: Vec<_> = out_rx.iter().take(3).collect();
+        assert_eq!(delivered, [0, 1, 2]);
+    }
+
+    #[test]
+    fn zero_capacity_is_unbounded() {
+        let (mut handler, permit_tx, out_rx) = gated(0);
+        for seq in 0..100 {
+            handler.handle_event(event(seq));
+        }
+        for _ in 0..100 {
+            permit_tx.send(()).unwrap();
+        }
+        assert_eq!(out_rx.iter().take(100).count(), 100);
+    }
+
+    #[test]
+    fn flush_waits_for_the_queued_events() {
+        let (mut handler, permit_tx, out_rx) = gated(0);
+        for seq in 0..10 {
+            handler.handle_event(event(seq));
+        }
+        for _ in 0..10 {
+            permit_tx.send(()).unwrap();
+        }
+        handler.flush().unwrap()();
+        assert_eq!(out_rx.try_iter().count(), 10);
+    }
+
+    #[test]
+    fn full_queue_emits_rescan() {
+        let (tx, rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let slow = move |event: Result<Event>| {
+            tx.send(event.unwrap()).unwrap();
+            let _ = release_rx.recv();
+        };
+        let dispatcher = Dispatcher::start("test", Some(2), Overflow::Rescan, slow).unwrap();
+        let mut queue = dispatcher.queue();
+        let event = |name: &str| Ok(Event::new(EventKind::Any).add_path(PathBuf::from(name)));
+
+        queue.handle_event(event("1"));
+        // the event handler is busy with the first event
+        assert_eq!(rx.recv().unwrap().paths, [PathBuf::from("1")]);
+        queue.handle_events(vec![event("2"), event("3"), event("4")]);
+        queue.handle_event(event("5"));
+        for _ in 0..3 {
+            release_tx.send(()).unwrap();
+        }
+        assert_eq!(rx.recv().unwrap().paths, [PathBuf::from("2")]);
+        assert_eq!(rx.recv().unwrap().paths, [PathBuf::from("3")]);
+
+        queue.handle_event(event("6"));
+        drop(release_tx);
+        dispatcher.close();
+        assert_eq!(rx.recv().unwrap().flag(), Some(Flag::Rescan));
+        assert_eq!(rx.recv().unwrap().paths, [PathBuf::from("6")]);
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -1,7 +1,9 @@
 //! Error types
 
 use crate::Config;
+use std::any::Any;
 use std::error::Error as StdError;
+use std::panic;
 use std::path::PathBuf;
 use std::result::Result as StdResult;
 use std::{self, fmt, io};
@@ -31,7 +33,30 @@ pub enum ErrorKind {
     InvalidConfig(Config),
 
     /// Can't watch (more) files, limit on the total number of inotify watches reached
+    ///
+    /// Also emitted by the [`PollWatcher`](crate::PollWatcher) when the configured
+    /// [maximum number of tracked paths](crate::Config::with_max_tracked_paths) is reached.
     MaxFilesWatch,
+
+    /// Can't watch (more) files, the process or the system ran out of file descriptors
+    ///
+    /// Emitted by the backends that need a file descriptor or handle per watch, i.e. kqueue and
+    /// Windows, and when creating an inotify instance. Raising the limit or switching to the
+    /// [`PollWatcher`](crate::PollWatcher) may help.
+    TooManyOpenFiles,
+
+    /// The path is on a filesystem that is known not to emit events, e.g. a network filesystem
+    ///
+    /// Reported through the event handler after the path was watched successfully, as the watch
+    /// itself works. Falling back to the [`PollWatcher`](crate::PollWatcher) may help. See also
+    /// [`Watcher::path_supports_events`](crate::Watcher::path_supports_events).
+    UnsupportedFilesystem,
+
+    /// A thread of the watcher panicked, with the panic message
+    ///
+    /// Reported through the event handler right before the thread ends, no more events are
+    /// delivered afterwards. The watcher should be recreated.
+    WatcherPanicked(String),
 }
 
 /// Notify error type.
@@ -81,10 +106,13 @@ impl Error {
         Self::new(ErrorKind::Io(err))
     }
 
-    /// Similar to [`Error::io`], but specifically handles [`io::ErrorKind::NotFound`].
+    /// Similar to [`Error::io`], but specifically handles [`io::ErrorKind::NotFound`] and
+    /// running out of file descriptors.
     pub fn io_watch(err: io::Error) -> Self {
         if err.kind() == io::ErrorKind::NotFound {
             Self::path_not_found()
+        } else if is_too_many_open_files(&err) {
+            Self::new(ErrorKind::TooManyOpenFiles)
         } else {
             Self::io(err)
         }
@@ -100,6 +128,31 @@ impl Error {
         Self::new(ErrorKind::WatchNotFound)
     }
 
+    /// Creates a new "unsupported filesystem" error.
+    pub fn unsupported_filesystem() -> Self {
+        Self::new(ErrorKind::UnsupportedFilesystem)
+    }
+
+    /// Creates a new "watcher panicked" error from the payload of a caught panic.
+    pub fn watcher_panicked(payload: Box<dyn Any + Send>) -> Self {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => match payload.downcast::<&str>() {
+                Ok(message) => message.to_string(),
+                Err(_) => "unknown panic".to_string(),
+            },
+        };
+        Self::new(ErrorKind::WatcherPanicked(message))
+    }
+
+    /// Runs `f`, returns a "watcher panicked" error if it panics.
+    ///
+    /// Used in the threads of a watcher, which report the error to the event handler before
+    /// they end.
+    pub(crate) fn catch_panic<F: FnOnce()>(f: F) -> StdResult<(), Self> {
+        panic::catch_unwind(panic::AssertUnwindSafe(f)).map_err(Self::watcher_panicked)
+    }
+
     /// Creates a new "invalid config" error from the given `Config`.
     pub fn invalid_config(config: &Config) -> Self {
         Self::new(ErrorKind::InvalidConfig(*config))
@@ -115,6 +168,9 @@ impl fmt::Display for Error {
             ErrorKind::Generic(ref err) => err.clone(),
             ErrorKind::Io(ref err) => err.to_string(),
             ErrorKind::MaxFilesWatch => "OS file watch limit reached.".into(),
+            ErrorKind::TooManyOpenFiles => "OS file descriptor limit reached.".into(),
+            ErrorKind::UnsupportedFilesystem => "The filesystem may not emit events.".into(),
+            ErrorKind::WatcherPanicked(ref msg) => format!("The watcher panicked: {}", msg),
         };
 
         if self.paths.is_empty() {
@@ -134,6 +190,24 @@ impl StdError for Error {
     }
 }
 
+/// Whether the error is caused by the process or the system running out of file descriptors.
+fn is_too_many_open_files(err: &io::Error) -> bool {
+    #[cfg(unix)]
+    {
+        matches!(err.raw_os_error(), Some(libc::EMFILE | libc::ENFILE))
+    }
+    #[cfg(windows)]
+    {
+        use windows_sys::Win32::Foundation::ERROR_TOO_MANY_OPEN_FILES;
+        err.raw_os_error() == Some(ERROR_TOO_MANY_OPEN_FILES as i32)
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = err;
+        false
+    }
+}
+
 impl From<io::Error> for Error {
     fn from(err: io::Error) -> Self {
         Error::io(err)
@@ -172,3 +246,17 @@ fn display_formatted_errors() {
         )
     );
 }
+
+#[cfg(any(unix, windows))]
+#[test]
+fn too_many_open_files() {
+    #[cfg(unix)]
+    let errors = [libc::EMFILE, libc::ENFILE];
+    #[cfg(windows)]
+    let errors = [windows_sys::Win32::Foundation::ERROR_TOO_MANY_OPEN_FILES as i32];
+
+    for code in errors {
+        let error = Error::io_watch(io::Error::from_raw_os_error(code));
+        assert!(matches!(error.kind, ErrorKind::TooManyOpenFiles));
+    }
+}
@@ -15,17 +15,27 @@
 #![allow(non_upper_case_globals, dead_code)]
 
 use crate::event::*;
-use crate::{unbounded, Config, Error, EventHandler, RecursiveMode, Result, Sender, Watcher};
+use crate::pause::PauseGate;
+use crate::stats::Counters;
+use crate::{
+    create_kind_of, unbounded, AttributeHandler, Config, Error, EventHandler, RecursiveMode,
+    Result, Sender, Watcher,
+};
+use crate::{dispatch, resync, settle};
 use fsevent_sys as fs;
 use fsevent_sys::core_foundation as cf;
 use std::collections::HashMap;
-use std::ffi::CStr;
+use std::ffi::{CStr, CString, OsStr};
 use std::fmt;
 use std::os::raw;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
 use std::ptr;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
 use std::thread;
+use std::time::Duration;
 
 bitflags::bitflags! {
   #[repr(C)]
@@ -65,8 +75,19 @@ pub struct FsEventWatcher {
     latency: cf::CFTimeInterval,
     flags: fs::FSEventStreamCreateFlags,
     event_handler: Arc<Mutex<dyn EventHandler>>,
+    gate: PauseGate,
+    follow_symlinks: bool,
     runloop: Option<(cf::CFRunLoopRef, thread::JoinHandle<()>)>,
     recursive_info: HashMap<PathBuf, bool>,
+    /// Watched files, mapped to the parent directory that is watched in their place
+    file_watches: HashMap<PathBuf, PathBuf>,
+    report_self_events: bool,
+    drop_leading_create: bool,
+    /// See [Config::with_precise_create_kinds]
+    precise_create_kinds: bool,
+    overflow_count: Arc<AtomicU64>,
+    /// Scope the stream to a device, see [Config::with_fsevent_device_stream]
+    device_stream: bool,
 }
 
 impl fmt::Debug for FsEventWatcher {
@@ -77,8 +98,15 @@ impl fmt::Debug for FsEventWatcher {
             .field("latency", &self.latency)
             .field("flags", &self.flags)
             .field("event_handler", &Arc::as_ptr(&self.event_handler))
+            .field("gate", &self.gate)
             .field("runloop", &self.runloop)
             .field("recursive_info", &self.recursive_info)
+            .field("file_watches", &self.file_watches)
+            .field("report_self_events", &self.report_self_events)
+            .field("drop_leading_create", &self.drop_leading_create)
+            .field("precise_create_kinds", &self.precise_create_kinds)
+            .field("overflow_count", &self.overflow_count)
+            .field("device_stream", &self.device_stream)
             .finish()
     }
 }
@@ -111,7 +139,13 @@ fn translate_flags(flags: StreamFlags, precise: bool) -> Vec<Event> {
     // however documentation on what those mean is scant, so we just pass them
     // through in the info attr field. The intent is clear enough, and the
     // additional information is provided if the user wants it.
-    if flags.contains(StreamFlags::MUST_SCAN_SUBDIRS) {
+    //
+    // The dropped flags are documented to come along with MustScanSubDirs, but
+    // as any of them means that changes below the path were missed, each one
+    // is reported as a rescan on its own.
+    if flags.intersects(
+        StreamFlags::MUST_SCAN_SUBDIRS | StreamFlags::USER_DROPPED | StreamFlags::KERNEL_DROPPED,
+    ) {
         let e = Event::new(EventKind::Other).set_flag(Flag::Rescan);
         evs.push(if flags.contains(StreamFlags::USER_DROPPED) {
             e.set_info("rescan: user dropped")
@@ -137,6 +171,7 @@ fn translate_flags(flags: StreamFlags, precise: bool) -> Vec<Event> {
             Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::From)))
                 .set_info("root changed"),
         );
+        evs.push(Event::new(EventKind::Other).set_info("watch-invalidated"));
     }
 
     // A path was mounted at the event path; we treat that as a create.
@@ -240,9 +275,46 @@ fn translate_flags(flags: StreamFlags, precise: bool) -> Vec<Event> {
     evs
 }
 
+/// Removes the `Create` events that are directly followed by a rename or removal of the same
+/// path, see [`Config::with_fsevent_drop_leading_create`].
+fn drop_leading_creates(events: Vec<Event>) -> Vec<Event> {
+    // whether the next event of a path is a rename or removal, filled back to front
+    let mut next_is_rename_or_remove: HashMap<PathBuf, bool> = HashMap::new();
+    let mut keep = vec![true; events.len()];
+
+    for (index, event) in events.iter().enumerate().rev() {
+        let Some(path) = event.paths.first() else {
+            continue;
+        };
+        if event.kind.is_create() && next_is_rename_or_remove.get(path) == Some(&true) {
+            log::trace!("dropping leading create event: {event:?}");
+            keep[index] = false;
+            continue;
+        }
+        let is_rename_or_remove = matches!(
+            event.kind,
+            EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_))
+        );
+        next_is_rename_or_remove.insert(path.clone(), is_rename_or_remove);
+    }
+
+    events
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(event, keep)| keep.then_some(event))
+        .collect()
+}
+
 struct StreamContextInfo {
     event_handler: Arc<Mutex<dyn EventHandler>>,
     recursive_info: HashMap<PathBuf, bool>,
+    report_self_events: bool,
+    drop_leading_create: bool,
+    precise_create_kinds: bool,
+    /// Number of times FSEvents dropped events so far
+    overflow_count: Arc<AtomicU64>,
+    /// Mount point of the device the stream is scoped to, its paths are relative to it
+    device_root: Option<PathBuf>,
 }
 
 // Free the context when the stream created by `FSEventStreamCreate` is released.
@@ -266,17 +338,36 @@ extern "C" {
 }
 
 impl FsEventWatcher {
-    fn from_event_handler(event_handler: Arc<Mutex<dyn EventHandler>>) -> Result<Self> {
+    fn from_event_handler(
+        event_handler: Box<dyn EventHandler>,
+        config: &Config,
+        counters: Arc<Counters>,
+    ) -> Result<Self> {
+        let gate = PauseGate::new(event_handler, config, counters);
+        let mut flags =
+            fs::kFSEventStreamCreateFlagFileEvents | fs::kFSEventStreamCreateFlagNoDefer;
+        if config.fsevent_watch_root() {
+            flags |= fs::kFSEventStreamCreateFlagWatchRoot;
+        }
+
         Ok(FsEventWatcher {
             paths: unsafe {
                 cf::CFArrayCreateMutable(cf::kCFAllocatorDefault, 0, &cf::kCFTypeArrayCallBacks)
             },
             since_when: fs::kFSEventStreamEventIdSinceNow,
             latency: 0.0,
-            flags: fs::kFSEventStreamCreateFlagFileEvents | fs::kFSEventStreamCreateFlagNoDefer,
-            event_handler,
+            flags,
+            event_handler: Arc::new(Mutex::new(gate.clone())),
+            gate,
+            follow_symlinks: config.follow_symlinks(),
             runloop: None,
             recursive_info: HashMap::new(),
+            file_watches: HashMap::new(),
+            report_self_events: config.report_self_events(),
+            drop_leading_create: config.fsevent_drop_leading_create(),
+            precise_create_kinds: config.precise_create_kinds(),
+            overflow_count: Arc::new(AtomicU64::new(0)),
+            device_stream: config.fsevent_device_stream(),
         })
     }
 
@@ -296,6 +387,34 @@ impl FsEventWatcher {
         result
     }
 
+    fn unwatch_prefix_inner(&mut self, prefix: &Path) -> Result<Vec<PathBuf>> {
+        let prefix = prefix.canonicalize().unwrap_or_else(|_| prefix.to_owned());
+        let mut roots: Vec<PathBuf> = self
+            .recursive_info
+            .keys()
+            .filter(|root| root.starts_with(&prefix))
+            .cloned()
+            .collect();
+        roots.sort();
+        if roots.is_empty() {
+            return Ok(roots);
+        }
+
+        // restart the stream only once for all paths
+        self.stop();
+        let mut result = Ok(());
+        for root in &roots {
+            // removes watched files from `file_watches` as well
+            let removed = self.remove_path(root);
+            if result.is_ok() {
+                result = removed;
+            }
+        }
+        // ignore return error: may be empty path list
+        let _ = self.run();
+        result.map(|()| roots)
+    }
+
     #[inline]
     fn is_running(&self) -> bool {
         self.runloop.is_some()
@@ -323,7 +442,15 @@ impl FsEventWatcher {
     }
 
     fn remove_path(&mut self, path: &Path) -> Result<()> {
-        let str_path = path.to_str().unwrap();
+        let p = if let Ok(canonicalized_path) = path.canonicalize() {
+            canonicalized_path
+        } else {
+            path.to_owned()
+        };
+        // a watched file is represented by its parent directory in the stream
+        let file_parent = self.file_watches.remove(&p);
+        let stream_path = file_parent.as_deref().unwrap_or(path);
+        let str_path = stream_path.to_str().unwrap();
         unsafe {
             let mut err: cf::CFErrorRef = ptr::null_mut();
             let cf_path = cf::str_path_to_cfstring_ref(str_path, &mut err);
@@ -339,6 +466,10 @@ impl FsEventWatcher {
                     == cf::kCFCompareEqualTo
                 {
                     to_remove.push(idx);
+                    // other watched files may share the same parent directory
+                    if file_parent.is_some() {
+                        break;
+                    }
                 }
             }
 
@@ -348,11 +479,6 @@ impl FsEventWatcher {
                 cf::CFArrayRemoveValueAtIndex(self.paths, *idx);
             }
         }
-        let p = if let Ok(canonicalized_path) = path.canonicalize() {
-            canonicalized_path
-        } else {
-            path.to_owned()
-        };
         match self.recursive_info.remove(&p) {
             Some(_) => Ok(()),
             None => Err(Error::watch_not_found()),
@@ -365,7 +491,15 @@ impl FsEventWatcher {
             return Err(Error::path_not_found().add_path(path.into()));
         }
         let canonical_path = path.to_path_buf().canonicalize()?;
-        let str_path = path.to_str().unwrap();
+        // FSEvents watches directories, so a file is watched through its parent directory and
+        // events are filtered down to the file in the callback
+        let file_parent = if path.is_dir() {
+            None
+        } else {
+            canonical_path.parent().map(Path::to_path_buf)
+        };
+        let stream_path = file_parent.as_deref().unwrap_or(path);
+        let str_path = stream_path.to_str().unwrap();
         unsafe {
             let mut err: cf::CFErrorRef = ptr::null_mut();
             let cf_path = cf::str_path_to_cfstring_ref(str_path, &mut err);
@@ -378,6 +512,9 @@ impl FsEventWatcher {
             cf::CFArrayAppendValue(self.paths, cf_path);
             cf::CFRelease(cf_path);
         }
+        if let Some(parent) = file_parent {
+            self.file_watches.insert(canonical_path.clone(), parent);
+        }
         self.recursive_info
             .insert(canonical_path, recursive_mode.is_recursive());
         Ok(())
@@ -389,6 +526,12 @@ impl FsEventWatcher {
             return Err(Error::path_not_found());
         }
 
+        let device = if self.device_stream {
+            self.stream_device()
+        } else {
+            None
+        };
+
         // We need to associate the stream context with our callback in order to propagate events
         // to the rest of the system. This will be owned by the stream, and will be freed when the
         // stream is closed. This means we will leak the context if we panic before reaching
@@ -396,6 +539,11 @@ impl FsEventWatcher {
         let context = Box::into_raw(Box::new(StreamContextInfo {
             event_handler: self.event_handler.clone(),
             recursive_info: self.recursive_info.clone(),
+            report_self_events: self.report_self_events,
+            drop_leading_create: self.drop_leading_create,
+            precise_create_kinds: self.precise_create_kinds,
+            overflow_count: self.overflow_count.clone(),
+            device_root: device.as_ref().map(|(_, mount_point)| mount_point.clone()),
         }));
 
         let stream_context = fs::FSEventStreamContext {
@@ -407,15 +555,32 @@ impl FsEventWatcher {
         };
 
         let stream = unsafe {
-            fs::FSEventStreamCreate(
-                cf::kCFAllocatorDefault,
-                callback,
-                &stream_context,
-                self.paths,
-                self.since_when,
-                self.latency,
-                self.flags,
-            )
+            match &device {
+                Some((device, mount_point)) => {
+                    let paths = self.device_relative_paths(mount_point);
+                    let stream = fs::FSEventStreamCreateRelativeToDevice(
+                        cf::kCFAllocatorDefault,
+                        callback,
+                        &stream_context,
+                        *device,
+                        paths,
+                        self.since_when,
+                        self.latency,
+                        self.flags,
+                    );
+                    cf::CFRelease(paths);
+                    stream
+                }
+                None => fs::FSEventStreamCreate(
+                    cf::kCFAllocatorDefault,
+                    callback,
+                    &stream_context,
+                    self.paths,
+                    self.since_when,
+                    self.latency,
+                    self.flags,
+                ),
+            }
         };
 
         // Wrapper to help send CFRef types across threads.
@@ -472,12 +637,61 @@ impl FsEventWatcher {
         Ok(())
     }
 
+    /// The device holding all watched paths and its mount point, if they are on the same device.
+    fn stream_device(&self) -> Option<(libc::dev_t, PathBuf)> {
+        let mut roots = self.stream_roots();
+        let first = roots.next()?;
+        let device = std::fs::metadata(first).ok()?.dev();
+        if !roots.all(|root| std::fs::metadata(root).is_ok_and(|m| m.dev() == device)) {
+            return None;
+        }
+        Some((device as libc::dev_t, mount_point(first)?))
+    }
+
+    /// The canonical paths watched by the stream, watched files are represented by their parent.
+    fn stream_roots(&self) -> impl Iterator<Item = &PathBuf> {
+        self.recursive_info
+            .keys()
+            .map(|path| self.file_watches.get(path).unwrap_or(path))
+    }
+
+    /// The paths watched by the stream relative to `mount_point`, to be released by the caller.
+    unsafe fn device_relative_paths(&self, mount_point: &Path) -> cf::CFMutableArrayRef {
+        let paths =
+            cf::CFArrayCreateMutable(cf::kCFAllocatorDefault, 0, &cf::kCFTypeArrayCallBacks);
+        for root in self.stream_roots() {
+            let relative = root.strip_prefix(mount_point).unwrap_or(root);
+            let Ok(c_path) = CString::new(relative.as_os_str().as_bytes()) else {
+                continue;
+            };
+            let cf_path = cf::CFStringCreateWithCString(
+                cf::kCFAllocatorDefault,
+                c_path.as_ptr(),
+                cf::kCFStringEncodingUTF8,
+            );
+            cf::CFArrayAppendValue(paths, cf_path);
+            cf::CFRelease(cf_path);
+        }
+        paths
+    }
+
     fn configure_raw_mode(&mut self, _config: Config, tx: Sender<Result<bool>>) {
         tx.send(Ok(false))
             .expect("configuration channel disconnect");
     }
 }
 
+/// The mount point of the filesystem holding `path`.
+fn mount_point(path: &Path) -> Option<PathBuf> {
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    let mount_point = unsafe { CStr::from_ptr(stat.f_mntonname.as_ptr()) };
+    Some(PathBuf::from(OsStr::from_bytes(mount_point.to_bytes())))
+}
+
 extern "C" fn callback(
     stream_ref: fs::FSEventStreamRef,
     info: *mut libc::c_void,
@@ -486,7 +700,8 @@ extern "C" fn callback(
     event_flags: *const fs::FSEventStreamEventFlags, // const FSEventStreamEventFlags eventFlags[]
     event_ids: *const fs::FSEventStreamEventId,      // const FSEventStreamEventId eventIds[]
 ) {
-    unsafe {
+    // unwinding into the run loop would abort the process
+    let res = Error::catch_panic(|| unsafe {
         callback_impl(
             stream_ref,
             info,
@@ -495,6 +710,12 @@ extern "C" fn callback(
             event_flags,
             event_ids,
         )
+    });
+    if let Err(error) = res {
+        let info = info as *const StreamContextInfo;
+        let event_handler = unsafe { &(*info).event_handler };
+        let mut event_handler = event_handler.lock().unwrap_or_else(PoisonError::into_inner);
+        event_handler.handle_event(Err(error));
     }
 }
 
@@ -509,12 +730,17 @@ unsafe fn callback_impl(
     let event_paths = event_paths as *const *const libc::c_char;
     let info = info as *const StreamContextInfo;
     let event_handler = &(*info).event_handler;
+    let mut events = Vec::new();
 
     for p in 0..num_events {
         let path = CStr::from_ptr(*event_paths.add(p))
             .to_str()
             .expect("Invalid UTF8 string.");
-        let path = PathBuf::from(path);
+        let path = match &(*info).device_root {
+            // paths of a device stream are relative to its mount point
+            Some(root) => root.join(path.trim_start_matches('/')),
+            None => PathBuf::from(path),
+        };
 
         let flag = *event_flags.add(p);
         let flag = StreamFlags::from_bits(flag).unwrap_or_else(|| {
@@ -542,35 +768,134 @@ unsafe fn callback_impl(
 
         log::trace!("FSEvent: path = `{}`, flag = {:?}", path.display(), flag);
 
-        for ev in translate_flags(flag, true).into_iter() {
+        let is_root = (*info).recursive_info.contains_key(&path);
+        let dropped = flag.intersects(StreamFlags::USER_DROPPED | StreamFlags::KERNEL_DROPPED);
+
+        for mut ev in translate_flags(flag, true).into_iter() {
+            // the watched path itself was removed or renamed
+            if is_root
+                && !(*info).report_self_events
+                && (matches!(
+                    ev.kind,
+                    EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_))
+                ) || ev.is_watch_invalidated())
+            {
+                continue;
+            }
+
+            if (*info).precise_create_kinds && ev.kind == EventKind::Create(CreateKind::Any) {
+                ev.kind = EventKind::Create(create_kind_of(&path));
+            }
+
+            if dropped && ev.need_rescan() {
+                let overflow_count = (*info).overflow_count.fetch_add(1, Ordering::Relaxed) + 1;
+                ev = ev.set_overflow_count(overflow_count);
+            }
+
             // TODO: precise
-            let ev = ev.add_path(path.clone());
-            let mut event_handler = event_handler.lock().expect("lock not to be poisoned");
-            event_handler.handle_event(Ok(ev));
+            events.push(ev.add_path(path.clone()));
         }
     }
+
+    if (*info).drop_leading_create {
+        events = drop_leading_creates(events);
+    }
+    if events.is_empty() {
+        return;
+    }
+    let mut event_handler = event_handler.lock().unwrap_or_else(PoisonError::into_inner);
+    event_handler.handle_events(events.into_iter().map(Ok).collect());
 }
 
 impl Watcher for FsEventWatcher {
     /// Create a new watcher.
-    fn new<F: EventHandler>(event_handler: F, _config: Config) -> Result<Self> {
-        Self::from_event_handler(Arc::new(Mutex::new(event_handler)))
+    fn new<F: EventHandler>(event_handler: F, config: Config) -> Result<Self> {
+        let counters = Arc::<Counters>::default();
+        let dispatch = dispatch::from_config(counters.handler(event_handler), &config)?;
+        Self::from_event_handler(
+            Box::new(AttributeHandler::new(
+                settle::from_config(dispatch, &config)?,
+                &config,
+                Self::kind(),
+            )),
+            &config,
+            counters,
+        )
     }
 
     fn watch(&mut self, path: &Path, recursive_mode: RecursiveMode) -> Result<()> {
-        self.watch_inner(path, recursive_mode)
+        self.watch_inner(path, recursive_mode)?;
+        self.gate.root_watched(path);
+        Ok(())
+    }
+
+    fn watch_many(&mut self, paths: &[(&Path, RecursiveMode)]) -> Vec<(PathBuf, Result<()>)> {
+        // restart the stream only once for all paths
+        self.stop();
+        let results = paths
+            .iter()
+            .map(|&(path, recursive_mode)| {
+                let res = self.append_path(path, recursive_mode);
+                if res.is_ok() {
+                    self.gate.root_watched(path);
+                }
+                (path.to_path_buf(), res)
+            })
+            .collect();
+        // ignore return error: may be empty path list
+        let _ = self.run();
+        results
     }
 
     fn unwatch(&mut self, path: &Path) -> Result<()> {
+        self.gate.root_unwatched(path, false);
         self.unwatch_inner(path)
     }
 
+    fn watched_paths(&self) -> Result<Vec<(PathBuf, RecursiveMode)>> {
+        Ok(self
+            .recursive_info
+            .iter()
+            .map(|(path, &is_recursive)| (path.clone(), RecursiveMode::from_bool(is_recursive)))
+            .collect())
+    }
+
+    fn unwatch_prefix(&mut self, prefix: &Path) -> Result<Vec<PathBuf>> {
+        self.gate.root_unwatched(prefix, true);
+        self.unwatch_prefix_inner(prefix)
+    }
+
+    fn expected_latency(&self) -> Duration {
+        Duration::from_secs_f64(self.latency)
+    }
+
     fn configure(&mut self, config: Config) -> Result<bool> {
         let (tx, rx) = unbounded();
         self.configure_raw_mode(config, tx);
         rx.recv()?
     }
 
+    fn pause(&mut self) -> Result<()> {
+        self.gate.pause();
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        self.gate.resume();
+        Ok(())
+    }
+
+    fn resync(&mut self) -> Result<()> {
+        let roots = self.watched_paths()?;
+        resync::resync(&roots, self.follow_symlinks, &mut self.gate);
+        Ok(())
+    }
+
+    fn stats(&self) -> crate::WatcherStats {
+        let watches = self.watched_paths().map_or(0, |paths| paths.len());
+        self.gate.stats(watches)
+    }
+
     fn kind() -> crate::WatcherKind {
         crate::WatcherKind::Fsevent
     }
@@ -620,3 +945,163 @@ fn test_steam_context_info_send_and_sync() {
     fn check_send<T: Send + Sync>() {}
     check_send::<StreamContextInfo>();
 }
+
+#[test]
+fn test_fsevent_watch_single_file() {
+    use super::*;
+    use std::time::Duration;
+
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("watched.txt");
+    let sibling = dir.path().join("sibling.txt");
+    std::fs::write(&file, b"").unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = FsEventWatcher::new(tx, Default::default()).unwrap();
+    watcher.watch(&file, RecursiveMode::NonRecursive).unwrap();
+    thread::sleep(Duration::from_millis(500));
+
+    std::fs::write(sibling, b"Lorem ipsum").unwrap();
+    std::fs::write(&file, b"Lorem ipsum").unwrap();
+
+    let canonical = file.canonicalize().unwrap();
+    let event = rx
+        .recv_timeout(Duration::from_secs(5))
+        .expect("no event received")
+        .unwrap();
+    assert_eq!(event.paths, vec![canonical]);
+
+    watcher.unwatch(&file).unwrap();
+    assert!(watcher.file_watches.is_empty());
+}
+
+#[test]
+fn test_fsevent_unwatch_prefix_with_files() {
+    use super::*;
+
+    let dir = tempfile::tempdir().unwrap();
+    let sub = dir.path().join("sub");
+    let file = sub.join("file.txt");
+    std::fs::create_dir(&sub).unwrap();
+    std::fs::write(&file, b"").unwrap();
+
+    let (tx, _rx) = std::sync::mpsc::channel();
+    let mut watcher = FsEventWatcher::new(tx, Default::default()).unwrap();
+    watcher.watch(&file, RecursiveMode::NonRecursive).unwrap();
+    watcher.watch(dir.path(), RecursiveMode::Recursive).unwrap();
+
+    // no match leaves the stream running as it is
+    let runloop = watcher.runloop.as_ref().map(|(runloop, _)| *runloop);
+    let other = tempfile::tempdir().unwrap();
+    assert!(watcher.unwatch_prefix(other.path()).unwrap().is_empty());
+    assert_eq!(
+        watcher.runloop.as_ref().map(|(runloop, _)| *runloop),
+        runloop
+    );
+
+    let removed = watcher.unwatch_prefix(&sub).unwrap();
+    assert_eq!(removed, vec![file.canonicalize().unwrap()]);
+    assert!(watcher.file_watches.is_empty());
+    assert_eq!(
+        watcher.watched_paths().unwrap(),
+        vec![(dir.path().canonicalize().unwrap(), RecursiveMode::Recursive)]
+    );
+}
+
+#[test]
+fn test_fsevent_device_stream() {
+    use super::*;
+    use std::time::Duration;
+
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("file.txt");
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let config = Config::default().with_fsevent_device_stream(true);
+    let mut watcher = FsEventWatcher::new(tx, config).unwrap();
+    watcher.watch(dir.path(), RecursiveMode::Recursive).unwrap();
+    assert!(watcher.stream_device().is_some());
+    thread::sleep(Duration::from_millis(500));
+
+    std::fs::write(&file, b"Lorem ipsum").unwrap();
+
+    let canonical = file.canonicalize().unwrap();
+    std::iter::from_fn(|| rx.recv_timeout(Duration::from_secs(5)).ok())
+        .map(|res| res.unwrap())
+        .find(|event| event.paths == [canonical.clone()])
+        .expect("no event received");
+}
+
+#[test]
+fn test_fsevent_watch_root() {
+    use super::*;
+    use std::time::Duration;
+
+    let dir = tempfile::tempdir().unwrap();
+    let watched = dir.path().join("watched");
+    std::fs::create_dir(&watched).unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let config = Config::default().with_fsevent_watch_root(true);
+    let mut watcher = FsEventWatcher::new(tx, config).unwrap();
+    watcher.watch(&watched, RecursiveMode::Recursive).unwrap();
+    thread::sleep(Duration::from_millis(500));
+
+    std::fs::rename(&watched, dir.path().join("moved")).unwrap();
+
+    std::iter::from_fn(|| rx.recv_timeout(Duration::from_secs(5)).ok())
+        .map(|res| res.unwrap())
+        .find(|event| event.info() == Some("root changed"))
+        .expect("no event received");
+}
+
+#[test]
+fn test_drop_leading_creates() {
+    let event = |kind, path: &str| Event::new(kind).add_path(PathBuf::from(path));
+    let create = EventKind::Create(CreateKind::File);
+    let rename = EventKind::Modify(ModifyKind::Name(RenameMode::Any));
+    let remove = EventKind::Remove(RemoveKind::File);
+    let modify = EventKind::Modify(ModifyKind::Data(DataChange::Content));
+
+    let events = vec![
+        event(create, "/renamed"),
+        event(rename, "/renamed"),
+        event(create, "/created"),
+        event(create, "/removed"),
+        event(modify, "/other"),
+        event(remove, "/removed"),
+        event(create, "/modified"),
+        event(modify, "/modified"),
+    ];
+
+    assert_eq!(
+        drop_leading_creates(events),
+        vec![
+            event(rename, "/renamed"),
+            event(create, "/created"),
+            event(modify, "/other"),
+            event(remove, "/removed"),
+            event(create, "/modified"),
+            event(modify, "/modified"),
+        ]
+    );
+}
+
+#[test]
+fn test_dropped_events_request_a_rescan() {
+    for flags in [
+        StreamFlags::MUST_SCAN_SUBDIRS,
+        StreamFlags::MUST_SCAN_SUBDIRS | StreamFlags::KERNEL_DROPPED,
+        StreamFlags::USER_DROPPED,
+    ] {
+        let events = translate_flags(flags, true);
+        assert!(events[0].need_rescan(), "{events:?}");
+        assert_eq!(events[0].kind, EventKind::Other);
+    }
+
+    let events = translate_flags(
+        StreamFlags::MUST_SCAN_SUBDIRS | StreamFlags::KERNEL_DROPPED,
+        false,
+    );
+    assert_eq!(events[0].info(), Some("rescan: kernel dropped"));
+}
@@ -0,0 +1,264 @@
+//! Event handler merging removals of paths that are restored right away
+//!
+//! Editors saving atomically may remove a file and put the new version in place right after,
+//! by creating it again or by renaming a temporary file over it. The [`RemoveGrace`] handler
+//! holds back removals for a short grace period and reports such a replacement as a single
+//! `Modify` event.
+
+use crate::event::{DataChange, ModifyKind, RenameMode};
+use crate::timed::{Hold, Timed};
+use crate::{Event, EventHandler, EventKind, Result};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// [`EventHandler`] holding back `Remove` events for a grace period, in which the path may be
+/// restored.
+///
+/// If the removed path is created again, or a file is renamed to it, within the grace period,
+/// a single `Modify(Data(Any))` event is delivered for it instead of the removal and the
+/// restoring event. Otherwise the removal is delivered once the grace period is over. A rename
+/// over a path that had other events within the grace period, like an editor saving by renaming
+/// a temporary file over the original, is reported as `Modify(Data(Any))` as well. Other events
+/// are forwarded right away, so events may be reordered. Held back removals are delivered when
+/// the handler is dropped.
+///
+/// The events are delivered from a background thread.
+///
+/// ```no_run
+/// # use notify::{RecursiveMode, RemoveGrace, Result, Watcher};
+/// # use std::path::Path;
+/// # use std::time::Duration;
+/// # fn main() -> Result<()> {
+/// let handler = RemoveGrace::new(
+///     |event: Result<notify::Event>| println!("{event:?}"),
+///     Duration::from_millis(100),
+/// )?;
+/// let mut watcher = notify::recommended_watcher(handler)?;
+/// watcher.watch(Path::new("."), RecursiveMode::Recursive)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct RemoveGrace {
+    timed: Timed,
+    grace_period: Duration,
+}
+
+impl RemoveGrace {
+    /// Create a new [`RemoveGrace`] delivering to `event_handler`, holding back removals for
+    /// `grace_period`.
+    pub fn new<F: EventHandler>(mut event_handler: F, grace_period: Duration) -> Result<Self> {
+        let removals = Removals {
+            held: HashMap::new(),
+            seen: HashMap::new(),
+            grace_period,
+        };
+        let timed = Timed::start("notify-rs grace loop", removals, move |events: Vec<_>| {
+            event_handler.handle_events(events)
+        })?;
+        Ok(Self {
+            timed,
+            grace_period,
+        })
+    }
+}
+
+impl fmt::Debug for RemoveGrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RemoveGrace")
+            .field("grace_period", &self.grace_period)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EventHandler for RemoveGrace {
+    fn handle_event(&mut self, event: Result<Event>) {
+        self.timed.send(event);
+    }
+}
+
+/// The removals in their grace period.
+struct Removals {
+    // PathBuf -> (removal, end of the grace period)
+    held: HashMap<PathBuf, (Event, Instant)>,
+    // PathBuf -> end of the grace period of its last event
+    seen: HashMap<PathBuf, Instant>,
+    grace_period: Duration,
+}
+
+impl Removals {
+    /// Holds back `event` if it is a removal, replaces it with a `Modify` event if it restores a
+    /// held back removal or renames a file over a path seen within the grace period. Returns the
+    /// event to be forwarded.
+    fn hold(&mut self, event: Event) -> Option<Event> {
+        let now = Instant::now();
+        let (restored, renamed) = match (event.kind, event.paths.as_slice()) {
+            (EventKind::Remove(_), [path]) => {
+                self.seen.remove(path);
+                let path = path.clone();
+                self.held.insert(path, (event, now + self.grace_period));
+                return None;
+            }
+            (EventKind::Create(_), [path]) => (path.clone(), false),
+            (EventKind::Modify(ModifyKind::Name(RenameMode::To)), [path])
+            | (EventKind::Modify(ModifyKind::Name(RenameMode::Both)), [_, path]) => {
+                (path.clone(), true)
+            }
+            _ => {
+                self.see(&event, now);
+                return Some(event);
+            }
+        };
+
+        let replaced = self.held.remove(&restored).is_some()
+            || (renamed && self.seen.get(&restored).is_some_and(|end| *end > now));
+        if !replaced {
+            self.see(&event, now);
+            return Some(event);
+        }
+        self.seen.insert(restored.clone(), now + self.grace_period);
+        let mut event = event.set_kind(EventKind::Modify(ModifyKind::Data(DataChange::Any)));
+        event.paths = vec![restored];
+        Some(event)
+    }
+
+    /// Remembers the paths that exist after `event` until the end of the grace period.
+    fn see(&mut self, event: &Event, now: Instant) {
+        let paths = match (event.kind, event.paths.as_slice()) {
+            (EventKind::Modify(ModifyKind::Name(RenameMode::From)), _) => return,
+            (EventKind::Modify(ModifyKind::Name(RenameMode::Both)), [_, to]) => {
+                std::slice::from_ref(to)
+            }
+            (_, paths) => paths,
+        };
+        for path in paths {
+            self.seen.insert(path.clone(), now + self.grace_period);
+        }
+    }
+}
+
+impl Hold for Removals {
+    fn push(&mut self, event: Result<Event>) -> Vec<Result<Event>> {
+        match event {
+            Ok(event) => self.hold(event).map(Ok).into_iter().collect(),
+            Err(error) => vec![Err(error)],
+        }
+    }
+
+    fn due(&self) -> Option<Instant> {
+        self.held.values().map(|(_, due)| *due).min()
+    }
+
+    fn take_due(&mut self, now: Instant) -> Vec<Result<Event>> {
+        self.seen.retain(|_, end| *end > now);
+        let mut expired = Vec::new();
+        self.held.retain(|_, (event, due)| {
+            if *due > now {
+                return true;
+            }
+            expired.push((*due, event.clone()));
+            false
+        });
+        expired.sort_by_key(|(due, _)| *due);
+        expired.into_iter().map(|(_, event)| Ok(event)).collect()
+    }
+
+    fn take_all(&mut self) -> Vec<Result<Event>> {
+        let mut rest: Vec<_> = self.held.drain().map(|(_, held)| held).collect();
+        rest.sort_by_key(|(_, due)| *due);
+        rest.into_iter().map(|(event, _)| Ok(event)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{CreateKind, RemoveKind};
+    use std::path::Path;
+
+    fn event(kind: EventKind, paths: &[&Path]) -> Result<Event> {
+        let event = paths.iter().fold(Event::new(kind), |event, path| {
+            event.add_path(path.to_path_buf())
+        });
+        Ok(event)
+    }
+
+    #[test]
+    fn restored_paths_are_reported_as_modified() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut handler = RemoveGrace::new(tx, Duration::from_millis(200)).unwrap();
+        let (saved, renamed, removed) = (
+            Path::new("/saved"),
+            Path::new("/renamed"),
+            Path::new("/removed"),
+        );
+
+        let remove = EventKind::Remove(RemoveKind::File);
+        handler.handle_event(event(remove, &[saved]));
+        handler.handle_event(event(remove, &[renamed]));
+        handler.handle_event(event(remove, &[removed]));
+        handler.handle_event(event(EventKind::Create(CreateKind::File), &[saved]));
+        handler.handle_event(event(
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+            &[Path::new("/renamed.tmp"), renamed],
+        ));
+
+        let received: Vec<_> = std::iter::from_fn(|| rx.recv_timeout(Duration::from_secs(1)).ok())
+            .take(3)
+            .map(|event| event.unwrap())
+            .map(|event| (event.kind, event.paths))
+            .collect();
+        let modify = EventKind::Modify(ModifyKind::Data(DataChange::Any));
+        assert_eq!(
+            received,
+            [
+                (modify, vec![saved.to_path_buf()]),
+                (modify, vec![renamed.to_path_buf()]),
+                (remove, vec![removed.to_path_buf()]),
+            ]
+        );
+
+        drop(handler);
+        assert!(rx.recv().is_err());
+    }
+
+    #[test]
+    fn renames_over_seen_paths_are_reported_as_modified() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut handler = RemoveGrace::new(tx, Duration::from_secs(60)).unwrap();
+        let (saved, temporary, unseen) = (
+            Path::new("/saved"),
+            Path::new("/saved.tmp"),
+            Path::new("/unseen"),
+        );
+
+        let data = EventKind::Modify(ModifyKind::Data(DataChange::Any));
+        let rename = EventKind::Modify(ModifyKind::Name(RenameMode::Both));
+        handler.handle_event(event(data, &[saved]));
+        handler.handle_event(event(EventKind::Create(CreateKind::File), &[temporary]));
+        handler.handle_event(event(rename, &[temporary, saved]));
+        handler.handle_event(event(rename, &[Path::new("/unseen.tmp"), unseen]));
+
+        let received: Vec<_> = std::iter::from_fn(|| rx.recv_timeout(Duration::from_secs(1)).ok())
+            .take(4)
+            .map(|event| event.unwrap())
+            .map(|event| (event.kind, event.paths))
+            .collect();
+        assert_eq!(
+            received,
+            [
+                (data, vec![saved.to_path_buf()]),
+                (
+                    EventKind::Create(CreateKind::File),
+                    vec![temporary.to_path_buf()]
+                ),
+                (data, vec![saved.to_path_buf()]),
+                (
+                    rename,
+                    vec![PathBuf::from("/unseen.tmp"), unseen.to_path_buf()]
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,293 @@
+//! Watcher combining the native backend with a polling safety net
+//!
+//! Native backends may miss events, e.g. on network filesystems, in some virtualized
+//! environments or for files not owned by the user on macOS. The [`HybridWatcher`] runs the
+//! [`RecommendedWatcher`] for low latency and a slow [`PollWatcher`] next to it. Events found by
+//! polling are only forwarded if the native backend didn't report the same change recently.
+
+use crate::event::{CreateKind, ModifyKind, RemoveKind, RenameMode};
+use crate::{
+    Config, Event, EventHandler, EventKind, PollWatcher, RecommendedWatcher, RecursiveMode, Result,
+    Watcher, WatcherKind,
+};
+use std::collections::HashMap;
+use std::fmt;
+use std::mem::{self, Discriminant};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Creates a [`HybridWatcher`] using `native_config` for the native backend and polling every
+/// `poll_interval`.
+///
+/// ```no_run
+/// # use notify::{Config, RecursiveMode, Result, Watcher};
+/// # use std::path::Path;
+/// # use std::time::Duration;
+/// # fn main() -> Result<()> {
+/// let mut watcher = notify::hybrid_watcher(
+///     Config::default(),
+///     Duration::from_secs(10),
+///     |event| println!("{event:?}"),
+/// )?;
+/// watcher.watch(Path::new("."), RecursiveMode::Recursive)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn hybrid_watcher<F: EventHandler>(
+    native_config: Config,
+    poll_interval: Duration,
+    event_handler: F,
+) -> Result<HybridWatcher> {
+    HybridWatcher::with_poll_interval(event_handler, native_config, poll_interval)
+}
+
+/// [`Watcher`] running the [`RecommendedWatcher`] together with a [`PollWatcher`] as a safety net.
+///
+/// Changes of the same kind to the same path that the native backend reported within two poll
+/// intervals are not reported again by the poll watcher. Modify events for directories are not
+/// polled, as the native backends don't report them either.
+pub struct HybridWatcher {
+    native: RecommendedWatcher,
+    poll: PollWatcher,
+}
+
+impl HybridWatcher {
+    /// Create a new [`HybridWatcher`] polling every `poll_interval`.
+    ///
+    /// `config` is used for both watchers, except for the poll interval.
+    pub fn with_poll_interval<F: EventHandler>(
+        event_handler: F,
+        config: Config,
+        poll_interval: Duration,
+    ) -> Result<Self> {
+        let dedup = Arc::new(Mutex::new(Dedup::new(event_handler, poll_interval * 2)));
+
+        let native = {
+            let dedup = dedup.clone();
+            RecommendedWatcher::new(move |res| dedup.lock().unwrap().handle_native(res), config)?
+        };
+        let poll = PollWatcher::new(
+            move |res| dedup.lock().unwrap().handle_poll(res),
+            config
+                .with_poll_interval(poll_interval)
+                .with_report_dir_modify_events(false),
+        )?;
+
+        Ok(Self { native, poll })
+    }
+}
+
+impl fmt::Debug for HybridWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HybridWatcher")
+            .field("native", &self.native)
+            .field("poll", &self.poll)
+            .finish()
+    }
+}
+
+impl Watcher for HybridWatcher {
+    /// Create a new watcher, polling at the interval set by [`Config::with_poll_interval`], or
+    /// every 30 seconds if polling is disabled.
+    fn new<F: EventHandler>(event_handler: F, config: Config) -> Result<Self> {
+        let poll_interval = config.poll_interval().unwrap_or(Duration::from_secs(30));
+        Self::with_poll_interval(event_handler, config, poll_interval)
+    }
+
+    fn watch(&mut self, path: &Path, recursive_mode: RecursiveMode) -> Result<()> {
+        self.native.watch(path, recursive_mode)?;
+        if let Err(e) = self.poll.watch(path, recursive_mode) {
+            let _ = self.native.unwatch(path);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    fn unwatch(&mut self, path: &Path) -> Result<()> {
+        let native = self.native.unwatch(path);
+        let poll = self.poll.unwatch(path);
+        native.and(poll)
+    }
+
+    fn watched_paths(&self) -> Result<Vec<(PathBuf, RecursiveMode)>> {
+        self.native.watched_paths()
+    }
+
+    fn unwatch_prefix(&mut self, prefix: &Path) -> Result<Vec<PathBuf>> {
+        let native = self.native.unwatch_prefix(prefix);
+        let poll = self.poll.unwatch_prefix(prefix);
+        poll.and(native)
+    }
+
+    /// Returns the latency of the native backend, the poll watcher only reports what it missed.
+    fn expected_latency(&self) -> Duration {
+        self.native.expected_latency()
+    }
+
+    fn stop(self) -> Vec<Result<Event>> {
+        let mut events = self.native.stop();
+        events.extend(self.poll.stop());
+        events
+    }
+
+    fn configure(&mut self, option: Config) -> Result<bool> {
+        self.native.configure(option)
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        self.native.pause()?;
+        self.poll.pause()
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        self.native.resume()?;
+        self.poll.resume()
+    }
+
+    /// Resyncs through the native backend only, which watches the same paths.
+    fn resync(&mut self) -> Result<()> {
+        self.native.resync()
+    }
+
+    /// Returns the counters of the native backend.
+    fn stats(&self) -> crate::WatcherStats {
+        self.native.stats()
+    }
+
+    fn path_supports_events(&self, _path: &Path) -> bool {
+        true
+    }
+
+    /// Returns the kind of the native backend.
+    fn kind() -> WatcherKind {
+        RecommendedWatcher::kind()
+    }
+}
+
+/// Forwards native events and poll events the native backend didn't report.
+struct Dedup {
+    event_handler: Box<dyn EventHandler>,
+    window: Duration,
+    /// (path, kind of change) -> time reported by the native backend
+    reported: HashMap<(PathBuf, Discriminant<EventKind>), Instant>,
+}
+
+impl Dedup {
+    fn new<F: EventHandler>(event_handler: F, window: Duration) -> Self {
+        Self {
+            event_handler: Box::new(event_handler),
+            window,
+            reported: HashMap::new(),
+        }
+    }
+
+    fn handle_native(&mut self, res: Result<Event>) {
+        if let Ok(event) = &res {
+            let now = Instant::now();
+            for (path, kind) in polled_changes(event) {
+                self.reported
+                    .insert((path.clone(), mem::discriminant(&kind)), now);
+            }
+        }
+        self.event_handler.handle_event(res);
+    }
+
+    fn handle_poll(&mut self, res: Result<Event>) {
+        if let Ok(event) = &res {
+            let window = self.window;
+            self.reported.retain(|_, time| time.elapsed() < window);
+
+            let kind = mem::discriminant(&event.kind);
+            if event
+                .paths
+                .iter()
+                .all(|path| self.reported.contains_key(&(path.clone(), kind)))
+            {
+                log::trace!("skipping poll event already reported natively: {event:?}");
+                return;
+            }
+        }
+        self.event_handler.handle_event(res);
+    }
+}
+
+/// The changes the poll watcher reports for a native event.
+///
+/// The poll watcher can't detect renames, they are reported as a removal and a creation.
+fn polled_changes(event: &Event) -> Vec<(&PathBuf, EventKind)> {
+    let removed = EventKind::Remove(RemoveKind::Any);
+    let created = EventKind::Create(CreateKind::Any);
+    match event.kind {
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+            event.paths.iter().map(|path| (path, removed)).collect()
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+            event.paths.iter().map(|path| (path, created)).collect()
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+            event.paths.iter().zip([removed, created]).collect()
+        }
+        kind => event.paths.iter().map(|path| (path, kind)).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{DataChange, MetadataKind};
+
+    fn collecting_dedup() -> (Arc<Mutex<Vec<Event>>>, Dedup) {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let collected = events.clone();
+        let dedup = Dedup::new(
+            move |res: Result<Event>| collected.lock().unwrap().push(res.unwrap()),
+            Duration::from_secs(60),
+        );
+        (events, dedup)
+    }
+
+    #[test]
+    fn poll_events_reported_natively_are_skipped() {
+        let (events, mut dedup) = collecting_dedup();
+        let path = PathBuf::from("/file");
+
+        dedup.handle_native(Ok(Event::new(EventKind::Modify(ModifyKind::Data(
+            DataChange::Any,
+        )))
+        .add_path(path.clone())));
+        dedup.handle_poll(Ok(Event::new(EventKind::Modify(ModifyKind::Metadata(
+            MetadataKind::WriteTime,
+        )))
+        .add_path(path.clone())));
+
+        assert_eq!(events.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn poll_events_missed_natively_are_forwarded() {
+        let (events, mut dedup) = collecting_dedup();
+        let from = PathBuf::from("/from");
+        let to = PathBuf::from("/to");
+        let other = PathBuf::from("/other");
+
+        dedup.handle_native(Ok(Event::new(EventKind::Modify(ModifyKind::Name(
+            RenameMode::Both,
+        )))
+        .add_path(from.clone())
+        .add_path(to.clone())));
+        dedup.handle_poll(Ok(
+            Event::new(EventKind::Remove(RemoveKind::Any)).add_path(from.clone())
+        ));
+        dedup.handle_poll(Ok(
+            Event::new(EventKind::Create(CreateKind::Any)).add_path(to.clone())
+        ));
+        dedup.handle_poll(Ok(
+            Event::new(EventKind::Create(CreateKind::Any)).add_path(other.clone())
+        ));
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].paths, vec![other]);
+    }
+}
@@ -6,22 +6,47 @@
 
 use super::event::*;
 use super::{Config, Error, ErrorKind, EventHandler, RecursiveMode, Result, Watcher};
-use crate::{bounded, unbounded, BoundSender, Receiver, Sender};
+use crate::pause::{Leftovers, PauseGate};
+use crate::stats::Counters;
+use crate::{
+    bounded, max_depth_exceeded, overflow_risk, unbounded, unmounted, watch_invalidated,
+    watch_ready, AttributeHandler, BoundSender, EventKindMask, Lifecycle, Receiver, Sender,
+    Traversal,
+};
+use crate::{dispatch, resync, settle};
 use inotify as inotify_sys;
 use inotify_sys::{EventMask, Inotify, WatchDescriptor, WatchMask};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::env;
-use std::ffi::OsStr;
-use std::fs::metadata;
+use std::ffi::{CString, OsStr};
+use std::fs::{metadata, symlink_metadata};
+use std::io;
+use std::mem::MaybeUninit;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
 use std::os::unix::io::AsRawFd;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::thread;
+use std::time::{Duration, Instant};
 use walkdir::WalkDir;
 
 const INOTIFY: mio::Token = mio::Token(0);
 const MESSAGE: mio::Token = mio::Token(1);
 
+/// Magic numbers of filesystems on which inotify misses changes, see `statfs(2)`
+///
+/// Changes made by other machines on network filesystems and changes of the kernel state exposed
+/// by pseudo-filesystems are not reported.
+const UNSUPPORTED_FILESYSTEMS: &[u32] = &[
+    0x6969,      // NFS
+    0x517b,      // SMB
+    0xff53_4d42, // CIFS
+    0xfe53_4d42, // SMB2
+    0x9fa0,      // proc
+    0x6265_6572, // sysfs
+];
+
 // The EventLoop will set up a mio::Poll and use it to wait for the following:
 //
 // -  messages telling it what to do
@@ -39,8 +64,33 @@ struct EventLoop {
     /// PathBuf -> (WatchDescriptor, WatchMask, is_recursive, is_dir)
     watches: HashMap<PathBuf, (WatchDescriptor, WatchMask, bool, bool)>,
     paths: HashMap<WatchDescriptor, PathBuf>,
+    /// Device IDs of the watched paths, if enabled with [Config::with_event_device_ids]
+    devices: Option<HashMap<WatchDescriptor, u64>>,
+    /// The last seen attributes of paths, if enabled with [Config::with_precise_metadata_kinds]
+    attributes: Option<AttributeCache>,
     rename_event: Option<Event>,
+    /// Events requested for every watch, narrowed by [Config::with_event_kinds]
+    watch_mask: WatchMask,
     follow_links: bool,
+    /// Watch symbolic links passed to `watch` as links, see [Config::with_watch_symlinks_as_links]
+    symlinks_as_links: bool,
+    /// Drop removals below removed directories, see [Config::with_coalesce_tree_removal]
+    coalesce_tree_removal: bool,
+    event_file_ids: bool,
+    /// Number of queue overflows so far
+    overflow_count: u64,
+    traversal: Traversal,
+    lifecycle: Lifecycle,
+    report_self_events: bool,
+    /// Held back modify events waiting for a close-write, see [Config::with_prefer_close_write]
+    pending_modify: HashMap<PathBuf, (Event, Instant)>,
+    prefer_close_write: Option<Duration>,
+    /// See [Config::with_auto_watch_limit]
+    auto_watch_limit: Option<(usize, Duration)>,
+    /// Start of the current interval of the auto watch limit and the watches added in it
+    auto_watches: (Instant, usize),
+    /// See [Config::with_max_watch_depth]
+    max_watch_depth: Option<usize>,
 }
 
 /// Watcher implementation based on inotify
@@ -48,15 +98,109 @@ struct EventLoop {
 pub struct INotifyWatcher {
     channel: Sender<EventLoopMsg>,
     waker: Arc<mio::Waker>,
+    gate: PauseGate,
+    leftovers: Leftovers,
+    follow_symlinks: bool,
 }
 
 enum EventLoopMsg {
-    AddWatch(PathBuf, RecursiveMode, Sender<Result<()>>),
+    /// Without a sender, the result is reported to the event handler, see
+    /// [`INotifyWatcher::watch_async`]
+    AddWatch(PathBuf, RecursiveMode, Option<Sender<Result<()>>>),
     RemoveWatch(PathBuf, Sender<Result<()>>),
-    Shutdown,
+    RemoveWatchPrefix(PathBuf, Sender<Result<Vec<PathBuf>>>),
+    WatchedPaths(Sender<Vec<(PathBuf, RecursiveMode)>>),
+    /// With a sender, the queued inotify events are read first and the sender is notified once
+    /// the event loop stopped, see [`Watcher::stop`]
+    Shutdown(Option<Sender<()>>),
     Configure(Config, BoundSender<Result<bool>>),
 }
 
+/// The attributes of a path whose changes are reported by `IN_ATTRIB`, see
+/// [Config::with_precise_metadata_kinds]
+#[derive(Clone, Copy, Debug)]
+struct Attributes {
+    mode: u32,
+    uid: u32,
+    gid: u32,
+    nlink: u64,
+    mtime: (i64, i64),
+    atime: (i64, i64),
+}
+
+impl Attributes {
+    fn new(metadata: &std::fs::Metadata) -> Self {
+        Self {
+            mode: metadata.mode(),
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+            nlink: metadata.nlink(),
+            mtime: (metadata.mtime(), metadata.mtime_nsec()),
+            atime: (metadata.atime(), metadata.atime_nsec()),
+        }
+    }
+
+    /// The kind of change from `old` to `self`. Without any visible change, an extended attribute
+    /// was changed.
+    fn changed_since(&self, old: &Attributes) -> MetadataKind {
+        if self.mode != old.mode {
+            MetadataKind::Permissions
+        } else if (self.uid, self.gid) != (old.uid, old.gid) {
+            MetadataKind::Ownership
+        } else if self.mtime != old.mtime {
+            MetadataKind::WriteTime
+        } else if self.atime != old.atime {
+            MetadataKind::AccessTime
+        } else if self.nlink != old.nlink {
+            MetadataKind::Any
+        } else {
+            MetadataKind::Extended
+        }
+    }
+}
+
+/// The last seen attributes of paths, see [Config::with_precise_metadata_kinds]
+#[derive(Debug)]
+struct AttributeCache {
+    follow_links: bool,
+    paths: HashMap<PathBuf, Attributes>,
+}
+
+impl AttributeCache {
+    fn new(follow_links: bool) -> Self {
+        Self {
+            follow_links,
+            paths: HashMap::new(),
+        }
+    }
+
+    /// Remembers the current attributes of `path`, returning the previous ones.
+    fn update(&mut self, path: &Path) -> Option<(Attributes, Option<Attributes>)> {
+        let metadata = if self.follow_links {
+            metadata(path)
+        } else {
+            symlink_metadata(path)
+        };
+        let attributes = Attributes::new(&metadata.ok()?);
+        Some((
+            attributes,
+            self.paths.insert(path.to_path_buf(), attributes),
+        ))
+    }
+
+    /// The kind of the attribute change of `path`.
+    fn change_kind(&mut self, path: &Path) -> MetadataKind {
+        match self.update(path) {
+            Some((new, Some(old))) => new.changed_since(&old),
+            _ => MetadataKind::Any,
+        }
+    }
+
+    fn forget(&mut self, path: &Path) {
+        self.paths.remove(path);
+    }
+}
+
 #[inline]
 fn add_watch_by_event(
     path: &Option<PathBuf>,
@@ -77,6 +221,86 @@ fn add_watch_by_event(
     }
 }
 
+/// Take the held back modify events matching `predicate`, ready to be emitted.
+fn take_pending_modify<P>(
+    pending_modify: &mut HashMap<PathBuf, (Event, Instant)>,
+    mut predicate: P,
+) -> Vec<Result<Event>>
+where
+    P: FnMut(&PathBuf, &(Event, Instant)) -> bool,
+{
+    let flush: Vec<_> = pending_modify
+        .iter()
+        .filter(|&(path, pending)| predicate(path, pending))
+        .map(|(path, _)| path.clone())
+        .collect();
+
+    flush
+        .into_iter()
+        .filter_map(|path| pending_modify.remove(&path))
+        .map(|(event, _)| Ok(event))
+        .collect()
+}
+
+/// Returns the inotify events to request for every watch.
+///
+/// Creations, removals and renames are always requested, as they are needed to keep track of the
+/// watched tree.
+fn watch_mask(config: &Config) -> WatchMask {
+    let mut mask =
+        WatchMask::CREATE | WatchMask::DELETE | WatchMask::MOVED_FROM | WatchMask::MOVED_TO;
+    if config.allows_kind(EventKindMask::Modify) {
+        // a close-write may complete a held back modification, see `prefer_close_write`
+        mask |= WatchMask::ATTRIB | WatchMask::MODIFY | WatchMask::CLOSE_WRITE;
+    }
+    if config.allows_kind(EventKindMask::Access) {
+        mask |= WatchMask::OPEN | WatchMask::CLOSE_WRITE;
+    }
+    mask
+}
+
+/// Drops the removals of paths below directories removed in the same batch, and repeated removals
+/// of these directories, see [Config::with_coalesce_tree_removal].
+///
+/// inotify reports the contents before the directory, so the last removal of each directory is
+/// kept. Nothing is carried over between batches, so the removals of contents read before the
+/// batch holding the directory's removal are not coalesced.
+fn coalesce_tree_removal(batch: &mut Vec<Result<Event>>) {
+    let removed_dirs: Vec<PathBuf> = batch
+        .iter()
+        .filter_map(|ev| match ev {
+            Ok(ev) if ev.kind == EventKind::Remove(RemoveKind::Folder) => ev.paths.first().cloned(),
+            _ => None,
+        })
+        .collect();
+    if removed_dirs.is_empty() {
+        return;
+    }
+
+    let mut reported = HashSet::new();
+    let mut kept: Vec<_> = batch
+        .drain(..)
+        .rev()
+        .filter(|ev| {
+            let Ok(ev) = ev else {
+                return true;
+            };
+            let Some(path) = ev.paths.first().filter(|_| ev.kind.is_remove()) else {
+                return true;
+            };
+            if removed_dirs
+                .iter()
+                .any(|dir| path != dir && path.starts_with(dir))
+            {
+                return false;
+            }
+            !removed_dirs.contains(path) || reported.insert(path.clone())
+        })
+        .collect();
+    kept.reverse();
+    *batch = kept;
+}
+
 #[inline]
 fn remove_watch_by_event(
     path: &Option<PathBuf>,
@@ -94,7 +318,7 @@ impl EventLoop {
     pub fn new(
         inotify: Inotify,
         event_handler: Box<dyn EventHandler>,
-        follow_links: bool,
+        config: &Config,
     ) -> Result<Self> {
         let (event_loop_tx, event_loop_rx) = unbounded::<EventLoopMsg>();
         let poll = mio::Poll::new()?;
@@ -116,8 +340,25 @@ impl EventLoop {
             event_handler,
             watches: HashMap::new(),
             paths: HashMap::new(),
+            devices: config.event_device_ids().then(HashMap::new),
+            attributes: config
+                .precise_metadata_kinds()
+                .then(|| AttributeCache::new(config.follow_symlinks())),
             rename_event: None,
-            follow_links,
+            watch_mask: watch_mask(config),
+            follow_links: config.follow_symlinks(),
+            symlinks_as_links: config.watch_symlinks_as_links(),
+            coalesce_tree_removal: config.coalesce_tree_removal(),
+            event_file_ids: config.event_file_ids(),
+            overflow_count: 0,
+            traversal: Traversal::default(),
+            lifecycle: Lifecycle::default(),
+            report_self_events: config.report_self_events(),
+            pending_modify: HashMap::new(),
+            prefer_close_write: config.prefer_close_write(),
+            auto_watch_limit: config.auto_watch_limit(),
+            auto_watches: (Instant::now(), 0),
+            max_watch_depth: config.max_watch_depth(),
         };
         Ok(event_loop)
     }
@@ -130,10 +371,18 @@ impl EventLoop {
     }
 
     fn event_loop_thread(mut self) {
+        self.lifecycle.thread_started();
+        if let Err(error) = Error::catch_panic(|| self.event_loop()) {
+            self.event_handler.handle_event(Err(error));
+        }
+        self.lifecycle.thread_exited();
+    }
+
+    fn event_loop(&mut self) {
         let mut events = mio::Events::with_capacity(16);
         loop {
             // Wait for something to happen.
-            match self.poll.poll(&mut events, None) {
+            match self.poll.poll(&mut events, self.pending_modify_timeout()) {
                 Err(ref e) if matches!(e.kind(), std::io::ErrorKind::Interrupted) => {
                     // System call was interrupted, we will retry
                     // TODO: Not covered by tests (to reproduce likely need to setup signal handlers)
@@ -147,6 +396,11 @@ impl EventLoop {
                 self.handle_event(event);
             }
 
+            let events = take_pending_modify(&mut self.pending_modify, |_, &(_, deadline)| {
+                deadline <= Instant::now()
+            });
+            self.event_handler.handle_events(events);
+
             // Stop, if we're done.
             if !self.running {
                 break;
@@ -173,17 +427,50 @@ impl EventLoop {
         while let Ok(msg) = self.event_loop_rx.try_recv() {
             match msg {
                 EventLoopMsg::AddWatch(path, recursive_mode, tx) => {
-                    let _ = tx.send(self.add_watch(path, recursive_mode.is_recursive(), true));
+                    let result = self.add_watch(path.clone(), recursive_mode.is_recursive(), true);
+                    if result.is_ok() && !supports_events(&path) {
+                        self.event_handler.handle_event(Err(
+                            Error::unsupported_filesystem().add_path(path.clone())
+                        ));
+                    }
+                    match tx {
+                        Some(tx) => {
+                            let _ = tx.send(result);
+                        }
+                        // errors are reported without the call that caused them, name the root
+                        None => {
+                            let event = match result {
+                                Ok(()) => Ok(watch_ready(path)),
+                                Err(e) if e.paths.is_empty() => Err(e.add_path(path)),
+                                Err(e) => Err(e),
+                            };
+                            self.event_handler.handle_event(event);
+                        }
+                    }
                 }
                 EventLoopMsg::RemoveWatch(path, tx) => {
                     let _ = tx.send(self.remove_watch(path, false));
                 }
-                EventLoopMsg::Shutdown => {
+                EventLoopMsg::RemoveWatchPrefix(prefix, tx) => {
+                    let _ = tx.send(self.remove_watch_prefix(&prefix));
+                }
+                EventLoopMsg::WatchedPaths(tx) => {
+                    let _ = tx.send(self.watched_paths());
+                }
+                EventLoopMsg::Shutdown(tx) => {
+                    if tx.is_some() {
+                        self.handle_inotify();
+                    }
+                    let events = take_pending_modify(&mut self.pending_modify, |_, _| true);
+                    self.event_handler.handle_events(events);
                     let _ = self.remove_all_watches();
                     if let Some(inotify) = self.inotify.take() {
                         let _ = inotify.close();
                     }
                     self.running = false;
+                    if let Some(tx) = tx {
+                        let _ = tx.send(());
+                    }
                     break;
                 }
                 EventLoopMsg::Configure(config, tx) => {
@@ -198,6 +485,14 @@ impl EventLoop {
             .expect("configuration channel disconnected");
     }
 
+    /// Time until the next held back modify event is due.
+    fn pending_modify_timeout(&self) -> Option<Duration> {
+        self.pending_modify
+            .values()
+            .map(|&(_, deadline)| deadline.saturating_duration_since(Instant::now()))
+            .min()
+    }
+
     fn handle_inotify(&mut self) {
         let mut add_watches = Vec::new();
         let mut remove_watches = Vec::new();
@@ -208,14 +503,19 @@ impl EventLoop {
             loop {
                 match inotify.read_events(&mut buffer) {
                     Ok(events) => {
+                        // everything read by this call is emitted at once, see `Batched`
+                        let mut batch = Vec::new();
                         let mut num_events = 0;
                         for event in events {
                             log::trace!("inotify event: {event:?}");
 
                             num_events += 1;
                             if event.mask.contains(EventMask::Q_OVERFLOW) {
-                                let ev = Ok(Event::new(EventKind::Other).set_flag(Flag::Rescan));
-                                self.event_handler.handle_event(ev);
+                                self.overflow_count += 1;
+                                let ev = Event::new(EventKind::Other)
+                                    .set_flag(Flag::Rescan)
+                                    .set_overflow_count(self.overflow_count);
+                                batch.push(Ok(ev));
                             }
 
                             let path = match event.name {
@@ -223,8 +523,42 @@ impl EventLoop {
                                 None => self.paths.get(&event.wd).cloned(),
                             };
 
+                            // another event arrived, stop waiting for a close-write; the held back
+                            // modification goes first, even if the event is for the same path
+                            if !self.pending_modify.is_empty() {
+                                let writes = event
+                                    .mask
+                                    .intersects(EventMask::MODIFY | EventMask::CLOSE_WRITE);
+                                batch.extend(take_pending_modify(
+                                    &mut self.pending_modify,
+                                    |pending_path, _| {
+                                        !writes || path.as_ref() != Some(pending_path)
+                                    },
+                                ));
+                            }
+
                             let mut evs = Vec::new();
 
+                            // remember the attributes of new paths, forget the ones of gone paths;
+                            // writes change the modification time, which isn't to be reported as
+                            // part of the next attribute change
+                            if let (Some(attributes), Some(path)) = (&mut self.attributes, &path) {
+                                if event.mask.intersects(
+                                    EventMask::CREATE
+                                        | EventMask::MOVED_TO
+                                        | EventMask::MODIFY
+                                        | EventMask::CLOSE_WRITE,
+                                ) {
+                                    attributes.update(path);
+                                } else if event.mask.intersects(
+                                    EventMask::DELETE
+                                        | EventMask::DELETE_SELF
+                                        | EventMask::MOVED_FROM,
+                                ) {
+                                    attributes.forget(path);
+                                }
+                            }
+
                             if event.mask.contains(EventMask::MOVED_FROM) {
                                 remove_watch_by_event(&path, &self.watches, &mut remove_watches);
 
@@ -265,7 +599,8 @@ impl EventLoop {
                                 }
                                 add_watch_by_event(&path, &event, &self.watches, &mut add_watches);
                             }
-                            if event.mask.contains(EventMask::MOVE_SELF) {
+                            if event.mask.contains(EventMask::MOVE_SELF) && self.report_self_events
+                            {
                                 evs.push(
                                     Event::new(EventKind::Modify(ModifyKind::Name(
                                         RenameMode::From,
@@ -319,21 +654,52 @@ impl EventLoop {
                                         RemoveKind::Other
                                     }
                                 };
-                                evs.push(
-                                    Event::new(EventKind::Remove(remove_kind))
-                                        .add_some_path(path.clone()),
-                                );
+                                if self.report_self_events {
+                                    evs.push(
+                                        Event::new(EventKind::Remove(remove_kind))
+                                            .add_some_path(path.clone()),
+                                    );
+                                }
+                                remove_watch_by_event(&path, &self.watches, &mut remove_watches);
+                            }
+                            if event.mask.contains(EventMask::UNMOUNT) {
+                                if let Some(path) = &path {
+                                    evs.push(unmounted(path.clone()));
+                                }
+                            }
+                            // the watch was removed by the kernel, e.g. because the watched path
+                            // was deleted or its filesystem unmounted
+                            if event.mask.contains(EventMask::IGNORED) {
+                                if let Some(path) = &path {
+                                    let is_root =
+                                        self.watches.get(path).is_some_and(|&(_, mask, _, _)| {
+                                            mask.contains(WatchMask::DELETE_SELF)
+                                        });
+                                    if is_root && self.report_self_events {
+                                        evs.push(watch_invalidated(path.clone()));
+                                    }
+                                }
                                 remove_watch_by_event(&path, &self.watches, &mut remove_watches);
                             }
                             if event.mask.contains(EventMask::MODIFY) {
-                                evs.push(
-                                    Event::new(EventKind::Modify(ModifyKind::Data(
-                                        DataChange::Any,
-                                    )))
-                                    .add_some_path(path.clone()),
-                                );
+                                let ev = Event::new(EventKind::Modify(ModifyKind::Data(
+                                    DataChange::Any,
+                                )))
+                                .add_some_path(path.clone());
+
+                                match (self.prefer_close_write, &path) {
+                                    (Some(timeout), Some(path)) => {
+                                        self.pending_modify
+                                            .entry(path.clone())
+                                            .or_insert_with(|| (ev, Instant::now() + timeout));
+                                    }
+                                    _ => evs.push(ev),
+                                }
                             }
                             if event.mask.contains(EventMask::CLOSE_WRITE) {
+                                if let Some(ref path) = path {
+                                    self.pending_modify.remove(path);
+                                }
                                 evs.push(
                                     Event::new(EventKind::Access(AccessKind::Close(
                                         AccessMode::Write,
@@ -350,11 +716,13 @@ impl EventLoop {
                                 );
                             }
                             if event.mask.contains(EventMask::ATTRIB) {
+                                let kind = match (&mut self.attributes, &path) {
+                                    (Some(attributes), Some(path)) => attributes.change_kind(path),
+                                    _ => MetadataKind::Any,
+                                };
                                 evs.push(
-                                    Event::new(EventKind::Modify(ModifyKind::Metadata(
-                                        MetadataKind::Any,
-                                    )))
-                                    .add_some_path(path.clone()),
+                                    Event::new(EventKind::Modify(ModifyKind::Metadata(kind)))
+                                        .add_some_path(path.clone()),
                                 );
                             }
                             if event.mask.contains(EventMask::OPEN) {
@@ -366,11 +734,30 @@ impl EventLoop {
                                 );
                             }
 
-                            for ev in evs {
-                                self.event_handler.handle_event(Ok(ev));
+                            for mut ev in evs {
+                                if self.event_file_ids && !ev.kind.is_remove() {
+                                    if let Some(file_id) =
+                                        ev.paths.last().and_then(|path| read_file_id(path))
+                                    {
+                                        ev = ev.set_file_id(file_id);
+                                    }
+                                }
+                                if let Some(&device_id) =
+                                    self.devices.as_ref().and_then(|d| d.get(&event.wd))
+                                {
+                                    ev = ev.set_device_id(device_id);
+                                }
+                                batch.push(Ok(ev));
                             }
                         }
 
+                        if self.coalesce_tree_removal {
+                            coalesce_tree_removal(&mut batch);
+                        }
+                        if !batch.is_empty() {
+                            self.event_handler.handle_events(batch);
+                        }
+
                         // All events read. Break out.
                         if num_events == 0 {
                             break;
@@ -399,42 +786,141 @@ impl EventLoop {
     fn add_watch(&mut self, path: PathBuf, is_recursive: bool, mut watch_self: bool) -> Result<()> {
         // If the watch is not recursive, or if we determine (by stat'ing the path to get its
         // metadata) that the watched path is not a directory, add a single path watch.
-        if !is_recursive || !metadata(&path).map_err(Error::io_watch)?.is_dir() {
+        let metadata = if self.follow_links && !(watch_self && self.symlinks_as_links) {
+            metadata(&path)
+        } else {
+            symlink_metadata(&path)
+        };
+        if !is_recursive || !metadata.map_err(Error::io_watch)?.is_dir() {
             return self.add_single_watch(path, false, true);
         }
 
-        for entry in WalkDir::new(path)
-            .follow_links(self.follow_links)
+        let mut max_depth = self.traversal.max_depth();
+        // unlike the traversal's depth, exceeding the configured one is reported
+        let mut depth_bound = self.max_watch_depth;
+        // a directory created below a recursive watch
+        let is_auto = !watch_self;
+        if is_auto {
+            if self.traversal.skips(&path) {
+                return Ok(());
+            }
+            let depth = self.depth_below_root(&path);
+            if let Some(max_depth) = &mut max_depth {
+                match max_depth.checked_sub(depth) {
+                    Some(remaining) => *max_depth = remaining,
+                    None => return Ok(()),
+                }
+            }
+            if let Some(depth_bound) = &mut depth_bound {
+                match depth_bound.checked_sub(depth) {
+                    Some(remaining) => *depth_bound = remaining,
+                    None => {
+                        self.event_handler
+                            .handle_event(Ok(max_depth_exceeded(path)));
+                        return Ok(());
+                    }
+                }
+            }
+        }
+
+        // the directories right beyond the bound are visited to report them
+        let walk_depth = match (max_depth, depth_bound) {
+            (Some(max_depth), Some(depth_bound)) => {
+                Some(max_depth.min(depth_bound.saturating_add(1)))
+            }
+            (max_depth, depth_bound) => {
+                max_depth.or(depth_bound.map(|bound| bound.saturating_add(1)))
+            }
+        };
+        let mut walker = WalkDir::new(&path).follow_links(self.follow_links);
+        if let Some(walk_depth) = walk_depth {
+            walker = walker.max_depth(walk_depth);
+        }
+        let traversal = self.traversal.clone();
+        let mut watched_dirs = 0;
+        for entry in walker
             .into_iter()
+            .filter_entry(move |e| {
+                e.depth() == 0 || !e.file_type().is_dir() || !traversal.skips(e.path())
+            })
             .filter_map(filter_dir)
         {
+            if depth_bound.is_some_and(|depth_bound| entry.depth() > depth_bound) {
+                self.event_handler
+                    .handle_event(Ok(max_depth_exceeded(entry.into_path())));
+                continue;
+            }
+            if is_auto && !self.take_auto_watch() {
+                let event = Event::new(EventKind::Other)
+                    .add_path(path.clone())
+                    .set_flag(Flag::Rescan)
+                    .set_info("auto-watch-limit");
+                self.event_handler.handle_event(Ok(event));
+                break;
+            }
             self.add_single_watch(entry.path().to_path_buf(), is_recursive, watch_self)?;
             watch_self = false;
+            watched_dirs += 1;
+        }
+
+        // removing the tree alone queues about three events per directory
+        if !is_auto {
+            if let Ok(limit) = INotifyWatcher::queued_events_limit() {
+                if watched_dirs > limit / 4 {
+                    self.event_handler.handle_event(Ok(overflow_risk(path)));
+                }
+            }
         }
 
         Ok(())
     }
 
+    /// Counts a watch added for a created directory, returns `false` if the
+    /// [auto watch limit](Config::with_auto_watch_limit) is reached.
+    fn take_auto_watch(&mut self) -> bool {
+        let Some((max_watches, interval)) = self.auto_watch_limit else {
+            return true;
+        };
+        let now = Instant::now();
+        if now.duration_since(self.auto_watches.0) >= interval {
+            self.auto_watches = (now, 0);
+        }
+        if self.auto_watches.1 >= max_watches {
+            return false;
+        }
+        self.auto_watches.1 += 1;
+        true
+    }
+
+    /// Number of levels `path` is below the closest explicitly watched path.
+    fn depth_below_root(&self, path: &Path) -> usize {
+        self.watches
+            .iter()
+            .filter(|(p, (_, mask, _, _))| {
+                path.starts_with(p) && mask.contains(WatchMask::DELETE_SELF)
+            })
+            .map(|(p, _)| path.components().count() - p.components().count())
+            .min()
+            .unwrap_or(0)
+    }
+
     fn add_single_watch(
         &mut self,
         path: PathBuf,
         is_recursive: bool,
         watch_self: bool,
     ) -> Result<()> {
-        let mut watchmask = WatchMask::ATTRIB
-            | WatchMask::CREATE
-            | WatchMask::OPEN
-            | WatchMask::DELETE
-            | WatchMask::CLOSE_WRITE
-            | WatchMask::MODIFY
-            | WatchMask::MOVED_FROM
-            | WatchMask::MOVED_TO;
+        let mut watchmask = self.watch_mask;
 
         if watch_self {
             watchmask.insert(WatchMask::DELETE_SELF);
             watchmask.insert(WatchMask::MOVE_SELF);
         }
 
+        if !self.follow_links || (watch_self && self.symlinks_as_links) {
+            watchmask.insert(WatchMask::DONT_FOLLOW);
+        }
+
         if let Some(&(_, old_watchmask, _, _)) = self.watches.get(&path) {
             watchmask.insert(old_watchmask);
             watchmask.insert(WatchMask::MASK_ADD);
@@ -455,10 +941,25 @@ impl EventLoop {
                 }
                 Ok(w) => {
                     watchmask.remove(WatchMask::MASK_ADD);
-                    let is_dir = metadata(&path).map_err(Error::io)?.is_dir();
-                    self.watches
-                        .insert(path.clone(), (w.clone(), watchmask, is_recursive, is_dir));
-                    self.paths.insert(w, path);
+                    let metadata = if watchmask.contains(WatchMask::DONT_FOLLOW) {
+                        symlink_metadata(&path)
+                    } else {
+                        metadata(&path)
+                    }
+                    .map_err(Error::io)?;
+                    self.watches.insert(
+                        path.clone(),
+                        (w.clone(), watchmask, is_recursive, metadata.is_dir()),
+                    );
+                    if let Some(devices) = &mut self.devices {
+                        devices.insert(w.clone(), metadata.dev());
+                    }
+                    if let Some(attributes) = &mut self.attributes {
+                        attributes.update(&path);
+                    }
+                    if self.paths.insert(w, path.clone()).is_none() {
+                        self.lifecycle.watch_added(&path);
+                    }
                     Ok(())
                 }
             }
@@ -470,29 +971,51 @@ impl EventLoop {
     fn remove_watch(&mut self, path: PathBuf, remove_recursive: bool) -> Result<()> {
         match self.watches.remove(&path) {
             None => return Err(Error::watch_not_found().add_path(path)),
-            Some((w, _, is_recursive, _)) => {
+            Some((w, _, _, _)) => {
                 if let Some(ref mut inotify) = self.inotify {
                     let mut inotify_watches = inotify.watches();
                     log::trace!("removing inotify watch: {}", path.display());
 
-                    inotify_watches
-                        .remove(w.clone())
-                        .map_err(|e| Error::io(e).add_path(path.clone()))?;
+                    // fails if the kernel already removed the watch, e.g. along with the path
+                    let removed = inotify_watches.remove(w.clone());
                     self.paths.remove(&w);
+                    if let Some(devices) = &mut self.devices {
+                        devices.remove(&w);
+                    }
+                    self.lifecycle.watch_removed(&path);
+                    removed.map_err(|e| Error::io(e).add_path(path.clone()))?;
 
-                    if is_recursive || remove_recursive {
-                        let mut remove_list = Vec::new();
-                        for (w, p) in &self.paths {
-                            if p.starts_with(&path) {
-                                inotify_watches
-                                    .remove(w.clone())
-                                    .map_err(|e| Error::io(e).add_path(p.into()))?;
-                                self.watches.remove(p);
-                                remove_list.push(w.clone());
-                            }
+                    // Paths below the removed one that were watched explicitly stay watched,
+                    // along with their own descendants. Watches added for them automatically
+                    // are removed, even if the removed path itself wasn't watched recursively.
+                    let explicit_roots: Vec<PathBuf> = if remove_recursive {
+                        Vec::new()
+                    } else {
+                        self.watches
+                            .iter()
+                            .filter(|(p, (_, mask, _, _))| {
+                                p.starts_with(&path) && mask.contains(WatchMask::DELETE_SELF)
+                            })
+                            .map(|(p, _)| p.clone())
+                            .collect()
+                    };
+
+                    let mut remove_list = Vec::new();
+                    for (w, p) in &self.paths {
+                        if p.starts_with(&path) && !explicit_roots.iter().any(|r| p.starts_with(r))
+                        {
+                            inotify_watches
+                                .remove(w.clone())
+                                .map_err(|e| Error::io(e).add_path(p.into()))?;
+                            self.watches.remove(p);
+                            self.lifecycle.watch_removed(p);
+                            remove_list.push(w.clone());
                         }
-                        for w in remove_list {
-                            self.paths.remove(&w);
+                    }
+                    for w in remove_list {
+                        self.paths.remove(&w);
+                        if let Some(devices) = &mut self.devices {
+                            devices.remove(&w);
                         }
                     }
                 }
@@ -501,6 +1024,38 @@ impl EventLoop {
         Ok(())
     }
 
+    /// Removes all explicitly watched paths below `prefix` and their descendants.
+    fn remove_watch_prefix(&mut self, prefix: &Path) -> Result<Vec<PathBuf>> {
+        let mut roots: Vec<PathBuf> = self
+            .watches
+            .iter()
+            .filter(|(p, (_, mask, _, _))| {
+                p.starts_with(prefix) && mask.contains(WatchMask::DELETE_SELF)
+            })
+            .map(|(p, _)| p.clone())
+            .collect();
+        roots.sort();
+
+        for root in &roots {
+            // already removed along with a root above it
+            if self.watches.contains_key(root) {
+                self.remove_watch(root.clone(), true)?;
+            }
+        }
+        Ok(roots)
+    }
+
+    /// The explicitly watched paths, recognizable by watching their own removal.
+    fn watched_paths(&self) -> Vec<(PathBuf, RecursiveMode)> {
+        self.watches
+            .iter()
+            .filter(|(_, (_, mask, _, _))| mask.contains(WatchMask::DELETE_SELF))
+            .map(|(p, &(_, _, is_recursive, _))| {
+                (p.clone(), RecursiveMode::from_bool(is_recursive))
+            })
+            .collect()
+    }
+
     fn remove_all_watches(&mut self) -> Result<()> {
         if let Some(ref mut inotify) = self.inotify {
             let mut inotify_watches = inotify.watches();
@@ -508,14 +1063,60 @@ impl EventLoop {
                 inotify_watches
                     .remove(w.clone())
                     .map_err(|e| Error::io(e).add_path(p.into()))?;
+                self.lifecycle.watch_removed(p);
             }
             self.watches.clear();
             self.paths.clear();
+            if let Some(devices) = &mut self.devices {
+                devices.clear();
+            }
         }
         Ok(())
     }
 }
 
+/// Converts `event` into the closest set of inotify event flags, for interop with tools expecting
+/// `IN_*` masks.
+///
+/// This reverses the translation of the [`INotifyWatcher`]: a folder creation maps to
+/// `IN_CREATE | IN_ISDIR`, a close-write to `IN_CLOSE_WRITE`, both sides of a rename to
+/// `IN_MOVE`, a rescan request without paths to `IN_Q_OVERFLOW`, an invalidated watch to
+/// `IN_IGNORED` and so on. Kinds without an inotify equivalent map to `0`.
+pub fn event_to_inotify_mask(event: &Event) -> u32 {
+    let dir_flag = |is_dir: bool| {
+        if is_dir {
+            EventMask::ISDIR
+        } else {
+            EventMask::empty()
+        }
+    };
+    if event.is_unmount() {
+        return EventMask::UNMOUNT.bits();
+    }
+    if event.is_watch_invalidated() {
+        return EventMask::IGNORED.bits();
+    }
+    let mask = match event.kind {
+        EventKind::Access(AccessKind::Read) => EventMask::ACCESS,
+        EventKind::Access(AccessKind::Open(_)) => EventMask::OPEN,
+        EventKind::Access(AccessKind::Close(AccessMode::Write)) => EventMask::CLOSE_WRITE,
+        EventKind::Access(AccessKind::Close(AccessMode::Read)) => EventMask::CLOSE_NOWRITE,
+        EventKind::Access(AccessKind::Close(_)) => {
+            EventMask::CLOSE_WRITE | EventMask::CLOSE_NOWRITE
+        }
+        EventKind::Create(kind) => EventMask::CREATE | dir_flag(kind == CreateKind::Folder),
+        EventKind::Remove(kind) => EventMask::DELETE | dir_flag(kind == RemoveKind::Folder),
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => EventMask::MOVED_FROM,
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => EventMask::MOVED_TO,
+        EventKind::Modify(ModifyKind::Name(_)) => EventMask::MOVED_FROM | EventMask::MOVED_TO,
+        EventKind::Modify(ModifyKind::Metadata(_)) => EventMask::ATTRIB,
+        EventKind::Modify(_) => EventMask::MODIFY,
+        EventKind::Other if event.need_rescan() && event.paths.is_empty() => EventMask::Q_OVERFLOW,
+        _ => EventMask::empty(),
+    };
+    mask.bits()
+}
+
 /// return `DirEntry` when it is a directory
 fn filter_dir(e: walkdir::Result<walkdir::DirEntry>) -> Option<walkdir::DirEntry> {
     if let Ok(e) = e {
@@ -528,17 +1129,134 @@ fn filter_dir(e: walkdir::Result<walkdir::DirEntry>) -> Option<walkdir::DirEntry
     None
 }
 
+/// The device and inode number of the file at `path`, if it still exists.
+fn read_file_id(path: &Path) -> Option<FileId> {
+    let metadata = metadata(path).ok()?;
+    Some(FileId::new_inode(metadata.dev(), metadata.ino()))
+}
+
+/// Whether `path` is on a filesystem for which inotify reports changes.
+///
+/// Returns `true` if the filesystem can't be determined.
+fn supports_events(path: &Path) -> bool {
+    let Ok(path) = CString::new(path.as_os_str().as_bytes()) else {
+        return true;
+    };
+    let mut stat = MaybeUninit::<libc::statfs>::uninit();
+    // SAFETY: `path` is a valid C string and `stat` is only read if `statfs` filled it
+    let f_type = unsafe {
+        if libc::statfs(path.as_ptr(), stat.as_mut_ptr()) != 0 {
+            return true;
+        }
+        stat.assume_init().f_type
+    };
+    !UNSUPPORTED_FILESYSTEMS.contains(&(f_type as u32))
+}
+
 impl INotifyWatcher {
+    /// Create a new watcher, using `traversal` to decide which directories get a watch when a
+    /// path is watched recursively.
+    pub fn with_traversal<F: EventHandler>(
+        event_handler: F,
+        config: Config,
+        traversal: Traversal,
+    ) -> Result<Self> {
+        Self::with_hooks(event_handler, config, traversal, Lifecycle::default())
+    }
+
+    /// Create a new watcher, invoking the `lifecycle` callbacks for the operations of its thread.
+    pub fn with_lifecycle<F: EventHandler>(
+        event_handler: F,
+        config: Config,
+        lifecycle: Lifecycle,
+    ) -> Result<Self> {
+        Self::with_hooks(event_handler, config, Traversal::default(), lifecycle)
+    }
+
+    /// Create a new watcher using both a `traversal`, see [`with_traversal`](Self::with_traversal),
+    /// and `lifecycle` callbacks, see [`with_lifecycle`](Self::with_lifecycle).
+    pub fn with_hooks<F: EventHandler>(
+        event_handler: F,
+        config: Config,
+        traversal: Traversal,
+        lifecycle: Lifecycle,
+    ) -> Result<Self> {
+        let counters = Arc::<Counters>::default();
+        let mut leftovers = Leftovers::default();
+        let dispatch = dispatch::from_config(counters.handler(event_handler), &config)?;
+        leftovers.flush_on_take(dispatch.flush());
+        let settle = settle::from_config(leftovers.handler(dispatch), &config)?;
+        leftovers.flush_on_take(settle.flush());
+        Self::from_event_handler(
+            Box::new(AttributeHandler::new(settle, &config, Self::kind())),
+            config,
+            traversal,
+            lifecycle,
+            leftovers,
+            counters,
+        )
+    }
+
+    /// The maximum number of events queued for an inotify instance, read from
+    /// `/proc/sys/fs/inotify/max_queued_events`.
+    ///
+    /// Once the queue is full, further events are lost and an event for which
+    /// [`Event::need_rescan`](crate::Event::need_rescan) returns `true` is emitted. When a path
+    /// is watched recursively whose tree has more directories than a quarter of the limit, an
+    /// event for which [`Event::is_overflow_risk`](crate::Event::is_overflow_risk) returns `true`
+    /// is emitted for it.
+    pub fn queued_events_limit() -> io::Result<usize> {
+        std::fs::read_to_string("/proc/sys/fs/inotify/max_queued_events")?
+            .trim()
+            .parse()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Watch `path` like [`Watcher::watch`], but set up the watches in the background.
+    ///
+    /// Returns right away, while the watches are added by the watcher's thread. This avoids
+    /// blocking the caller while a large tree is traversed. Once all watches are installed, an
+    /// event for which [`Event::is_watch_ready`](crate::Event::is_watch_ready) returns `true` is
+    /// emitted for `path`. Errors are emitted to the event handler as well. Changes are reported
+    /// for the parts of the tree that are already watched.
+    pub fn watch_async(&mut self, path: &Path, recursive_mode: RecursiveMode) -> Result<()> {
+        let pb = if path.is_absolute() {
+            path.to_owned()
+        } else {
+            let p = env::current_dir().map_err(Error::io)?;
+            p.join(path)
+        };
+        let msg = EventLoopMsg::AddWatch(pb, recursive_mode, None);
+
+        // the event loop is gone if it panicked
+        self.channel.send(msg)?;
+        self.waker.wake()?;
+        Ok(())
+    }
+
     fn from_event_handler(
         event_handler: Box<dyn EventHandler>,
-        follow_links: bool,
+        config: Config,
+        traversal: Traversal,
+        lifecycle: Lifecycle,
+        leftovers: Leftovers,
+        counters: Arc<Counters>,
     ) -> Result<Self> {
-        let inotify = Inotify::init()?;
-        let event_loop = EventLoop::new(inotify, event_handler, follow_links)?;
+        let inotify = Inotify::init().map_err(Error::io_watch)?;
+        let gate = PauseGate::new(event_handler, &config, counters);
+        let mut event_loop = EventLoop::new(inotify, Box::new(gate.clone()), &config)?;
+        event_loop.traversal = traversal;
+        event_loop.lifecycle = lifecycle;
         let channel = event_loop.event_loop_tx.clone();
         let waker = event_loop.event_loop_waker.clone();
         event_loop.run();
-        Ok(INotifyWatcher { channel, waker })
+        Ok(INotifyWatcher {
+            channel,
+            waker,
+            gate,
+            leftovers,
+            follow_symlinks: config.follow_symlinks(),
+        })
     }
 
     fn watch_inner(&mut self, path: &Path, recursive_mode: RecursiveMode) -> Result<()> {
@@ -549,12 +1267,12 @@ impl INotifyWatcher {
             p.join(path)
         };
         let (tx, rx) = unbounded();
-        let msg = EventLoopMsg::AddWatch(pb, recursive_mode, tx);
+        let msg = EventLoopMsg::AddWatch(pb, recursive_mode, Some(tx));
 
-        // we expect the event loop to live and reply => unwraps must not panic
-        self.channel.send(msg).unwrap();
-        self.waker.wake().unwrap();
-        rx.recv().unwrap()
+        // the event loop is gone if it panicked
+        self.channel.send(msg)?;
+        self.waker.wake()?;
+        rx.recv()?
     }
 
     fn unwatch_inner(&mut self, path: &Path) -> Result<()> {
@@ -567,27 +1285,64 @@ impl INotifyWatcher {
         let (tx, rx) = unbounded();
         let msg = EventLoopMsg::RemoveWatch(pb, tx);
 
-        // we expect the event loop to live and reply => unwraps must not panic
-        self.channel.send(msg).unwrap();
-        self.waker.wake().unwrap();
-        rx.recv().unwrap()
+        // the event loop is gone if it panicked
+        self.channel.send(msg)?;
+        self.waker.wake()?;
+        rx.recv()?
+    }
+
+    fn watched_paths_inner(&self) -> Result<Vec<(PathBuf, RecursiveMode)>> {
+        let (tx, rx) = unbounded();
+
+        // the event loop is gone if it panicked
+        self.channel.send(EventLoopMsg::WatchedPaths(tx))?;
+        self.waker.wake()?;
+        Ok(rx.recv()?)
+    }
+
+    fn unwatch_prefix_inner(&mut self, prefix: &Path) -> Result<Vec<PathBuf>> {
+        let pb = if prefix.is_absolute() {
+            prefix.to_owned()
+        } else {
+            let p = env::current_dir().map_err(Error::io)?;
+            p.join(prefix)
+        };
+        let (tx, rx) = unbounded();
+        let msg = EventLoopMsg::RemoveWatchPrefix(pb, tx);
+
+        // the event loop is gone if it panicked
+        self.channel.send(msg)?;
+        self.waker.wake()?;
+        rx.recv()?
     }
 }
 
 impl Watcher for INotifyWatcher {
     /// Create a new watcher.
     fn new<F: EventHandler>(event_handler: F, config: Config) -> Result<Self> {
-        Self::from_event_handler(Box::new(event_handler), config.follow_symlinks())
+        Self::with_traversal(event_handler, config, Traversal::default())
     }
 
     fn watch(&mut self, path: &Path, recursive_mode: RecursiveMode) -> Result<()> {
-        self.watch_inner(path, recursive_mode)
+        self.watch_inner(path, recursive_mode)?;
+        self.gate.root_watched(path);
+        Ok(())
     }
 
     fn unwatch(&mut self, path: &Path) -> Result<()> {
+        self.gate.root_unwatched(path, false);
         self.unwatch_inner(path)
     }
 
+    fn watched_paths(&self) -> Result<Vec<(PathBuf, RecursiveMode)>> {
+        self.watched_paths_inner()
+    }
+
+    fn unwatch_prefix(&mut self, prefix: &Path) -> Result<Vec<PathBuf>> {
+        self.gate.root_unwatched(prefix, true);
+        self.unwatch_prefix_inner(prefix)
+    }
+
     fn configure(&mut self, config: Config) -> Result<bool> {
         let (tx, rx) = bounded(1);
         self.channel.send(EventLoopMsg::Configure(config, tx))?;
@@ -595,6 +1350,44 @@ impl Watcher for INotifyWatcher {
         rx.recv()?
     }
 
+    fn pause(&mut self) -> Result<()> {
+        self.gate.pause();
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        self.gate.resume();
+        Ok(())
+    }
+
+    fn resync(&mut self) -> Result<()> {
+        let roots = self.watched_paths()?;
+        resync::resync(&roots, self.follow_symlinks, &mut self.gate);
+        Ok(())
+    }
+
+    fn stats(&self) -> crate::WatcherStats {
+        let watches = self.watched_paths().map_or(0, |paths| paths.len());
+        self.gate.stats(watches)
+    }
+
+    fn path_supports_events(&self, path: &Path) -> bool {
+        supports_events(path)
+    }
+
+    fn stop(self) -> Vec<Result<Event>> {
+        self.leftovers.collect();
+        self.gate.resume();
+
+        let (tx, rx) = unbounded();
+        // the event loop is gone if it panicked, there is nothing to wait for then
+        if self.channel.send(EventLoopMsg::Shutdown(Some(tx))).is_ok() {
+            let _ = self.waker.wake();
+            let _ = rx.recv();
+        }
+        self.leftovers.take()
+    }
+
     fn kind() -> crate::WatcherKind {
         crate::WatcherKind::Inotify
     }
@@ -602,9 +1395,9 @@ impl Watcher for INotifyWatcher {
 
 impl Drop for INotifyWatcher {
     fn drop(&mut self) {
-        // we expect the event loop to live => unwrap must not panic
-        self.channel.send(EventLoopMsg::Shutdown).unwrap();
-        self.waker.wake().unwrap();
+        // the event loop is already gone if the watcher was stopped
+        let _ = self.channel.send(EventLoopMsg::Shutdown(None));
+        let _ = self.waker.wake();
     }
 }
 
@@ -613,3 +1406,962 @@ fn inotify_watcher_is_send_and_sync() {
     fn check<T: Send + Sync>() {}
     check::<INotifyWatcher>();
 }
+
+#[test]
+fn prefer_close_write_suppresses_modify() {
+    use std::io::Write;
+
+    let dir = tempfile::tempdir().unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    let config = Config::default().with_prefer_close_write(Duration::from_secs(10));
+    let mut watcher = INotifyWatcher::new(tx, config).unwrap();
+    watcher
+        .watch(dir.path(), RecursiveMode::NonRecursive)
+        .unwrap();
+
+    let path = dir.path().join("file");
+    let mut file = std::fs::File::create(path).unwrap();
+    file.write_all(b"Lorem ipsum").unwrap();
+    drop(file);
+
+    let kinds: Vec<_> = rx
+        .iter()
+        .map(|res| res.unwrap().kind)
+        .take_while(|kind| *kind != EventKind::Access(AccessKind::Close(AccessMode::Write)))
+        .collect();
+    assert!(!kinds.contains(&EventKind::Modify(ModifyKind::Data(DataChange::Any))));
+}
+
+#[test]
+fn prefer_close_write_flushes_modify_on_timeout() {
+    use std::io::Write;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    let mut file = std::fs::File::create(&path).unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let config = Config::default().with_prefer_close_write(Duration::from_millis(100));
+    let mut watcher = INotifyWatcher::new(tx, config).unwrap();
+    watcher
+        .watch(dir.path(), RecursiveMode::NonRecursive)
+        .unwrap();
+
+    file.write_all(b"Lorem ipsum").unwrap();
+
+    let event = rx
+        .recv_timeout(Duration::from_secs(5))
+        .expect("no events received")
+        .unwrap();
+    assert_eq!(
+        event.kind,
+        EventKind::Modify(ModifyKind::Data(DataChange::Any))
+    );
+    assert_eq!(event.paths, vec![path]);
+}
+
+#[test]
+fn prefer_close_write_flushes_modify_before_newer_events() {
+    use std::io::Write;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    let mut file = std::fs::File::create(&path).unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let config = Config::default().with_prefer_close_write(Duration::from_secs(10));
+    let mut watcher = INotifyWatcher::new(tx, config).unwrap();
+    watcher
+        .watch(dir.path(), RecursiveMode::NonRecursive)
+        .unwrap();
+
+    file.write_all(b"Lorem ipsum").unwrap();
+    let mut permissions = file.metadata().unwrap().permissions();
+    permissions.set_readonly(true);
+    std::fs::set_permissions(&path, permissions).unwrap();
+
+    let kinds: Vec<_> = std::iter::from_fn(|| rx.recv_timeout(Duration::from_secs(1)).ok())
+        .map(|res| res.unwrap().kind)
+        .take(2)
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            EventKind::Modify(ModifyKind::Data(DataChange::Any)),
+            EventKind::Modify(ModifyKind::Metadata(MetadataKind::Any)),
+        ]
+    );
+}
+
+#[test]
+fn unwatch_removes_automatically_added_descendants() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().to_path_buf();
+    let child = root.join("child");
+    let explicit = root.join("explicit");
+    let explicit_child = explicit.join("child");
+    for path in [&child, &explicit_child] {
+        std::fs::create_dir_all(path).unwrap();
+    }
+
+    let mut event_loop = EventLoop::new(
+        Inotify::init().unwrap(),
+        Box::new(|_| {}),
+        &Config::default(),
+    )
+    .unwrap();
+    event_loop.add_watch(root.clone(), false, true).unwrap();
+    // as added for a directory created below a recursive watch
+    event_loop.add_watch(child.clone(), true, false).unwrap();
+    event_loop.add_watch(explicit.clone(), true, true).unwrap();
+
+    event_loop.remove_watch(root.clone(), false).unwrap();
+
+    let mut watched: Vec<_> = event_loop.watches.keys().cloned().collect();
+    watched.sort();
+    assert_eq!(watched, vec![explicit, explicit_child]);
+    assert_eq!(event_loop.paths.len(), 2);
+}
+
+#[test]
+fn traversal_skips_directories() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().to_path_buf();
+    for path in ["a/x/skip", "skip/y", "deep/1/2/3"] {
+        std::fs::create_dir_all(root.join(path)).unwrap();
+    }
+
+    let mut event_loop = EventLoop::new(
+        Inotify::init().unwrap(),
+        Box::new(|_| {}),
+        &Config::default(),
+    )
+    .unwrap();
+    event_loop.traversal = Traversal::new()
+        .with_max_depth(2)
+        .with_skip(|path| path.ends_with("skip"));
+    event_loop.add_watch(root.clone(), true, true).unwrap();
+    // as added for a directory created below a recursive watch
+    event_loop
+        .add_watch(root.join("a/x/skip"), true, false)
+        .unwrap();
+    std::fs::create_dir_all(root.join("a/z/w")).unwrap();
+    event_loop.add_watch(root.join("a/z"), true, false).unwrap();
+
+    let mut watched: Vec<_> = event_loop.watches.keys().cloned().collect();
+    watched.sort();
+    let expected: Vec<_> = ["", "a", "a/x", "a/z", "deep", "deep/1"]
+        .into_iter()
+        .map(|p| {
+            if p.is_empty() {
+                root.clone()
+            } else {
+                root.join(p)
+            }
+        })
+        .collect();
+    assert_eq!(watched, expected);
+    assert_eq!(event_loop.paths.len(), expected.len());
+}
+
+#[test]
+fn unsupported_filesystem_is_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let proc = Path::new("/proc/self");
+    if supports_events(proc) {
+        // procfs is not mounted
+        return;
+    }
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = INotifyWatcher::new(tx, Config::default()).unwrap();
+    assert!(watcher.path_supports_events(dir.path()));
+    assert!(!watcher.path_supports_events(proc));
+
+    watcher.watch(proc, RecursiveMode::NonRecursive).unwrap();
+    let error = rx
+        .recv_timeout(Duration::from_secs(5))
+        .expect("no events received")
+        .unwrap_err();
+    assert!(matches!(error.kind, ErrorKind::UnsupportedFilesystem));
+    assert_eq!(error.paths, vec![proc.to_path_buf()]);
+}
+
+#[test]
+fn deleted_root_invalidates_watch() {
+    let dir = tempfile::tempdir().unwrap();
+    let watched = dir.path().join("watched");
+    std::fs::create_dir(&watched).unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = INotifyWatcher::new(tx, Config::default()).unwrap();
+    watcher.watch(&watched, RecursiveMode::Recursive).unwrap();
+
+    std::fs::remove_dir(&watched).unwrap();
+
+    let event = rx
+        .iter()
+        .map(|res| res.unwrap())
+        .find(|event| event.is_watch_invalidated())
+        .unwrap();
+    assert_eq!(event.paths, vec![watched]);
+}
+
+#[test]
+fn event_file_ids() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = INotifyWatcher::new(tx, Config::default().with_event_file_ids(true)).unwrap();
+    watcher
+        .watch(dir.path(), RecursiveMode::NonRecursive)
+        .unwrap();
+
+    std::fs::write(&path, b"Lorem ipsum").unwrap();
+    let metadata = std::fs::metadata(&path).unwrap();
+    let file_id = FileId::new_inode(metadata.dev(), metadata.ino());
+
+    let close_write = EventKind::Access(AccessKind::Close(AccessMode::Write));
+    for event in rx.iter().map(|res| res.unwrap()) {
+        assert_eq!(event.file_id(), Some(file_id), "{event:?}");
+        if event.kind == close_write {
+            break;
+        }
+    }
+
+    std::fs::remove_file(&path).unwrap();
+    let event = rx
+        .iter()
+        .map(|res| res.unwrap())
+        .find(|event| event.kind.is_remove())
+        .unwrap();
+    assert_eq!(event.file_id(), None);
+}
+
+#[test]
+fn event_device_ids() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    let device_id = std::fs::metadata(dir.path()).unwrap().dev();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher =
+        INotifyWatcher::new(tx, Config::default().with_event_device_ids(true)).unwrap();
+    watcher
+        .watch(dir.path(), RecursiveMode::NonRecursive)
+        .unwrap();
+
+    std::fs::write(&path, b"").unwrap();
+    std::fs::remove_file(&path).unwrap();
+    for event in rx.iter().map(|res| res.unwrap()) {
+        assert_eq!(event.device_id(), Some(device_id), "{event:?}");
+        if event.kind.is_remove() {
+            break;
+        }
+    }
+}
+
+#[test]
+fn symlinked_directories_are_not_followed() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("root");
+    let target = dir.path().join("target");
+    std::fs::create_dir_all(target.join("sub")).unwrap();
+    std::fs::create_dir(&root).unwrap();
+    std::os::unix::fs::symlink(&target, root.join("link")).unwrap();
+
+    let mut event_loop = EventLoop::new(
+        Inotify::init().unwrap(),
+        Box::new(|_| {}),
+        &Config::default().with_follow_symlinks(false),
+    )
+    .unwrap();
+    event_loop.add_watch(root.clone(), true, true).unwrap();
+
+    let watched: Vec<_> = event_loop.watches.keys().cloned().collect();
+    assert_eq!(watched, vec![root]);
+}
+
+#[test]
+fn events_read_at_once_are_batched() {
+    let dir = tempfile::tempdir().unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut event_loop = EventLoop::new(
+        Inotify::init().unwrap(),
+        Box::new(crate::Batched::new(tx)),
+        &Config::default(),
+    )
+    .unwrap();
+    event_loop
+        .add_watch(dir.path().to_path_buf(), false, true)
+        .unwrap();
+
+    const FILES: usize = 100;
+    for i in 0..FILES {
+        std::fs::File::create(dir.path().join(i.to_string())).unwrap();
+    }
+    event_loop.handle_inotify();
+
+    let batches: Vec<_> = rx.try_iter().collect();
+    let created = batches
+        .iter()
+        .flatten()
+        .filter(|event| event.as_ref().unwrap().kind.is_create())
+        .count();
+    assert_eq!(created, FILES);
+    assert!(batches.len() < FILES, "{} batches", batches.len());
+}
+
+#[test]
+fn overflows_are_counted() {
+    let dir = tempfile::tempdir().unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut event_loop =
+        EventLoop::new(Inotify::init().unwrap(), Box::new(tx), &Config::default()).unwrap();
+    event_loop
+        .add_watch(dir.path().to_path_buf(), false, true)
+        .unwrap();
+
+    let max_queued_events = INotifyWatcher::queued_events_limit().unwrap_or(16384);
+
+    let file = dir.path().join("file");
+    let mut overflow_counts = Vec::new();
+    for _ in 0..2 {
+        // at least three events each
+        for _ in 0..max_queued_events / 2 {
+            std::fs::File::create(&file).unwrap();
+            std::fs::remove_file(&file).unwrap();
+        }
+        event_loop.handle_inotify();
+        overflow_counts.extend(
+            rx.try_iter()
+                .filter_map(|event| event.unwrap().overflow_count()),
+        );
+    }
+    assert_eq!(overflow_counts, vec![1, 2]);
+}
+
+#[test]
+fn watch_async_reports_ready() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("a/b/c")).unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = INotifyWatcher::new(tx, Config::default()).unwrap();
+    watcher
+        .watch_async(dir.path(), RecursiveMode::Recursive)
+        .unwrap();
+
+    // traversing the tree emits access events for the directories
+    let next_event = || loop {
+        let event = rx.recv_timeout(Duration::from_secs(5)).unwrap().unwrap();
+        if !event.kind.is_access() {
+            break event;
+        }
+    };
+    let event = next_event();
+    assert!(event.is_watch_ready(), "{event:?}");
+    assert_eq!(event.paths, vec![dir.path().to_path_buf()]);
+
+    std::fs::write(dir.path().join("a/b/c/file"), b"").unwrap();
+    let event = next_event();
+    assert!(event.kind.is_create(), "{event:?}");
+
+    watcher
+        .watch_async(&dir.path().join("missing"), RecursiveMode::Recursive)
+        .unwrap();
+    let error = rx.iter().find_map(|res| res.err()).unwrap();
+    assert_eq!(error.paths, vec![dir.path().join("missing")]);
+}
+
+#[test]
+fn unwatch_prefix_removes_roots_and_descendants() {
+    let dir = tempfile::tempdir().unwrap();
+    let old_module = dir.path().join("old_module");
+    let nested = old_module.join("a/b");
+    let other = dir.path().join("other");
+    std::fs::create_dir_all(&nested).unwrap();
+    std::fs::create_dir(&other).unwrap();
+
+    let (tx, _rx) = std::sync::mpsc::channel();
+    let mut event_loop =
+        EventLoop::new(Inotify::init().unwrap(), Box::new(tx), &Config::default()).unwrap();
+    event_loop
+        .add_watch(old_module.clone(), true, true)
+        .unwrap();
+    event_loop.add_watch(nested.clone(), false, true).unwrap();
+    event_loop.add_watch(other.clone(), true, true).unwrap();
+
+    let removed = event_loop.remove_watch_prefix(&old_module).unwrap();
+    assert_eq!(removed, vec![old_module, nested]);
+
+    let watched: Vec<_> = event_loop.watches.keys().cloned().collect();
+    assert_eq!(watched, vec![other.clone()]);
+    assert_eq!(event_loop.paths.len(), 1);
+
+    assert!(event_loop
+        .remove_watch_prefix(&dir.path().join("missing"))
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn set_watches_replaces_the_watched_paths() {
+    let dir = tempfile::tempdir().unwrap();
+    for name in ["a", "b", "c"] {
+        std::fs::create_dir_all(dir.path().join(name).join("sub")).unwrap();
+    }
+
+    let (tx, _rx) = std::sync::mpsc::channel();
+    let mut watcher = INotifyWatcher::new(tx, Config::default()).unwrap();
+    watcher
+        .set_watches(&[
+            (&dir.path().join("a"), RecursiveMode::Recursive),
+            (&dir.path().join("b"), RecursiveMode::Recursive),
+        ])
+        .unwrap();
+    watcher
+        .set_watches(&[
+            (&dir.path().join("b"), RecursiveMode::Recursive),
+            (&dir.path().join("c"), RecursiveMode::NonRecursive),
+        ])
+        .unwrap();
+
+    let mut watched = watcher.watched_paths().unwrap();
+    watched.sort();
+    assert_eq!(
+        watched,
+        vec![
+            (dir.path().join("b"), RecursiveMode::Recursive),
+            (dir.path().join("c"), RecursiveMode::NonRecursive),
+        ]
+    );
+}
+
+#[test]
+fn stop_returns_the_undelivered_events() {
+    let dir = tempfile::tempdir().unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    let config = Config::default().with_buffer_paused_events(true);
+    let mut watcher = INotifyWatcher::new(tx, config).unwrap();
+    watcher.watch(dir.path(), RecursiveMode::Recursive).unwrap();
+
+    watcher.pause().unwrap();
+    let files: Vec<_> = (0..20).map(|i| dir.path().join(i.to_string())).collect();
+    for file in &files {
+        std::fs::write(file, b"").unwrap();
+    }
+    let leftovers = watcher.stop();
+
+    assert!(rx.try_recv().is_err());
+    for file in &files {
+        assert!(
+            leftovers.iter().any(|event| {
+                let event = event.as_ref().unwrap();
+                event.kind.is_create() && event.paths == [file.clone()]
+            }),
+            "missing create of {file:?}: {leftovers:?}"
+        );
+    }
+}
+
+#[test]
+fn stop_returns_the_unsettled_creations() {
+    let dir = tempfile::tempdir().unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    let config = Config::default().with_min_create_age(Duration::from_secs(60));
+    let mut watcher = INotifyWatcher::new(tx, config).unwrap();
+    watcher.watch(dir.path(), RecursiveMode::Recursive).unwrap();
+
+    let file = dir.path().join("file");
+    std::fs::write(&file, b"").unwrap();
+    // wait until the creation is held back
+    thread::sleep(Duration::from_millis(100));
+    let leftovers = watcher.stop();
+
+    assert!(rx.try_recv().is_err());
+    assert!(
+        leftovers.iter().any(|event| {
+            let event = event.as_ref().unwrap();
+            event.kind.is_create() && event.paths == [file.clone()]
+        }),
+        "missing create of {file:?}: {leftovers:?}"
+    );
+}
+
+#[test]
+fn stop_waits_for_the_dispatched_events() {
+    let dir = tempfile::tempdir().unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    let slow = move |res: Result<Event>| {
+        thread::sleep(Duration::from_millis(20));
+        tx.send(res).unwrap();
+    };
+    let config = Config::default().with_channel_capacity(0);
+    let mut watcher = INotifyWatcher::new(slow, config).unwrap();
+    watcher.watch(dir.path(), RecursiveMode::Recursive).unwrap();
+
+    for i in 0..5 {
+        std::fs::write(dir.path().join(i.to_string()), b"").unwrap();
+    }
+    thread::sleep(Duration::from_millis(50));
+    let leftovers = watcher.stop();
+    let delivered = rx.try_iter().count();
+
+    // nothing is delivered once stopped
+    thread::sleep(Duration::from_millis(200));
+    assert!(rx.try_recv().is_err());
+    assert!(delivered + leftovers.len() >= 5);
+}
+
+#[test]
+fn symlinks_can_be_watched_as_links() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("target");
+    let other = dir.path().join("other");
+    let link = dir.path().join("link");
+    std::fs::write(&target, b"").unwrap();
+    std::fs::write(&other, b"").unwrap();
+    std::os::unix::fs::symlink(&target, &link).unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let config = Config::default().with_watch_symlinks_as_links(true);
+    let mut watcher = INotifyWatcher::new(tx, config).unwrap();
+    watcher.watch(&link, RecursiveMode::Recursive).unwrap();
+
+    std::fs::write(&target, b"changed").unwrap();
+    if let Ok(event) = rx.recv_timeout(Duration::from_millis(200)) {
+        panic!("unexpected event: {event:?}");
+    }
+
+    // repoint the link
+    let new_link = dir.path().join("new_link");
+    std::os::unix::fs::symlink(&other, &new_link).unwrap();
+    std::fs::rename(&new_link, &link).unwrap();
+    let event = rx.recv_timeout(Duration::from_secs(1)).unwrap().unwrap();
+    assert_eq!(event.paths, [link]);
+}
+
+#[test]
+fn tree_removals_are_coalesced() {
+    let remove = |kind, path: &str| Ok(Event::new(EventKind::Remove(kind)).add_path(path.into()));
+    let mut batch = vec![
+        remove(RemoveKind::File, "/root/dir/file"),
+        remove(RemoveKind::File, "/root/dir/sub/file"),
+        remove(RemoveKind::Folder, "/root/dir/sub"),
+        Ok(
+            Event::new(EventKind::Access(AccessKind::Close(AccessMode::Write)))
+                .add_path("/root/other".into()),
+        ),
+        remove(RemoveKind::Folder, "/root/dir"),
+        remove(RemoveKind::File, "/root/directory"),
+        remove(RemoveKind::Folder, "/root/dir"),
+    ];
+    coalesce_tree_removal(&mut batch);
+
+    let events: Vec<_> = batch
+        .into_iter()
+        .map(|ev| {
+            let ev = ev.unwrap();
+            (ev.kind, ev.paths[0].clone())
+        })
+        .collect();
+    assert_eq!(
+        events,
+        [
+            (
+                EventKind::Access(AccessKind::Close(AccessMode::Write)),
+                PathBuf::from("/root/other")
+            ),
+            (
+                EventKind::Remove(RemoveKind::File),
+                PathBuf::from("/root/directory")
+            ),
+            (
+                EventKind::Remove(RemoveKind::Folder),
+                PathBuf::from("/root/dir")
+            ),
+        ]
+    );
+}
+
+#[test]
+fn lifecycle_callbacks_follow_the_watches() {
+    let dir = tempfile::tempdir().unwrap();
+    let sub = dir.path().join("sub");
+    std::fs::create_dir(&sub).unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let (started, added, removed, exited) = (tx.clone(), tx.clone(), tx.clone(), tx);
+    let lifecycle = Lifecycle::new()
+        .with_on_thread_start(move || started.send("start".to_string()).unwrap())
+        .with_on_watch_added(move |path| added.send(format!("add {}", path.display())).unwrap())
+        .with_on_watch_removed(move |path| {
+            removed.send(format!("remove {}", path.display())).unwrap()
+        })
+        .with_on_thread_exit(move || exited.send("exit".to_string()).unwrap());
+    let mut watcher = INotifyWatcher::with_lifecycle(|_| {}, Config::default(), lifecycle).unwrap();
+    let next = || rx.recv_timeout(Duration::from_secs(1)).unwrap();
+    assert_eq!(next(), "start");
+
+    watcher.watch(dir.path(), RecursiveMode::Recursive).unwrap();
+    let mut added = [next(), next()];
+    added.sort();
+    assert_eq!(
+        added,
+        [
+            format!("add {}", dir.path().display()),
+            format!("add {}", sub.display())
+        ]
+    );
+
+    // removed by the kernel along with the directory
+    std::fs::remove_dir(&sub).unwrap();
+    assert_eq!(next(), format!("remove {}", sub.display()));
+
+    watcher.unwatch(dir.path()).unwrap();
+    assert_eq!(next(), format!("remove {}", dir.path().display()));
+
+    drop(watcher);
+    assert_eq!(next(), "exit");
+}
+
+#[test]
+fn lifecycle_callbacks_follow_the_traversal() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("sub/deep")).unwrap();
+    std::fs::create_dir(dir.path().join("skip")).unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let lifecycle =
+        Lifecycle::new().with_on_watch_added(move |path| tx.send(path.to_path_buf()).unwrap());
+    let traversal = Traversal::new()
+        .with_max_depth(1)
+        .with_skip(|path| path.ends_with("skip"));
+    let mut watcher =
+        INotifyWatcher::with_hooks(|_| {}, Config::default(), traversal, lifecycle).unwrap();
+
+    watcher.watch(dir.path(), RecursiveMode::Recursive).unwrap();
+    let mut added: Vec<_> = rx.try_iter().collect();
+    added.sort();
+    assert_eq!(added, [dir.path().to_path_buf(), dir.path().join("sub")]);
+}
+
+#[test]
+fn event_kinds_narrow_the_watch_mask() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    let config =
+        Config::default().with_event_kinds(&[EventKindMask::Create, EventKindMask::Remove]);
+    assert!(!watch_mask(&config).intersects(WatchMask::MODIFY | WatchMask::OPEN));
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = INotifyWatcher::new(tx, config).unwrap();
+    watcher
+        .watch(dir.path(), RecursiveMode::NonRecursive)
+        .unwrap();
+
+    std::fs::write(&path, b"data").unwrap();
+    std::fs::write(&path, b"more data").unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let kinds: Vec<_> = std::iter::from_fn(|| rx.recv_timeout(Duration::from_millis(500)).ok())
+        .map(|event| event.unwrap().kind)
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            EventKind::Create(CreateKind::File),
+            EventKind::Remove(RemoveKind::File)
+        ]
+    );
+}
+
+#[test]
+fn only_hidden_files_are_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = Config::default().with_hidden_files(crate::HiddenFiles::Only);
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = INotifyWatcher::new(tx, config).unwrap();
+    watcher
+        .watch(dir.path(), RecursiveMode::NonRecursive)
+        .unwrap();
+
+    std::fs::write(dir.path().join("visible"), b"data").unwrap();
+    std::fs::write(dir.path().join(".hidden"), b"data").unwrap();
+
+    let paths: Vec<_> = std::iter::from_fn(|| rx.recv_timeout(Duration::from_millis(500)).ok())
+        .flat_map(|event| event.unwrap().paths)
+        .collect();
+    assert!(!paths.is_empty());
+    assert!(
+        paths.iter().all(|path| path.ends_with(".hidden")),
+        "{paths:?}"
+    );
+}
+
+#[test]
+fn panics_in_the_event_loop_are_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut panicked = false;
+    let event_handler = move |res: Result<Event>| {
+        if res.is_ok() && !panicked {
+            panicked = true;
+            panic!("forced panic");
+        }
+        tx.send(res).unwrap();
+    };
+    let mut watcher = INotifyWatcher::new(event_handler, Config::default()).unwrap();
+    watcher
+        .watch(dir.path(), RecursiveMode::NonRecursive)
+        .unwrap();
+
+    std::fs::write(dir.path().join("file"), b"data").unwrap();
+
+    let error = rx
+        .recv_timeout(Duration::from_secs(1))
+        .unwrap()
+        .unwrap_err();
+    assert!(
+        matches!(&error.kind, ErrorKind::WatcherPanicked(message) if message == "forced panic"),
+        "{error:?}"
+    );
+
+    // the watcher reports errors instead of panicking once its thread is gone
+    let other = tempfile::tempdir().unwrap();
+    assert!(watcher
+        .watch(other.path(), RecursiveMode::NonRecursive)
+        .is_err());
+    assert!(watcher.unwatch(dir.path()).is_err());
+    assert!(watcher.watched_paths().is_err());
+    assert!(watcher.stop().is_empty());
+}
+
+#[test]
+fn resync_reports_the_existing_paths() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("sub")).unwrap();
+    std::fs::write(dir.path().join("sub/nested"), b"data").unwrap();
+    std::fs::write(dir.path().join("file"), b"data").unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = INotifyWatcher::new(tx, Config::default()).unwrap();
+    watcher.watch(dir.path(), RecursiveMode::Recursive).unwrap();
+    watcher.resync().unwrap();
+
+    // other events may be reported for the traversal of the directories
+    let mut events: Vec<_> = rx
+        .try_iter()
+        .map(|res| res.unwrap())
+        .filter(|event| event.is_resync())
+        .collect();
+    events.sort_by(|a, b| a.paths.cmp(&b.paths));
+    assert_eq!(
+        events
+            .into_iter()
+            .map(|event| (event.kind, event.paths))
+            .collect::<Vec<_>>(),
+        [
+            (
+                EventKind::Create(CreateKind::File),
+                vec![dir.path().join("file")]
+            ),
+            (
+                EventKind::Create(CreateKind::Folder),
+                vec![dir.path().join("sub")]
+            ),
+            (
+                EventKind::Create(CreateKind::File),
+                vec![dir.path().join("sub/nested")]
+            ),
+        ]
+    );
+}
+
+#[test]
+fn auto_watch_limit_requests_a_rescan() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = Config::default().with_auto_watch_limit(2, Duration::from_secs(3600));
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = INotifyWatcher::new(tx, config).unwrap();
+    watcher.watch(dir.path(), RecursiveMode::Recursive).unwrap();
+
+    let tree = dir.path().join("tree");
+    std::fs::create_dir_all(tree.join("a/b/c/d")).unwrap();
+
+    let event = std::iter::from_fn(|| rx.recv_timeout(Duration::from_secs(1)).ok())
+        .map(|res| res.unwrap())
+        .find(|event| event.need_rescan())
+        .unwrap();
+    assert_eq!(event.info(), Some("auto-watch-limit"));
+    assert!(event.paths[0].starts_with(&tree));
+}
+
+#[test]
+fn events_convert_to_inotify_masks() {
+    let mask = |kind| event_to_inotify_mask(&Event::new(kind).add_path("/a".into()));
+    assert_eq!(mask(EventKind::Create(CreateKind::File)), libc::IN_CREATE);
+    assert_eq!(
+        mask(EventKind::Create(CreateKind::Folder)),
+        libc::IN_CREATE | libc::IN_ISDIR
+    );
+    assert_eq!(
+        mask(EventKind::Access(AccessKind::Close(AccessMode::Write))),
+        libc::IN_CLOSE_WRITE
+    );
+    assert_eq!(
+        mask(EventKind::Modify(ModifyKind::Name(RenameMode::Both))),
+        libc::IN_MOVE
+    );
+    assert_eq!(
+        mask(EventKind::Modify(ModifyKind::Metadata(MetadataKind::Any))),
+        libc::IN_ATTRIB
+    );
+    assert_eq!(
+        event_to_inotify_mask(&Event::new(EventKind::Other).set_flag(Flag::Rescan)),
+        libc::IN_Q_OVERFLOW
+    );
+    assert_eq!(
+        event_to_inotify_mask(&unmounted("/a".into())),
+        libc::IN_UNMOUNT
+    );
+    assert_eq!(mask(EventKind::Any), 0);
+}
+
+#[test]
+fn extended_attribute_changes_are_told_apart() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("file");
+    std::fs::write(&file, b"").unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let config = Config::default().with_precise_metadata_kinds(true);
+    let mut watcher = INotifyWatcher::new(tx, config).unwrap();
+    watcher.watch(&file, RecursiveMode::NonRecursive).unwrap();
+
+    std::fs::write(&file, b"data").unwrap();
+    let c_path = CString::new(file.as_os_str().as_bytes()).unwrap();
+    let value = b"1";
+    // SAFETY: the path and the value are valid for the call
+    let res = unsafe {
+        libc::setxattr(
+            c_path.as_ptr(),
+            c"user.notify".as_ptr(),
+            value.as_ptr().cast(),
+            value.len(),
+            0,
+        )
+    };
+    if res != 0 {
+        // the filesystem doesn't support user extended attributes
+        return;
+    }
+    // skips the events of the write
+    let next_kind = || loop {
+        let kind = rx
+            .recv_timeout(Duration::from_secs(1))
+            .unwrap()
+            .unwrap()
+            .kind;
+        if matches!(kind, EventKind::Modify(ModifyKind::Metadata(_))) {
+            return kind;
+        }
+    };
+    assert_eq!(
+        next_kind(),
+        EventKind::Modify(ModifyKind::Metadata(MetadataKind::Extended))
+    );
+
+    // read before the next change, inotify merges equal events in a row
+    std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o600)).unwrap();
+    assert_eq!(
+        next_kind(),
+        EventKind::Modify(ModifyKind::Metadata(MetadataKind::Permissions))
+    );
+}
+
+#[test]
+fn max_watch_depth_reports_deeper_directories() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("a/b/c")).unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let config = Config::default().with_max_watch_depth(1);
+    let mut watcher = INotifyWatcher::new(tx, config).unwrap();
+    watcher.watch(dir.path(), RecursiveMode::Recursive).unwrap();
+
+    let mut exceeded = std::iter::from_fn(|| rx.recv_timeout(Duration::from_secs(1)).ok())
+        .map(|res| res.unwrap())
+        .filter(|event| event.is_max_depth_exceeded());
+    assert_eq!(exceeded.next().unwrap().paths, [dir.path().join("a/b")]);
+
+    // created later on, beyond the bound
+    std::fs::create_dir(dir.path().join("d")).unwrap();
+    std::fs::create_dir(dir.path().join("d/e")).unwrap();
+    assert_eq!(exceeded.next().unwrap().paths, [dir.path().join("d/e")]);
+}
+
+#[test]
+fn large_trees_are_reported_as_overflow_risk() {
+    let limit = INotifyWatcher::queued_events_limit().unwrap();
+    if limit > 65536 {
+        // too many directories to create
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let small = dir.path().join("small");
+    let large = dir.path().join("large");
+    std::fs::create_dir_all(small.join("a")).unwrap();
+    for i in 0..limit / 4 {
+        std::fs::create_dir_all(large.join(i.to_string())).unwrap();
+    }
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = INotifyWatcher::new(tx, Config::default()).unwrap();
+    watcher.watch(&small, RecursiveMode::Recursive).unwrap();
+    watcher.watch(&large, RecursiveMode::Recursive).unwrap();
+
+    let risks: Vec<_> = rx
+        .try_iter()
+        .map(|res| res.unwrap())
+        .filter(|event| event.is_overflow_risk())
+        .collect();
+    assert_eq!(risks.len(), 1, "{risks:?}");
+    assert_eq!(risks[0].paths, [large]);
+}
+
+#[test]
+fn running_out_of_file_descriptors_is_reported() {
+    // the file descriptor limit is per process, so it is lowered in a child running only this test
+    if env::var_os("NOTIFY_TEST_EXHAUST_FDS").is_none() {
+        let status = std::process::Command::new(env::current_exe().unwrap())
+            .args([
+                "--exact",
+                "inotify::running_out_of_file_descriptors_is_reported",
+            ])
+            .env("NOTIFY_TEST_EXHAUST_FDS", "1")
+            .status()
+            .unwrap();
+        assert!(status.success());
+        return;
+    }
+
+    let limit = libc::rlimit {
+        rlim_cur: 64,
+        rlim_max: 64,
+    };
+    assert_eq!(unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &limit) }, 0);
+    let _files: Vec<_> = std::iter::from_fn(|| std::fs::File::open("/dev/null").ok()).collect();
+
+    let res = INotifyWatcher::new(|_: Result<Event>| {}, Config::default());
+    assert!(matches!(
+        res.map(|_| ()).unwrap_err().kind,
+        ErrorKind::TooManyOpenFiles
+    ));
+}
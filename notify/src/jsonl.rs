@@ -0,0 +1,128 @@
+//! Event handler writing events as JSON lines
+//!
+//! Each event is written as a single line containing its serde representation. Errors are written
+//! as an object with a single `error` key, holding the error message and the affected paths.
+
+use crate::{Event, EventHandler, Result};
+use std::io::{BufWriter, Write};
+use std::time::{Duration, Instant};
+
+/// Buffered output is flushed at least this often.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Creates an [`EventHandler`] that writes every event as a JSON line to `writer`.
+///
+/// See [`JsonlWriter`] for details.
+///
+/// ```no_run
+/// # use notify::{RecursiveMode, Result, Watcher};
+/// # use std::path::Path;
+/// # fn main() -> Result<()> {
+/// let log = std::fs::File::create("events.jsonl")?;
+/// let mut watcher = notify::recommended_watcher(notify::jsonl_writer(log))?;
+/// watcher.watch(Path::new("."), RecursiveMode::Recursive)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn jsonl_writer<W>(writer: W) -> JsonlWriter<W>
+where
+    W: Write + Send + 'static,
+{
+    JsonlWriter::new(writer)
+}
+
+/// [`EventHandler`] writing events as JSON lines.
+///
+/// Output is buffered and flushed periodically, as well as when the handler is dropped.
+/// I/O errors never panic, they are logged instead.
+#[derive(Debug)]
+pub struct JsonlWriter<W: Write> {
+    writer: BufWriter<W>,
+    last_flush: Instant,
+}
+
+impl<W: Write> JsonlWriter<W> {
+    /// Creates a new handler writing to `writer`.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: BufWriter::new(writer),
+            last_flush: Instant::now(),
+        }
+    }
+
+    fn write_line(&mut self, event: &Result<Event>) -> std::io::Result<()> {
+        match event {
+            Ok(event) => serde_json::to_writer(&mut self.writer, event)?,
+            Err(error) => serde_json::to_writer(
+                &mut self.writer,
+                &serde_json::json!({
+                    "error": {
+                        "message": error.to_string(),
+                        "paths": error.paths,
+                    }
+                }),
+            )?,
+        }
+        self.writer.write_all(b"\n")
+    }
+
+    fn flush(&mut self) {
+        if let Err(e) = self.writer.flush() {
+            log::warn!("failed to flush JSON lines: {e}");
+        }
+        self.last_flush = Instant::now();
+    }
+}
+
+impl<W> EventHandler for JsonlWriter<W>
+where
+    W: Write + Send + 'static,
+{
+    fn handle_event(&mut self, event: Result<Event>) {
+        if let Err(e) = self.write_line(&event) {
+            log::warn!("failed to write JSON line: {e}");
+        }
+
+        if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.flush();
+        }
+    }
+}
+
+impl<W: Write> Drop for JsonlWriter<W> {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{CreateKind, EventKind};
+    use crate::Error;
+    use std::path::PathBuf;
+
+    #[test]
+    fn one_json_object_per_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.jsonl");
+
+        {
+            let mut handler = jsonl_writer(std::fs::File::create(&path).unwrap());
+            handler
+                .handle_event(Ok(Event::new(EventKind::Create(CreateKind::File))
+                    .add_path(PathBuf::from("/file"))));
+            handler.handle_event(Err(Error::path_not_found().add_path(PathBuf::from("/gone"))));
+        }
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["paths"][0], "/file");
+        assert_eq!(lines[1]["error"]["paths"][0], "/gone");
+    }
+}
@@ -6,9 +6,12 @@
 
 use super::event::*;
 use super::{Config, Error, EventHandler, RecursiveMode, Result, Watcher};
-use crate::{unbounded, Receiver, Sender};
+use crate::pause::PauseGate;
+use crate::stats::Counters;
+use crate::{dispatch, resync, settle};
+use crate::{unbounded, unmounted, watch_invalidated, AttributeHandler, Receiver, Sender};
 use kqueue::{EventData, EventFilter, FilterFlag, Ident};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::env;
 use std::fs::metadata;
 use std::os::unix::io::AsRawFd;
@@ -34,7 +37,12 @@ struct EventLoop {
     kqueue: kqueue::Watcher,
     event_handler: Box<dyn EventHandler>,
     watches: HashMap<PathBuf, bool>,
+    roots: HashSet<PathBuf>,
+    /// Entries of the watched directories, only tracked if directories are diffed
+    dir_entries: HashMap<PathBuf, HashSet<PathBuf>>,
     follow_symlinks: bool,
+    report_self_events: bool,
+    diff_directories: bool,
 }
 
 /// Watcher implementation based on inotify
@@ -42,11 +50,15 @@ struct EventLoop {
 pub struct KqueueWatcher {
     channel: Sender<EventLoopMsg>,
     waker: Arc<mio::Waker>,
+    gate: PauseGate,
+    follow_symlinks: bool,
 }
 
 enum EventLoopMsg {
     AddWatch(PathBuf, RecursiveMode, Sender<Result<()>>),
     RemoveWatch(PathBuf, Sender<Result<()>>),
+    RemoveWatchPrefix(PathBuf, Sender<Result<Vec<PathBuf>>>),
+    WatchedPaths(Sender<Vec<(PathBuf, RecursiveMode)>>),
     Shutdown,
 }
 
@@ -54,7 +66,7 @@ impl EventLoop {
     pub fn new(
         kqueue: kqueue::Watcher,
         event_handler: Box<dyn EventHandler>,
-        follow_symlinks: bool,
+        config: &Config,
     ) -> Result<Self> {
         let (event_loop_tx, event_loop_rx) = unbounded::<EventLoopMsg>();
         let poll = mio::Poll::new()?;
@@ -75,7 +87,11 @@ impl EventLoop {
             kqueue,
             event_handler,
             watches: HashMap::new(),
-            follow_symlinks,
+            roots: HashSet::new(),
+            dir_entries: HashMap::new(),
+            follow_symlinks: config.follow_symlinks(),
+            report_self_events: config.report_self_events(),
+            diff_directories: config.kqueue_diff_directories(),
         };
         Ok(event_loop)
     }
@@ -88,6 +104,12 @@ impl EventLoop {
     }
 
     fn event_loop_thread(mut self) {
+        if let Err(error) = Error::catch_panic(|| self.event_loop()) {
+            self.event_handler.handle_event(Err(error));
+        }
+    }
+
+    fn event_loop(&mut self) {
         let mut events = mio::Events::with_capacity(16);
         loop {
             // Wait for something to happen.
@@ -131,11 +153,22 @@ impl EventLoop {
         while let Ok(msg) = self.event_loop_rx.try_recv() {
             match msg {
                 EventLoopMsg::AddWatch(path, recursive_mode, tx) => {
-                    let _ = tx.send(self.add_watch(path, recursive_mode.is_recursive()));
+                    let res = self.add_watch(path.clone(), recursive_mode.is_recursive());
+                    if res.is_ok() {
+                        self.roots.insert(path);
+                    }
+                    let _ = tx.send(res);
                 }
                 EventLoopMsg::RemoveWatch(path, tx) => {
+                    self.roots.remove(&path);
                     let _ = tx.send(self.remove_watch(path, false));
                 }
+                EventLoopMsg::RemoveWatchPrefix(prefix, tx) => {
+                    let _ = tx.send(self.remove_watch_prefix(&prefix));
+                }
+                EventLoopMsg::WatchedPaths(tx) => {
+                    let _ = tx.send(self.watched_paths());
+                }
                 EventLoopMsg::Shutdown => {
                     self.running = false;
                     break;
@@ -157,6 +190,36 @@ impl EventLoop {
                     ident: Ident::Filename(_, path),
                 } => {
                     let path = PathBuf::from(path);
+                    let is_root = self.roots.contains(&path);
+                    let invalidated = is_root
+                        && self.report_self_events
+                        && matches!(
+                            data,
+                            kqueue::Vnode::Delete | kqueue::Vnode::Rename | kqueue::Vnode::Revoke
+                        );
+                    let invalidated = invalidated.then(|| path.clone());
+
+                    // a write to a directory means that entries were added or removed, report
+                    // them individually if the directory's entries are tracked
+                    if matches!(data, kqueue::Vnode::Write) {
+                        if let Some(res) = self.diff_directory(&path, &mut add_watches) {
+                            match res {
+                                Ok(events) if events.is_empty() => {
+                                    self.event_handler.handle_event(Ok(Event::new(
+                                        EventKind::Modify(ModifyKind::Data(DataChange::Any)),
+                                    )
+                                    .add_path(path)))
+                                }
+                                Ok(events) => {
+                                    for event in events {
+                                        self.event_handler.handle_event(Ok(event));
+                                    }
+                                }
+                                Err(e) => self.event_handler.handle_event(Err(e)),
+                            }
+                            continue;
+                        }
+                    }
                     let event = match data {
                         /*
                         TODO: Differentiate folders and files
@@ -269,14 +332,31 @@ impl EventLoop {
                         // Access to the file was revoked via revoke(2) or the underlying file system was unmounted.
                         kqueue::Vnode::Revoke => {
                             remove_watches.push(path.clone());
-                            Ok(Event::new(EventKind::Remove(RemoveKind::Any)).add_path(path))
+                            Ok(unmounted(path))
                         }
 
                         // On different BSD variants, different extra events may be present
                         #[allow(unreachable_patterns)]
                         _ => Ok(Event::new(EventKind::Other)),
                     };
-                    self.event_handler.handle_event(event);
+
+                    // the watched path itself was removed or renamed, unmounts are always reported
+                    let suppressed = is_root
+                        && !self.report_self_events
+                        && matches!(
+                            &event,
+                            Ok(event @ Event {
+                                kind: EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_)),
+                                ..
+                            }) if !event.is_unmount()
+                        );
+                    if !suppressed {
+                        self.event_handler.handle_event(event);
+                    }
+
+                    if let Some(root) = invalidated {
+                        self.event_handler.handle_event(Ok(watch_invalidated(root)));
+                    }
                 }
                 // as we don't add any other EVFILTER to kqueue we should never get here
                 kqueue::Event { ident: _, data: _ } => unreachable!(),
@@ -330,15 +410,68 @@ impl EventLoop {
 
         self.kqueue
             .add_filename(&path, event_filter, filter_flags)
-            .map_err(|e| Error::io(e).add_path(path.clone()))?;
+            .map_err(|e| Error::io_watch(e).add_path(path.clone()))?;
+
+        if self.diff_directories && path.is_dir() {
+            let entries = read_entries(&path).map_err(|e| Error::io(e).add_path(path.clone()))?;
+            self.dir_entries.insert(path.clone(), entries);
+        }
+
         self.watches.insert(path, is_recursive);
 
         Ok(())
     }
 
+    /// Compares the entries of a watched directory with the ones seen before and returns events
+    /// for the added and removed entries.
+    ///
+    /// Returns `None` if the entries of the directory aren't tracked.
+    fn diff_directory(
+        &mut self,
+        path: &Path,
+        add_watches: &mut Vec<PathBuf>,
+    ) -> Option<Result<Vec<Event>>> {
+        let old_entries = self.dir_entries.get_mut(path)?;
+        let new_entries = match read_entries(path) {
+            Ok(entries) => entries,
+            Err(e) => return Some(Err(Error::io(e).add_path(path.to_path_buf()))),
+        };
+        let is_recursive = self.watches.get(path).copied().unwrap_or_default();
+
+        let mut events = Vec::new();
+        for added in new_entries.difference(old_entries) {
+            if self.watches.contains_key(added) {
+                continue;
+            }
+            if is_recursive {
+                add_watches.push(added.clone());
+            }
+            let kind = if added.is_dir() {
+                CreateKind::Folder
+            } else if added.is_file() {
+                CreateKind::File
+            } else {
+                CreateKind::Other
+            };
+            events.push(Event::new(EventKind::Create(kind)).add_path(added.clone()));
+        }
+        for removed in old_entries.difference(&new_entries) {
+            // watched entries report their removal themselves
+            if !self.watches.contains_key(removed) {
+                events
+                    .push(Event::new(EventKind::Remove(RemoveKind::Any)).add_path(removed.clone()));
+            }
+        }
+
+        *old_entries = new_entries;
+        Some(Ok(events))
+    }
+
     fn remove_watch(&mut self, path: PathBuf, remove_recursive: bool) -> Result<()> {
         log::trace!("removing kqueue watch: {}", path.display());
 
+        self.dir_entries.remove(&path);
+
         match self.watches.remove(&path) {
             None => return Err(Error::watch_not_found()),
             Some(is_recursive) => {
@@ -352,6 +485,7 @@ impl EventLoop {
                         .into_iter()
                     {
                         let p = entry.map_err(map_walkdir_error)?.path().to_path_buf();
+                        self.dir_entries.remove(&p);
                         self.kqueue
                             .remove_filename(&p, EventFilter::EVFILT_VNODE)
                             .map_err(|e| Error::io(e).add_path(p))?;
@@ -362,6 +496,44 @@ impl EventLoop {
         }
         Ok(())
     }
+
+    fn watched_paths(&self) -> Vec<(PathBuf, RecursiveMode)> {
+        self.roots
+            .iter()
+            .map(|root| {
+                let is_recursive = self.watches.get(root).copied().unwrap_or_default();
+                (root.clone(), RecursiveMode::from_bool(is_recursive))
+            })
+            .collect()
+    }
+
+    /// Removes all watched roots below `prefix` and their descendants.
+    fn remove_watch_prefix(&mut self, prefix: &Path) -> Result<Vec<PathBuf>> {
+        let mut roots: Vec<PathBuf> = self
+            .roots
+            .iter()
+            .filter(|root| root.starts_with(prefix))
+            .cloned()
+            .collect();
+        roots.sort();
+
+        for root in &roots {
+            self.roots.remove(root);
+            // already removed along with a root above it
+            if self.watches.contains_key(root) {
+                self.remove_watch(root.clone(), true)?;
+                self.watches.retain(|path, _| !path.starts_with(root));
+            }
+        }
+        Ok(roots)
+    }
+}
+
+/// Paths of all entries of the directory at `path`.
+fn read_entries(path: &Path) -> std::io::Result<HashSet<PathBuf>> {
+    std::fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect()
 }
 
 fn map_walkdir_error(e: walkdir::Error) -> Error {
@@ -376,14 +548,21 @@ fn map_walkdir_error(e: walkdir::Error) -> Error {
 impl KqueueWatcher {
     fn from_event_handler(
         event_handler: Box<dyn EventHandler>,
-        follow_symlinks: bool,
+        config: &Config,
+        counters: Arc<Counters>,
     ) -> Result<Self> {
-        let kqueue = kqueue::Watcher::new()?;
-        let event_loop = EventLoop::new(kqueue, event_handler, follow_symlinks)?;
+        let kqueue = kqueue::Watcher::new().map_err(Error::io_watch)?;
+        let gate = PauseGate::new(event_handler, config, counters);
+        let event_loop = EventLoop::new(kqueue, Box::new(gate.clone()), config)?;
         let channel = event_loop.event_loop_tx.clone();
         let waker = event_loop.event_loop_waker.clone();
         event_loop.run();
-        Ok(KqueueWatcher { channel, waker })
+        Ok(KqueueWatcher {
+            channel,
+            waker,
+            gate,
+            follow_symlinks: config.follow_symlinks(),
+        })
     }
 
     fn watch_inner(&mut self, path: &Path, recursive_mode: RecursiveMode) -> Result<()> {
@@ -402,9 +581,8 @@ impl KqueueWatcher {
         self.waker
             .wake()
             .map_err(|e| Error::generic(&e.to_string()))?;
-        rx.recv()
-            .unwrap()
-            .map_err(|e| Error::generic(&e.to_string()))
+        // keep the error kind, so running out of file descriptors can be told apart
+        rx.recv().unwrap()
     }
 
     fn unwatch_inner(&mut self, path: &Path) -> Result<()> {
@@ -427,22 +605,96 @@ impl KqueueWatcher {
             .unwrap()
             .map_err(|e| Error::generic(&e.to_string()))
     }
+
+    fn watched_paths_inner(&self) -> Result<Vec<(PathBuf, RecursiveMode)>> {
+        let (tx, rx) = unbounded();
+
+        self.channel
+            .send(EventLoopMsg::WatchedPaths(tx))
+            .map_err(|e| Error::generic(&e.to_string()))?;
+        self.waker
+            .wake()
+            .map_err(|e| Error::generic(&e.to_string()))?;
+        Ok(rx.recv().unwrap())
+    }
+
+    fn unwatch_prefix_inner(&mut self, prefix: &Path) -> Result<Vec<PathBuf>> {
+        let pb = if prefix.is_absolute() {
+            prefix.to_owned()
+        } else {
+            let p = env::current_dir().map_err(Error::io)?;
+            p.join(prefix)
+        };
+        let (tx, rx) = unbounded();
+        let msg = EventLoopMsg::RemoveWatchPrefix(pb, tx);
+
+        self.channel
+            .send(msg)
+            .map_err(|e| Error::generic(&e.to_string()))?;
+        self.waker
+            .wake()
+            .map_err(|e| Error::generic(&e.to_string()))?;
+        rx.recv().unwrap()
+    }
 }
 
 impl Watcher for KqueueWatcher {
     /// Create a new watcher.
     fn new<F: EventHandler>(event_handler: F, config: Config) -> Result<Self> {
-        Self::from_event_handler(Box::new(event_handler), config.follow_symlinks())
+        let counters = Arc::<Counters>::default();
+        let dispatch = dispatch::from_config(counters.handler(event_handler), &config)?;
+        Self::from_event_handler(
+            Box::new(AttributeHandler::new(
+                settle::from_config(dispatch, &config)?,
+                &config,
+                Self::kind(),
+            )),
+            &config,
+            counters,
+        )
     }
 
     fn watch(&mut self, path: &Path, recursive_mode: RecursiveMode) -> Result<()> {
-        self.watch_inner(path, recursive_mode)
+        self.watch_inner(path, recursive_mode)?;
+        self.gate.root_watched(path);
+        Ok(())
     }
 
     fn unwatch(&mut self, path: &Path) -> Result<()> {
+        self.gate.root_unwatched(path, false);
         self.unwatch_inner(path)
     }
 
+    fn watched_paths(&self) -> Result<Vec<(PathBuf, RecursiveMode)>> {
+        self.watched_paths_inner()
+    }
+
+    fn unwatch_prefix(&mut self, prefix: &Path) -> Result<Vec<PathBuf>> {
+        self.gate.root_unwatched(prefix, true);
+        self.unwatch_prefix_inner(prefix)
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        self.gate.pause();
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        self.gate.resume();
+        Ok(())
+    }
+
+    fn resync(&mut self) -> Result<()> {
+        let roots = self.watched_paths()?;
+        resync::resync(&roots, self.follow_symlinks, &mut self.gate);
+        Ok(())
+    }
+
+    fn stats(&self) -> crate::WatcherStats {
+        let watches = self.watched_paths().map_or(0, |paths| paths.len());
+        self.gate.stats(watches)
+    }
+
     fn kind() -> crate::WatcherKind {
         crate::WatcherKind::Kqueue
     }
@@ -455,3 +707,24 @@ impl Drop for KqueueWatcher {
         self.waker.wake().unwrap();
     }
 }
+
+#[test]
+fn diff_directories_reports_created_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    let config = Config::default().with_kqueue_diff_directories(true);
+    let mut watcher = KqueueWatcher::new(tx, config).unwrap();
+    watcher
+        .watch(dir.path(), RecursiveMode::NonRecursive)
+        .unwrap();
+
+    let path = dir.path().join("file");
+    std::fs::write(&path, b"").unwrap();
+
+    let event = rx
+        .recv_timeout(std::time::Duration::from_secs(5))
+        .expect("no events received")
+        .unwrap();
+    assert_eq!(event.kind, EventKind::Create(CreateKind::File));
+    assert_eq!(event.paths, vec![path]);
+}
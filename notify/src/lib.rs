@@ -18,6 +18,8 @@
 //! - `macos_fsevent` enabled by default, for fsevent backend on macos
 //! - `macos_kqueue` for kqueue backend on macos
 //! - `serialization-compat-6` restores the serialization behavior of notify 6, off by default
+//! - `testing` for the [`testing`](https://docs.rs/notify/latest/notify/testing/) helpers asserting events in tests, off by default
+//! - `tokio` for delivering batches of events, e.g. of [`Windowed`], to `tokio::sync::mpsc` senders, off by default
 //!
 //! ### Serde
 //!
@@ -27,6 +29,8 @@
 //! notify = { version = "7.0.0", features = ["serde"] }
 //! ```
 //!
+//! This also enables [`jsonl_writer`], an [`EventHandler`] that writes events as JSON lines.
+//!
 //! # Known Problems
 //!
 //! ### Network filesystems
@@ -163,14 +167,18 @@
 
 #![deny(missing_docs)]
 
-pub use config::{Config, RecursiveMode};
+pub use buffered::BufferedWatcher;
+pub use config::{covers, CompareMode, Config, EventKindMask, HiddenFiles, RecursiveMode};
 pub use error::{Error, ErrorKind, Result};
-pub use notify_types::event::{self, Event, EventKind};
-use std::path::Path;
+#[cfg(feature = "serde")]
+pub use jsonl::{jsonl_writer, JsonlWriter};
+pub use lifecycle::Lifecycle;
+pub use notify_types::event::{self, Event, EventKind, WatcherKind};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 pub(crate) type Receiver<T> = std::sync::mpsc::Receiver<T>;
 pub(crate) type Sender<T> = std::sync::mpsc::Sender<T>;
-#[cfg(any(target_os = "linux", target_os = "android", target_os = "windows"))]
 pub(crate) type BoundSender<T> = std::sync::mpsc::SyncSender<T>;
 
 #[inline]
@@ -178,7 +186,6 @@ pub(crate) fn unbounded<T>() -> (Sender<T>, Receiver<T>) {
     std::sync::mpsc::channel()
 }
 
-#[cfg(any(target_os = "linux", target_os = "android", target_os = "windows"))]
 #[inline]
 pub(crate) fn bounded<T>(cap: usize) -> (BoundSender<T>, Receiver<T>) {
     std::sync::mpsc::sync_channel(cap)
@@ -197,10 +204,22 @@ pub use crate::inotify::INotifyWatcher;
     all(target_os = "macos", feature = "macos_kqueue")
 ))]
 pub use crate::kqueue::KqueueWatcher;
+pub use grace::RemoveGrace;
+pub use hybrid::{hybrid_watcher, HybridWatcher};
 pub use null::NullWatcher;
 pub use poll::PollWatcher;
+pub use rearm::RearmWatcher;
+pub use reload::{watch_file_for_reload, ReloadWatcher};
+pub use route::{route, ErrorRoute, Router};
+pub use settle::Settle;
+pub use stats::WatcherStats;
+pub use symlink::SymlinkWatcher;
+pub use throttle::Throttle;
+pub use traversal::Traversal;
+pub use window::Windowed;
 #[cfg(target_os = "windows")]
 pub use windows::ReadDirectoryChangesWatcher;
+pub use written::FileWritten;
 
 #[cfg(all(target_os = "macos", not(feature = "macos_kqueue")))]
 pub mod fsevent;
@@ -218,11 +237,37 @@ pub mod kqueue;
 #[cfg(target_os = "windows")]
 pub mod windows;
 
+pub mod buffered;
+pub mod grace;
+pub mod hybrid;
+#[cfg(feature = "serde")]
+pub mod jsonl;
+pub mod lifecycle;
 pub mod null;
 pub mod poll;
+pub mod rearm;
+#[cfg(feature = "serde")]
+pub mod record;
+pub mod reload;
+pub mod route;
+pub mod settle;
+pub mod symlink;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod throttle;
+pub mod traversal;
+pub mod window;
+pub mod written;
 
+mod case;
 mod config;
+mod dispatch;
 mod error;
+mod pause;
+mod resync;
+mod stats;
+mod timed;
+mod trash;
 
 /// The set of requirements for watcher event handling functions.
 ///
@@ -245,6 +290,16 @@ mod error;
 pub trait EventHandler: Send + 'static {
     /// Handles an event.
     fn handle_event(&mut self, event: Result<Event>);
+
+    /// Handles the events a backend read at once, in the order they were emitted.
+    ///
+    /// Backends that read several events at once pass them here, see [`Batched`]. The
+    /// default implementation calls [`handle_event`](EventHandler::handle_event) for each event.
+    fn handle_events(&mut self, events: Vec<Result<Event>>) {
+        for event in events {
+            self.handle_event(event);
+        }
+    }
 }
 
 impl<F> EventHandler for F
@@ -269,22 +324,175 @@ impl EventHandler for std::sync::mpsc::Sender<Result<Event>> {
     }
 }
 
-/// Watcher kind enumeration
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-#[non_exhaustive]
-pub enum WatcherKind {
-    /// inotify backend (linux)
-    Inotify,
-    /// FS-Event backend (mac)
-    Fsevent,
-    /// KQueue backend (bsd,optionally mac)
-    Kqueue,
-    /// Polling based backend (fallback)
-    PollWatcher,
-    /// Windows backend
-    ReadDirectoryChangesWatcher,
-    /// Fake watcher for testing
-    NullWatcher,
+/// The set of requirements for handling batches of watcher events.
+///
+/// Used by event handler adapters that deliver several events at once, like [`Throttle`] and
+/// [`Windowed`].
+pub trait EventBatchHandler: Send + 'static {
+    /// Handles a batch of events, in the order they were emitted.
+    fn handle_events(&mut self, events: Vec<Result<Event>>);
+}
+
+impl<F> EventBatchHandler for F
+where
+    F: FnMut(Vec<Result<Event>>) + Send + 'static,
+{
+    fn handle_events(&mut self, events: Vec<Result<Event>>) {
+        (self)(events);
+    }
+}
+
+#[cfg(feature = "crossbeam-channel")]
+impl EventBatchHandler for crossbeam_channel::Sender<Vec<Result<Event>>> {
+    fn handle_events(&mut self, events: Vec<Result<Event>>) {
+        let _ = self.send(events);
+    }
+}
+
+impl EventBatchHandler for std::sync::mpsc::Sender<Vec<Result<Event>>> {
+    fn handle_events(&mut self, events: Vec<Result<Event>>) {
+        let _ = self.send(events);
+    }
+}
+
+/// Waits for room in the channel, so it must not be called from within an async runtime. The
+/// adapters like [`Windowed`] call it from their own thread.
+#[cfg(feature = "tokio")]
+impl EventBatchHandler for tokio::sync::mpsc::Sender<Vec<Result<Event>>> {
+    fn handle_events(&mut self, events: Vec<Result<Event>>) {
+        let _ = self.blocking_send(events);
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl EventBatchHandler for tokio::sync::mpsc::UnboundedSender<Vec<Result<Event>>> {
+    fn handle_events(&mut self, events: Vec<Result<Event>>) {
+        let _ = self.send(events);
+    }
+}
+
+/// [`EventHandler`] adapter delivering events to an [`EventBatchHandler`] in the batches they were
+/// read by the backend.
+///
+/// The [INotifyWatcher](crate::INotifyWatcher) and the
+/// [ReadDirectoryChangesWatcher](crate::ReadDirectoryChangesWatcher) deliver all events read by one
+/// system call as one batch, and the [FsEventWatcher](crate::FsEventWatcher) all events of one
+/// stream callback, which saves channel and lock overhead under bursts of events. The kqueue and
+/// poll watchers deliver every event as a batch of its own. Batches are never empty and events
+/// keep their order.
+///
+/// ```no_run
+/// # use notify::{Batched, RecursiveMode, Result, Watcher};
+/// # use std::path::Path;
+/// # fn main() -> Result<()> {
+/// let (tx, rx) = std::sync::mpsc::channel();
+/// let mut watcher = notify::recommended_watcher(Batched::new(tx))?;
+/// watcher.watch(Path::new("."), RecursiveMode::Recursive)?;
+///
+/// for batch in rx {
+///     println!("{} events", batch.len());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Batched<F> {
+    batch_handler: F,
+}
+
+impl<F: EventBatchHandler> Batched<F> {
+    /// Create a new adapter delivering batches to `batch_handler`.
+    pub fn new(batch_handler: F) -> Self {
+        Self { batch_handler }
+    }
+}
+
+impl<F: EventBatchHandler> EventHandler for Batched<F> {
+    fn handle_event(&mut self, event: Result<Event>) {
+        self.batch_handler.handle_events(vec![event]);
+    }
+
+    fn handle_events(&mut self, events: Vec<Result<Event>>) {
+        if !events.is_empty() {
+            self.batch_handler.handle_events(events);
+        }
+    }
+}
+
+/// Sets the event attributes that are enabled in the [`Config`] on every event, see
+/// [`Config::with_event_timestamps`], [`Config::with_event_source_kind`] and
+/// [`Config::with_event_sequence_numbers`], and classifies moves to the trash, see
+/// [`Config::with_trash_detection`]. Drops the events not allowed by
+/// [`Config::with_event_kinds`] and [`Config::with_hidden_files`].
+pub(crate) struct AttributeHandler<F> {
+    inner: F,
+    /// Events to be delivered, see [`Config::with_event_kinds`]
+    config: Config,
+    timestamps: bool,
+    source_kind: Option<WatcherKind>,
+    /// Sequence number of the next event, if enabled
+    next_seq: Option<u64>,
+    trash: Option<trash::TrashDetector>,
+}
+
+impl<F: EventHandler> AttributeHandler<F> {
+    pub(crate) fn new(inner: F, config: &Config, kind: WatcherKind) -> Self {
+        Self {
+            inner,
+            config: *config,
+            timestamps: config.event_timestamps(),
+            source_kind: config.event_source_kind().then_some(kind),
+            next_seq: config.event_sequence_numbers().then_some(0),
+            trash: config.trash_detection().then(trash::TrashDetector::new),
+        }
+    }
+}
+
+impl<F: EventHandler> AttributeHandler<F> {
+    fn set_attributes(&mut self, event: Result<Event>) -> Option<Result<Event>> {
+        let mut event = match event {
+            Ok(event) => event,
+            Err(error) => return Some(Err(error)),
+        };
+        if let Some(trash) = &mut self.trash {
+            event = trash.classify(event);
+        }
+        if !self.config.allows_event(&event.kind)
+            || !self.config.hidden_files().allows(&event.paths)
+        {
+            return None;
+        }
+        // events buffered while paused are stamped already
+        if self.timestamps && event.time().is_none() {
+            event = event.set_time(std::time::SystemTime::now());
+        }
+        if let Some(source_kind) = self.source_kind {
+            event = event.set_source_kind(source_kind);
+        }
+        if let Some(seq) = &mut self.next_seq {
+            event = event.set_seq(*seq);
+            *seq += 1;
+        }
+        Some(Ok(event))
+    }
+}
+
+impl<F: EventHandler> EventHandler for AttributeHandler<F> {
+    fn handle_event(&mut self, event: Result<Event>) {
+        if let Some(event) = self.set_attributes(event) {
+            self.inner.handle_event(event);
+        }
+    }
+
+    fn handle_events(&mut self, events: Vec<Result<Event>>) {
+        let events: Vec<_> = events
+            .into_iter()
+            .filter_map(|event| self.set_attributes(event))
+            .collect();
+        if !events.is_empty() {
+            self.inner.handle_events(events);
+        }
+    }
 }
 
 /// Type that can deliver file activity notifications
@@ -314,6 +522,19 @@ pub trait Watcher {
     /// [#166]: https://github.com/notify-rs/notify/issues/166
     fn watch(&mut self, path: &Path, recursive_mode: RecursiveMode) -> Result<()>;
 
+    /// Begin watching several paths at once.
+    ///
+    /// Every path is attempted, even if watching an earlier one failed. The outcome is reported
+    /// for each path individually, in the order they were given. Backends which can register
+    /// multiple paths more efficiently than one at a time do so, the default implementation
+    /// calls [`watch`](Watcher::watch) for every path.
+    fn watch_many(&mut self, paths: &[(&Path, RecursiveMode)]) -> Vec<(PathBuf, Result<()>)> {
+        paths
+            .iter()
+            .map(|&(path, recursive_mode)| (path.to_path_buf(), self.watch(path, recursive_mode)))
+            .collect()
+    }
+
     /// Stop watching a path.
     ///
     /// # Errors
@@ -322,6 +543,70 @@ pub trait Watcher {
     /// fails.
     fn unwatch(&mut self, path: &Path) -> Result<()>;
 
+    /// Returns the paths that are watched explicitly, along with their recursive mode.
+    ///
+    /// Watches the backend added on its own, e.g. for the subdirectories of a recursively watched
+    /// directory, are not included. Paths are reported the way the backend stores them, which
+    /// may be absolute or canonicalized.
+    ///
+    /// Returns an error if the watcher doesn't keep track of its watched paths.
+    fn watched_paths(&self) -> Result<Vec<(PathBuf, RecursiveMode)>> {
+        Err(Error::generic(
+            "listing the watched paths is not supported by this watcher",
+        ))
+    }
+
+    /// Watch exactly `paths`, replacing the current set of watched paths.
+    ///
+    /// Only the difference to the [watched paths](Watcher::watched_paths) is applied: paths
+    /// that are no longer listed are unwatched, new paths are watched and paths that are already
+    /// watched with the same recursive mode are left untouched, so no changes to them are missed.
+    /// A path whose recursive mode changed is unwatched and watched again.
+    ///
+    /// Every change is attempted, even if an earlier one failed. Returns the first error.
+    fn set_watches(&mut self, paths: &[(&Path, RecursiveMode)]) -> Result<()> {
+        let current = self.watched_paths()?;
+        let is_listed = |watched: &Path, mode: RecursiveMode| {
+            paths
+                .iter()
+                .any(|&(path, m)| m == mode && is_same_watch(watched, path))
+        };
+        let is_watched = |path: &Path, mode: RecursiveMode| {
+            current
+                .iter()
+                .any(|(watched, m)| *m == mode && is_same_watch(watched, path))
+        };
+
+        let mut result = Ok(());
+        for (watched, mode) in &current {
+            if !is_listed(watched, *mode) {
+                result = result.and(self.unwatch(watched));
+            }
+        }
+        let added: Vec<_> = paths
+            .iter()
+            .copied()
+            .filter(|&(path, mode)| !is_watched(path, mode))
+            .collect();
+        for (_, res) in self.watch_many(&added) {
+            result = result.and(res);
+        }
+        result
+    }
+
+    /// Stop watching all paths below `prefix`.
+    ///
+    /// Every watched path that starts with `prefix`, including `prefix` itself, is unwatched
+    /// along with the watches that were added for it automatically. Returns the unwatched paths,
+    /// which is empty if no watched path starts with `prefix`.
+    ///
+    /// Returns an error if the watcher doesn't support unwatching by prefix.
+    fn unwatch_prefix(&mut self, _prefix: &Path) -> Result<Vec<PathBuf>> {
+        Err(Error::generic(
+            "unwatching by prefix is not supported by this watcher",
+        ))
+    }
+
     /// Configure the watcher at runtime.
     ///
     /// See the [`Config`](config/struct.Config.html) struct for all configuration options.
@@ -335,6 +620,88 @@ pub trait Watcher {
         Ok(false)
     }
 
+    /// Suspend event delivery without removing any watches.
+    ///
+    /// Events emitted while paused are discarded, or buffered if enabled with
+    /// [`Config::with_buffer_paused_events`]. This is cheaper than removing and re-adding all
+    /// watches, e.g. while the application itself makes many changes.
+    ///
+    /// Returns an error if the watcher doesn't support pausing.
+    fn pause(&mut self) -> Result<()> {
+        Err(Error::generic("pausing is not supported by this watcher"))
+    }
+
+    /// Resume event delivery after [`pause`](Watcher::pause).
+    ///
+    /// Delivers the buffered events, or an [`EventKind::Other`] event with the
+    /// [`Flag::Rescan`](event::Flag::Rescan) flag and the info `resumed` if events were discarded,
+    /// as changes may have been missed in the meantime. Does nothing if the watcher isn't paused.
+    fn resume(&mut self) -> Result<()> {
+        Err(Error::generic("pausing is not supported by this watcher"))
+    }
+
+    /// Report every path that currently exists below the watched paths.
+    ///
+    /// A [`Create`](EventKind::Create) event flagged with [`Flag::Resync`](event::Flag::Resync)
+    /// is delivered for each file and directory below the [watched
+    /// paths](Watcher::watched_paths), and for watched files, so an application can rebuild its
+    /// state from scratch, e.g. after a [`Flag::Rescan`](event::Flag::Rescan) notice. Recursive
+    /// modes and [`Config::with_follow_symlinks`] are honored, the events pass the same filters as
+    /// other events. They are delivered through the event handler before this returns, unless
+    /// the watcher is paused or the event handler runs on a thread of its own.
+    ///
+    /// Returns an error if the watcher doesn't support resyncing.
+    fn resync(&mut self) -> Result<()> {
+        Err(Error::generic("resyncing is not supported by this watcher"))
+    }
+
+    /// Returns a snapshot of the cumulative counters of the watcher, see [`WatcherStats`].
+    ///
+    /// The counters cover what the watcher passed on to the event handler. Events dropped by
+    /// [`Config::with_event_kinds`] or [`Config::with_hidden_files`] are counted as well, events
+    /// discarded while the watcher is paused are not. The default implementation returns zero
+    /// counters.
+    fn stats(&self) -> WatcherStats {
+        WatcherStats::default()
+    }
+
+    /// Whether changes to `path` are expected to be reported by this watcher.
+    ///
+    /// Returns `false` if `path` is on a filesystem known not to emit events for the backend,
+    /// e.g. a network filesystem or a pseudo-filesystem like `/proc`, in which case the
+    /// [`PollWatcher`](crate::PollWatcher) may be used instead. Detection is best effort, the
+    /// default implementation always returns `true`.
+    fn path_supports_events(&self, _path: &Path) -> bool {
+        true
+    }
+
+    /// The delay after which the watcher is expected to report a change, at most.
+    ///
+    /// This is an estimate, which may help to choose a debounce timeout. The
+    /// [`PollWatcher`](crate::PollWatcher) returns its poll interval and the
+    /// [`FsEventWatcher`](crate::FsEventWatcher) the latency of its stream. The default
+    /// implementation returns zero, which suits backends reporting changes as they happen.
+    fn expected_latency(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    /// Stop the watcher and return the events it emitted but didn't deliver yet.
+    ///
+    /// Unlike dropping the watcher, this waits for the backend to shut down. The events the
+    /// backend has read or queued by then, as well as those buffered while paused, are returned
+    /// instead of being passed to the event handler, so no event is delivered after this
+    /// returns. The [`INotifyWatcher`](crate::INotifyWatcher) and the
+    /// [`ReadDirectoryChangesWatcher`](crate::ReadDirectoryChangesWatcher) support this, as does
+    /// the [`HybridWatcher`] if its native watcher does. The default implementation drops the
+    /// watcher and returns no events.
+    fn stop(self) -> Vec<Result<Event>>
+    where
+        Self: Sized,
+    {
+        drop(self);
+        Vec::new()
+    }
+
     /// Returns the watcher kind, allowing to perform backend-specific tasks
     fn kind() -> WatcherKind
     where
@@ -374,6 +741,100 @@ pub type RecommendedWatcher = KqueueWatcher;
 )))]
 pub type RecommendedWatcher = PollWatcher;
 
+/// The [`Watcher`] implementation for the current platform, under a name that exists on all
+/// platforms
+///
+/// The backend types, like [`FsEventWatcher`](crate::FsEventWatcher) or
+/// [`INotifyWatcher`](crate::INotifyWatcher), only exist on their platforms. Naming this alias
+/// instead compiles everywhere, and [`watcher_of_kind`] selects a backend at runtime.
+pub type PlatformWatcher = RecommendedWatcher;
+
+/// Whether the path a backend reports as watched refers to the requested `path`, which may be
+/// relative or not canonicalized.
+fn is_same_watch(watched: &Path, path: &Path) -> bool {
+    if watched == path {
+        return true;
+    }
+    let absolute = std::env::current_dir().map(|cwd| cwd.join(path));
+    absolute.is_ok_and(|absolute| absolute == watched)
+        || path
+            .canonicalize()
+            .is_ok_and(|canonical| canonical == watched)
+}
+
+/// Creates the event reporting that the watch of `root` stopped working, see
+/// [`Event::is_watch_invalidated`].
+#[allow(dead_code)] // not every backend knows the path
+pub(crate) fn watch_invalidated(root: PathBuf) -> Event {
+    Event::new(EventKind::Other)
+        .add_path(root)
+        .set_info("watch-invalidated")
+}
+
+/// Creates the event reporting that the filesystem containing `path` was unmounted, see
+/// [`Event::is_unmount`].
+#[cfg_attr(
+    not(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "dragonfly",
+        target_os = "netbsd",
+        target_os = "ios",
+        all(target_os = "macos", feature = "macos_kqueue")
+    )),
+    allow(dead_code)
+)]
+pub(crate) fn unmounted(path: PathBuf) -> Event {
+    Event::new(EventKind::Remove(event::RemoveKind::Other))
+        .add_path(path)
+        .set_info("unmount")
+}
+
+/// The kind of creation of `path`, looked up for backends that can't tell, see
+/// [`Config::with_precise_create_kinds`].
+#[cfg_attr(
+    not(any(
+        target_os = "windows",
+        all(target_os = "macos", not(feature = "macos_kqueue"))
+    )),
+    allow(dead_code)
+)]
+pub(crate) fn create_kind_of(path: &Path) -> event::CreateKind {
+    if path.is_dir() {
+        event::CreateKind::Folder
+    } else {
+        event::CreateKind::File
+    }
+}
+
+/// Creates the event reporting that the directory `dir` is beyond the maximum depth of a
+/// recursive watch, see [`Event::is_max_depth_exceeded`].
+pub(crate) fn max_depth_exceeded(dir: PathBuf) -> Event {
+    Event::new(EventKind::Other)
+        .add_path(dir)
+        .set_info("max-depth-exceeded")
+}
+
+/// Creates the event reporting that the event queue may overflow while watching `root`, see
+/// [`Event::is_overflow_risk`].
+#[cfg_attr(not(any(target_os = "linux", target_os = "android")), allow(dead_code))]
+pub(crate) fn overflow_risk(root: PathBuf) -> Event {
+    Event::new(EventKind::Other)
+        .add_path(root)
+        .set_info("overflow-risk")
+}
+
+/// Creates the event reporting that the watch of `root` has been set up, see
+/// [`Event::is_watch_ready`].
+#[cfg_attr(not(any(target_os = "linux", target_os = "android")), allow(dead_code))]
+pub(crate) fn watch_ready(root: PathBuf) -> Event {
+    Event::new(EventKind::Other)
+        .add_path(root)
+        .set_info("watch-ready")
+}
+
 /// Convenience method for creating the [`RecommendedWatcher`] for the current platform.
 pub fn recommended_watcher<F>(event_handler: F) -> Result<RecommendedWatcher>
 where
@@ -383,9 +844,85 @@ where
     RecommendedWatcher::new(event_handler, Config::default())
 }
 
+/// Convenience method for creating the [`RecommendedWatcher`] together with a blocking iterator
+/// over its events.
+///
+/// The iterator ends once the watcher is dropped.
+///
+/// ```no_run
+/// # use notify::{RecursiveMode, Result, Watcher};
+/// # use std::path::Path;
+/// # fn main() -> Result<()> {
+/// let (mut watcher, events) = notify::watcher_iter()?;
+/// watcher.watch(Path::new("."), RecursiveMode::Recursive)?;
+///
+/// for event in events {
+///     println!("event: {:?}", event?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn watcher_iter() -> Result<(RecommendedWatcher, impl Iterator<Item = Result<Event>>)> {
+    let (tx, rx) = unbounded();
+    let watcher = recommended_watcher(tx)?;
+    Ok((watcher, rx.into_iter()))
+}
+
+/// Create a watcher of the given `kind`, if its backend is available on the current platform.
+///
+/// This selects the backend at runtime without naming its type, which only exists on its
+/// platform. The [`PollWatcher`] and the [`NullWatcher`] are available everywhere.
+///
+/// ```no_run
+/// # use notify::{Config, RecursiveMode, Result, WatcherKind};
+/// # use std::path::Path;
+/// # fn main() -> Result<()> {
+/// let (tx, rx) = std::sync::mpsc::channel();
+/// let mut watcher = notify::watcher_of_kind(WatcherKind::Fsevent, tx.clone(), Config::default())
+///     .or_else(|_| notify::watcher_of_kind(WatcherKind::PollWatcher, tx, Config::default()))?;
+/// watcher.watch(Path::new("."), RecursiveMode::Recursive)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn watcher_of_kind<F>(
+    kind: WatcherKind,
+    event_handler: F,
+    config: Config,
+) -> Result<Box<dyn Watcher>>
+where
+    F: EventHandler,
+{
+    Ok(match kind {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        WatcherKind::Inotify => Box::new(INotifyWatcher::new(event_handler, config)?),
+        #[cfg(all(target_os = "macos", not(feature = "macos_kqueue")))]
+        WatcherKind::Fsevent => Box::new(FsEventWatcher::new(event_handler, config)?),
+        #[cfg(any(
+            target_os = "freebsd",
+            target_os = "openbsd",
+            target_os = "netbsd",
+            target_os = "dragonfly",
+            target_os = "ios",
+            all(target_os = "macos", feature = "macos_kqueue")
+        ))]
+        WatcherKind::Kqueue => Box::new(KqueueWatcher::new(event_handler, config)?),
+        #[cfg(target_os = "windows")]
+        WatcherKind::ReadDirectoryChangesWatcher => {
+            Box::new(ReadDirectoryChangesWatcher::new(event_handler, config)?)
+        }
+        WatcherKind::PollWatcher => Box::new(PollWatcher::new(event_handler, config)?),
+        WatcherKind::NullWatcher => Box::new(NullWatcher),
+        kind => {
+            return Err(Error::generic(&format!(
+                "the {kind:?} backend is not available on this platform"
+            )))
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
-    use std::{fs, time::Duration};
+    use std::fs;
 
     use tempfile::tempdir;
 
@@ -396,6 +933,25 @@ mod tests {
         let _watcher: &dyn Watcher = &NullWatcher;
     }
 
+    #[test]
+    fn watcher_of_kind_creates_available_backends() {
+        let watcher: PlatformWatcher = recommended_watcher(|_| {}).unwrap();
+        drop(watcher);
+
+        for kind in [RecommendedWatcher::kind(), WatcherKind::PollWatcher] {
+            let mut watcher = watcher_of_kind(kind, |_| {}, Config::default()).unwrap();
+            let dir = tempdir().unwrap();
+            watcher.watch(dir.path(), RecursiveMode::Recursive).unwrap();
+        }
+
+        let unavailable = if RecommendedWatcher::kind() == WatcherKind::Fsevent {
+            WatcherKind::Inotify
+        } else {
+            WatcherKind::Fsevent
+        };
+        assert!(watcher_of_kind(unavailable, |_| {}, Config::default()).is_err());
+    }
+
     #[test]
     fn test_debug_impl() {
         macro_rules! assert_debug_impl {
@@ -437,4 +993,349 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn ignore_self_events() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+        let watched = dir.path().join("watched");
+        fs::create_dir(&watched)?;
+
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        let config = Config::default().with_report_self_events(false);
+        let mut watcher = RecommendedWatcher::new(tx, config)?;
+
+        watcher.watch(&watched, RecursiveMode::NonRecursive)?;
+
+        fs::remove_dir(&watched)?;
+
+        if let Ok(event) = rx.recv_timeout(Duration::from_secs(1)) {
+            panic!("unexpected event: {event:?}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn poll_ignore_self_events() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+        let watched = dir.path().join("watched");
+        fs::create_dir(&watched)?;
+
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        let config = Config::default()
+            .with_manual_polling()
+            .with_report_self_events(false);
+        let mut watcher = PollWatcher::new(tx, config)?;
+
+        watcher.watch(&watched, RecursiveMode::Recursive)?;
+
+        fs::remove_dir(&watched)?;
+        watcher.poll()?;
+
+        assert!(rx.try_recv().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn poll_suppress_dir_modify_events() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+        // make sure the change of the directory's modification time is detected
+        filetime::set_file_mtime(dir.path(), filetime::FileTime::zero())?;
+
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        let config = Config::default()
+            .with_manual_polling()
+            .with_report_dir_modify_events(false);
+        let mut watcher = PollWatcher::new(tx, config)?;
+
+        watcher.watch(dir.path(), RecursiveMode::Recursive)?;
+
+        let file_path = dir.path().join("file.txt");
+        fs::write(&file_path, b"Lorem ipsum")?;
+        watcher.poll()?;
+
+        let events: Vec<_> =
+            std::iter::from_fn(|| rx.recv_timeout(Duration::from_millis(500)).ok())
+                .collect::<Result<_>>()?;
+
+        assert_eq!(events.len(), 1, "unexpected events: {events:?}");
+        assert_eq!(events[0].paths, vec![file_path]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn watcher_iter_ends_when_watcher_is_dropped(
+    ) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+
+        let (mut watcher, mut events) = watcher_iter()?;
+        watcher.watch(dir.path(), RecursiveMode::Recursive)?;
+
+        let file_path = dir.path().join("file.txt");
+        fs::write(&file_path, b"Lorem ipsum")?;
+
+        let event = events.next().expect("no events received")?;
+        assert_eq!(event.paths, vec![file_path]);
+
+        drop(watcher);
+        assert!(events.all(|event| event.is_ok()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn poll_interval_from_config() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        let config = Config::default().with_poll_interval(Duration::from_millis(10));
+        let mut watcher = PollWatcher::new(tx, config)?;
+
+        watcher.watch(dir.path(), RecursiveMode::Recursive)?;
+
+        let file_path = dir.path().join("file.txt");
+        fs::write(&file_path, b"Lorem ipsum")?;
+
+        let event = rx
+            .recv_timeout(Duration::from_secs(5))
+            .expect("no events received")
+            .expect("received an error");
+
+        assert_eq!(event.paths, vec![file_path]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn watch_many_reports_each_path() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+        let missing = dir.path().join("missing");
+
+        let mut watcher = RecommendedWatcher::new(|_| {}, Config::default())?;
+        let results = watcher.watch_many(&[
+            (dir.path(), RecursiveMode::Recursive),
+            (&missing, RecursiveMode::Recursive),
+        ]);
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, dir.path());
+        assert!(results[0].1.is_ok());
+        assert_eq!(results[1].0, missing);
+        assert!(results[1].1.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn event_timestamps() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let config = Config::default().with_event_timestamps(true);
+        let mut watcher = RecommendedWatcher::new(tx, config)?;
+        watcher.watch(dir.path(), RecursiveMode::Recursive)?;
+
+        let before = std::time::SystemTime::now();
+        fs::write(dir.path().join("file.txt"), b"Lorem ipsum")?;
+
+        let event = rx
+            .recv_timeout(Duration::from_secs(5))
+            .expect("no events received")
+            .expect("received an error");
+
+        assert!(event.time().expect("event without timestamp") >= before);
+
+        Ok(())
+    }
+
+    #[test]
+    fn event_source_kind() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let config = Config::default()
+            .with_poll_interval(Duration::from_millis(10))
+            .with_event_source_kind(true);
+        let mut watcher = PollWatcher::new(tx, config)?;
+        watcher.watch(dir.path(), RecursiveMode::Recursive)?;
+
+        fs::write(dir.path().join("file.txt"), b"Lorem ipsum")?;
+
+        let event = rx
+            .recv_timeout(Duration::from_secs(5))
+            .expect("no events received")
+            .expect("received an error");
+
+        assert_eq!(event.source_kind(), Some(WatcherKind::PollWatcher));
+
+        Ok(())
+    }
+
+    #[test]
+    fn event_sequence_numbers() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let config = Config::default().with_event_sequence_numbers(true);
+        let mut watcher = RecommendedWatcher::new(tx, config)?;
+        watcher.watch(dir.path(), RecursiveMode::Recursive)?;
+
+        for i in 0..20 {
+            fs::write(dir.path().join(format!("file{i}.txt")), b"Lorem ipsum")?;
+        }
+
+        let seqs: Vec<_> = rx
+            .iter()
+            .take(20)
+            .map(|res| {
+                res.expect("received an error")
+                    .seq()
+                    .expect("no sequence number")
+            })
+            .collect();
+        assert!(seqs.windows(2).all(|w| w[0] < w[1]), "{seqs:?}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn pause_and_resume() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut watcher = RecommendedWatcher::new(tx, Config::default())?;
+        watcher.watch(dir.path(), RecursiveMode::Recursive)?;
+
+        watcher.pause()?;
+        fs::write(dir.path().join("file.txt"), b"Lorem ipsum")?;
+        if let Ok(event) = rx.recv_timeout(Duration::from_millis(500)) {
+            panic!("unexpected event: {event:?}");
+        }
+
+        watcher.resume()?;
+        let event = rx
+            .recv_timeout(Duration::from_secs(5))
+            .expect("no events received")
+            .expect("received an error");
+        assert!(event.need_rescan());
+        assert!(event.paths.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn covers_recursive_and_non_recursive_watches() {
+        let watches = [
+            (Path::new("/recursive"), RecursiveMode::Recursive),
+            (Path::new("/flat"), RecursiveMode::NonRecursive),
+        ];
+
+        assert!(covers(watches, Path::new("/recursive/a/b")));
+        assert!(covers(watches, Path::new("/flat")));
+        assert!(covers(watches, Path::new("/flat/a")));
+        assert!(!covers(watches, Path::new("/flat/a/b")));
+        assert!(!covers(watches, Path::new("/other")));
+        assert!(!covers(watches, Path::new("/recursive-sibling")));
+    }
+
+    #[test]
+    fn poll_max_tracked_paths() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+        for i in 0..5 {
+            fs::write(dir.path().join(format!("file{i}")), b"Lorem ipsum")?;
+        }
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let (scan_tx, scan_rx) = std::sync::mpsc::channel();
+
+        let config = Config::default()
+            .with_manual_polling()
+            .with_max_tracked_paths(3);
+        let mut watcher = PollWatcher::with_initial_scan(tx, config, scan_tx)?;
+
+        watcher.watch(dir.path(), RecursiveMode::Recursive)?;
+
+        assert_eq!(scan_rx.try_iter().count(), 3);
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(Err(Error {
+                kind: ErrorKind::MaxFilesWatch,
+                ..
+            }))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn set_watches_applies_the_difference() {
+        /// Watcher recording the calls made to it
+        #[derive(Default)]
+        struct RecordingWatcher {
+            watches: Vec<(PathBuf, RecursiveMode)>,
+            calls: Vec<String>,
+        }
+
+        impl Watcher for RecordingWatcher {
+            fn new<F: EventHandler>(_event_handler: F, _config: Config) -> Result<Self> {
+                Ok(Self::default())
+            }
+
+            fn watch(&mut self, path: &Path, recursive_mode: RecursiveMode) -> Result<()> {
+                self.calls
+                    .push(format!("watch {} {recursive_mode}", path.display()));
+                self.watches.push((path.to_path_buf(), recursive_mode));
+                Ok(())
+            }
+
+            fn unwatch(&mut self, path: &Path) -> Result<()> {
+                self.calls.push(format!("unwatch {}", path.display()));
+                self.watches.retain(|(p, _)| p != path);
+                Ok(())
+            }
+
+            fn watched_paths(&self) -> Result<Vec<(PathBuf, RecursiveMode)>> {
+                Ok(self.watches.clone())
+            }
+
+            fn kind() -> WatcherKind {
+                WatcherKind::NullWatcher
+            }
+        }
+
+        let mut watcher = RecordingWatcher::default();
+        watcher
+            .set_watches(&[
+                (Path::new("/a"), RecursiveMode::Recursive),
+                (Path::new("/b"), RecursiveMode::Recursive),
+                (Path::new("/c"), RecursiveMode::Recursive),
+            ])
+            .unwrap();
+        watcher.calls.clear();
+
+        watcher
+            .set_watches(&[
+                (Path::new("/b"), RecursiveMode::Recursive),
+                (Path::new("/c"), RecursiveMode::NonRecursive),
+                (Path::new("/d"), RecursiveMode::Recursive),
+            ])
+            .unwrap();
+
+        assert_eq!(
+            watcher.calls,
+            [
+                "unwatch /a",
+                "unwatch /c",
+                "watch /c non-recursive",
+                "watch /d recursive"
+            ]
+        );
+        assert_eq!(watcher.watches.len(), 3);
+    }
 }
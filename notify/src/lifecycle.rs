@@ -0,0 +1,133 @@
+//! Callbacks observing the operations of a watcher backend
+
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+type PathCallback = Arc<dyn Fn(&Path) + Send + Sync>;
+type ThreadCallback = Arc<dyn Fn() + Send + Sync>;
+
+/// Callbacks invoked when a backend starts or stops its thread and when it installs or removes a
+/// watch with the operating system.
+///
+/// This is about the watcher's own operations rather than changes on the filesystem, e.g. to
+/// count the watches in use. The callbacks are invoked from the backend's thread, so they should
+/// return quickly.
+///
+/// Currently used by the [INotifyWatcher](crate::INotifyWatcher) and the
+/// [ReadDirectoryChangesWatcher](crate::ReadDirectoryChangesWatcher) backends, see
+/// `INotifyWatcher::with_lifecycle` and `ReadDirectoryChangesWatcher::with_lifecycle`.
+///
+/// ```
+/// # use notify::Lifecycle;
+/// # use std::sync::atomic::{AtomicUsize, Ordering};
+/// # use std::sync::Arc;
+/// let watches = Arc::new(AtomicUsize::new(0));
+/// let added = watches.clone();
+/// let removed = watches.clone();
+/// let lifecycle = Lifecycle::new()
+///     .with_on_watch_added(move |_| {
+///         added.fetch_add(1, Ordering::Relaxed);
+///     })
+///     .with_on_watch_removed(move |_| {
+///         removed.fetch_sub(1, Ordering::Relaxed);
+///     });
+/// ```
+#[derive(Clone, Default)]
+pub struct Lifecycle {
+    on_thread_start: Option<ThreadCallback>,
+    on_watch_added: Option<PathCallback>,
+    on_watch_removed: Option<PathCallback>,
+    on_thread_exit: Option<ThreadCallback>,
+}
+
+impl Lifecycle {
+    /// Creates callbacks that do nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Call `callback` on the backend's thread once it started.
+    pub fn with_on_thread_start<F>(mut self, callback: F) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.on_thread_start = Some(Arc::new(callback));
+        self
+    }
+
+    /// Call `callback` with the path of each watch installed with the operating system.
+    ///
+    /// A recursively watched directory may be watched with a watch per subdirectory.
+    pub fn with_on_watch_added<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&Path) + Send + Sync + 'static,
+    {
+        self.on_watch_added = Some(Arc::new(callback));
+        self
+    }
+
+    /// Call `callback` with the path of each watch torn down, including watches the operating
+    /// system removed on its own, e.g. because the path was deleted.
+    pub fn with_on_watch_removed<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&Path) + Send + Sync + 'static,
+    {
+        self.on_watch_removed = Some(Arc::new(callback));
+        self
+    }
+
+    /// Call `callback` on the backend's thread right before it exits.
+    pub fn with_on_thread_exit<F>(mut self, callback: F) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.on_thread_exit = Some(Arc::new(callback));
+        self
+    }
+}
+
+#[cfg_attr(
+    not(any(target_os = "linux", target_os = "android", windows)),
+    allow(dead_code)
+)]
+impl Lifecycle {
+    pub(crate) fn thread_started(&self) {
+        if let Some(callback) = &self.on_thread_start {
+            callback();
+        }
+    }
+
+    pub(crate) fn watch_added(&self, path: &Path) {
+        if let Some(callback) = &self.on_watch_added {
+            callback(path);
+        }
+    }
+
+    pub(crate) fn watch_removed(&self, path: &Path) {
+        if let Some(callback) = &self.on_watch_removed {
+            callback(path);
+        }
+    }
+
+    pub(crate) fn thread_exited(&self) {
+        if let Some(callback) = &self.on_thread_exit {
+            callback();
+        }
+    }
+}
+
+impl fmt::Debug for Lifecycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let callback = |set: bool| set.then_some("<callback>");
+        f.debug_struct("Lifecycle")
+            .field("on_thread_start", &callback(self.on_thread_start.is_some()))
+            .field("on_watch_added", &callback(self.on_watch_added.is_some()))
+            .field(
+                "on_watch_removed",
+                &callback(self.on_watch_removed.is_some()),
+            )
+            .field("on_thread_exit", &callback(self.on_thread_exit.is_some()))
+            .finish()
+    }
+}
@@ -5,7 +5,7 @@
 use crate::Config;
 
 use super::{RecursiveMode, Result, Watcher};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// Stub `Watcher` implementation
 ///
@@ -22,6 +22,14 @@ impl Watcher for NullWatcher {
         Ok(())
     }
 
+    fn watched_paths(&self) -> Result<Vec<(PathBuf, RecursiveMode)>> {
+        Ok(Vec::new())
+    }
+
+    fn unwatch_prefix(&mut self, prefix: &Path) -> Result<Vec<PathBuf>> {
+        Ok(Vec::new())
+    }
+
     fn new<F: crate::EventHandler>(event_handler: F, config: Config) -> Result<Self>
     where
         Self: Sized,
@@ -33,6 +41,18 @@ impl Watcher for NullWatcher {
         Ok(false)
     }
 
+    fn pause(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn resync(&mut self) -> Result<()> {
+        Ok(())
+    }
+
     fn kind() -> crate::WatcherKind {
         crate::WatcherKind::NullWatcher
     }
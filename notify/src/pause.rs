@@ -0,0 +1,308 @@
+//! Suspending event delivery while a watcher is paused or stopping
+
+use crate::case::CaseRoots;
+use crate::event::{Event, EventKind, Flag};
+use crate::stats::Counters;
+use crate::{Config, EventHandler, Result, WatcherStats};
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// [`EventHandler`] wrapper shared between a watcher and its backend, holding back events while
+/// the watcher is paused, see [`Watcher::pause`](crate::Watcher::pause).
+#[derive(Clone)]
+pub(crate) struct PauseGate {
+    inner: Arc<Mutex<PauseGateInner>>,
+    /// The events delivered to the user's event handler, see
+    /// [`Watcher::stats`](crate::Watcher::stats)
+    counters: Arc<Counters>,
+    /// The watched paths whose case is applied to event paths, if enabled, see
+    /// [`Config::with_normalize_path_case`]
+    case_roots: Option<CaseRoots>,
+}
+
+struct PauseGateInner {
+    event_handler: Box<dyn EventHandler>,
+    paused: bool,
+    /// Events emitted while paused, if they are buffered, see
+    /// [`Config::with_buffer_paused_events`]
+    buffer: Option<Vec<Result<Event>>>,
+    /// Buffered events are stamped with the time they were emitted, see
+    /// [`Config::with_event_timestamps`]
+    timestamps: bool,
+}
+
+impl PauseGate {
+    /// Creates a gate for `event_handler`, whose end counts the delivered events in `counters`,
+    /// see [`Counters::handler`].
+    pub(crate) fn new(
+        event_handler: Box<dyn EventHandler>,
+        config: &Config,
+        counters: Arc<Counters>,
+    ) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(PauseGateInner {
+                event_handler,
+                paused: false,
+                buffer: config.buffer_paused_events().then(Vec::new),
+                timestamps: config.event_timestamps(),
+            })),
+            counters,
+            case_roots: config.normalize_path_case().then(CaseRoots::default),
+        }
+    }
+
+    /// Locks the shared state. A panic of the event handler poisons the lock, which is recovered
+    /// from so that the panic can still be reported to it.
+    fn lock(&self) -> MutexGuard<'_, PauseGateInner> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub(crate) fn pause(&self) {
+        self.lock().paused = true;
+    }
+
+    /// Resumes event delivery, either by emitting the buffered events or a rescan event.
+    pub(crate) fn resume(&self) {
+        let mut inner = self.lock();
+        if !inner.paused {
+            return;
+        }
+        inner.paused = false;
+
+        let inner = &mut *inner;
+        match &mut inner.buffer {
+            Some(buffer) => {
+                inner.event_handler.handle_events(std::mem::take(buffer));
+            }
+            None => {
+                let event = Event::new(EventKind::Other)
+                    .set_flag(Flag::Rescan)
+                    .set_info("resumed");
+                inner.event_handler.handle_event(Ok(event));
+            }
+        }
+    }
+
+    /// Notes that `path` is watched now, for normalizing the case of event paths.
+    pub(crate) fn root_watched(&self, path: &Path) {
+        if let Some(case_roots) = &self.case_roots {
+            case_roots.add(path);
+        }
+    }
+
+    /// Notes that `path`, or every path starting with it if `is_prefix` is set, isn't watched
+    /// anymore.
+    pub(crate) fn root_unwatched(&self, path: &Path, is_prefix: bool) {
+        if let Some(case_roots) = &self.case_roots {
+            case_roots.remove(path, is_prefix);
+        }
+    }
+
+    fn normalize(&self, event: &mut Result<Event>) {
+        if let (Some(case_roots), Ok(event)) = (&self.case_roots, event) {
+            event
+                .paths
+                .iter_mut()
+                .for_each(|path| case_roots.normalize(path));
+        }
+    }
+
+    /// Returns the counters of the events delivered so far, along with the number of `watches`.
+    pub(crate) fn stats(&self, watches: usize) -> WatcherStats {
+        self.counters.snapshot(watches)
+    }
+}
+
+impl EventHandler for PauseGate {
+    fn handle_event(&mut self, mut event: Result<Event>) {
+        self.normalize(&mut event);
+        let mut inner = self.lock();
+        let timestamps = inner.timestamps;
+        match (inner.paused, &mut inner.buffer) {
+            (false, _) => inner.event_handler.handle_event(event),
+            (true, Some(buffer)) => buffer.push(stamp(event, timestamps)),
+            (true, None) => log::trace!("discarding event while paused: {event:?}"),
+        }
+    }
+
+    fn handle_events(&mut self, mut events: Vec<Result<Event>>) {
+        events.iter_mut().for_each(|event| self.normalize(event));
+        let mut inner = self.lock();
+        let timestamps = inner.timestamps;
+        match (inner.paused, &mut inner.buffer) {
+            (false, _) => inner.event_handler.handle_events(events),
+            (true, Some(buffer)) => {
+                buffer.extend(events.into_iter().map(|event| stamp(event, timestamps)))
+            }
+            (true, None) => log::trace!("discarding {} events while paused", events.len()),
+        }
+    }
+}
+
+/// Stamps a buffered event with the current time if `timestamps` are enabled, as the event
+/// handler behind the gate would only stamp it on resume.
+fn stamp(event: Result<Event>, timestamps: bool) -> Result<Event> {
+    match event {
+        Ok(event) if timestamps => Ok(event.set_time(std::time::SystemTime::now())),
+        event => event,
+    }
+}
+
+impl fmt::Debug for PauseGate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PauseGate")
+            .field("paused", &self.lock().paused)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Waits for an event handler running its own thread to pass on the events it holds.
+pub(crate) type Flush = Box<dyn Fn() + Send + Sync>;
+
+/// Collected events, `None` until the watcher is stopping
+type Collected = Arc<Mutex<Option<Vec<Result<Event>>>>>;
+
+/// Locks the collected events, recovering from a panic of the event handler like [`PauseGate`]
+/// does.
+fn lock(events: &Collected) -> MutexGuard<'_, Option<Vec<Result<Event>>>> {
+    events.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Events left over when a watcher stops, see [`Watcher::stop`](crate::Watcher::stop).
+///
+/// Shared between a watcher and the [`LeftoverHandler`] wrapping its event handler, which
+/// collects the events instead of delivering them once the watcher is stopping.
+#[cfg_attr(
+    not(any(target_os = "linux", target_os = "android", target_os = "windows")),
+    allow(dead_code)
+)]
+#[derive(Default)]
+pub(crate) struct Leftovers {
+    events: Collected,
+    /// The event handlers running their own thread, flushed by [`take`](Leftovers::take)
+    flushes: Vec<Flush>,
+}
+
+#[cfg_attr(
+    not(any(target_os = "linux", target_os = "android", target_os = "windows")),
+    allow(dead_code)
+)]
+impl Leftovers {
+    /// Wraps `event_handler`, so it doesn't receive the events emitted after
+    /// [`collect`](Leftovers::collect).
+    pub(crate) fn handler<F: EventHandler>(&self, event_handler: F) -> LeftoverHandler<F> {
+        LeftoverHandler {
+            event_handler,
+            events: self.events.clone(),
+        }
+    }
+
+    /// Flushes an event handler with `flush` before the events are taken. The flushes run in the
+    /// reverse order they were added, so the handlers closer to the backend are to be added last.
+    pub(crate) fn flush_on_take(&mut self, flush: Option<Flush>) {
+        self.flushes.extend(flush);
+    }
+
+    /// Collects the events from now on instead of delivering them.
+    pub(crate) fn collect(&self) {
+        lock(&self.events).get_or_insert_with(Vec::new);
+    }
+
+    /// Flushes the event handlers running their own thread, then takes the events collected so
+    /// far.
+    pub(crate) fn take(&self) -> Vec<Result<Event>> {
+        self.flushes.iter().rev().for_each(|flush| flush());
+        lock(&self.events)
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Debug for Leftovers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Leftovers")
+            .field("stopping", &lock(&self.events).is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+/// [`EventHandler`] wrapper created by [`Leftovers::handler`].
+#[cfg_attr(
+    not(any(target_os = "linux", target_os = "android", target_os = "windows")),
+    allow(dead_code)
+)]
+pub(crate) struct LeftoverHandler<F> {
+    event_handler: F,
+    events: Collected,
+}
+
+impl<F: EventHandler> EventHandler for LeftoverHandler<F> {
+    fn handle_event(&mut self, event: Result<Event>) {
+        match &mut *lock(&self.events) {
+            Some(leftovers) => leftovers.push(event),
+            None => self.event_handler.handle_event(event),
+        }
+    }
+
+    fn handle_events(&mut self, mut events: Vec<Result<Event>>) {
+        match &mut *lock(&self.events) {
+            Some(leftovers) => leftovers.append(&mut events),
+            None => self.event_handler.handle_events(events),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::CreateKind;
+    use std::path::PathBuf;
+
+    #[test]
+    fn buffered_events_are_delivered_on_resume() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let collected = events.clone();
+        let mut gate = PauseGate::new(
+            Box::new(move |res: Result<Event>| collected.lock().unwrap().push(res.unwrap())),
+            &Config::default().with_buffer_paused_events(true),
+            Arc::default(),
+        );
+
+        gate.pause();
+        let event = Event::new(EventKind::Create(CreateKind::File)).add_path(PathBuf::from("/a"));
+        gate.handle_event(Ok(event.clone()));
+        assert!(events.lock().unwrap().is_empty());
+
+        gate.resume();
+        assert_eq!(*events.lock().unwrap(), vec![event]);
+    }
+
+    #[test]
+    fn buffered_events_keep_the_time_they_were_emitted() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let collected = events.clone();
+        let config = Config::default()
+            .with_buffer_paused_events(true)
+            .with_event_timestamps(true);
+        let mut gate = PauseGate::new(
+            Box::new(crate::AttributeHandler::new(
+                move |res: Result<Event>| collected.lock().unwrap().push(res.unwrap()),
+                &config,
+                crate::WatcherKind::NullWatcher,
+            )),
+            &config,
+            Arc::default(),
+        );
+
+        gate.pause();
+        gate.handle_event(Ok(Event::new(EventKind::Create(CreateKind::File))));
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        let resumed = std::time::SystemTime::now();
+        gate.resume();
+
+        let time = events.lock().unwrap()[0].time().unwrap();
+        assert!(time < resumed);
+    }
+}
@@ -3,9 +3,19 @@
 //! Checks the `watch`ed paths periodically to detect changes. This implementation only uses
 //! Rust stdlib APIs and should work on all of the platforms it supports.
 
-use crate::{unbounded, Config, Error, EventHandler, Receiver, RecursiveMode, Sender, Watcher};
+use crate::event::{EventKind, FileId, ModifyKind, RenameMode};
+use crate::pause::PauseGate;
+use crate::stats::Counters;
+use crate::{dispatch, resync, settle};
+use crate::{
+    unbounded, AttributeHandler, Config, Error, Event, EventHandler, Receiver, RecursiveMode,
+    Sender, Watcher,
+};
+use filetime::FileTime;
 use std::{
     collections::HashMap,
+    ffi::OsString,
+    fs,
     path::{Path, PathBuf},
     sync::{
         atomic::{AtomicBool, Ordering},
@@ -15,6 +25,136 @@ use std::{
     time::Duration,
 };
 
+/// State of a path as last seen by the [`PollWatcher`], see [`PollWatcher::snapshot`].
+///
+/// With the `serde` feature, snapshots can be serialized to be compared after a restart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PathSnapshot {
+    /// Size in bytes.
+    pub size: u64,
+    /// Last modification time, in seconds since the Unix epoch.
+    pub mtime: i64,
+    /// Hash of the contents, only set for files if [`CompareMode::Contents`](crate::CompareMode)
+    /// is enabled.
+    ///
+    /// Hashes are computed with 64-bit FNV-1a, so they are stable across process restarts and
+    /// Rust releases.
+    pub hash: Option<u64>,
+    /// Whether the path is a directory.
+    pub is_dir: bool,
+    /// The ID of the file, only set on Unix-like platforms, see [`diff_snapshots`].
+    pub file_id: Option<FileId>,
+}
+
+impl PathSnapshot {
+    /// Create a snapshot of a path from its metadata, without a hash of the contents.
+    pub fn from_metadata(metadata: &fs::Metadata) -> Self {
+        Self {
+            size: metadata.len(),
+            mtime: FileTime::from_last_modification_time(metadata).seconds(),
+            hash: None,
+            is_dir: metadata.is_dir(),
+            file_id: file_id(metadata),
+        }
+    }
+}
+
+/// The ID of the file described by `metadata`, if it can be read without opening the file.
+fn file_id(metadata: &fs::Metadata) -> Option<FileId> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        Some(FileId::new_inode(metadata.dev(), metadata.ino()))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+/// Returns the events describing the changes from the snapshot `before` to the snapshot `after`.
+///
+/// The snapshots may come from [`PollWatcher::snapshot`] or be created from directory listings
+/// with [`PathSnapshot::from_metadata`]. Paths are compared like the [`PollWatcher`] does, in
+/// addition a removed and a created path with the same [`file_id`](PathSnapshot::file_id) are
+/// reported as a rename. The events are ordered by kind: removals, children before their
+/// parents, then renames, creations, parents before their children, and modifications.
+///
+/// ```
+/// # use notify::poll::{diff_snapshots, PathSnapshot};
+/// # use std::collections::HashMap;
+/// # use std::path::PathBuf;
+/// let file = PathSnapshot { size: 0, mtime: 0, hash: None, is_dir: false, file_id: None };
+/// let before = HashMap::from([(PathBuf::from("/a"), file)]);
+/// let after = HashMap::from([(PathBuf::from("/b"), file)]);
+///
+/// let events = diff_snapshots(&before, &after);
+/// assert!(events[0].kind.is_remove());
+/// assert!(events[1].kind.is_create());
+/// ```
+pub fn diff_snapshots(
+    before: &HashMap<PathBuf, PathSnapshot>,
+    after: &HashMap<PathBuf, PathSnapshot>,
+) -> Vec<Event> {
+    let mut removed: Vec<_> = before
+        .iter()
+        .filter(|(path, _)| !after.contains_key(*path))
+        .collect();
+    let mut created: Vec<_> = after
+        .iter()
+        .filter(|(path, _)| !before.contains_key(*path))
+        .collect();
+    let mut kept: Vec<_> = after
+        .iter()
+        .filter_map(|(path, new)| Some((path, before.get(path)?, new)))
+        .collect();
+    removed.sort_by(|a, b| b.0.cmp(a.0));
+    created.sort_by(|a, b| a.0.cmp(b.0));
+    kept.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut renamed = Vec::new();
+    removed.retain(|&(from, old)| {
+        let Some(file_id) = old.file_id else {
+            return true;
+        };
+        let Some(index) = created
+            .iter()
+            .position(|(_, new)| new.file_id == Some(file_id) && new.is_dir == old.is_dir)
+        else {
+            return true;
+        };
+        let (to, new) = created.remove(index);
+        renamed.push((from, to, old, new));
+        false
+    });
+    renamed.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut events: Vec<_> = removed
+        .into_iter()
+        .filter_map(|(path, old)| data::snapshot_event(path, Some(old), None))
+        .collect();
+    for (from, to, old, new) in renamed {
+        events.push(
+            Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+                .add_path(from.clone())
+                .add_path(to.clone()),
+        );
+        events.extend(data::snapshot_event(to, Some(old), Some(new)));
+    }
+    events.extend(
+        created
+            .into_iter()
+            .filter_map(|(path, new)| data::snapshot_event(path, None, Some(new))),
+    );
+    events.extend(
+        kept.into_iter()
+            .filter_map(|(path, old, new)| data::snapshot_event(path, Some(old), Some(new))),
+    );
+    events
+}
+
 /// Event sent for registered handlers on initial directory scans
 pub type ScanEvent = crate::Result<PathBuf>;
 
@@ -56,23 +196,27 @@ impl ScanEventHandler for () {
 use data::{DataBuilder, WatchData};
 mod data {
     use crate::{
-        event::{CreateKind, DataChange, Event, EventKind, MetadataKind, ModifyKind, RemoveKind},
-        EventHandler,
+        event::{
+            CreateKind, DataChange, Event, EventKind, FileId, MetadataKind, ModifyKind, RemoveKind,
+        },
+        max_depth_exceeded, CompareMode, Config, Error, ErrorKind, EventHandler, HiddenFiles,
     };
     use filetime::FileTime;
     use std::{
         cell::RefCell,
-        collections::{hash_map::RandomState, HashMap},
+        collections::HashMap,
+        ffi::OsString,
         fmt::{self, Debug},
-        fs::{self, File, Metadata},
-        hash::{BuildHasher, Hasher},
+        fs::{self, Metadata, OpenOptions},
+        hash::{BuildHasher, BuildHasherDefault, Hasher},
         io::{self, Read},
         path::{Path, PathBuf},
-        time::Instant,
+        sync::Arc,
+        time::{Duration, Instant},
     };
     use walkdir::WalkDir;
 
-    use super::ScanEventHandler;
+    use super::{file_id, PathSnapshot, ScanEventHandler};
 
     /// Builder for [`WatchData`] & [`PathData`].
     pub(super) struct DataBuilder {
@@ -81,18 +225,39 @@ mod data {
 
         // TODO: May allow user setup their custom BuildHasher / BuildHasherDefault
         // in future.
-        build_hasher: Option<RandomState>,
+        // A fixed algorithm, so hashes in snapshots stay valid across restarts.
+        build_hasher: Option<ContentHasher>,
+
+        // whether a changed size counts as a change.
+        compare_size: bool,
+
+        // whether to emit events about the watched root itself.
+        report_self_events: bool,
+
+        // maximum number of paths tracked across all watches.
+        max_tracked_paths: Option<usize>,
+
+        // whether to emit modify events for directories.
+        report_dir_modify_events: bool,
+
+        // extensions of the files to track in new watches, all files are tracked if unset.
+        pub(super) extensions: Option<Arc<[OsString]>>,
+
+        // whether hidden files are tracked.
+        hidden_files: HiddenFiles,
+
+        // whether created paths are reported as files or folders rather than `CreateKind::Any`.
+        precise_create_kinds: bool,
+
+        // maximum number of directory levels scanned below a recursively watched path.
+        max_depth: Option<usize>,
 
         // current timestamp for building Data.
         now: Instant,
     }
 
     impl DataBuilder {
-        pub(super) fn new<F, G>(
-            event_handler: F,
-            compare_content: bool,
-            scan_emitter: Option<G>,
-        ) -> Self
+        pub(super) fn new<F, G>(event_handler: F, config: &Config, scan_emitter: Option<G>) -> Self
         where
             F: EventHandler,
             G: ScanEventHandler,
@@ -109,16 +274,53 @@ mod data {
             Self {
                 emitter: EventEmitter::new(event_handler),
                 scan_emitter,
-                build_hasher: compare_content.then(RandomState::default),
+                build_hasher: (config.compare() == CompareMode::Contents)
+                    .then(ContentHasher::default),
+                compare_size: config.compare() == CompareMode::SizeAndMtime,
+                report_self_events: config.report_self_events(),
+                max_tracked_paths: config.max_tracked_paths(),
+                report_dir_modify_events: config.report_dir_modify_events(),
+                extensions: None,
+                hidden_files: config.hidden_files(),
+                precise_create_kinds: config.precise_create_kinds(),
+                max_depth: config.max_watch_depth(),
                 now: Instant::now(),
             }
         }
 
+        /// Whether the entry is skipped by the `extensions` of its watch, see
+        /// [`PollWatcher::set_extensions`](super::PollWatcher::set_extensions), or because it is
+        /// a hidden file, see [Config::with_hidden_files](crate::Config::with_hidden_files).
+        fn skips(&self, entry: &walkdir::DirEntry, extensions: Option<&[OsString]>) -> bool {
+            // a watched file itself is never skipped
+            entry.depth() > 0
+                && entry.file_type().is_file()
+                && self.skips_file(entry.path(), extensions)
+        }
+
+        fn skips_file(&self, path: &Path, extensions: Option<&[OsString]>) -> bool {
+            if !self.hidden_files.allows_path(path) {
+                return true;
+            }
+            extensions.is_some_and(|extensions| {
+                !path
+                    .extension()
+                    .is_some_and(|extension| extensions.iter().any(|e| e == extension))
+            })
+        }
+
         /// Update internal timestamp.
         pub(super) fn update_timestamp(&mut self) {
             self.now = Instant::now();
         }
 
+        /// Number of paths a watch may track, given the number of paths tracked by all other
+        /// watches.
+        pub(super) fn tracking_budget(&self, tracked_elsewhere: usize) -> Option<usize> {
+            self.max_tracked_paths
+                .map(|max| max.saturating_sub(tracked_elsewhere))
+        }
+
         /// Create [`WatchData`].
         ///
         /// This function will return `Err(_)` if can not retrieve metadata from
@@ -128,8 +330,62 @@ mod data {
             root: PathBuf,
             is_recursive: bool,
             follow_symlinks: bool,
+            budget: Option<usize>,
         ) -> Option<WatchData> {
-            WatchData::new(self, root, is_recursive, follow_symlinks)
+            WatchData::new(self, root, is_recursive, follow_symlinks, budget)
+        }
+
+        /// Create [`WatchData`] from a previously taken snapshot, without scanning.
+        ///
+        /// The snapshot entries are filtered like the entries of a scan. The next rescan reports
+        /// the differences between the snapshot and the filesystem.
+        pub(super) fn build_watch_data_from_snapshot(
+            &self,
+            root: PathBuf,
+            is_recursive: bool,
+            follow_symlinks: bool,
+            budget: Option<usize>,
+            snapshot: HashMap<PathBuf, PathSnapshot>,
+        ) -> WatchData {
+            // before `now`, so paths missing in the next scan are detected as removed
+            let last_check = self
+                .now
+                .checked_sub(Duration::from_millis(1))
+                .unwrap_or(self.now);
+            let max_depth = WatchData::dir_scan_depth(is_recursive, self.max_depth);
+            let mut entries: Vec<_> = snapshot
+                .into_iter()
+                .filter(|(path, snapshot)| {
+                    let Ok(rel) = path.strip_prefix(&root) else {
+                        return false;
+                    };
+                    let depth = rel.components().count();
+                    depth <= max_depth
+                        && !(depth > 0
+                            && !snapshot.is_dir
+                            && self.skips_file(path, self.extensions.as_deref()))
+                })
+                .collect();
+            // like a scan, keep the parents when the budget runs out
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+            let mut watch_data = WatchData {
+                root,
+                is_recursive,
+                follow_symlinks,
+                extensions: self.extensions.clone(),
+                all_path_data: HashMap::new(),
+                limit_reported: false,
+            };
+            let mut remaining = budget;
+            for (path, snapshot) in entries {
+                if !watch_data.admits_new_path(self, &mut remaining) {
+                    continue;
+                }
+                let path_data = PathData::from_snapshot(snapshot, last_check);
+                watch_data.all_path_data.insert(path, path_data);
+            }
+            watch_data
         }
 
         /// Create [`PathData`].
@@ -153,9 +409,14 @@ mod data {
         root: PathBuf,
         is_recursive: bool,
         follow_symlinks: bool,
+        // extensions of the files to track, all files are tracked if unset.
+        extensions: Option<Arc<[OsString]>>,
 
         // current status part.
         all_path_data: HashMap<PathBuf, PathData>,
+
+        // whether reaching the maximum number of tracked paths has been reported.
+        limit_reported: bool,
     }
 
     impl WatchData {
@@ -169,6 +430,7 @@ mod data {
             root: PathBuf,
             is_recursive: bool,
             follow_symlinks: bool,
+            budget: Option<usize>,
         ) -> Option<Self> {
             // If metadata read error at `root` path, it will emit
             // a error event and stop to create the whole `WatchData`.
@@ -193,21 +455,32 @@ mod data {
                 return None;
             }
 
-            let all_path_data = Self::scan_all_path_data(
-                data_builder,
-                root.clone(),
+            let mut watch_data = Self {
+                root: root.clone(),
                 is_recursive,
                 follow_symlinks,
-                true,
-            )
-            .collect();
-
-            Some(Self {
+                extensions: data_builder.extensions.clone(),
+                all_path_data: HashMap::new(),
+                limit_reported: false,
+            };
+            let mut remaining = budget;
+            for entry in Self::scan_entries(
+                data_builder,
                 root,
                 is_recursive,
                 follow_symlinks,
-                all_path_data,
-            })
+                watch_data.extensions.clone(),
+                true,
+            ) {
+                if !watch_data.admits_new_path(data_builder, &mut remaining) {
+                    continue;
+                }
+                if let Some((path, path_data)) = Self::build_path_data(data_builder, entry, true) {
+                    watch_data.all_path_data.insert(path, path_data);
+                }
+            }
+
+            Some(watch_data)
         }
 
         /// Rescan filesystem and update this `WatchData`.
@@ -215,23 +488,52 @@ mod data {
         /// # Side effect
         ///
         /// This function may emit event by `data_builder.emitter`.
-        pub(super) fn rescan(&mut self, data_builder: &mut DataBuilder) {
+        pub(super) fn rescan(&mut self, data_builder: &mut DataBuilder, budget: Option<usize>) {
+            // paths tracked already are kept, only new paths count against the budget
+            let mut remaining =
+                budget.map(|budget| budget.saturating_sub(self.all_path_data.len()));
+
             // scan current filesystem.
-            for (path, new_path_data) in Self::scan_all_path_data(
+            for entry in Self::scan_entries(
                 data_builder,
                 self.root.clone(),
                 self.is_recursive,
                 self.follow_symlinks,
+                self.extensions.clone(),
                 false,
             ) {
+                if !self.all_path_data.contains_key(entry.path())
+                    && !self.admits_new_path(data_builder, &mut remaining)
+                {
+                    continue;
+                }
+                let Some((path, new_path_data)) = Self::build_path_data(data_builder, entry, false)
+                else {
+                    continue;
+                };
                 let old_path_data = self
                     .all_path_data
                     .insert(path.clone(), new_path_data.clone());
 
                 // emit event
-                let event =
-                    PathData::compare_to_event(path, old_path_data.as_ref(), Some(&new_path_data));
-                if let Some(event) = event {
+                let event = PathData::compare_to_event(
+                    path,
+                    old_path_data.as_ref(),
+                    Some(&new_path_data),
+                    data_builder.compare_size,
+                )
+                .filter(|event| {
+                    data_builder.report_dir_modify_events
+                        || !(new_path_data.is_dir && event.kind.is_modify())
+                });
+                if let Some(mut event) = event {
+                    if data_builder.precise_create_kinds && event.kind.is_create() {
+                        event.kind = EventKind::Create(if new_path_data.is_dir {
+                            CreateKind::Folder
+                        } else {
+                            CreateKind::File
+                        });
+                    }
                     data_builder.emitter.emit_ok(event);
                 }
             }
@@ -248,27 +550,64 @@ mod data {
             for path in disappeared_paths {
                 let old_path_data = self.all_path_data.remove(&path);
 
+                // the watched root itself disappeared
+                if !data_builder.report_self_events && path == self.root {
+                    continue;
+                }
+
                 // emit event
-                let event = PathData::compare_to_event(path, old_path_data.as_ref(), None);
+                let event = PathData::compare_to_event(path, old_path_data.as_ref(), None, false);
                 if let Some(event) = event {
                     data_builder.emitter.emit_ok(event);
                 }
             }
         }
 
-        /// Get all `PathData` by given configuration.
+        /// Whether a path that isn't tracked yet may be tracked, given the `remaining` number of
+        /// paths that may be added.
+        ///
+        /// Reaching the limit is reported once per watch.
+        fn admits_new_path(
+            &mut self,
+            data_builder: &DataBuilder,
+            remaining: &mut Option<usize>,
+        ) -> bool {
+            match remaining {
+                None => true,
+                Some(0) => {
+                    if !self.limit_reported {
+                        self.limit_reported = true;
+                        log::warn!(
+                            "maximum number of tracked paths reached scanning {:?}",
+                            self.root
+                        );
+                        data_builder.emitter.emit(Err(
+                            Error::new(ErrorKind::MaxFilesWatch).add_path(self.root.clone())
+                        ));
+                    }
+                    false
+                }
+                Some(remaining) => {
+                    *remaining -= 1;
+                    true
+                }
+            }
+        }
+
+        /// Get all entries by given configuration.
         ///
         /// # Side Effect
         ///
         /// This function may emit some IO Error events by `data_builder.emitter`.
-        fn scan_all_path_data(
+        fn scan_entries(
             data_builder: &'_ DataBuilder,
             root: PathBuf,
             is_recursive: bool,
             follow_symlinks: bool,
+            extensions: Option<Arc<[OsString]>>,
             // whether this is an initial scan, used only for events
             is_initial: bool,
-        ) -> impl Iterator<Item = (PathBuf, PathData)> + '_ {
+        ) -> impl Iterator<Item = walkdir::DirEntry> + '_ {
             log::trace!("rescanning {root:?}");
             // WalkDir return only one entry if root is a file (not a folder),
             // so we can use single logic to do the both file & dir's jobs.
@@ -276,13 +615,21 @@ mod data {
             // See: https://docs.rs/walkdir/2.0.1/walkdir/struct.WalkDir.html#method.new
             WalkDir::new(root)
                 .follow_links(follow_symlinks)
-                .max_depth(Self::dir_scan_depth(is_recursive))
+                .max_depth(Self::dir_scan_depth(is_recursive, data_builder.max_depth))
                 .into_iter()
                 .filter_map(|entry_res| match entry_res {
                     Ok(entry) => Some(entry),
                     Err(err) => {
                         log::warn!("walkdir error scanning {err:?}");
                         if let Some(io_error) = err.io_error() {
+                            // the watched root itself disappeared
+                            if !data_builder.report_self_events
+                                && err.depth() == 0
+                                && io_error.kind() == io::ErrorKind::NotFound
+                            {
+                                return None;
+                            }
+
                             // clone an io::Error, so we have to create a new one.
                             let new_io_error = io::Error::new(io_error.kind(), err.to_string());
                             data_builder.emitter.emit_io_err(new_io_error, err.path());
@@ -294,33 +641,77 @@ mod data {
                         None
                     }
                 })
-                .filter_map(move |entry| match entry.metadata() {
-                    Ok(metadata) => {
-                        let path = entry.into_path();
-                        if is_initial {
-                            // emit initial scans
-                            if let Some(ref emitter) = data_builder.scan_emitter {
-                                emitter.borrow_mut().handle_event(Ok(path.clone()));
-                            }
-                        }
-                        let meta_path = MetaPath::from_parts_unchecked(path, metadata);
-                        let data_path = data_builder.build_path_data(&meta_path);
-
-                        Some((meta_path.into_path(), data_path))
+                // before the metadata is read, the file type is known from the directory listing
+                .filter(move |entry| !data_builder.skips(entry, extensions.as_deref()))
+                .inspect(move |entry| {
+                    let is_too_deep = data_builder
+                        .max_depth
+                        .is_some_and(|max_depth| is_recursive && entry.depth() > max_depth);
+                    if is_initial && is_too_deep && entry.file_type().is_dir() {
+                        data_builder
+                            .emitter
+                            .emit_ok(max_depth_exceeded(entry.path().to_path_buf()));
                     }
-                    Err(e) => {
-                        // emit event.
-                        let path = entry.into_path();
-                        data_builder.emitter.emit_io_err(e, Some(path));
+                })
+        }
 
-                        None
+        /// Get the `PathData` of a scanned entry.
+        ///
+        /// # Side Effect
+        ///
+        /// This function may emit an IO Error event by `data_builder.emitter`.
+        fn build_path_data(
+            data_builder: &DataBuilder,
+            entry: walkdir::DirEntry,
+            // whether this is an initial scan, used only for events
+            is_initial: bool,
+        ) -> Option<(PathBuf, PathData)> {
+            match entry.metadata() {
+                Ok(metadata) => {
+                    let path = entry.into_path();
+                    if is_initial {
+                        // emit initial scans
+                        if let Some(ref emitter) = data_builder.scan_emitter {
+                            emitter.borrow_mut().handle_event(Ok(path.clone()));
+                        }
                     }
-                })
+                    let meta_path = MetaPath::from_parts_unchecked(path, metadata);
+                    let data_path = data_builder.build_path_data(&meta_path);
+
+                    Some((meta_path.into_path(), data_path))
+                }
+                Err(e) => {
+                    // emit event.
+                    let path = entry.into_path();
+                    data_builder.emitter.emit_io_err(e, Some(path));
+
+                    None
+                }
+            }
+        }
+
+        /// Whether the root is watched recursively.
+        pub(super) fn is_recursive(&self) -> bool {
+            self.is_recursive
         }
 
-        fn dir_scan_depth(is_recursive: bool) -> usize {
+        /// Number of currently tracked paths.
+        pub(super) fn tracked_paths(&self) -> usize {
+            self.all_path_data.len()
+        }
+
+        /// The state of all tracked paths.
+        pub(super) fn snapshot(&self) -> impl Iterator<Item = (PathBuf, PathSnapshot)> + '_ {
+            self.all_path_data
+                .iter()
+                .map(|(path, path_data)| (path.clone(), path_data.snapshot()))
+        }
+
+        /// The depth of the scan, the directories right beyond the `max_depth` are scanned to
+        /// report them.
+        fn dir_scan_depth(is_recursive: bool, max_depth: Option<usize>) -> usize {
             if is_recursive {
-                usize::MAX
+                max_depth.map_or(usize::MAX, |max_depth| max_depth.saturating_add(1))
             } else {
                 1
             }
@@ -335,10 +726,19 @@ mod data {
         /// File updated time.
         mtime: i64,
 
+        /// File size.
+        size: u64,
+
         /// Content's hash value, only available if user request compare file
         /// contents and read successful.
         hash: Option<u64>,
 
+        /// Whether the path is a directory.
+        is_dir: bool,
+
+        /// The ID of the file, if available without opening it.
+        file_id: Option<FileId>,
+
         /// Checked time.
         last_check: Instant,
     }
@@ -350,22 +750,60 @@ mod data {
 
             PathData {
                 mtime: FileTime::from_last_modification_time(metadata).seconds(),
+                size: metadata.len(),
+                // FIFOs, sockets and devices are compared by their metadata only, as reading
+                // them may block or never end
                 hash: data_builder
                     .build_hasher
                     .as_ref()
-                    .filter(|_| metadata.is_file())
+                    .filter(|_| metadata.file_type().is_file())
                     .and_then(|build_hasher| {
                         Self::get_content_hash(build_hasher, meta_path.path()).ok()
                     }),
 
+                is_dir: metadata.is_dir(),
+
+                file_id: file_id(metadata),
+
                 last_check: data_builder.now,
             }
         }
 
+        fn from_snapshot(snapshot: PathSnapshot, last_check: Instant) -> PathData {
+            PathData {
+                mtime: snapshot.mtime,
+                size: snapshot.size,
+                hash: snapshot.hash,
+                is_dir: snapshot.is_dir,
+                file_id: snapshot.file_id,
+                last_check,
+            }
+        }
+
+        fn snapshot(&self) -> PathSnapshot {
+            PathSnapshot {
+                size: self.size,
+                mtime: self.mtime,
+                hash: self.hash,
+                is_dir: self.is_dir,
+                file_id: self.file_id,
+            }
+        }
+
         /// Get hash value for the data content in given file `path`.
-        fn get_content_hash(build_hasher: &RandomState, path: &Path) -> io::Result<u64> {
+        ///
+        /// Fails if `path` isn't a regular file (anymore), without blocking on special files.
+        fn get_content_hash(build_hasher: &ContentHasher, path: &Path) -> io::Result<u64> {
             let mut hasher = build_hasher.build_hasher();
-            let mut file = File::open(path)?;
+            let mut options = OpenOptions::new();
+            options.read(true);
+            // opening a FIFO blocks until it is opened for writing as well
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::custom_flags(&mut options, libc::O_NONBLOCK);
+            let mut file = options.open(path)?;
+            if !file.metadata()?.file_type().is_file() {
+                return Err(io::Error::other("not a regular file"));
+            }
             let mut buf = [0; 512];
 
             loop {
@@ -387,6 +825,7 @@ mod data {
             path: P,
             old: Option<&PathData>,
             new: Option<&PathData>,
+            compare_size: bool,
         ) -> Option<Event>
         where
             P: Into<PathBuf>,
@@ -397,6 +836,8 @@ mod data {
                         Some(EventKind::Modify(ModifyKind::Metadata(
                             MetadataKind::WriteTime,
                         )))
+                    } else if compare_size && new.size != old.size && !new.is_dir {
+                        Some(EventKind::Modify(ModifyKind::Data(DataChange::Size)))
                     } else if new.hash != old.hash {
                         Some(EventKind::Modify(ModifyKind::Data(DataChange::Any)))
                     } else {
@@ -411,6 +852,45 @@ mod data {
         }
     }
 
+    /// The event describing the change of `path` between two snapshots, see
+    /// [`diff_snapshots`](super::diff_snapshots).
+    pub(super) fn snapshot_event(
+        path: &Path,
+        old: Option<&PathSnapshot>,
+        new: Option<&PathSnapshot>,
+    ) -> Option<Event> {
+        let now = Instant::now();
+        let old = old.map(|snapshot| PathData::from_snapshot(*snapshot, now));
+        let new = new.map(|snapshot| PathData::from_snapshot(*snapshot, now));
+        PathData::compare_to_event(path, old.as_ref(), new.as_ref(), true)
+    }
+
+    type ContentHasher = BuildHasherDefault<Fnv1a>;
+
+    /// 64-bit FNV-1a, whose hashes don't change between Rust releases, unlike the ones of
+    /// the standard library's `DefaultHasher`.
+    #[derive(Debug)]
+    pub(super) struct Fnv1a(u64);
+
+    impl Default for Fnv1a {
+        fn default() -> Self {
+            Self(0xcbf2_9ce4_8422_2325)
+        }
+    }
+
+    impl Hasher for Fnv1a {
+        fn write(&mut self, bytes: &[u8]) {
+            for byte in bytes {
+                self.0 ^= u64::from(*byte);
+                self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+            }
+        }
+
+        fn finish(&self) -> u64 {
+            self.0
+        }
+    }
+
     /// Compose path and its metadata.
     ///
     /// This data structure designed for make sure path and its metadata can be
@@ -499,6 +979,7 @@ pub struct PollWatcher {
     message_channel: Sender<()>,
     delay: Option<Duration>,
     follow_sylinks: bool,
+    gate: PauseGate,
 }
 
 impl PollWatcher {
@@ -515,6 +996,27 @@ impl PollWatcher {
         Ok(())
     }
 
+    /// Only track regular files with one of the given `extensions`, e.g. `["rs", "toml"]`.
+    ///
+    /// Other files are skipped during the scan before their metadata is read, so no events are
+    /// emitted for them. Directories are still traversed and tracked, as is a watched file
+    /// itself. Applies to paths watched afterwards, paths watched already keep the extensions
+    /// they were watched with.
+    ///
+    /// ```
+    /// # use notify::{Config, PollWatcher, Result};
+    /// # fn main() -> Result<()> {
+    /// let mut watcher = PollWatcher::new(|_: Result<notify::Event>| {}, Config::default())?;
+    /// watcher.set_extensions(&["rs", "toml", "lock"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_extensions(&mut self, extensions: &[&str]) {
+        if let Ok(mut data_builder) = self.data_builder.lock() {
+            data_builder.extensions = Some(extensions.iter().map(OsString::from).collect());
+        }
+    }
+
     /// Create a new [`PollWatcher`] with an scan event handler.
     ///
     /// `scan_fallback` is called on the initial scan with all files seen by the pollwatcher.
@@ -532,8 +1034,18 @@ impl PollWatcher {
         config: Config,
         scan_callback: Option<G>,
     ) -> crate::Result<PollWatcher> {
-        let data_builder =
-            DataBuilder::new(event_handler, config.compare_contents(), scan_callback);
+        let counters = Arc::<Counters>::default();
+        let dispatch = dispatch::from_config(counters.handler(event_handler), &config)?;
+        let gate = PauseGate::new(
+            Box::new(AttributeHandler::new(
+                settle::from_config(dispatch, &config)?,
+                &config,
+                Self::kind(),
+            )),
+            &config,
+            counters,
+        );
+        let data_builder = DataBuilder::new(gate.clone(), &config, scan_callback);
 
         let (tx, rx) = unbounded();
 
@@ -544,6 +1056,7 @@ impl PollWatcher {
             delay: config.poll_interval(),
             follow_sylinks: config.follow_symlinks(),
             message_channel: tx,
+            gate,
         };
 
         poll_watcher.run(rx);
@@ -556,11 +1069,12 @@ impl PollWatcher {
         let data_builder = Arc::clone(&self.data_builder);
         let want_to_stop = Arc::clone(&self.want_to_stop);
         let delay = self.delay;
+        let mut gate = self.gate.clone();
 
         let _ = thread::Builder::new()
             .name("notify-rs poll loop".to_string())
             .spawn(move || {
-                loop {
+                let scan = || loop {
                     if want_to_stop.load(Ordering::SeqCst) {
                         break;
                     }
@@ -574,9 +1088,14 @@ impl PollWatcher {
                     {
                         data_builder.update_timestamp();
 
+                        let mut tracked: usize =
+                            watches.values().map(WatchData::tracked_paths).sum();
                         let vals = watches.values_mut();
                         for watch_data in vals {
-                            watch_data.rescan(&mut data_builder);
+                            tracked -= watch_data.tracked_paths();
+                            let budget = data_builder.tracking_budget(tracked);
+                            watch_data.rescan(&mut data_builder, budget);
+                            tracked += watch_data.tracked_paths();
                         }
                     }
                     // TODO: v7.0 use delay - (Instant::now().saturating_duration_since(start))
@@ -585,6 +1104,9 @@ impl PollWatcher {
                     } else {
                         let _ = rx.recv();
                     }
+                };
+                if let Err(error) = Error::catch_panic(scan) {
+                    gate.handle_event(Err(error));
                 }
             });
     }
@@ -602,10 +1124,16 @@ impl PollWatcher {
         {
             data_builder.update_timestamp();
 
+            let tracked = watches
+                .iter()
+                .filter(|(watched, _)| *watched != path)
+                .map(|(_, watch_data)| watch_data.tracked_paths())
+                .sum();
             let watch_data = data_builder.build_watch_data(
                 path.to_path_buf(),
                 recursive_mode.is_recursive(),
                 self.follow_sylinks,
+                data_builder.tracking_budget(tracked),
             );
 
             // if create watch_data successful, add it to watching list.
@@ -615,6 +1143,53 @@ impl PollWatcher {
         }
     }
 
+    /// Watch a path, starting from a snapshot taken earlier by [`PollWatcher::snapshot`].
+    ///
+    /// Instead of scanning `path` right away, the first scan compares the filesystem to
+    /// `snapshot` and reports the paths that were created, modified or removed since. This allows
+    /// to detect the changes made while the application wasn't running, by persisting the
+    /// snapshot before exiting. Snapshot entries outside of `path` are ignored.
+    pub fn watch_with_snapshot(
+        &mut self,
+        path: &Path,
+        recursive_mode: RecursiveMode,
+        snapshot: HashMap<PathBuf, PathSnapshot>,
+    ) -> crate::Result<()> {
+        {
+            let (mut watches, mut data_builder) = (
+                self.watches.lock().unwrap(),
+                self.data_builder.lock().unwrap(),
+            );
+            data_builder.update_timestamp();
+            let tracked = watches
+                .iter()
+                .filter(|(watched, _)| *watched != path)
+                .map(|(_, watch_data)| watch_data.tracked_paths())
+                .sum();
+            let watch_data = data_builder.build_watch_data_from_snapshot(
+                path.to_path_buf(),
+                recursive_mode.is_recursive(),
+                self.follow_sylinks,
+                data_builder.tracking_budget(tracked),
+                snapshot,
+            );
+            watches.insert(path.to_path_buf(), watch_data);
+        }
+        self.gate.root_watched(path);
+        self.poll()
+    }
+
+    /// Returns the state of all paths as seen by the last scan, to be used with
+    /// [`PollWatcher::watch_with_snapshot`].
+    pub fn snapshot(&self) -> HashMap<PathBuf, PathSnapshot> {
+        self.watches
+            .lock()
+            .unwrap()
+            .values()
+            .flat_map(WatchData::snapshot)
+            .collect()
+    }
+
     /// Unwatch a path.
     ///
     /// Return `Err(_)` if given path has't be monitored.
@@ -627,6 +1202,20 @@ impl PollWatcher {
             .map(|_| ())
             .ok_or_else(crate::Error::watch_not_found)
     }
+
+    fn unwatch_prefix_inner(&mut self, prefix: &Path) -> Vec<PathBuf> {
+        let mut watches = self.watches.lock().unwrap();
+        let mut removed: Vec<PathBuf> = watches
+            .keys()
+            .filter(|path| path.starts_with(prefix))
+            .cloned()
+            .collect();
+        removed.sort();
+        for path in &removed {
+            watches.remove(path);
+        }
+        removed
+    }
 }
 
 impl Watcher for PollWatcher {
@@ -637,14 +1226,56 @@ impl Watcher for PollWatcher {
 
     fn watch(&mut self, path: &Path, recursive_mode: RecursiveMode) -> crate::Result<()> {
         self.watch_inner(path, recursive_mode);
+        self.gate.root_watched(path);
 
         Ok(())
     }
 
     fn unwatch(&mut self, path: &Path) -> crate::Result<()> {
+        self.gate.root_unwatched(path, false);
         self.unwatch_inner(path)
     }
 
+    fn watched_paths(&self) -> crate::Result<Vec<(PathBuf, RecursiveMode)>> {
+        let watches = self.watches.lock().unwrap();
+        Ok(watches
+            .iter()
+            .map(|(path, data)| (path.clone(), RecursiveMode::from_bool(data.is_recursive())))
+            .collect())
+    }
+
+    fn unwatch_prefix(&mut self, prefix: &Path) -> crate::Result<Vec<PathBuf>> {
+        self.gate.root_unwatched(prefix, true);
+        Ok(self.unwatch_prefix_inner(prefix))
+    }
+
+    /// Returns the poll interval, or zero with manual polling, as changes are then reported when
+    /// [`PollWatcher::poll`] is called.
+    fn expected_latency(&self) -> Duration {
+        self.delay.unwrap_or(Duration::ZERO)
+    }
+
+    fn pause(&mut self) -> crate::Result<()> {
+        self.gate.pause();
+        Ok(())
+    }
+
+    fn resume(&mut self) -> crate::Result<()> {
+        self.gate.resume();
+        Ok(())
+    }
+
+    fn resync(&mut self) -> crate::Result<()> {
+        let roots = self.watched_paths()?;
+        resync::resync(&roots, self.follow_sylinks, &mut self.gate);
+        Ok(())
+    }
+
+    fn stats(&self) -> crate::WatcherStats {
+        let watches = self.watched_paths().map_or(0, |paths| paths.len());
+        self.gate.stats(watches)
+    }
+
     fn kind() -> crate::WatcherKind {
         crate::WatcherKind::PollWatcher
     }
@@ -661,3 +1292,478 @@ fn poll_watcher_is_send_and_sync() {
     fn check<T: Send + Sync>() {}
     check::<PollWatcher>();
 }
+
+#[test]
+fn changes_since_snapshot_are_reported() {
+    use crate::event::EventKind;
+    use std::fs;
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().to_path_buf();
+    fs::write(root.join("modified"), b"a").unwrap();
+    fs::write(root.join("removed"), b"").unwrap();
+    fs::write(root.join("unchanged"), b"").unwrap();
+
+    // the files are changed within the resolution of the modification time
+    let config = Config::default()
+        .with_manual_polling()
+        .with_compare(crate::CompareMode::SizeAndMtime);
+    let snapshot = {
+        let mut watcher = PollWatcher::new(|_| {}, config).unwrap();
+        watcher.watch(&root, RecursiveMode::Recursive).unwrap();
+        watcher.snapshot()
+    };
+    assert_eq!(snapshot.len(), 4);
+
+    fs::write(root.join("modified"), b"ab").unwrap();
+    fs::remove_file(root.join("removed")).unwrap();
+    fs::write(root.join("created"), b"").unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = PollWatcher::new(tx, config).unwrap();
+    watcher
+        .watch_with_snapshot(&root, RecursiveMode::Recursive, snapshot)
+        .unwrap();
+
+    let mut events: Vec<_> = std::iter::from_fn(|| rx.recv_timeout(Duration::from_secs(1)).ok())
+        .map(|event| event.unwrap())
+        .filter(|event| event.paths != [root.clone()])
+        .map(|event| (event.paths[0].file_name().unwrap().to_owned(), event.kind))
+        .collect();
+    events.sort_by(|a, b| a.0.cmp(&b.0));
+
+    assert_eq!(events.len(), 3, "{events:?}");
+    assert_eq!(events[0].0, "created");
+    assert!(matches!(events[0].1, EventKind::Create(_)));
+    assert_eq!(events[1].0, "modified");
+    assert!(events[1].1.is_modify());
+    assert_eq!(events[2].0, "removed");
+    assert!(events[2].1.is_remove());
+}
+
+#[test]
+#[cfg(unix)]
+fn snapshots_are_filtered_like_scans() {
+    use std::fs;
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().to_path_buf();
+    for name in ["lib.rs", "notes.txt", ".hidden.rs"] {
+        fs::write(root.join(name), b"").unwrap();
+    }
+    let snapshot = {
+        let config = Config::default().with_manual_polling();
+        let mut watcher = PollWatcher::new(|_| {}, config).unwrap();
+        watcher.watch(&root, RecursiveMode::Recursive).unwrap();
+        watcher.snapshot()
+    };
+    assert_eq!(snapshot.len(), 4);
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let config = Config::default()
+        .with_manual_polling()
+        .with_hidden_files(crate::HiddenFiles::Exclude);
+    let mut watcher = PollWatcher::new(tx, config).unwrap();
+    watcher.set_extensions(&["rs"]);
+    watcher
+        .watch_with_snapshot(&root, RecursiveMode::Recursive, snapshot.clone())
+        .unwrap();
+    let mut tracked: Vec<_> = watcher.snapshot().into_keys().collect();
+    tracked.sort();
+    assert_eq!(tracked, [root.clone(), root.join("lib.rs")]);
+    // the skipped entries are not reported as removed
+    let events: Vec<_> = std::iter::from_fn(|| rx.recv_timeout(Duration::from_millis(500)).ok())
+        .map(|event| event.unwrap())
+        .filter(|event| event.paths != [root.clone()])
+        .collect();
+    assert_eq!(events, []);
+
+    let config = Config::default()
+        .with_manual_polling()
+        .with_max_tracked_paths(2);
+    let mut watcher = PollWatcher::new(|_| {}, config).unwrap();
+    watcher
+        .watch_with_snapshot(&root, RecursiveMode::Recursive, snapshot)
+        .unwrap();
+    assert_eq!(watcher.snapshot().len(), 2);
+}
+
+#[test]
+fn expected_latency_is_the_poll_interval() {
+    let config = Config::default().with_poll_interval(Duration::from_secs(2));
+    let watcher = PollWatcher::new(|_: crate::Result<crate::Event>| {}, config).unwrap();
+    assert_eq!(watcher.expected_latency(), Duration::from_secs(2));
+
+    let config = Config::default().with_manual_polling();
+    let watcher = PollWatcher::new(|_: crate::Result<crate::Event>| {}, config).unwrap();
+    assert_eq!(watcher.expected_latency(), Duration::ZERO);
+}
+
+#[test]
+fn compare_mode_selects_the_compared_properties() {
+    use filetime::{set_file_mtime, FileTime};
+    use std::fs;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    let mtime = FileTime::from_unix_time(1_000_000_000, 0);
+    fs::write(&path, b"a").unwrap();
+    set_file_mtime(&path, mtime).unwrap();
+
+    let mut changes = Vec::new();
+    for compare in [
+        crate::CompareMode::MetadataOnly,
+        crate::CompareMode::SizeAndMtime,
+        crate::CompareMode::Contents,
+    ] {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let config = Config::default()
+            .with_manual_polling()
+            .with_compare(compare);
+        let mut watcher = PollWatcher::new(tx, config).unwrap();
+        watcher.watch(&path, RecursiveMode::NonRecursive).unwrap();
+        // let the initial scan of the poll loop pass
+        watcher.poll().unwrap();
+        while rx.recv_timeout(Duration::from_millis(200)).is_ok() {}
+
+        let mut contents = fs::read(&path).unwrap();
+        contents.push(b'b');
+        fs::write(&path, contents).unwrap();
+        set_file_mtime(&path, mtime).unwrap();
+        watcher.poll().unwrap();
+
+        let events = std::iter::from_fn(|| rx.recv_timeout(Duration::from_millis(500)).ok());
+        changes.push(events.map(|event| event.unwrap().kind).collect::<Vec<_>>());
+    }
+
+    assert_eq!(changes[0], []);
+    assert_eq!(
+        changes[1],
+        [crate::EventKind::Modify(crate::event::ModifyKind::Data(
+            crate::event::DataChange::Size
+        ))]
+    );
+    assert_eq!(
+        changes[2],
+        [crate::EventKind::Modify(crate::event::ModifyKind::Data(
+            crate::event::DataChange::Any
+        ))]
+    );
+}
+
+#[test]
+fn diff_snapshots_reports_the_differences() {
+    use crate::event::{CreateKind, DataChange, RemoveKind};
+
+    let snapshot = |size, inode| PathSnapshot {
+        size,
+        mtime: 0,
+        hash: None,
+        is_dir: false,
+        file_id: Some(FileId::new_inode(1, inode)),
+    };
+    let dir = PathSnapshot {
+        is_dir: true,
+        ..snapshot(0, 1)
+    };
+    let before = HashMap::from([
+        (PathBuf::from("/dir"), dir),
+        (PathBuf::from("/dir/removed"), snapshot(0, 2)),
+        (PathBuf::from("/dir/renamed"), snapshot(0, 3)),
+        (PathBuf::from("/dir/modified"), snapshot(0, 4)),
+        (
+            PathBuf::from("/gone"),
+            PathSnapshot {
+                file_id: Some(FileId::new_inode(1, 5)),
+                ..dir
+            },
+        ),
+        (PathBuf::from("/gone/file"), snapshot(0, 6)),
+    ]);
+    let after = HashMap::from([
+        (PathBuf::from("/dir"), dir),
+        (PathBuf::from("/dir/new_name"), snapshot(0, 3)),
+        (PathBuf::from("/dir/modified"), snapshot(1, 4)),
+        (PathBuf::from("/dir/created"), snapshot(0, 7)),
+    ]);
+
+    let events: Vec<_> = diff_snapshots(&before, &after)
+        .into_iter()
+        .map(|event| (event.kind, event.paths))
+        .collect();
+    let paths = |paths: &[&str]| paths.iter().map(PathBuf::from).collect::<Vec<_>>();
+    assert_eq!(
+        events,
+        [
+            (EventKind::Remove(RemoveKind::Any), paths(&["/gone/file"])),
+            (EventKind::Remove(RemoveKind::Any), paths(&["/gone"])),
+            (EventKind::Remove(RemoveKind::Any), paths(&["/dir/removed"])),
+            (
+                EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+                paths(&["/dir/renamed", "/dir/new_name"])
+            ),
+            (EventKind::Create(CreateKind::Any), paths(&["/dir/created"])),
+            (
+                EventKind::Modify(ModifyKind::Data(DataChange::Size)),
+                paths(&["/dir/modified"])
+            ),
+        ]
+    );
+}
+
+#[test]
+fn extensions_select_the_tracked_files() {
+    use std::fs;
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().to_path_buf();
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(root.join("src/lib.rs"), b"").unwrap();
+    fs::write(root.join("Cargo.toml"), b"").unwrap();
+    fs::write(root.join("README.md"), b"").unwrap();
+    fs::write(root.join("src/notes.txt"), b"").unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let config = Config::default()
+        .with_manual_polling()
+        .with_compare(crate::CompareMode::SizeAndMtime);
+    let mut watcher = PollWatcher::new(tx, config).unwrap();
+    watcher.set_extensions(&["rs", "toml"]);
+    watcher.watch(&root, RecursiveMode::Recursive).unwrap();
+
+    let mut tracked: Vec<_> = watcher.snapshot().into_keys().collect();
+    tracked.sort();
+    assert_eq!(
+        tracked,
+        [
+            root.clone(),
+            root.join("Cargo.toml"),
+            root.join("src"),
+            root.join("src/lib.rs")
+        ]
+    );
+
+    watcher.poll().unwrap();
+    while rx.recv_timeout(Duration::from_millis(200)).is_ok() {}
+    fs::write(root.join("src/notes.txt"), b"changed").unwrap();
+    fs::write(root.join("README.md"), b"changed").unwrap();
+    fs::write(root.join("src/lib.rs"), b"changed").unwrap();
+    watcher.poll().unwrap();
+
+    let paths: Vec<_> = std::iter::from_fn(|| rx.recv_timeout(Duration::from_millis(500)).ok())
+        .map(|event| event.unwrap())
+        .filter(|event| event.kind.is_modify() && !event.paths[0].is_dir())
+        .map(|event| event.paths[0].clone())
+        .collect();
+    assert_eq!(paths, [root.join("src/lib.rs")]);
+}
+
+#[test]
+fn extensions_leave_existing_watches_alone() {
+    use std::fs;
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().to_path_buf();
+    fs::write(root.join("lib.rs"), b"").unwrap();
+    fs::write(root.join("README.md"), b"").unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let config = Config::default().with_manual_polling();
+    let mut watcher = PollWatcher::new(tx, config).unwrap();
+    watcher.watch(&root, RecursiveMode::Recursive).unwrap();
+    watcher.set_extensions(&["rs"]);
+
+    watcher.poll().unwrap();
+    watcher.poll().unwrap();
+    let removed: Vec<_> = std::iter::from_fn(|| rx.recv_timeout(Duration::from_millis(500)).ok())
+        .map(|event| event.unwrap())
+        .filter(|event| event.kind.is_remove())
+        .collect();
+    assert_eq!(removed, []);
+    assert!(watcher.snapshot().contains_key(&root.join("README.md")));
+}
+
+#[test]
+#[cfg(unix)]
+fn excluded_hidden_files_are_not_tracked() {
+    use crate::HiddenFiles;
+    use std::fs;
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().to_path_buf();
+    fs::create_dir(root.join(".git")).unwrap();
+    fs::write(root.join(".git/HEAD"), b"").unwrap();
+    fs::write(root.join(".env"), b"").unwrap();
+    fs::write(root.join("main.rs"), b"").unwrap();
+
+    let config = Config::default()
+        .with_manual_polling()
+        .with_hidden_files(HiddenFiles::Exclude);
+    let mut watcher = PollWatcher::new(|_| {}, config).unwrap();
+    watcher.watch(&root, RecursiveMode::Recursive).unwrap();
+
+    let mut tracked: Vec<_> = watcher.snapshot().into_keys().collect();
+    tracked.sort();
+    // only the file name is considered, so files in hidden directories are still tracked
+    assert_eq!(
+        tracked,
+        [
+            root.clone(),
+            root.join(".git"),
+            root.join(".git/HEAD"),
+            root.join("main.rs")
+        ]
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn fifos_are_compared_by_metadata() {
+    use crate::event::CreateKind;
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::sync::mpsc;
+
+    let mkfifo = |path: &Path| {
+        let c_path = CString::new(path.as_os_str().as_bytes()).unwrap();
+        assert_eq!(unsafe { libc::mkfifo(c_path.as_ptr(), 0o644) }, 0);
+    };
+    let dir = tempfile::tempdir().unwrap();
+    let (existing, created) = (dir.path().join("existing"), dir.path().join("created"));
+    mkfifo(&existing);
+
+    let (tx, rx) = mpsc::channel();
+    let config = Config::default()
+        .with_manual_polling()
+        .with_compare(crate::CompareMode::Contents);
+    let mut watcher = PollWatcher::new(tx, config).unwrap();
+    watcher.watch(dir.path(), RecursiveMode::Recursive).unwrap();
+    assert_eq!(watcher.snapshot()[&existing].hash, None);
+
+    mkfifo(&created);
+    watcher.poll().unwrap();
+    let event = rx.recv_timeout(Duration::from_secs(1)).unwrap().unwrap();
+    assert_eq!(event.kind, EventKind::Create(CreateKind::Any));
+    assert_eq!(event.paths, [created]);
+}
+
+#[test]
+fn precise_create_kinds_tell_files_from_folders() {
+    use crate::event::CreateKind;
+
+    let dir = tempfile::tempdir().unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    let config = Config::default()
+        .with_manual_polling()
+        .with_precise_create_kinds(true);
+    let mut watcher = PollWatcher::new(tx, config).unwrap();
+    watcher.watch(dir.path(), RecursiveMode::Recursive).unwrap();
+
+    fs::create_dir(dir.path().join("folder")).unwrap();
+    fs::write(dir.path().join("file"), b"").unwrap();
+    watcher.poll().unwrap();
+
+    let mut events: Vec<_> = (0..2)
+        .map(|_| rx.recv_timeout(Duration::from_secs(1)).unwrap().unwrap())
+        .map(|event| (event.kind, event.paths))
+        .collect();
+    events.sort_by(|a, b| a.1.cmp(&b.1));
+    assert_eq!(
+        events,
+        [
+            (
+                EventKind::Create(CreateKind::File),
+                vec![dir.path().join("file")]
+            ),
+            (
+                EventKind::Create(CreateKind::Folder),
+                vec![dir.path().join("folder")]
+            ),
+        ]
+    );
+}
+
+#[test]
+fn max_watch_depth_bounds_the_scan() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("a/b/c")).unwrap();
+    fs::write(dir.path().join("a/b/c/file"), b"").unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let config = Config::default()
+        .with_manual_polling()
+        .with_max_watch_depth(1);
+    let mut watcher = PollWatcher::new(tx, config).unwrap();
+    watcher.watch(dir.path(), RecursiveMode::Recursive).unwrap();
+
+    let event = rx.recv_timeout(Duration::from_secs(1)).unwrap().unwrap();
+    assert!(event.is_max_depth_exceeded());
+    assert_eq!(event.paths, [dir.path().join("a/b")]);
+
+    let snapshot = watcher.snapshot();
+    assert!(snapshot.contains_key(&dir.path().join("a/b")));
+    assert!(!snapshot.contains_key(&dir.path().join("a/b/c")));
+}
+
+#[test]
+fn capped_scans_keep_the_tracked_paths() {
+    use std::fs;
+
+    let dir = tempfile::tempdir().unwrap();
+    for i in 0..5 {
+        fs::write(dir.path().join(format!("file{i}")), b"").unwrap();
+    }
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let config = Config::default()
+        .with_manual_polling()
+        .with_max_tracked_paths(3);
+    let mut watcher = PollWatcher::new(tx, config).unwrap();
+    watcher.watch(dir.path(), RecursiveMode::Recursive).unwrap();
+    assert_eq!(watcher.snapshot().len(), 3);
+
+    for _ in 0..2 {
+        watcher.poll().unwrap();
+    }
+    let events: Vec<_> =
+        std::iter::from_fn(|| rx.recv_timeout(Duration::from_millis(500)).ok()).collect();
+    let limits = events
+        .iter()
+        .filter(|res| matches!(res, Err(e) if matches!(e.kind, crate::ErrorKind::MaxFilesWatch)))
+        .count();
+    assert_eq!(limits, 1, "{events:?}");
+    assert!(
+        !events
+            .iter()
+            .any(|res| res.as_ref().is_ok_and(|event| event.kind.is_remove())),
+        "{events:?}"
+    );
+    assert_eq!(watcher.snapshot().len(), 3);
+}
+
+#[test]
+fn content_hashes_are_stable() {
+    use std::hash::Hasher;
+
+    let mut hasher = data::Fnv1a::default();
+    hasher.write(b"hello");
+    assert_eq!(hasher.finish(), 0xa430_d846_80aa_bd0b);
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn snapshots_can_be_serialized() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("file"), b"data").unwrap();
+
+    let config = Config::default()
+        .with_manual_polling()
+        .with_compare_contents(true);
+    let mut watcher = PollWatcher::new(|_| {}, config).unwrap();
+    watcher.watch(dir.path(), RecursiveMode::Recursive).unwrap();
+    let snapshot = watcher.snapshot();
+
+    let json = serde_json::to_string(&snapshot).unwrap();
+    let restored: HashMap<PathBuf, PathSnapshot> = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored, snapshot);
+}
@@ -0,0 +1,362 @@
+//! Watcher wrapper re-establishing lost watches
+//!
+//! A watch may be dropped by the operating system while the watched path is temporarily gone,
+//! e.g. when a directory is moved away and restored, or on network filesystems. The
+//! [`RearmWatcher`] notices when a watched root is removed or renamed, waits for it to reappear
+//! and watches it again. A [`Flag::Rescan`] event is emitted for the root afterwards, as events
+//! may have been missed in the meantime. If a watched root was replaced by a directory or the
+//! other way around, a `Create` event with the info `type-changed` is emitted first, see
+//! [`Event::is_type_changed`]. Only the watched roots are checked, the backends don't report type
+//! changes of the paths below them.
+
+use crate::event::{CreateKind, Event, EventKind, Flag, ModifyKind, RenameMode};
+use crate::{unbounded, Config, EventHandler, Receiver, RecursiveMode, Result, Watcher};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Delay before the first attempt to re-establish a watch.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+/// Upper bound for the delay between two attempts.
+const MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+type Roots = Arc<Mutex<HashMap<PathBuf, Root>>>;
+
+/// A path watched by the user.
+#[derive(Debug, Clone, Copy)]
+struct Root {
+    recursive_mode: RecursiveMode,
+    /// Whether the path was a directory when it was last watched
+    is_dir: bool,
+}
+
+/// [`Watcher`] wrapper that re-establishes watches which were lost because the watched path
+/// disappeared for a while.
+///
+/// Losing a watch is detected through the remove and rename events the inner watcher emits for
+/// the watched path itself, so [`Config::with_report_self_events`] must not be disabled.
+pub struct RearmWatcher<W> {
+    inner: Arc<Mutex<W>>,
+    roots: Roots,
+}
+
+impl<W: Watcher + Send + 'static> RearmWatcher<W> {
+    /// Create a new [`RearmWatcher`] with a custom backoff.
+    ///
+    /// The first attempt to re-establish a watch is made after `initial_backoff`, the delay is
+    /// doubled after every unsuccessful attempt, up to `max_backoff`.
+    pub fn with_backoff<F: EventHandler>(
+        event_handler: F,
+        config: Config,
+        initial_backoff: Duration,
+        max_backoff: Duration,
+    ) -> Result<Self> {
+        let event_handler: Arc<Mutex<dyn EventHandler>> = Arc::new(Mutex::new(event_handler));
+        let roots = Roots::default();
+        let (lost_tx, lost_rx) = unbounded();
+
+        let inner = {
+            let event_handler = event_handler.clone();
+            let roots = roots.clone();
+            W::new(
+                move |res: Result<Event>| {
+                    if let Ok(event) = &res {
+                        if is_lost(&event.kind) {
+                            let roots = roots.lock().unwrap();
+                            for path in event.paths.iter().filter(|p| roots.contains_key(*p)) {
+                                let _ = lost_tx.send(path.clone());
+                            }
+                        }
+                    }
+                    event_handler.lock().unwrap().handle_event(res);
+                },
+                config,
+            )?
+        };
+        let inner = Arc::new(Mutex::new(inner));
+
+        let supervisor = Supervisor {
+            inner: Arc::downgrade(&inner),
+            roots: roots.clone(),
+            event_handler,
+            lost_rx,
+            pending: HashMap::new(),
+            initial_backoff,
+            max_backoff,
+        };
+        thread::Builder::new()
+            .name("notify-rs rearm loop".to_string())
+            .spawn(move || supervisor.run())?;
+
+        Ok(Self { inner, roots })
+    }
+}
+
+impl<W> fmt::Debug for RearmWatcher<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RearmWatcher")
+            .field("roots", &self.roots)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<W: Watcher + Send + 'static> Watcher for RearmWatcher<W> {
+    fn new<F: EventHandler>(event_handler: F, config: Config) -> Result<Self> {
+        Self::with_backoff(event_handler, config, INITIAL_BACKOFF, MAX_BACKOFF)
+    }
+
+    fn watch(&mut self, path: &Path, recursive_mode: RecursiveMode) -> Result<()> {
+        self.inner.lock().unwrap().watch(path, recursive_mode)?;
+        let root = Root {
+            recursive_mode,
+            is_dir: path.is_dir(),
+        };
+        self.roots.lock().unwrap().insert(path.to_path_buf(), root);
+        Ok(())
+    }
+
+    fn unwatch(&mut self, path: &Path) -> Result<()> {
+        self.roots.lock().unwrap().remove(path);
+        self.inner.lock().unwrap().unwatch(path)
+    }
+
+    fn watched_paths(&self) -> Result<Vec<(PathBuf, RecursiveMode)>> {
+        let roots = self.roots.lock().unwrap();
+        Ok(roots
+            .iter()
+            .map(|(path, root)| (path.clone(), root.recursive_mode))
+            .collect())
+    }
+
+    fn unwatch_prefix(&mut self, prefix: &Path) -> Result<Vec<PathBuf>> {
+        self.roots
+            .lock()
+            .unwrap()
+            .retain(|root, _| !root.starts_with(prefix));
+        self.inner.lock().unwrap().unwatch_prefix(prefix)
+    }
+
+    fn expected_latency(&self) -> Duration {
+        self.inner.lock().unwrap().expected_latency()
+    }
+
+    fn configure(&mut self, option: Config) -> Result<bool> {
+        self.inner.lock().unwrap().configure(option)
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        self.inner.lock().unwrap().pause()
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        self.inner.lock().unwrap().resume()
+    }
+
+    fn resync(&mut self) -> Result<()> {
+        self.inner.lock().unwrap().resync()
+    }
+
+    fn stats(&self) -> crate::WatcherStats {
+        self.inner.lock().unwrap().stats()
+    }
+
+    fn path_supports_events(&self, path: &Path) -> bool {
+        self.inner.lock().unwrap().path_supports_events(path)
+    }
+
+    fn kind() -> crate::WatcherKind {
+        W::kind()
+    }
+}
+
+/// Whether the event indicates that the path it is about is gone.
+fn is_lost(kind: &EventKind) -> bool {
+    matches!(
+        kind,
+        EventKind::Remove(_)
+            | EventKind::Modify(ModifyKind::Name(RenameMode::From | RenameMode::Any))
+    )
+}
+
+struct Supervisor<W> {
+    inner: Weak<Mutex<W>>,
+    roots: Roots,
+    event_handler: Arc<Mutex<dyn EventHandler>>,
+    lost_rx: Receiver<PathBuf>,
+    /// PathBuf -> (next attempt, current backoff)
+    pending: HashMap<PathBuf, (Instant, Duration)>,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl<W: Watcher> Supervisor<W> {
+    fn run(mut self) {
+        loop {
+            let timeout = self
+                .pending
+                .values()
+                .map(|&(next, _)| next.saturating_duration_since(Instant::now()))
+                .min();
+            let res = match timeout {
+                Some(timeout) => self.lost_rx.recv_timeout(timeout),
+                None => self
+                    .lost_rx
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected),
+            };
+
+            match res {
+                Ok(path) => {
+                    log::trace!("watch lost: {}", path.display());
+                    self.pending
+                        .entry(path)
+                        .or_insert((Instant::now() + self.initial_backoff, self.initial_backoff));
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                // the watcher was dropped
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+
+            if !self.rearm_due() {
+                break;
+            }
+        }
+    }
+
+    /// Try to re-establish all due watches. Returns `false` if the watcher was dropped.
+    fn rearm_due(&mut self) -> bool {
+        let now = Instant::now();
+        let due: Vec<_> = self
+            .pending
+            .iter()
+            .filter(|(_, &(next, _))| next <= now)
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        for path in due {
+            let Some(root) = self.roots.lock().unwrap().get(&path).copied() else {
+                // unwatched in the meantime
+                self.pending.remove(&path);
+                continue;
+            };
+
+            let Some(inner) = self.inner.upgrade() else {
+                return false;
+            };
+
+            let rearmed = path.exists() && {
+                let mut inner = inner.lock().unwrap();
+                // the old watch may still be registered
+                let _ = inner.unwatch(&path);
+                inner.watch(&path, root.recursive_mode).is_ok()
+            };
+
+            if rearmed {
+                log::trace!("watch re-established: {}", path.display());
+                self.pending.remove(&path);
+                let is_dir = path.is_dir();
+                if is_dir != root.is_dir {
+                    if let Some(root) = self.roots.lock().unwrap().get_mut(&path) {
+                        root.is_dir = is_dir;
+                    }
+                    let kind = if is_dir {
+                        CreateKind::Folder
+                    } else {
+                        CreateKind::File
+                    };
+                    let event = Event::new(EventKind::Create(kind))
+                        .add_path(path.clone())
+                        .set_info("type-changed");
+                    self.event_handler.lock().unwrap().handle_event(Ok(event));
+                }
+                let event = Event::new(EventKind::Other)
+                    .add_path(path)
+                    .set_flag(Flag::Rescan);
+                self.event_handler.lock().unwrap().handle_event(Ok(event));
+            } else if let Some((next, backoff)) = self.pending.get_mut(&path) {
+                *backoff = (*backoff * 2).min(self.max_backoff);
+                *next = now + *backoff;
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RecommendedWatcher;
+    use std::fs;
+
+    #[test]
+    fn restored_directory_is_watched_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let watched = dir.path().join("watched");
+        let moved = dir.path().join("moved");
+        fs::create_dir(&watched).unwrap();
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut watcher = RearmWatcher::<RecommendedWatcher>::with_backoff(
+            tx,
+            Config::default(),
+            Duration::from_millis(10),
+            Duration::from_millis(50),
+        )
+        .unwrap();
+        watcher.watch(&watched, RecursiveMode::Recursive).unwrap();
+
+        fs::rename(&watched, &moved).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        fs::rename(&moved, &watched).unwrap();
+
+        rx.iter()
+            .map(|res| res.unwrap())
+            .find(|event| event.need_rescan() && event.paths == [watched.clone()])
+            .unwrap();
+
+        let file_path = watched.join("file.txt");
+        fs::write(&file_path, b"Lorem ipsum").unwrap();
+
+        rx.iter()
+            .map(|res| res.unwrap())
+            .find(|event| event.paths == [file_path.clone()])
+            .unwrap();
+    }
+
+    #[test]
+    fn file_replaced_by_directory_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let watched = dir.path().join("watched");
+        fs::write(&watched, b"Lorem ipsum").unwrap();
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut watcher = RearmWatcher::<RecommendedWatcher>::with_backoff(
+            tx,
+            Config::default(),
+            Duration::from_millis(10),
+            Duration::from_millis(50),
+        )
+        .unwrap();
+        watcher.watch(&watched, RecursiveMode::Recursive).unwrap();
+
+        fs::remove_file(&watched).unwrap();
+        fs::create_dir(&watched).unwrap();
+
+        let mut events = std::iter::from_fn(|| rx.recv_timeout(Duration::from_secs(5)).ok())
+            .map(|res| res.unwrap());
+        let changed = events.find(|event| event.is_type_changed()).unwrap();
+        assert_eq!(changed.kind, EventKind::Create(CreateKind::Folder));
+        assert_eq!(changed.paths, [watched.clone()]);
+
+        let file_path = watched.join("file.txt");
+        fs::write(&file_path, b"Lorem ipsum").unwrap();
+        events
+            .find(|event| event.paths == [file_path.clone()])
+            .unwrap();
+    }
+}
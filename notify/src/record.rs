@@ -0,0 +1,223 @@
+//! Recording and replaying event streams
+//!
+//! A [`Recorder`] writes the events a watcher emits as JSON lines, together with the delay since
+//! the previous event. [`replay`] feeds a recording back into an event handler, which allows to
+//! reproduce a problematic sequence of events captured elsewhere, e.g. in a regression test.
+//!
+//! Errors are recorded with their message and paths, they are replayed as generic errors.
+
+use crate::{Error, Event, EventHandler, Result};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufWriter, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Creates an [`EventHandler`] that records every event to `writer`.
+///
+/// See [`Recorder`] for details.
+///
+/// ```no_run
+/// # use notify::{RecursiveMode, Result, Watcher};
+/// # use std::path::Path;
+/// # fn main() -> Result<()> {
+/// let recording = std::fs::File::create("session.jsonl")?;
+/// let mut watcher = notify::recommended_watcher(notify::record::recorder(recording))?;
+/// watcher.watch(Path::new("."), RecursiveMode::Recursive)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn recorder<W>(writer: W) -> Recorder<W>
+where
+    W: Write + Send + 'static,
+{
+    Recorder::new(writer)
+}
+
+/// [`EventHandler`] recording events for [`replay`].
+///
+/// Every event is written as one line, holding the delay since the previous event in
+/// microseconds and the event's serde representation. Output is buffered and flushed when the
+/// handler is dropped. I/O errors never panic, they are logged instead.
+#[derive(Debug)]
+pub struct Recorder<W: Write> {
+    writer: BufWriter<W>,
+    last_event: Option<Instant>,
+}
+
+impl<W: Write> Recorder<W> {
+    /// Creates a new recorder writing to `writer`.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: BufWriter::new(writer),
+            last_event: None,
+        }
+    }
+
+    fn write_line(&mut self, delay: Duration, event: &Result<Event>) -> std::io::Result<()> {
+        let delay_us = u64::try_from(delay.as_micros()).unwrap_or(u64::MAX);
+        let line = match event {
+            Ok(event) => json!({ "delay_us": delay_us, "event": event }),
+            Err(error) => json!({
+                "delay_us": delay_us,
+                "error": {
+                    "message": error_message(error),
+                    "paths": error.paths,
+                },
+            }),
+        };
+        serde_json::to_writer(&mut self.writer, &line)?;
+        self.writer.write_all(b"\n")
+    }
+}
+
+impl<W> EventHandler for Recorder<W>
+where
+    W: Write + Send + 'static,
+{
+    fn handle_event(&mut self, event: Result<Event>) {
+        let now = Instant::now();
+        let delay = self
+            .last_event
+            .map_or(Duration::ZERO, |last| now.saturating_duration_since(last));
+        self.last_event = Some(now);
+
+        if let Err(e) = self.write_line(delay, &event) {
+            log::warn!("failed to record event: {e}");
+        }
+    }
+}
+
+impl<W: Write> Drop for Recorder<W> {
+    fn drop(&mut self) {
+        if let Err(e) = self.writer.flush() {
+            log::warn!("failed to flush recording: {e}");
+        }
+    }
+}
+
+/// The speed at which [`replay`] delivers events.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pacing {
+    /// Wait for the recorded delay before every event
+    Original,
+
+    /// Deliver all events without waiting
+    Immediate,
+}
+
+/// Feeds the events recorded by a [`Recorder`] into `event_handler`, in the recorded order.
+///
+/// Blocks until all events are delivered. Returns an error if `reader` fails or holds a line
+/// that isn't a recorded event, the events before that line have been delivered by then.
+///
+/// ```no_run
+/// # use notify::record::{replay, Pacing};
+/// # fn main() -> notify::Result<()> {
+/// let recording = std::io::BufReader::new(std::fs::File::open("session.jsonl")?);
+/// replay(recording, |event| println!("{event:?}"), Pacing::Original)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn replay<R, F>(reader: R, mut event_handler: F, pacing: Pacing) -> Result<()>
+where
+    R: BufRead,
+    F: EventHandler,
+{
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (delay, event) = parse_line(&line)
+            .ok_or_else(|| Error::generic(&format!("invalid recording at line {}", index + 1)))?;
+
+        if pacing == Pacing::Original {
+            thread::sleep(delay);
+        }
+        event_handler.handle_event(event);
+    }
+    Ok(())
+}
+
+/// The message of `error`, without the paths.
+fn error_message(error: &Error) -> String {
+    let message = error.to_string();
+    if error.paths.is_empty() {
+        return message;
+    }
+    let paths = format!(" about {:?}", error.paths);
+    message
+        .strip_suffix(&paths)
+        .map_or(message.clone(), str::to_string)
+}
+
+fn parse_line(line: &str) -> Option<(Duration, Result<Event>)> {
+    let mut line: Value = serde_json::from_str(line).ok()?;
+    let delay = Duration::from_micros(line["delay_us"].as_u64()?);
+
+    if let Some(error) = line.get_mut("error") {
+        let message = error["message"].as_str()?.to_string();
+        let paths: Vec<_> = serde_json::from_value(error["paths"].take()).ok()?;
+        let error = paths
+            .into_iter()
+            .fold(Error::generic(&message), Error::add_path);
+        return Some((delay, Err(error)));
+    }
+
+    let event = serde_json::from_value(line.get_mut("event")?.take()).ok()?;
+    Some((delay, Ok(event)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{CreateKind, EventKind, ModifyKind, RenameMode};
+    use std::path::PathBuf;
+    use std::sync::mpsc;
+
+    #[test]
+    fn replay_delivers_recorded_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.jsonl");
+
+        let events = vec![
+            Ok(Event::new(EventKind::Create(CreateKind::File)).add_path(PathBuf::from("/a"))),
+            Ok(
+                Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+                    .add_path(PathBuf::from("/a"))
+                    .add_path(PathBuf::from("/b"))
+                    .set_tracker(7),
+            ),
+            Err(Error::path_not_found().add_path(PathBuf::from("/gone"))),
+        ];
+
+        {
+            let mut recorder = recorder(std::fs::File::create(&path).unwrap());
+            for event in &events {
+                let event = match event {
+                    Ok(event) => Ok(event.clone()),
+                    Err(error) => Err(Error::path_not_found().add_path(error.paths[0].clone())),
+                };
+                recorder.handle_event(event);
+                thread::sleep(Duration::from_millis(10));
+            }
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let start = Instant::now();
+        let recording = std::io::BufReader::new(std::fs::File::open(&path).unwrap());
+        replay(recording, tx, Pacing::Original).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        let replayed: Vec<_> = rx.try_iter().collect();
+        assert_eq!(replayed.len(), 3);
+        assert_eq!(replayed[0].as_ref().unwrap(), events[0].as_ref().unwrap());
+        assert_eq!(replayed[1].as_ref().unwrap(), events[1].as_ref().unwrap());
+        let error = replayed[2].as_ref().unwrap_err();
+        assert_eq!(error.paths, vec![PathBuf::from("/gone")]);
+        assert_eq!(
+            error.to_string(),
+            events[2].as_ref().unwrap_err().to_string()
+        );
+    }
+}
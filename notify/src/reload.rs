@@ -0,0 +1,215 @@
+//! Reloading a file whenever it changes
+//!
+//! Editors often save a file by writing a new file and renaming it over the old one. A watch on
+//! the file itself follows the replaced file and stops reporting changes afterwards. The
+//! [`watch_file_for_reload`] helper watches the directory holding the file instead and reports
+//! the changes of the file, whichever way it is saved.
+//!
+//! ```no_run
+//! # fn main() -> notify::Result<()> {
+//! let _watcher = notify::watch_file_for_reload("config.toml", || {
+//!     println!("config.toml changed, reloading");
+//! })?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::event::{AccessKind, AccessMode, EventKind, ModifyKind, RenameMode};
+use crate::{
+    Config, Error, Event, EventHandler, RecommendedWatcher, RecursiveMode, Result, Watcher,
+    WatcherKind,
+};
+use std::env;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Call `on_change` whenever the file at `path` is created, modified or replaced.
+///
+/// The directory holding the file is watched rather than the file, so changes are still reported
+/// after the file was replaced, e.g. by an editor renaming a new file over it. All changes
+/// reported at once result in a single call. On inotify, only the file being closed after
+/// writing and renames count as changes, so a save written in several steps results in a single
+/// call as well. The file doesn't need to exist yet, its directory does.
+///
+/// The file is watched until the returned [`ReloadWatcher`] is dropped.
+pub fn watch_file_for_reload<P, F>(path: P, on_change: F) -> Result<ReloadWatcher>
+where
+    P: AsRef<Path>,
+    F: FnMut() + Send + 'static,
+{
+    let path = path.as_ref();
+    let path = if path.is_relative() {
+        env::current_dir().map_err(Error::io)?.join(path)
+    } else {
+        path.to_path_buf()
+    };
+    let (Some(dir), Some(file_name)) = (path.parent(), path.file_name()) else {
+        return Err(Error::generic("no file to watch for reload").add_path(path));
+    };
+
+    // backends like FSEvents report the paths with the symlinks resolved
+    let canonical = dir.canonicalize().ok().map(|dir| dir.join(file_name));
+    let handler = ReloadHandler {
+        paths: [Some(path.clone()), canonical]
+            .into_iter()
+            .flatten()
+            .collect(),
+        closes: RecommendedWatcher::kind() == WatcherKind::Inotify,
+        on_change,
+    };
+    let mut watcher = RecommendedWatcher::new(handler, Config::default())?;
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    Ok(ReloadWatcher { watcher, path })
+}
+
+/// Watches a file for [reloading](watch_file_for_reload) until dropped.
+pub struct ReloadWatcher {
+    watcher: RecommendedWatcher,
+    path: PathBuf,
+}
+
+impl ReloadWatcher {
+    /// The watched file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl fmt::Debug for ReloadWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReloadWatcher")
+            .field("watcher", &self.watcher)
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+struct ReloadHandler<F> {
+    /// The watched file, and the same path with the symlinks resolved
+    paths: Vec<PathBuf>,
+    /// The backend reports files being closed after writing
+    closes: bool,
+    on_change: F,
+}
+
+impl<F> ReloadHandler<F> {
+    /// Whether `event` reports new contents of the watched file.
+    fn is_change(&self, event: &Event) -> bool {
+        let path = match event.kind {
+            EventKind::Create(_)
+            | EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Any | ModifyKind::Other)
+                if !self.closes =>
+            {
+                event.paths.first()
+            }
+            EventKind::Access(AccessKind::Close(AccessMode::Write)) => event.paths.first(),
+            // the new name of a rename
+            EventKind::Modify(ModifyKind::Name(
+                RenameMode::To | RenameMode::Both | RenameMode::Any,
+            )) => event.paths.last(),
+            _ => None,
+        };
+        path.is_some_and(|path| self.paths.contains(path))
+    }
+}
+
+impl<F: FnMut() + Send + 'static> EventHandler for ReloadHandler<F> {
+    fn handle_event(&mut self, event: Result<Event>) {
+        self.handle_events(vec![event]);
+    }
+
+    fn handle_events(&mut self, events: Vec<Result<Event>>) {
+        let mut changed = false;
+        for event in events {
+            match event {
+                Ok(event) => changed |= self.is_change(&event),
+                Err(error) => log::warn!("error watching for reload: {error}"),
+            }
+        }
+        if changed {
+            (self.on_change)();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn changes_are_reported_after_the_file_was_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("config.toml");
+        fs::write(&config, b"a = 1").unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let _watcher = watch_file_for_reload(&config, move || {
+            let _ = tx.send(());
+        })
+        .unwrap();
+        let changed = || {
+            rx.recv_timeout(Duration::from_secs(5)).unwrap();
+            // skip the further calls of the same save
+            while rx.recv_timeout(Duration::from_millis(200)).is_ok() {}
+        };
+
+        // unrelated files in the directory are ignored
+        fs::write(dir.path().join("other"), b"").unwrap();
+        assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+
+        fs::write(&config, b"a = 2").unwrap();
+        changed();
+
+        let saved = dir.path().join(".config.toml.swp");
+        fs::write(&saved, b"a = 3").unwrap();
+        fs::rename(&saved, &config).unwrap();
+        changed();
+
+        fs::write(&config, b"a = 4").unwrap();
+        changed();
+    }
+
+    #[test]
+    fn only_the_watched_path_is_a_change() {
+        let handler = ReloadHandler {
+            paths: vec![PathBuf::from("/etc/app/app")],
+            closes: false,
+            on_change: || {},
+        };
+        let modify = |path: &str| {
+            Event::new(EventKind::Modify(ModifyKind::Any)).add_path(PathBuf::from(path))
+        };
+        assert!(handler.is_change(&modify("/etc/app/app")));
+        // the watched directory itself has the same name
+        assert!(!handler.is_change(&modify("/etc/app")));
+        assert!(!handler.is_change(&modify("/etc/other/app")));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn a_save_written_in_steps_is_one_change() {
+        use std::io::Write;
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("config.toml");
+        let (tx, rx) = mpsc::channel();
+        let _watcher = watch_file_for_reload(&config, move || {
+            let _ = tx.send(());
+        })
+        .unwrap();
+
+        let mut file = fs::File::create(&config).unwrap();
+        for line in ["a = 1\n", "b = 2\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        assert!(rx.try_recv().is_err());
+        drop(file);
+
+        rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+    }
+}
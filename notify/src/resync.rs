@@ -0,0 +1,47 @@
+//! Reporting the existing paths below the watched paths, see [`Watcher::resync`](crate::Watcher::resync)
+
+use crate::event::{CreateKind, Event, EventKind, Flag};
+use crate::{EventHandler, RecursiveMode};
+use std::path::PathBuf;
+use walkdir::WalkDir;
+
+/// Passes a `Create` event flagged with [`Flag::Resync`] for every path below the `roots` to
+/// `event_handler`, at once.
+///
+/// Watched directories themselves are not reported, watched files are. Entries that can't be read
+/// are skipped.
+pub(crate) fn resync<F: EventHandler + ?Sized>(
+    roots: &[(PathBuf, RecursiveMode)],
+    follow_symlinks: bool,
+    event_handler: &mut F,
+) {
+    let mut events = Vec::new();
+    for (root, recursive_mode) in roots {
+        let max_depth = match recursive_mode {
+            RecursiveMode::Recursive => usize::MAX,
+            RecursiveMode::NonRecursive => 1,
+        };
+        let entries = WalkDir::new(root)
+            .follow_links(follow_symlinks)
+            .max_depth(max_depth)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.depth() > 0 || !entry.file_type().is_dir());
+
+        for entry in entries {
+            let file_type = entry.file_type();
+            let kind = if file_type.is_dir() {
+                CreateKind::Folder
+            } else if file_type.is_file() {
+                CreateKind::File
+            } else {
+                CreateKind::Any
+            };
+            let event = Event::new(EventKind::Create(kind))
+                .add_path(entry.into_path())
+                .set_flag(Flag::Resync);
+            events.push(Ok(event));
+        }
+    }
+    event_handler.handle_events(events);
+}
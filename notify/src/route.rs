@@ -0,0 +1,236 @@
+//! Event handler dispatching events by path
+//!
+//! A [`Router`] allows a single watcher to serve several consumers, each interested in a
+//! different part of the watched tree, instead of running a watcher per consumer.
+
+use crate::{Error, ErrorKind, Event, EventHandler, Result};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Creates an [`EventHandler`] dispatching every event to the handler of the longest path prefix
+/// matching one of the event's paths, or to `default` if no prefix matches.
+///
+/// See [`Router`] for details.
+///
+/// ```no_run
+/// # use notify::{Event, EventHandler, RecursiveMode, Result, Watcher};
+/// # use std::path::{Path, PathBuf};
+/// # fn main() -> Result<()> {
+/// let tenant_a = |event: Result<Event>| println!("A: {event:?}");
+/// let tenant_b = |event: Result<Event>| println!("B: {event:?}");
+/// let handler = notify::route(
+///     [
+///         (PathBuf::from("/tenants/a"), Box::new(tenant_a) as Box<dyn EventHandler>),
+///         (PathBuf::from("/tenants/b"), Box::new(tenant_b)),
+///     ],
+///     |event: Result<Event>| println!("other: {event:?}"),
+/// );
+/// let mut watcher = notify::recommended_watcher(handler)?;
+/// watcher.watch(Path::new("/tenants"), RecursiveMode::Recursive)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn route<I, F>(routes: I, default: F) -> Router
+where
+    I: IntoIterator<Item = (PathBuf, Box<dyn EventHandler>)>,
+    F: EventHandler,
+{
+    Router::new(routes, default)
+}
+
+/// Where a [`Router`] delivers errors.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ErrorRoute {
+    /// Deliver errors to the default handler only
+    #[default]
+    Default,
+
+    /// Deliver errors to every handler, including the default handler
+    ///
+    /// As errors can't be cloned, the handlers receive copies which keep the kind and paths of
+    /// the error, but turn I/O errors into new I/O errors with the same kind and message.
+    All,
+}
+
+/// [`EventHandler`] dispatching events to sub-handlers by path prefix.
+///
+/// Prefixes are compared component-wise, so `/tenants/a` matches `/tenants/a/file` but not
+/// `/tenants/ab`. The longest matching prefix wins. An event with several paths, e.g. a rename
+/// between two prefixes, is delivered to the handler of each path once. Events without a
+/// matching path go to the default handler. Errors are delivered according to the
+/// [`ErrorRoute`], to the default handler unless configured otherwise.
+pub struct Router {
+    /// Sub-handlers, ordered from the longest prefix to the shortest
+    routes: Vec<(PathBuf, Box<dyn EventHandler>)>,
+    default: Box<dyn EventHandler>,
+    error_route: ErrorRoute,
+}
+
+impl Router {
+    /// Create a new [`Router`] dispatching to the handlers of `routes` and to `default`.
+    pub fn new<I, F>(routes: I, default: F) -> Self
+    where
+        I: IntoIterator<Item = (PathBuf, Box<dyn EventHandler>)>,
+        F: EventHandler,
+    {
+        let mut routes: Vec<_> = routes.into_iter().collect();
+        routes.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.components().count()));
+        Self {
+            routes,
+            default: Box::new(default),
+            error_route: ErrorRoute::Default,
+        }
+    }
+
+    /// Set where errors are delivered.
+    pub fn with_error_route(mut self, error_route: ErrorRoute) -> Self {
+        self.error_route = error_route;
+        self
+    }
+
+    /// Index of the route for `path`, if any.
+    fn route_of(&self, path: &Path) -> Option<usize> {
+        self.routes
+            .iter()
+            .position(|(prefix, _)| path.starts_with(prefix))
+    }
+}
+
+impl fmt::Debug for Router {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Router")
+            .field(
+                "routes",
+                &self
+                    .routes
+                    .iter()
+                    .map(|(prefix, _)| prefix)
+                    .collect::<Vec<_>>(),
+            )
+            .field("error_route", &self.error_route)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EventHandler for Router {
+    fn handle_event(&mut self, event: Result<Event>) {
+        let event = match event {
+            Ok(event) => event,
+            Err(error) => {
+                if self.error_route == ErrorRoute::All {
+                    for (_, handler) in &mut self.routes {
+                        handler.handle_event(Err(duplicate(&error)));
+                    }
+                }
+                self.default.handle_event(Err(error));
+                return;
+            }
+        };
+
+        let mut targets: Vec<Option<usize>> = Vec::new();
+        for path in &event.paths {
+            let target = self.route_of(path);
+            if !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
+        if targets.is_empty() {
+            targets.push(None);
+        }
+
+        for target in targets {
+            let handler = match target {
+                Some(index) => &mut self.routes[index].1,
+                None => &mut self.default,
+            };
+            handler.handle_event(Ok(event.clone()));
+        }
+    }
+}
+
+/// A copy of `error`, see [`ErrorRoute::All`].
+fn duplicate(error: &Error) -> Error {
+    let kind = match &error.kind {
+        ErrorKind::Generic(message) => ErrorKind::Generic(message.clone()),
+        ErrorKind::Io(err) => ErrorKind::Io(io::Error::new(err.kind(), err.to_string())),
+        ErrorKind::PathNotFound => ErrorKind::PathNotFound,
+        ErrorKind::WatchNotFound => ErrorKind::WatchNotFound,
+        ErrorKind::InvalidConfig(config) => ErrorKind::InvalidConfig(*config),
+        ErrorKind::MaxFilesWatch => ErrorKind::MaxFilesWatch,
+        ErrorKind::TooManyOpenFiles => ErrorKind::TooManyOpenFiles,
+        ErrorKind::UnsupportedFilesystem => ErrorKind::UnsupportedFilesystem,
+        ErrorKind::WatcherPanicked(message) => ErrorKind::WatcherPanicked(message.clone()),
+    };
+    Error::new(kind).set_paths(error.paths.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{CreateKind, EventKind, ModifyKind, RenameMode};
+    use std::sync::mpsc;
+
+    fn event(kind: EventKind, paths: &[&str]) -> Result<Event> {
+        Ok(paths.iter().fold(Event::new(kind), |event, path| {
+            event.add_path(PathBuf::from(path))
+        }))
+    }
+
+    #[test]
+    fn events_reach_the_handler_of_the_longest_prefix() {
+        let (a_tx, a_rx) = mpsc::channel::<Result<Event>>();
+        let (nested_tx, nested_rx) = mpsc::channel::<Result<Event>>();
+        let (default_tx, default_rx) = mpsc::channel();
+        let mut router = route(
+            [
+                (
+                    PathBuf::from("/tenants/a"),
+                    Box::new(a_tx) as Box<dyn EventHandler>,
+                ),
+                (PathBuf::from("/tenants/a/nested"), Box::new(nested_tx)),
+            ],
+            default_tx,
+        )
+        .with_error_route(ErrorRoute::All);
+
+        let create = EventKind::Create(CreateKind::File);
+        router.handle_event(event(create, &["/tenants/a/file"]));
+        router.handle_event(event(create, &["/tenants/a/nested/file"]));
+        router.handle_event(event(create, &["/tenants/ab/file"]));
+        router.handle_event(event(EventKind::Other, &[]));
+        router.handle_event(event(
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+            &["/tenants/a/nested/file", "/tenants/b/file"],
+        ));
+        router.handle_event(Err(Error::path_not_found().add_path("/gone".into())));
+
+        let a: Vec<_> = a_rx.try_iter().collect();
+        assert_eq!(a.len(), 2);
+        assert_eq!(
+            a[0].as_ref().unwrap().paths,
+            [PathBuf::from("/tenants/a/file")]
+        );
+        assert!(matches!(
+            a[1].as_ref().unwrap_err().kind,
+            ErrorKind::PathNotFound
+        ));
+
+        let nested: Vec<_> = nested_rx.try_iter().collect();
+        assert_eq!(nested.len(), 3);
+        assert!(nested[1].as_ref().unwrap().kind.is_modify());
+
+        let default: Vec<_> = default_rx.try_iter().collect();
+        assert_eq!(default.len(), 4);
+        assert_eq!(
+            default[0].as_ref().unwrap().paths,
+            [PathBuf::from("/tenants/ab/file")]
+        );
+        assert!(default[1].as_ref().unwrap().paths.is_empty());
+        assert!(default[2].as_ref().unwrap().kind.is_modify());
+        assert_eq!(
+            default[3].as_ref().unwrap_err().paths,
+            [PathBuf::from("/gone")]
+        );
+    }
+}
@@ -0,0 +1,303 @@
+//! Event handler holding back creations until the files are settled
+//!
+//! Tools writing a file in place produce a `Create` event as soon as the file appears, while it
+//! is still being written. The [`Settle`] handler holds back such events until the file stopped
+//! changing, see [`Config::with_min_create_age`](crate::Config::with_min_create_age).
+
+use crate::event::{ModifyKind, RenameMode};
+use crate::pause::Flush;
+use crate::timed::{Hold, Timed};
+use crate::{Config, Event, EventHandler, EventKind, Result};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// [`EventHandler`] delaying `Create` events of files until the file kept its size and
+/// modification time for a minimum age.
+///
+/// The file is checked again once the age has passed, and the check is repeated as long as the
+/// file changes. Other events of a held back file are dropped, as they are part of its creation.
+/// If the file is removed or renamed away before it settled, its events are dropped altogether,
+/// a rename within the watched tree moves the held back creation to the new name. Creations of
+/// directories and all other events are forwarded right away, so events may be reordered.
+/// Held back creations are delivered when the handler is dropped, or right away when the watcher
+/// is stopped, see [`Watcher::stop`](crate::Watcher::stop).
+///
+/// The events are delivered from a background thread.
+///
+/// ```no_run
+/// # use notify::{RecursiveMode, Result, Settle, Watcher};
+/// # use std::path::Path;
+/// # use std::time::Duration;
+/// # fn main() -> Result<()> {
+/// let handler = Settle::new(
+///     |event: Result<notify::Event>| println!("{event:?}"),
+///     Duration::from_secs(1),
+/// )?;
+/// let mut watcher = notify::recommended_watcher(handler)?;
+/// watcher.watch(Path::new("."), RecursiveMode::Recursive)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Settle {
+    timed: Timed,
+    min_age: Duration,
+}
+
+impl Settle {
+    /// Create a new [`Settle`] delivering to `event_handler`, holding back creations until the
+    /// file is unchanged for `min_age`.
+    pub fn new<F: EventHandler>(mut event_handler: F, min_age: Duration) -> Result<Self> {
+        let creations = Creations {
+            held: HashMap::new(),
+            min_age,
+        };
+        let timed = Timed::start("notify-rs settle loop", creations, move |events: Vec<_>| {
+            event_handler.handle_events(events)
+        })?;
+        Ok(Self { timed, min_age })
+    }
+
+    /// Returns a [`Flush`] delivering the held back creations right away.
+    #[cfg_attr(
+        not(any(target_os = "linux", target_os = "android", target_os = "windows")),
+        allow(dead_code)
+    )]
+    pub(crate) fn flush(&self) -> Flush {
+        self.timed.flush()
+    }
+}
+
+impl fmt::Debug for Settle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Settle")
+            .field("min_age", &self.min_age)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EventHandler for Settle {
+    fn handle_event(&mut self, event: Result<Event>) {
+        self.timed.send(event);
+    }
+}
+
+/// Wraps `event_handler` in a [`Settle`] if enabled in the `config`.
+pub(crate) fn from_config<F: EventHandler>(
+    event_handler: F,
+    config: &Config,
+) -> Result<SettleHandler<F>> {
+    Ok(match config.min_create_age() {
+        Some(min_age) => SettleHandler::On(Settle::new(event_handler, min_age)?),
+        None => SettleHandler::Off(event_handler),
+    })
+}
+
+/// [`EventHandler`] created by [`from_config`].
+pub(crate) enum SettleHandler<F> {
+    Off(F),
+    On(Settle),
+}
+
+#[cfg_attr(
+    not(any(target_os = "linux", target_os = "android", target_os = "windows")),
+    allow(dead_code)
+)]
+impl<F> SettleHandler<F> {
+    /// Returns a [`Flush`] of the held back creations, if enabled.
+    pub(crate) fn flush(&self) -> Option<Flush> {
+        match self {
+            SettleHandler::Off(_) => None,
+            SettleHandler::On(settle) => Some(settle.flush()),
+        }
+    }
+}
+
+impl<F: EventHandler> EventHandler for SettleHandler<F> {
+    fn handle_event(&mut self, event: Result<Event>) {
+        match self {
+            SettleHandler::Off(event_handler) => event_handler.handle_event(event),
+            SettleHandler::On(settle) => settle.handle_event(event),
+        }
+    }
+
+    fn handle_events(&mut self, events: Vec<Result<Event>>) {
+        match self {
+            SettleHandler::Off(event_handler) => event_handler.handle_events(events),
+            SettleHandler::On(settle) => settle.handle_events(events),
+        }
+    }
+}
+
+/// A held back `Create` event.
+struct Held {
+    event: Event,
+    state: Option<FileState>,
+    due: Instant,
+}
+
+/// Size and modification time of a file.
+type FileState = (u64, Option<SystemTime>);
+
+fn file_state(path: &Path) -> Option<FileState> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()))
+}
+
+/// The creations of files that didn't settle yet.
+struct Creations {
+    held: HashMap<PathBuf, Held>,
+    min_age: Duration,
+}
+
+impl Hold for Creations {
+    fn push(&mut self, event: Result<Event>) -> Vec<Result<Event>> {
+        match event {
+            Ok(event) => hold(&mut self.held, event, self.min_age)
+                .map(Ok)
+                .into_iter()
+                .collect(),
+            Err(error) => vec![Err(error)],
+        }
+    }
+
+    fn due(&self) -> Option<Instant> {
+        self.held.values().map(|held| held.due).min()
+    }
+
+    fn take_due(&mut self, now: Instant) -> Vec<Result<Event>> {
+        let min_age = self.min_age;
+        let mut settled = Vec::new();
+        self.held.retain(|path, held| {
+            if held.due > now {
+                return true;
+            }
+            match file_state(path) {
+                // removed without an event, e.g. outside of the watched tree
+                None => false,
+                Some(state) if Some(state) == held.state => {
+                    settled.push((held.due, held.event.clone()));
+                    false
+                }
+                Some(state) => {
+                    held.state = Some(state);
+                    held.due = now + min_age;
+                    true
+                }
+            }
+        });
+        settled.sort_by_key(|(due, _)| *due);
+        settled.into_iter().map(|(_, event)| Ok(event)).collect()
+    }
+
+    fn take_all(&mut self) -> Vec<Result<Event>> {
+        let mut rest: Vec<_> = self.held.drain().map(|(_, held)| held).collect();
+        rest.sort_by_key(|held| held.due);
+        rest.into_iter().map(|held| Ok(held.event)).collect()
+    }
+}
+
+/// Holds back `event` if it is a creation of a file or belongs to a held back creation, returns
+/// the event if it is to be forwarded.
+fn hold(held: &mut HashMap<PathBuf, Held>, event: Event, min_age: Duration) -> Option<Event> {
+    if let Some(path) = event.paths.first().filter(|path| held.contains_key(*path)) {
+        match (event.kind, event.paths.get(1)) {
+            (EventKind::Modify(ModifyKind::Name(RenameMode::Both)), Some(to)) => {
+                let mut creation = held.remove(path)?;
+                creation.event.paths = vec![to.clone()];
+                creation.state = file_state(to);
+                creation.due = Instant::now() + min_age;
+                held.insert(to.clone(), creation);
+            }
+            (EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)), _) => {
+                held.remove(path);
+            }
+            _ => {}
+        }
+        return None;
+    }
+
+    if let (EventKind::Create(_), [path]) = (event.kind, event.paths.as_slice()) {
+        let path = path.clone();
+        if fs::metadata(&path).is_ok_and(|metadata| !metadata.is_dir()) {
+            let state = file_state(&path);
+            held.insert(
+                path,
+                Held {
+                    event,
+                    state,
+                    due: Instant::now() + min_age,
+                },
+            );
+            return None;
+        }
+    }
+    Some(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{CreateKind, DataChange, RemoveKind};
+    use std::io::Write;
+    use std::thread;
+
+    fn event(kind: EventKind, path: &Path) -> Result<Event> {
+        Ok(Event::new(kind).add_path(path.to_path_buf()))
+    }
+
+    #[test]
+    fn creations_are_delivered_once_the_file_settled() {
+        let dir = tempfile::tempdir().unwrap();
+        let streamed = dir.path().join("streamed");
+        let removed = dir.path().join("removed");
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut settle = Settle::new(tx, Duration::from_millis(150)).unwrap();
+
+        let start = Instant::now();
+        let mut file = fs::File::create(&streamed).unwrap();
+        settle.handle_event(event(EventKind::Create(CreateKind::File), &streamed));
+        fs::write(&removed, b"").unwrap();
+        settle.handle_event(event(EventKind::Create(CreateKind::File), &removed));
+        // the file is kept, it must still exist when the background thread looks at the creation
+        settle.handle_event(event(EventKind::Remove(RemoveKind::File), &removed));
+        settle.handle_event(event(EventKind::Create(CreateKind::Folder), dir.path()));
+
+        for _ in 0..6 {
+            thread::sleep(Duration::from_millis(60));
+            file.write_all(b"data").unwrap();
+            settle.handle_event(event(
+                EventKind::Modify(ModifyKind::Data(DataChange::Any)),
+                &streamed,
+            ));
+        }
+        let writing = start.elapsed();
+
+        let first = rx.recv_timeout(Duration::from_secs(1)).unwrap().unwrap();
+        assert_eq!(first.kind, EventKind::Create(CreateKind::Folder));
+        let second = rx.recv_timeout(Duration::from_secs(1)).unwrap().unwrap();
+        assert_eq!(second.kind, EventKind::Create(CreateKind::File));
+        assert_eq!(second.paths, [streamed]);
+        assert!(start.elapsed() >= writing + Duration::from_millis(150));
+
+        drop(settle);
+        assert!(rx.recv().is_err());
+    }
+
+    #[test]
+    fn flush_delivers_the_held_creations() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut settle = Settle::new(tx, Duration::from_secs(60)).unwrap();
+
+        fs::write(&path, b"").unwrap();
+        settle.handle_event(event(EventKind::Create(CreateKind::File), &path));
+        settle.flush()();
+
+        let event = rx.try_recv().unwrap().unwrap();
+        assert_eq!(event.paths, [path]);
+    }
+}
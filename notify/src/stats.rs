@@ -0,0 +1,117 @@
+//! Cumulative counters of a watcher, see [`Watcher::stats`](crate::Watcher::stats)
+
+use crate::{Event, EventHandler, Result};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Snapshot of the counters of a watcher, see [`Watcher::stats`](crate::Watcher::stats).
+///
+/// The counters start at zero when the watcher is created and only ever grow, apart from
+/// `watches`. Comparing two snapshots tells how many events were delivered in between, e.g. to
+/// notice a watcher that stopped delivering events or queue overflows piling up.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct WatcherStats {
+    /// Number of events delivered to the event handler
+    pub events: u64,
+    /// Number of errors delivered to the event handler
+    pub errors: u64,
+    /// Number of events with the [`Rescan`](crate::event::Flag::Rescan) flag among `events`,
+    /// e.g. for overflows of the kernel queue
+    pub rescans: u64,
+    /// Number of paths currently watched, see
+    /// [`Watcher::watched_paths`](crate::Watcher::watched_paths)
+    pub watches: usize,
+}
+
+/// The counters of [`WatcherStats`] that are updated as events are delivered.
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    events: AtomicU64,
+    errors: AtomicU64,
+    rescans: AtomicU64,
+}
+
+impl Counters {
+    /// Wraps the user's `event_handler`, counting the events delivered to it.
+    pub(crate) fn handler<F: EventHandler>(self: &Arc<Self>, event_handler: F) -> Counted<F> {
+        Counted {
+            event_handler,
+            counters: self.clone(),
+        }
+    }
+
+    fn count(&self, event: &Result<Event>) {
+        match event {
+            Ok(event) => {
+                self.events.fetch_add(1, Ordering::Relaxed);
+                if event.need_rescan() {
+                    self.rescans.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            Err(_) => {
+                self.errors.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub(crate) fn snapshot(&self, watches: usize) -> WatcherStats {
+        WatcherStats {
+            events: self.events.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            rescans: self.rescans.load(Ordering::Relaxed),
+            watches,
+        }
+    }
+}
+
+/// [`EventHandler`] wrapper created by [`Counters::handler`].
+pub(crate) struct Counted<F> {
+    event_handler: F,
+    counters: Arc<Counters>,
+}
+
+impl<F: EventHandler> EventHandler for Counted<F> {
+    fn handle_event(&mut self, event: Result<Event>) {
+        self.counters.count(&event);
+        self.event_handler.handle_event(event);
+    }
+
+    fn handle_events(&mut self, events: Vec<Result<Event>>) {
+        events.iter().for_each(|event| self.counters.count(event));
+        self.event_handler.handle_events(events);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{CreateKind, EventKind, Flag};
+    use crate::Config;
+
+    #[test]
+    fn events_are_counted_as_delivered() {
+        let counters = Arc::<Counters>::default();
+        let config = Config::default().with_event_kinds(&[crate::EventKindMask::Create]);
+        let mut handler = crate::AttributeHandler::new(
+            counters.handler(|_: Result<Event>| {}),
+            &config,
+            crate::WatcherKind::NullWatcher,
+        );
+        handler.handle_event(Ok(Event::new(EventKind::Create(CreateKind::File))));
+        handler.handle_events(vec![
+            Ok(Event::new(EventKind::Other).set_flag(Flag::Rescan)),
+            Err(crate::Error::generic("error")),
+        ]);
+        // filtered events are not delivered
+        handler.handle_event(Ok(Event::new(EventKind::Access(
+            crate::event::AccessKind::Any,
+        ))));
+
+        let stats = counters.snapshot(1);
+        assert_eq!(
+            (stats.events, stats.errors, stats.rescans, stats.watches),
+            (2, 1, 1, 1)
+        );
+    }
+}
@@ -0,0 +1,316 @@
+//! Watcher wrapper following repointed symbolic links
+//!
+//! Deployments often switch between releases by repointing a symbolic link, e.g. `current` to
+//! `releases/N`. Backends resolve the link once, when the watch is installed, and keep watching
+//! the old target afterwards. The [`SymlinkWatcher`] also watches the directory containing the
+//! link, notices when the link is repointed and watches its new target. A [`Flag::Rescan`] event
+//! is emitted for the link afterwards, as the contents of the new target are unknown.
+
+use crate::event::{Event, EventKind, Flag};
+use crate::{covers, unbounded, Config, EventHandler, Receiver, RecursiveMode, Result, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::Duration;
+
+/// Watched paths, shared with the event handler of the inner watcher.
+#[derive(Debug, Default)]
+struct State {
+    /// The paths as watched by the user
+    roots: HashMap<PathBuf, RecursiveMode>,
+    /// Watched symbolic links -> their current target
+    links: HashMap<PathBuf, PathBuf>,
+    /// Directories watched to notice when the links in them are repointed
+    parents: HashSet<PathBuf>,
+}
+
+impl State {
+    /// Whether `event` only concerns entries of the directories watched for the links.
+    fn is_parent_noise(&self, event: &Event) -> bool {
+        let roots = self
+            .roots
+            .iter()
+            .map(|(root, &mode)| (root.as_path(), mode));
+        !event.paths.is_empty()
+            && event.paths.iter().all(|path| {
+                path.parent().is_some_and(|dir| self.parents.contains(dir))
+                    && !covers(roots.clone(), path)
+            })
+    }
+}
+
+/// [`Watcher`] wrapper that keeps watching the current target of watched symbolic links.
+///
+/// When a path passed to [`watch`](Watcher::watch) is a symbolic link, its parent directory is
+/// watched as well. Once the link is replaced or repointed, the link is watched again, which
+/// makes the inner watcher resolve the new target, and a [`Flag::Rescan`] event is emitted for
+/// the link. Events for other entries of the parent directory are not delivered.
+///
+/// The inner watcher must follow symbolic links, see [`Config::with_follow_symlinks`].
+///
+/// ```no_run
+/// # use notify::{RecommendedWatcher, RecursiveMode, Result, SymlinkWatcher, Watcher};
+/// # use std::path::Path;
+/// # fn main() -> Result<()> {
+/// let mut watcher = SymlinkWatcher::<RecommendedWatcher>::new(
+///     |event: Result<notify::Event>| println!("{event:?}"),
+///     notify::Config::default(),
+/// )?;
+/// watcher.watch(Path::new("/app/current"), RecursiveMode::Recursive)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct SymlinkWatcher<W> {
+    inner: Arc<Mutex<W>>,
+    state: Arc<Mutex<State>>,
+}
+
+impl<W> fmt::Debug for SymlinkWatcher<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SymlinkWatcher")
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<W: Watcher + Send + 'static> Watcher for SymlinkWatcher<W> {
+    fn new<F: EventHandler>(event_handler: F, config: Config) -> Result<Self> {
+        let event_handler: Arc<Mutex<dyn EventHandler>> = Arc::new(Mutex::new(event_handler));
+        let state = Arc::new(Mutex::new(State::default()));
+        let (changed_tx, changed_rx) = unbounded();
+
+        let inner = {
+            let event_handler = event_handler.clone();
+            let state = state.clone();
+            W::new(
+                move |res: Result<Event>| {
+                    if let Ok(event) = &res {
+                        let state = state.lock().unwrap();
+                        for path in event.paths.iter().filter(|p| state.links.contains_key(*p)) {
+                            let _ = changed_tx.send(path.clone());
+                        }
+                        if state.is_parent_noise(event) {
+                            return;
+                        }
+                    }
+                    event_handler.lock().unwrap().handle_event(res);
+                },
+                config,
+            )?
+        };
+        let inner = Arc::new(Mutex::new(inner));
+
+        let resolver = Resolver {
+            inner: Arc::downgrade(&inner),
+            state: state.clone(),
+            event_handler,
+            changed_rx,
+        };
+        thread::Builder::new()
+            .name("notify-rs symlink resolver".to_string())
+            .spawn(move || resolver.run())?;
+
+        Ok(Self { inner, state })
+    }
+
+    fn watch(&mut self, path: &Path, recursive_mode: RecursiveMode) -> Result<()> {
+        let is_link = fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink());
+        let mut inner = self.inner.lock().unwrap();
+        inner.watch(path, recursive_mode)?;
+
+        let parent = match path.parent() {
+            Some(parent) if is_link => parent,
+            _ => {
+                let mut state = self.state.lock().unwrap();
+                state.links.remove(path);
+                state.roots.insert(path.to_path_buf(), recursive_mode);
+                return Ok(());
+            }
+        };
+
+        let watch_parent = {
+            let state = self.state.lock().unwrap();
+            !state.parents.contains(parent) && !state.roots.contains_key(parent)
+        };
+        if watch_parent {
+            if let Err(error) = inner.watch(parent, RecursiveMode::NonRecursive) {
+                let _ = inner.unwatch(path);
+                return Err(error);
+            }
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if watch_parent {
+            state.parents.insert(parent.to_path_buf());
+        }
+        state.roots.insert(path.to_path_buf(), recursive_mode);
+        let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        state.links.insert(path.to_path_buf(), target);
+        Ok(())
+    }
+
+    fn unwatch(&mut self, path: &Path) -> Result<()> {
+        let unwatch_parent = {
+            let mut state = self.state.lock().unwrap();
+            state.roots.remove(path);
+            match path.parent() {
+                Some(parent) if state.links.remove(path).is_some() => {
+                    let shared = state.links.keys().any(|link| link.parent() == Some(parent));
+                    (!shared && state.parents.remove(parent)).then(|| parent.to_path_buf())
+                }
+                _ => None,
+            }
+        };
+
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(parent) = unwatch_parent {
+            let _ = inner.unwatch(&parent);
+        }
+        inner.unwatch(path)
+    }
+
+    fn watched_paths(&self) -> Result<Vec<(PathBuf, RecursiveMode)>> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .roots
+            .iter()
+            .map(|(path, &mode)| (path.clone(), mode))
+            .collect())
+    }
+
+    fn expected_latency(&self) -> Duration {
+        self.inner.lock().unwrap().expected_latency()
+    }
+
+    fn configure(&mut self, option: Config) -> Result<bool> {
+        self.inner.lock().unwrap().configure(option)
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        self.inner.lock().unwrap().pause()
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        self.inner.lock().unwrap().resume()
+    }
+
+    fn resync(&mut self) -> Result<()> {
+        self.inner.lock().unwrap().resync()
+    }
+
+    fn stats(&self) -> crate::WatcherStats {
+        self.inner.lock().unwrap().stats()
+    }
+
+    fn path_supports_events(&self, path: &Path) -> bool {
+        self.inner.lock().unwrap().path_supports_events(path)
+    }
+
+    fn kind() -> crate::WatcherKind {
+        W::kind()
+    }
+}
+
+/// Re-watches symbolic links whose target changed.
+struct Resolver<W> {
+    inner: Weak<Mutex<W>>,
+    state: Arc<Mutex<State>>,
+    event_handler: Arc<Mutex<dyn EventHandler>>,
+    changed_rx: Receiver<PathBuf>,
+}
+
+impl<W: Watcher> Resolver<W> {
+    fn run(self) {
+        // ends once the watcher was dropped
+        while let Ok(link) = self.changed_rx.recv() {
+            if !self.resolve(link) {
+                break;
+            }
+        }
+    }
+
+    /// Watch `link` again if it points somewhere else now. Returns `false` if the watcher was
+    /// dropped.
+    fn resolve(&self, link: PathBuf) -> bool {
+        let Ok(target) = fs::canonicalize(&link) else {
+            // removed or dangling, watched again once it is recreated
+            return true;
+        };
+        let recursive_mode = {
+            let state = self.state.lock().unwrap();
+            match (state.links.get(&link), state.roots.get(&link)) {
+                (Some(current), Some(&mode)) if *current != target => mode,
+                _ => return true,
+            }
+        };
+
+        let Some(inner) = self.inner.upgrade() else {
+            return false;
+        };
+        let rewatched = {
+            let mut inner = inner.lock().unwrap();
+            let _ = inner.unwatch(&link);
+            inner.watch(&link, recursive_mode)
+        };
+        if let Err(error) = rewatched {
+            self.event_handler.lock().unwrap().handle_event(Err(error));
+            return true;
+        }
+
+        log::trace!("{} now points to {}", link.display(), target.display());
+        self.state
+            .lock()
+            .unwrap()
+            .links
+            .insert(link.clone(), target);
+        let event = Event::new(EventKind::Other)
+            .add_path(link)
+            .set_flag(Flag::Rescan);
+        self.event_handler.lock().unwrap().handle_event(Ok(event));
+        true
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::RecommendedWatcher;
+    use std::os::unix::fs::symlink;
+
+    #[test]
+    fn repointed_symlink_is_followed() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path().canonicalize().unwrap();
+        let (first, second) = (dir.join("1"), dir.join("2"));
+        fs::create_dir(&first).unwrap();
+        fs::create_dir(&second).unwrap();
+        let current = dir.join("current");
+        symlink(&first, &current).unwrap();
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut watcher = SymlinkWatcher::<RecommendedWatcher>::new(tx, Config::default()).unwrap();
+        watcher.watch(&current, RecursiveMode::Recursive).unwrap();
+
+        let next = dir.join("next");
+        symlink(&second, &next).unwrap();
+        fs::rename(&next, &current).unwrap();
+
+        let rx = std::iter::from_fn(|| rx.recv_timeout(Duration::from_secs(5)).ok());
+        let mut events = rx.map(|res| res.unwrap());
+        events
+            .find(|event| event.need_rescan() && event.paths == [current.clone()])
+            .unwrap();
+        assert_eq!(
+            watcher.watched_paths().unwrap(),
+            [(current.clone(), RecursiveMode::Recursive)]
+        );
+
+        fs::write(second.join("file"), b"Lorem ipsum").unwrap();
+        events
+            .find(|event| event.paths.iter().any(|path| path.ends_with("file")))
+            .unwrap();
+    }
+}
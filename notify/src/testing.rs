@@ -0,0 +1,179 @@
+//! Helpers for asserting the events a watcher delivers in tests
+//!
+//! Watchers deliver events asynchronously and timing varies between platforms, so tests
+//! typically wait for events with a timeout. The functions in this module receive events from a
+//! [`std::sync::mpsc`] channel, used as the event handler of a watcher, until the expected events
+//! arrived or the timeout elapsed, and panic with the expected and the received events otherwise.
+//!
+//! Events are compared by their kind and paths, other attributes are ignored.
+//!
+//! Requires the `testing` feature.
+//!
+//! ```no_run
+//! # use notify::event::{CreateKind, Event, EventKind};
+//! # use notify::{testing::assert_events, RecursiveMode, Watcher};
+//! # use std::path::Path;
+//! # use std::time::Duration;
+//! let (tx, rx) = std::sync::mpsc::channel();
+//! let mut watcher = notify::recommended_watcher(tx).unwrap();
+//! watcher.watch(Path::new("/tmp/dir"), RecursiveMode::Recursive).unwrap();
+//!
+//! std::fs::write("/tmp/dir/file", b"data").unwrap();
+//!
+//! let created = Event::new(EventKind::Create(CreateKind::File)).add_path("/tmp/dir/file".into());
+//! assert_events(&rx, &[created], Duration::from_secs(5));
+//! ```
+
+use crate::event::{Event, EventKind};
+use crate::Result;
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+/// Receive the events arriving at `rx` within `timeout`.
+///
+/// Waits for the whole `timeout`, unless the channel is disconnected earlier. Panics if an error
+/// is received.
+pub fn recv_events_with_timeout(rx: &Receiver<Result<Event>>, timeout: Duration) -> Vec<Event> {
+    let mut events = Vec::new();
+    recv_until(rx, timeout, &mut events, |_| false);
+    events
+}
+
+/// Assert that `expected` events arrive at `rx` in order within `timeout`.
+///
+/// The events must be the first ones received, in the given order. Returns as soon as they
+/// arrived, without checking for further events.
+pub fn assert_events(rx: &Receiver<Result<Event>>, expected: &[Event], timeout: Duration) {
+    let expected: Vec<_> = expected.iter().map(key).collect();
+    let mut received = Vec::new();
+    let matched = recv_until(rx, timeout, &mut received, |received| {
+        received.len() >= expected.len() || !expected.starts_with(&keys(received))
+    });
+    if !matched || keys(&received) != expected {
+        fail("in order", &expected, &received);
+    }
+}
+
+/// Assert that `expected` events arrive at `rx` in any order within `timeout`.
+///
+/// The events must be the first ones received. Returns as soon as they arrived, without checking
+/// for further events.
+pub fn assert_events_unordered(
+    rx: &Receiver<Result<Event>>,
+    expected: &[Event],
+    timeout: Duration,
+) {
+    let expected: Vec<_> = expected.iter().map(key).collect();
+    let mut received = Vec::new();
+    let matched = recv_until(rx, timeout, &mut received, |received| {
+        received.len() >= expected.len()
+    });
+    if !matched || !is_permutation(&expected, &keys(&received)) {
+        fail("in any order", &expected, &received);
+    }
+}
+
+type Key = (EventKind, Vec<PathBuf>);
+
+fn key(event: &Event) -> Key {
+    (event.kind, event.paths.clone())
+}
+
+fn keys(events: &[Event]) -> Vec<Key> {
+    events.iter().map(key).collect()
+}
+
+/// Whether `received` holds the same keys as `expected`, in any order.
+fn is_permutation(expected: &[Key], received: &[Key]) -> bool {
+    let mut missing = expected.to_vec();
+    for key in received {
+        match missing.iter().position(|expected| expected == key) {
+            Some(index) => {
+                missing.swap_remove(index);
+            }
+            None => return false,
+        }
+    }
+    missing.is_empty()
+}
+
+/// Receive events into `received` until `done` returns `true`, which is then returned, or the
+/// timeout elapsed.
+fn recv_until<P>(
+    rx: &Receiver<Result<Event>>,
+    timeout: Duration,
+    received: &mut Vec<Event>,
+    mut done: P,
+) -> bool
+where
+    P: FnMut(&[Event]) -> bool,
+{
+    let deadline = Instant::now() + timeout;
+    loop {
+        if done(received) {
+            return true;
+        }
+        match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(Ok(event)) => received.push(event),
+            Ok(Err(error)) => {
+                panic!("received an error: {error:?}\nreceived before: {received:#?}")
+            }
+            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => return false,
+        }
+    }
+}
+
+fn fail(order: &str, expected: &[Key], received: &[Event]) -> ! {
+    let mut message = format!("expected events {order}:\n");
+    for (kind, paths) in expected {
+        let _ = writeln!(message, "    {kind:?} {paths:?}");
+    }
+    message.push_str("received:\n");
+    for event in received {
+        let _ = writeln!(message, "    {:?} {:?}", event.kind, event.paths);
+    }
+    panic!("{message}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{CreateKind, RemoveKind};
+    use std::sync::mpsc;
+
+    fn event(kind: EventKind, path: &str) -> Event {
+        Event::new(kind).add_path(PathBuf::from(path))
+    }
+
+    fn channel(events: &[Event]) -> Receiver<Result<Event>> {
+        let (tx, rx) = mpsc::channel();
+        for event in events {
+            tx.send(Ok(event.clone().set_info("ignored"))).unwrap();
+        }
+        rx
+    }
+
+    #[test]
+    fn expected_events_are_matched() {
+        let create = event(EventKind::Create(CreateKind::File), "/a");
+        let remove = event(EventKind::Remove(RemoveKind::File), "/a");
+        let timeout = Duration::from_millis(100);
+
+        let rx = channel(&[create.clone(), remove.clone()]);
+        assert_events(&rx, &[create.clone(), remove.clone()], timeout);
+
+        let rx = channel(&[remove.clone(), create.clone()]);
+        assert_events_unordered(&rx, &[create, remove], timeout);
+    }
+
+    #[test]
+    #[should_panic(expected = "received:\n    Remove(File)")]
+    fn unexpected_events_fail() {
+        let create = event(EventKind::Create(CreateKind::File), "/a");
+        let remove = event(EventKind::Remove(RemoveKind::File), "/a");
+        let rx = channel(&[remove.clone(), create.clone()]);
+        assert_events(&rx, &[create, remove], Duration::from_millis(100));
+    }
+}
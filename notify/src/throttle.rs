@@ -0,0 +1,147 @@
+//! Event handler limiting the rate of event delivery
+//!
+//! Unlike a debouncer, which waits until no more events arrive, the [`Throttle`] delivers the
+//! events collected so far at a fixed rate, even while events keep coming in. This suits
+//! consumers like user interfaces that only need to refresh a few times per second.
+
+use crate::timed::{Hold, Timed};
+use crate::{Event, EventBatchHandler, EventHandler, Result};
+use std::collections::HashSet;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// [`EventHandler`] forwarding events as batches, at most once per interval.
+///
+/// Events that are equal to an event already in the current batch are dropped, errors are always
+/// kept. A batch is delivered as soon as the interval since the previous batch has elapsed, so
+/// the first event after a quiet period is delivered right away. Remaining events are delivered
+/// when the handler is dropped.
+///
+/// The batches are delivered from a background thread.
+///
+/// ```no_run
+/// # use notify::{RecursiveMode, Result, Throttle, Watcher};
+/// # use std::path::Path;
+/// # use std::time::Duration;
+/// # fn main() -> Result<()> {
+/// let handler = Throttle::new(
+///     |events: Vec<Result<notify::Event>>| println!("{} events", events.len()),
+///     Duration::from_millis(250),
+/// )?;
+/// let mut watcher = notify::recommended_watcher(handler)?;
+/// watcher.watch(Path::new("."), RecursiveMode::Recursive)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Throttle {
+    timed: Timed,
+    interval: Duration,
+}
+
+impl Throttle {
+    /// Create a new [`Throttle`] delivering batches to `batch_handler` at most once per
+    /// `interval`.
+    pub fn new<F: EventBatchHandler>(batch_handler: F, interval: Duration) -> Result<Self> {
+        let batch = Batch {
+            interval,
+            ..Batch::default()
+        };
+        let timed = Timed::start("notify-rs throttle loop", batch, batch_handler)?;
+        Ok(Self { timed, interval })
+    }
+}
+
+impl fmt::Debug for Throttle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Throttle")
+            .field("interval", &self.interval)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EventHandler for Throttle {
+    fn handle_event(&mut self, event: Result<Event>) {
+        self.timed.send(event);
+    }
+}
+
+/// Events collected since the last delivery.
+#[derive(Default)]
+struct Batch {
+    events: Vec<Result<Event>>,
+    seen: HashSet<Event>,
+    interval: Duration,
+    /// When the next batch may be delivered, right away if `None`
+    next_flush: Option<Instant>,
+}
+
+impl Hold for Batch {
+    fn push(&mut self, event: Result<Event>) -> Vec<Result<Event>> {
+        if let Ok(event) = &event {
+            if !self.seen.insert(event.clone()) {
+                return Vec::new();
+            }
+        }
+        self.events.push(event);
+        Vec::new()
+    }
+
+    fn due(&self) -> Option<Instant> {
+        match self.events.is_empty() {
+            true => None,
+            false => Some(self.next_flush.unwrap_or_else(Instant::now)),
+        }
+    }
+
+    fn take_due(&mut self, now: Instant) -> Vec<Result<Event>> {
+        if self.events.is_empty() || self.next_flush.is_some_and(|next| now < next) {
+            return Vec::new();
+        }
+        self.next_flush = Some(now + self.interval);
+        self.take_all()
+    }
+
+    fn take_all(&mut self) -> Vec<Result<Event>> {
+        self.seen.clear();
+        std::mem::take(&mut self.events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{DataChange, EventKind, ModifyKind};
+    use std::path::PathBuf;
+    use std::thread;
+
+    fn modify(path: &str) -> Result<Event> {
+        Ok(
+            Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Any)))
+                .add_path(PathBuf::from(path)),
+        )
+    }
+
+    #[test]
+    fn flood_is_delivered_in_deduplicated_batches() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut throttle = Throttle::new(tx, Duration::from_millis(100)).unwrap();
+
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_millis(450) {
+            throttle.handle_event(modify("/a"));
+            throttle.handle_event(modify("/b"));
+            thread::sleep(Duration::from_millis(1));
+        }
+        drop(throttle);
+
+        let batches: Vec<_> = rx.iter().collect();
+        assert!(
+            (3..=7).contains(&batches.len()),
+            "{} batches",
+            batches.len()
+        );
+        for batch in &batches {
+            assert!(batch.len() <= 2, "{batch:?}");
+        }
+    }
+}
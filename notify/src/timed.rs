@@ -0,0 +1,114 @@
+//! Background thread of the event handlers holding back events for a while, like [`Settle`],
+//! [`Throttle`], [`FileWritten`] and [`RemoveGrace`]
+//!
+//! [`Settle`]: crate::Settle
+//! [`Throttle`]: crate::Throttle
+//! [`FileWritten`]: crate::FileWritten
+//! [`RemoveGrace`]: crate::RemoveGrace
+
+use crate::pause::Flush;
+use crate::{bounded, unbounded, BoundSender, Event, EventBatchHandler, Receiver, Result, Sender};
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread;
+use std::time::Instant;
+
+/// The events held back by a [`Timed`] handler.
+pub(crate) trait Hold: Send + 'static {
+    /// Takes `event`, returns the events to be delivered right away.
+    fn push(&mut self, event: Result<Event>) -> Vec<Result<Event>>;
+
+    /// Returns when the next held back events are due, if there are any.
+    fn due(&self) -> Option<Instant>;
+
+    /// Returns the held back events that are due at `now`.
+    fn take_due(&mut self, now: Instant) -> Vec<Result<Event>>;
+
+    /// Returns all held back events.
+    fn take_all(&mut self) -> Vec<Result<Event>>;
+}
+
+/// Messages passed to the background thread.
+#[cfg_attr(
+    not(any(target_os = "linux", target_os = "android", target_os = "windows")),
+    allow(dead_code)
+)]
+enum Message {
+    Event(Result<Event>),
+    /// Deliver the held back events, then notify the sender
+    Flush(BoundSender<()>),
+}
+
+/// Sending side of a background thread passing the events through a [`Hold`].
+///
+/// Held back events are delivered when the last `Timed` is dropped.
+pub(crate) struct Timed {
+    tx: Sender<Message>,
+}
+
+impl Timed {
+    /// Starts the thread named `name`, delivering the events released by `hold` to
+    /// `batch_handler`. Batches are never empty.
+    pub(crate) fn start<H: Hold, F: EventBatchHandler>(
+        name: &str,
+        hold: H,
+        batch_handler: F,
+    ) -> Result<Self> {
+        let (tx, rx) = unbounded();
+        thread::Builder::new()
+            .name(name.to_string())
+            .spawn(move || run(rx, hold, batch_handler))?;
+        Ok(Self { tx })
+    }
+
+    pub(crate) fn send(&self, event: Result<Event>) {
+        let _ = self.tx.send(Message::Event(event));
+    }
+
+    /// Returns a [`Flush`] delivering the held back events right away.
+    #[cfg_attr(
+        not(any(target_os = "linux", target_os = "android", target_os = "windows")),
+        allow(dead_code)
+    )]
+    pub(crate) fn flush(&self) -> Flush {
+        let tx = self.tx.clone();
+        Box::new(move || {
+            let (done_tx, done_rx) = bounded(1);
+            // the thread is gone if the event handler panicked
+            if tx.send(Message::Flush(done_tx)).is_ok() {
+                let _ = done_rx.recv();
+            }
+        })
+    }
+}
+
+fn run<H: Hold, F: EventBatchHandler>(rx: Receiver<Message>, mut hold: H, mut batch_handler: F) {
+    let mut deliver = |events: Vec<Result<Event>>| {
+        if !events.is_empty() {
+            batch_handler.handle_events(events);
+        }
+    };
+
+    loop {
+        let res = match hold.due() {
+            Some(due) => rx.recv_timeout(due.saturating_duration_since(Instant::now())),
+            None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+
+        match res {
+            Ok(Message::Event(event)) => deliver(hold.push(event)),
+            Ok(Message::Flush(done_tx)) => {
+                deliver(hold.take_all());
+                let _ = done_tx.send(());
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            // the handler was dropped
+            Err(RecvTimeoutError::Disconnected) => {
+                deliver(hold.take_all());
+                break;
+            }
+        }
+
+        // checked after every message, as the channel may never run empty under load
+        deliver(hold.take_due(Instant::now()));
+    }
+}
@@ -0,0 +1,593 @@
+//! Best-effort detection of files moved to the trash, see [`Config::with_trash_detection`]
+//!
+//! [`Config::with_trash_detection`]: crate::Config::with_trash_detection
+
+use crate::event::{Event, EventKind, ModifyKind, RemoveKind, RenameMode};
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Only files trashed this recently can belong to a removal that is being reported.
+const RECENT: Duration = Duration::from_secs(60);
+
+/// A listing of the `.trashinfo` files is used this long before it's read again.
+#[cfg(unix)]
+const LISTING_TTL: Duration = Duration::from_secs(1);
+
+/// Recognizes events of files that were moved to the trash.
+#[derive(Debug)]
+pub(crate) struct TrashDetector {
+    /// Directories holding the trashed files
+    dirs: Vec<PathBuf>,
+    /// The `.trashinfo` files of the freedesktop trashes, by the directory holding the files
+    infos: HashMap<PathBuf, TrashInfos>,
+    recycle_bin: RecycleBin,
+}
+
+impl TrashDetector {
+    pub(crate) fn new() -> Self {
+        Self::with_dirs(trash_dirs())
+    }
+
+    fn with_dirs(dirs: Vec<PathBuf>) -> Self {
+        Self {
+            dirs,
+            infos: HashMap::new(),
+            recycle_bin: RecycleBin::default(),
+        }
+    }
+
+    /// Turns the event into a `Remove(Other)` event with the info `trash` if it reports a file
+    /// that was moved to the trash.
+    ///
+    /// A rename is recognized by its destination. A rename without destination, or a removal, is
+    /// recognized by finding a file trashed just now from the same path.
+    pub(crate) fn classify(&mut self, mut event: Event) -> Event {
+        let trashed = match (event.kind, event.paths.as_slice()) {
+            (EventKind::Modify(ModifyKind::Name(RenameMode::Both)), [from, to]) => {
+                self.is_in_trash(to).then(|| from.clone())
+            }
+            (
+                EventKind::Modify(ModifyKind::Name(RenameMode::From | RenameMode::Any))
+                | EventKind::Remove(_),
+                [path],
+            ) => (path.symlink_metadata().is_err() && self.was_trashed(path)).then(|| path.clone()),
+            _ => None,
+        };
+
+        if let Some(path) = trashed {
+            event.kind = EventKind::Remove(RemoveKind::Other);
+            event.paths = vec![path];
+            event = event.set_info("trash");
+        }
+        event
+    }
+
+    fn is_in_trash(&self, path: &Path) -> bool {
+        self.dirs.iter().any(|dir| path.starts_with(dir)) || is_in_recycle_bin(path)
+    }
+
+    /// Whether a file that vanished from `path` was just moved to the trash.
+    fn was_trashed(&mut self, path: &Path) -> bool {
+        let Some(name) = path.file_name() else {
+            return false;
+        };
+        self.dirs
+            .iter()
+            .any(|dir| was_trashed_to(&mut self.infos, dir, name, path))
+            || self.recycle_bin.was_recycled(path)
+    }
+}
+
+/// Whether the file that vanished from `path` was just moved to the trash directory `dir`.
+#[cfg(unix)]
+fn was_trashed_to(
+    infos: &mut HashMap<PathBuf, TrashInfos>,
+    dir: &Path,
+    name: &OsStr,
+    path: &Path,
+) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    // the freedesktop trash records the original path and the deletion date next to the files
+    if let Some(info_dir) = dir.parent().map(|trash| trash.join("info")) {
+        if let Ok(changed) = info_dir.metadata().and_then(|metadata| metadata.modified()) {
+            let infos = infos.entry(dir.to_path_buf()).or_default();
+            return infos.was_trashed(&info_dir, changed, name, path);
+        }
+    }
+
+    // the macOS trash only keeps the file, moving it there changes its status
+    dir.join(name)
+        .symlink_metadata()
+        .is_ok_and(|metadata| is_recent_secs(metadata.ctime()))
+}
+
+#[cfg(not(unix))]
+fn was_trashed_to(
+    _infos: &mut HashMap<PathBuf, TrashInfos>,
+    _dir: &Path,
+    _name: &OsStr,
+    _path: &Path,
+) -> bool {
+    false
+}
+
+/// The `.trashinfo` files of a freedesktop trash, see [`TrashInfos::was_trashed`]
+#[cfg_attr(not(unix), allow(dead_code))]
+#[derive(Debug, Default)]
+struct TrashInfos {
+    /// When the listing was read, and the modification time of the directory at that point
+    listed: Option<(Instant, SystemTime)>,
+    /// The file names, with the original path and deletion date recorded in the file once read
+    files: HashMap<OsString, Option<TrashInfo>>,
+}
+
+#[cfg(unix)]
+impl TrashInfos {
+    /// Whether the file that vanished from `path` was just moved to the trash whose `.trashinfo`
+    /// files are in `dir`, which was last modified at `changed`.
+    ///
+    /// The `.trashinfo` file is looked up by `name` first. Only if there's none, or it's about
+    /// another file, the listing of `dir` is searched for files the trash renamed because the
+    /// name was taken. The listing is read again at most every [`LISTING_TTL`], once `dir`
+    /// changed, and only new files are read. This keeps the work for each event independent of
+    /// the size of the trash, apart from the reads of the listing.
+    fn was_trashed(&mut self, dir: &Path, changed: SystemTime, name: &OsStr, path: &Path) -> bool {
+        use std::os::unix::ffi::OsStrExt;
+
+        let mut file_name = name.to_os_string();
+        file_name.push(".trashinfo");
+        if TrashInfo::read(&dir.join(&file_name)).is_some_and(|info| info.matches(path)) {
+            return true;
+        }
+
+        self.list(dir, changed);
+        self.files
+            .iter_mut()
+            .filter(|(file_name, _)| file_name.as_bytes().starts_with(name.as_bytes()))
+            .any(|(file_name, info)| {
+                // the file may have been listed before it was written
+                if info.is_none() {
+                    *info = TrashInfo::read(&dir.join(file_name));
+                }
+                info.as_ref().is_some_and(|info| info.matches(path))
+            })
+    }
+
+    /// Reads the listing of `dir` again if it's due, keeping the files read before.
+    fn list(&mut self, dir: &Path, changed: SystemTime) {
+        use std::os::unix::ffi::OsStrExt;
+
+        if self.listed.is_some_and(|(listed, listed_changed)| {
+            listed_changed == changed || listed.elapsed() < LISTING_TTL
+        }) {
+            return;
+        }
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        let mut files = HashMap::new();
+        for entry in entries.flatten() {
+            let file_name = entry.file_name();
+            if file_name.as_bytes().ends_with(b".trashinfo") {
+                let info = self.files.remove(&file_name).flatten();
+                files.insert(file_name, info);
+            }
+        }
+        self.files = files;
+        self.listed = Some((Instant::now(), changed));
+    }
+}
+
+/// The contents of a `.trashinfo` file
+#[cfg_attr(not(unix), allow(dead_code))]
+#[derive(Debug)]
+struct TrashInfo {
+    /// The original path of the file
+    original: Vec<u8>,
+    /// When the file was trashed, in seconds since the Unix epoch
+    deleted: i64,
+}
+
+#[cfg(unix)]
+impl TrashInfo {
+    /// Reads the `.trashinfo` file at `path`, if it's complete.
+    fn read(path: &Path) -> Option<Self> {
+        let info = std::fs::read_to_string(path).ok()?;
+        let mut original = None;
+        let mut deleted = None;
+        for line in info.lines() {
+            if let Some(value) = line.strip_prefix("Path=") {
+                original = Some(percent_decode(value));
+            } else if let Some(value) = line.strip_prefix("DeletionDate=") {
+                deleted = parse_local_time(value);
+            }
+        }
+        Some(Self {
+            original: original?,
+            deleted: deleted?,
+        })
+    }
+
+    /// Whether this records a file trashed just now from `path`.
+    fn matches(&self, path: &Path) -> bool {
+        use std::os::unix::ffi::OsStrExt;
+
+        self.original == path.as_os_str().as_bytes() && is_recent_secs(self.deleted)
+    }
+}
+
+/// Decodes the `%XX` escapes of a URL path.
+#[cfg(unix)]
+fn percent_decode(value: &str) -> Vec<u8> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    decoded
+}
+
+/// Parses a local time of the form `YYYY-MM-DDThh:mm:ss` into seconds since the Unix epoch.
+#[cfg(unix)]
+fn parse_local_time(value: &str) -> Option<i64> {
+    let (date, time) = value.trim().split_once('T')?;
+    let mut date = date.splitn(3, '-').map(|part| part.parse::<i32>().ok());
+    let mut time = time.splitn(3, ':').map(|part| part.parse::<i32>().ok());
+    // SAFETY: all fields of `tm` are integers or pointers, for which zero is valid
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    tm.tm_year = date.next()?? - 1900;
+    tm.tm_mon = date.next()?? - 1;
+    tm.tm_mday = date.next()??;
+    tm.tm_hour = time.next()??;
+    tm.tm_min = time.next()??;
+    tm.tm_sec = time.next()??;
+    // let `mktime` tell whether daylight saving time applies
+    tm.tm_isdst = -1;
+    // SAFETY: `tm` is a valid, initialized `struct tm`
+    let secs = unsafe { libc::mktime(&mut tm) };
+    (secs != -1).then_some(secs as i64)
+}
+
+/// Whether `secs` since the Unix epoch lie less than [`RECENT`] in the past.
+#[cfg(unix)]
+fn is_recent_secs(secs: i64) -> bool {
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs() as i64);
+    now.saturating_sub(secs) < RECENT.as_secs() as i64
+}
+
+/// The trash directories of the current user.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn trash_dirs() -> Vec<PathBuf> {
+    // https://specifications.freedesktop.org/trash-spec/trashspec-latest.html
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")));
+    data_home
+        .map(|dir| dir.join("Trash/files"))
+        .into_iter()
+        .collect()
+}
+
+/// The trash directories of the current user.
+#[cfg(target_os = "macos")]
+fn trash_dirs() -> Vec<PathBuf> {
+    std::env::var_os("HOME")
+        .map(|home| Path::new(&home).join(".Trash"))
+        .into_iter()
+        .collect()
+}
+
+/// The trash directories of the current user, the recycle bin is handled separately.
+#[cfg(not(unix))]
+fn trash_dirs() -> Vec<PathBuf> {
+    Vec::new()
+}
+
+/// Whether `path` is inside a `$Recycle.Bin` directory.
+fn is_in_recycle_bin(path: &Path) -> bool {
+    cfg!(windows)
+        && path
+            .components()
+            .any(|component| component.as_os_str().eq_ignore_ascii_case("$Recycle.Bin"))
+}
+
+/// The recycle bins of the drives, see [`RecycleBin::was_recycled`]
+#[derive(Debug, Default)]
+struct RecycleBin {
+    /// The files recycled by each user, by the directory holding them
+    #[cfg(windows)]
+    users: std::collections::HashMap<PathBuf, RecycledFiles>,
+}
+
+/// The recycled files of a user directory, as of the time the directory was last changed
+#[cfg(windows)]
+#[derive(Debug)]
+struct RecycledFiles {
+    changed: SystemTime,
+    /// Original path and time of recycling of each file, by the name of its `$I` file
+    files: HashMap<OsString, (PathBuf, SystemTime)>,
+}
+
+impl RecycleBin {
+    /// Whether a file that vanished from `path` was just moved to the recycle bin of its drive.
+    ///
+    /// The recycle bin renames the file, but records its original path in an `$I` file next to
+    /// it. The `$I` files of a user directory are only listed again once the directory changed,
+    /// and only new files are read.
+    #[cfg(windows)]
+    fn was_recycled(&mut self, path: &Path) -> bool {
+        use std::path::Component;
+
+        // the root of the drive, e.g. `C:\`
+        let Some(root) = path.ancestors().last().filter(|root| {
+            root.has_root() && matches!(root.components().next(), Some(Component::Prefix(_)))
+        }) else {
+            return false;
+        };
+        let Ok(users) = std::fs::read_dir(root.join("$Recycle.Bin")) else {
+            return false;
+        };
+
+        let mut recycled = false;
+        for user in users.flatten() {
+            let Ok(changed) = user.metadata().and_then(|metadata| metadata.modified()) else {
+                continue;
+            };
+            let dir = user.path();
+            let files = self
+                .users
+                .entry(dir)
+                .or_insert_with_key(|dir| RecycledFiles {
+                    changed,
+                    files: read_recycled_files(dir, HashMap::new()),
+                });
+            if files.changed != changed {
+                files.changed = changed;
+                files.files = read_recycled_files(&user.path(), std::mem::take(&mut files.files));
+            }
+            recycled |= files.files.values().any(|(original, recycled)| {
+                original == path
+                    && SystemTime::now()
+                        .duration_since(*recycled)
+                        .map_or(true, |age| age < RECENT)
+            });
+        }
+        recycled
+    }
+
+    #[cfg(not(windows))]
+    fn was_recycled(&mut self, _path: &Path) -> bool {
+        false
+    }
+}
+
+/// The original paths and times of recycling of the files recycled to `dir`, taking the files
+/// still there from `known` instead of reading them again.
+#[cfg(windows)]
+fn read_recycled_files(
+    dir: &Path,
+    mut known: HashMap<OsString, (PathBuf, SystemTime)>,
+) -> HashMap<OsString, (PathBuf, SystemTime)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return HashMap::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("$I"))
+        .filter_map(|entry| {
+            let file_name = entry.file_name();
+            if let Some(file) = known.remove(&file_name) {
+                return Some((file_name, file));
+            }
+            let recycled = entry.metadata().and_then(|metadata| metadata.modified());
+            let original = recycled_path(&std::fs::read(entry.path()).ok()?)?;
+            Some((file_name, (PathBuf::from(original), recycled.ok()?)))
+        })
+        .collect()
+}
+
+/// The original path recorded in the `$I` file of a recycled file.
+#[cfg_attr(not(any(windows, test)), allow(dead_code))]
+fn recycled_path(data: &[u8]) -> Option<String> {
+    // version, file size and deletion time precede the path
+    let version = u64::from_le_bytes(data.get(..8)?.try_into().ok()?);
+    let path = match version {
+        // fixed size buffer of MAX_PATH characters
+        1 => data.get(24..24 + 520)?,
+        // length in characters, including the terminating null
+        2 => {
+            let len = u32::from_le_bytes(data.get(24..28)?.try_into().ok()?) as usize;
+            data.get(28..28 + len * 2)?
+        }
+        _ => return None,
+    };
+    let wide: Vec<u16> = path
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .take_while(|&c| c != 0)
+        .collect();
+    String::from_utf16(&wide).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(unix)]
+    use crate::event::{CreateKind, DataChange};
+
+    #[cfg(unix)]
+    fn event(kind: EventKind, paths: &[&Path]) -> Event {
+        paths.iter().fold(Event::new(kind), |event, path| {
+            event.add_path(path.to_path_buf())
+        })
+    }
+
+    /// Formats `time` as the local time of a `.trashinfo` file.
+    #[cfg(unix)]
+    fn local_time(time: SystemTime) -> String {
+        let secs = time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as libc::time_t;
+        // SAFETY: all fields of `tm` are integers or pointers, for which zero is valid
+        let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+        // SAFETY: both pointers are valid
+        assert!(!unsafe { libc::localtime_r(&secs, &mut tm) }.is_null());
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            tm.tm_year + 1900,
+            tm.tm_mon + 1,
+            tm.tm_mday,
+            tm.tm_hour,
+            tm.tm_min,
+            tm.tm_sec
+        )
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn moves_to_the_trash_are_recognized() {
+        let dir = tempfile::tempdir().unwrap();
+        let trash = dir.path().join("Trash/files");
+        let info = dir.path().join("Trash/info");
+        let watched = dir.path().join("watched");
+        std::fs::create_dir_all(&trash).unwrap();
+        std::fs::create_dir_all(&info).unwrap();
+        std::fs::create_dir(&watched).unwrap();
+        let mut detector = TrashDetector::with_dirs(vec![trash.clone()]);
+
+        let trash_file = |name: &str, original: &str, deleted: SystemTime| {
+            std::fs::write(trash.join(name), b"").unwrap();
+            std::fs::write(
+                info.join(format!("{name}.trashinfo")),
+                format!(
+                    "[Trash Info]\nPath={original}\nDeletionDate={}\n",
+                    local_time(deleted)
+                ),
+            )
+            .unwrap();
+        };
+
+        let trashed = watched.join("trashed file");
+        let deleted = watched.join("deleted");
+        let elsewhere = watched.join("elsewhere");
+        let stale = watched.join("stale");
+        let renamed = watched.join("renamed");
+        let now = SystemTime::now();
+        trash_file(
+            "trashed file",
+            &format!("{}/trashed%20file", watched.display()),
+            now,
+        );
+        // a file of the same name trashed from another directory
+        trash_file("elsewhere", "/elsewhere", now);
+        // renamed by the trash, as the name is taken
+        trash_file("renamed", "/renamed", now);
+        trash_file("renamed.2", &renamed.display().to_string(), now);
+        // a file of the same name trashed a while ago
+        trash_file(
+            "stale",
+            &stale.display().to_string(),
+            now - Duration::from_secs(3600),
+        );
+
+        let rename_from = EventKind::Modify(ModifyKind::Name(RenameMode::From));
+        for (event, expected) in [
+            (event(rename_from, &[&trashed]), Some(&trashed)),
+            (
+                event(EventKind::Remove(RemoveKind::File), &[&trashed]),
+                Some(&trashed),
+            ),
+            (
+                event(
+                    EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+                    &[&deleted, &trash.join("deleted")],
+                ),
+                Some(&deleted),
+            ),
+            (event(rename_from, &[&deleted]), None),
+            (
+                event(EventKind::Remove(RemoveKind::File), &[&deleted]),
+                None,
+            ),
+            (
+                event(EventKind::Remove(RemoveKind::File), &[&elsewhere]),
+                None,
+            ),
+            (event(EventKind::Remove(RemoveKind::File), &[&stale]), None),
+            (
+                event(EventKind::Remove(RemoveKind::File), &[&renamed]),
+                Some(&renamed),
+            ),
+            (
+                event(EventKind::Create(CreateKind::File), &[&trashed]),
+                None,
+            ),
+            (
+                event(
+                    EventKind::Modify(ModifyKind::Data(DataChange::Any)),
+                    &[&trashed],
+                ),
+                None,
+            ),
+        ] {
+            let original = event.clone();
+            let classified = detector.classify(event);
+            match expected {
+                Some(path) => {
+                    assert_eq!(classified.kind, EventKind::Remove(RemoveKind::Other));
+                    assert_eq!(classified.info(), Some("trash"));
+                    assert_eq!(classified.paths, vec![path.clone()]);
+                }
+                None => assert_eq!(classified, original),
+            }
+        }
+    }
+
+    #[test]
+    fn recycled_paths_are_read() {
+        let path: Vec<u16> = "C:\\Users\\me\\file.txt".encode_utf16().collect();
+        let mut v2 = Vec::new();
+        v2.extend_from_slice(&2u64.to_le_bytes());
+        v2.extend_from_slice(&[0; 16]);
+        v2.extend_from_slice(&(path.len() as u32 + 1).to_le_bytes());
+        v2.extend(path.iter().chain(&[0]).flat_map(|c| c.to_le_bytes()));
+        assert_eq!(
+            recycled_path(&v2).as_deref(),
+            Some("C:\\Users\\me\\file.txt")
+        );
+
+        let mut v1 = Vec::new();
+        v1.extend_from_slice(&1u64.to_le_bytes());
+        v1.extend_from_slice(&[0; 16]);
+        v1.extend(path.iter().flat_map(|c| c.to_le_bytes()));
+        v1.resize(24 + 520, 0);
+        assert_eq!(
+            recycled_path(&v1).as_deref(),
+            Some("C:\\Users\\me\\file.txt")
+        );
+
+        assert_eq!(recycled_path(&[0; 4]), None);
+    }
+}
@@ -0,0 +1,75 @@
+//! Configuration of the directory traversal when setting up recursive watches
+
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+type SkipPredicate = Arc<dyn Fn(&Path) -> bool + Send + Sync>;
+
+/// Controls which directories get a watch when a path is watched recursively.
+///
+/// Directories that are skipped or too deep are not watched at all, which saves watches on
+/// backends with a limit on the number of watches. This applies to the initial traversal as well
+/// as to directories created later on. Symbolic links are followed according to
+/// [`Config::with_follow_symlinks`](crate::Config::with_follow_symlinks).
+///
+/// Currently used by the [INotifyWatcher](crate::INotifyWatcher) backend, see
+/// `INotifyWatcher::with_traversal`.
+///
+/// ```
+/// # use notify::Traversal;
+/// let traversal = Traversal::new()
+///     .with_max_depth(3)
+///     .with_skip(|path| path.ends_with("target") || path.ends_with(".git"));
+/// ```
+#[derive(Clone, Default)]
+pub struct Traversal {
+    max_depth: Option<usize>,
+    skip: Option<SkipPredicate>,
+}
+
+impl Traversal {
+    /// Creates a traversal that visits all directories.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only watch directories up to `max_depth` levels below the watched path.
+    ///
+    /// A depth of 0 only watches the path itself.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Don't watch directories for which `skip` returns `true`, nor anything below them.
+    ///
+    /// The watched path itself is never skipped.
+    pub fn with_skip<F>(mut self, skip: F) -> Self
+    where
+        F: Fn(&Path) -> bool + Send + Sync + 'static,
+    {
+        self.skip = Some(Arc::new(skip));
+        self
+    }
+
+    /// Returns current setting
+    pub fn max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    /// Whether the directory at `path` is skipped.
+    #[cfg_attr(not(any(target_os = "linux", target_os = "android")), allow(dead_code))]
+    pub(crate) fn skips(&self, path: &Path) -> bool {
+        self.skip.as_ref().is_some_and(|skip| skip(path))
+    }
+}
+
+impl fmt::Debug for Traversal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Traversal")
+            .field("max_depth", &self.max_depth)
+            .field("skip", &self.skip.as_ref().map(|_| "<predicate>"))
+            .finish()
+    }
+}
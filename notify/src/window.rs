@@ -0,0 +1,204 @@
+//! Event handler collecting events into time windows
+//!
+//! The [`Windowed`] handler delivers the events of a short time window as one batch, cutting the
+//! batch early once it reaches a size cap. Unlike a debouncer it doesn't wait for a quiet period
+//! and unlike the [`Throttle`](crate::Throttle) it doesn't drop duplicate events, which bounds
+//! the latency of every event. This suits async pipelines, where a few large messages are
+//! cheaper than many small ones.
+
+use crate::timed::{Hold, Timed};
+use crate::{Event, EventBatchHandler, EventHandler, Result};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// [`EventHandler`] forwarding the events of each time window as one batch.
+///
+/// A window starts with the first event after the previous batch. Its events are delivered once
+/// `window` has elapsed, or as soon as `max_batch_size` events have been collected. Events are
+/// delivered unchanged and in order, and batches are never empty. Remaining events are delivered
+/// when the handler is dropped.
+///
+/// The batches are delivered from a background thread. With the `tokio` feature, they can be
+/// sent to a `tokio::sync::mpsc::Sender<Vec<Result<Event>>>` or `UnboundedSender`, to be
+/// received by an async task.
+///
+/// ```no_run
+/// # use notify::{RecursiveMode, Result, Watcher, Windowed};
+/// # use std::path::Path;
+/// # use std::time::Duration;
+/// # fn main() -> Result<()> {
+/// let (tx, rx) = std::sync::mpsc::channel();
+/// let handler = Windowed::new(tx, Duration::from_millis(50), 1024)?;
+/// let mut watcher = notify::recommended_watcher(handler)?;
+/// watcher.watch(Path::new("."), RecursiveMode::Recursive)?;
+///
+/// for batch in rx {
+///     println!("{} events", batch.len());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Windowed {
+    timed: Timed,
+    window: Duration,
+    max_batch_size: usize,
+}
+
+impl Windowed {
+    /// Create a new [`Windowed`] delivering the events of each `window` to `batch_handler`, in
+    /// batches of at most `max_batch_size` events.
+    ///
+    /// A `max_batch_size` of 0 is treated as 1.
+    pub fn new<F: EventBatchHandler>(
+        batch_handler: F,
+        window: Duration,
+        max_batch_size: usize,
+    ) -> Result<Self> {
+        let max_batch_size = max_batch_size.max(1);
+        let batch = Batch {
+            events: Vec::new(),
+            window,
+            max_batch_size,
+            window_end: Instant::now(),
+        };
+        let timed = Timed::start("notify-rs window loop", batch, batch_handler)?;
+        Ok(Self {
+            timed,
+            window,
+            max_batch_size,
+        })
+    }
+}
+
+impl fmt::Debug for Windowed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Windowed")
+            .field("window", &self.window)
+            .field("max_batch_size", &self.max_batch_size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EventHandler for Windowed {
+    fn handle_event(&mut self, event: Result<Event>) {
+        self.timed.send(event);
+    }
+}
+
+/// Events of the current window.
+struct Batch {
+    events: Vec<Result<Event>>,
+    window: Duration,
+    max_batch_size: usize,
+    /// When the current window ends, if there are events
+    window_end: Instant,
+}
+
+impl Hold for Batch {
+    fn push(&mut self, event: Result<Event>) -> Vec<Result<Event>> {
+        if self.events.is_empty() {
+            self.window_end = Instant::now() + self.window;
+        }
+        self.events.push(event);
+        if self.events.len() < self.max_batch_size {
+            return Vec::new();
+        }
+        self.take_all()
+    }
+
+    fn due(&self) -> Option<Instant> {
+        (!self.events.is_empty()).then_some(self.window_end)
+    }
+
+    fn take_due(&mut self, now: Instant) -> Vec<Result<Event>> {
+        if now < self.window_end {
+            return Vec::new();
+        }
+        self.take_all()
+    }
+
+    fn take_all(&mut self) -> Vec<Result<Event>> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{CreateKind, EventKind};
+    use std::path::PathBuf;
+
+    fn create(i: usize) -> Result<Event> {
+        Ok(
+            Event::new(EventKind::Create(CreateKind::File))
+                .add_path(PathBuf::from(format!("/{i}"))),
+        )
+    }
+
+    #[test]
+    fn burst_is_delivered_in_capped_batches() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut windowed = Windowed::new(tx, Duration::from_secs(60), 100).unwrap();
+
+        for i in 0..1050 {
+            windowed.handle_event(create(i));
+        }
+        // the full batches are delivered without waiting for the window
+        for _ in 0..10 {
+            let batch = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+            assert_eq!(batch.len(), 100);
+        }
+        drop(windowed);
+
+        let rest = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(rest.len(), 50);
+        assert_eq!(rest[49].as_ref().unwrap().paths, [PathBuf::from("/1049")]);
+        assert!(rx.recv().is_err());
+    }
+
+    #[test]
+    fn duplicate_events_are_kept() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut windowed = Windowed::new(tx, Duration::from_secs(60), 3).unwrap();
+
+        for _ in 0..3 {
+            windowed.handle_event(create(0));
+        }
+        let batch = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(batch.len(), 3);
+    }
+
+    #[test]
+    fn window_bounds_the_latency() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut windowed = Windowed::new(tx, Duration::from_millis(50), 100).unwrap();
+
+        let start = Instant::now();
+        windowed.handle_event(create(0));
+        windowed.handle_event(create(1));
+        let batch = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(batch.len(), 2);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn batches_are_received_by_an_async_task() {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let mut windowed = Windowed::new(tx, Duration::from_millis(50), 100).unwrap();
+
+        for i in 0..250 {
+            windowed.handle_event(create(i));
+        }
+        drop(windowed);
+
+        let sizes = futures::executor::block_on(async move {
+            let mut sizes = Vec::new();
+            while let Some(batch) = rx.recv().await {
+                sizes.push(batch.len());
+            }
+            sizes
+        });
+        assert_eq!(sizes, [100, 100, 50]);
+    }
+}
@@ -5,30 +5,46 @@
 //!
 //! [ref]: https://msdn.microsoft.com/en-us/library/windows/desktop/aa363950(v=vs.85).aspx
 
-use crate::{bounded, unbounded, BoundSender, Config, Receiver, Sender};
+use crate::dispatch::{Dispatcher, Overflow};
+use crate::pause::{Leftovers, PauseGate};
+use crate::stats::Counters;
+use crate::{
+    bounded, create_kind_of, unbounded, watch_invalidated, BoundSender, Config, Lifecycle,
+    Receiver, Sender,
+};
+use crate::{dispatch, resync, settle};
 use crate::{event::*, WatcherKind};
-use crate::{Error, EventHandler, RecursiveMode, Result, Watcher};
+use crate::{
+    AttributeHandler, Error, ErrorKind, EventHandler, EventKindMask, RecursiveMode, Result, Watcher,
+};
 use std::alloc;
 use std::collections::HashMap;
 use std::env;
 use std::ffi::OsString;
+use std::fs;
 use std::os::raw::c_void;
 use std::os::windows::ffi::{OsStrExt, OsStringExt};
+use std::os::windows::fs::MetadataExt;
 use std::path::{Path, PathBuf};
 use std::ptr;
 use std::slice;
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use windows_sys::Win32::Foundation::{
-    CloseHandle, ERROR_OPERATION_ABORTED, HANDLE, INVALID_HANDLE_VALUE, WAIT_OBJECT_0,
+    CloseHandle, GetLastError, ERROR_ACCESS_DENIED, ERROR_INVALID_FUNCTION,
+    ERROR_INVALID_PARAMETER, ERROR_NOT_SUPPORTED, ERROR_OPERATION_ABORTED, HANDLE,
+    INVALID_HANDLE_VALUE, WAIT_OBJECT_0,
 };
 use windows_sys::Win32::Storage::FileSystem::{
-    CreateFileW, ReadDirectoryChangesW, FILE_ACTION_ADDED, FILE_ACTION_MODIFIED,
-    FILE_ACTION_REMOVED, FILE_ACTION_RENAMED_NEW_NAME, FILE_ACTION_RENAMED_OLD_NAME,
-    FILE_FLAG_BACKUP_SEMANTICS, FILE_FLAG_OVERLAPPED, FILE_LIST_DIRECTORY,
-    FILE_NOTIFY_CHANGE_ATTRIBUTES, FILE_NOTIFY_CHANGE_CREATION, FILE_NOTIFY_CHANGE_DIR_NAME,
-    FILE_NOTIFY_CHANGE_FILE_NAME, FILE_NOTIFY_CHANGE_LAST_WRITE, FILE_NOTIFY_CHANGE_SECURITY,
-    FILE_NOTIFY_CHANGE_SIZE, FILE_NOTIFY_INFORMATION, FILE_SHARE_DELETE, FILE_SHARE_READ,
+    CreateFileW, GetFileInformationByHandle, ReadDirectoryChangesExW, ReadDirectoryChangesW,
+    ReadDirectoryNotifyExtendedInformation, BY_HANDLE_FILE_INFORMATION, FILE_ACTION_ADDED,
+    FILE_ACTION_MODIFIED, FILE_ACTION_REMOVED, FILE_ACTION_RENAMED_NEW_NAME,
+    FILE_ACTION_RENAMED_OLD_NAME, FILE_ATTRIBUTE_REPARSE_POINT, FILE_FLAG_BACKUP_SEMANTICS,
+    FILE_FLAG_OVERLAPPED, FILE_LIST_DIRECTORY, FILE_NOTIFY_CHANGE_ATTRIBUTES,
+    FILE_NOTIFY_CHANGE_CREATION, FILE_NOTIFY_CHANGE_DIR_NAME, FILE_NOTIFY_CHANGE_FILE_NAME,
+    FILE_NOTIFY_CHANGE_LAST_WRITE, FILE_NOTIFY_CHANGE_SECURITY, FILE_NOTIFY_CHANGE_SIZE,
+    FILE_NOTIFY_EXTENDED_INFORMATION, FILE_NOTIFY_INFORMATION, FILE_SHARE_DELETE, FILE_SHARE_READ,
     FILE_SHARE_WRITE, OPEN_EXISTING,
 };
 use windows_sys::Win32::System::Threading::{
@@ -37,15 +53,44 @@ use windows_sys::Win32::System::Threading::{
 use windows_sys::Win32::System::IO::{CancelIo, OVERLAPPED};
 
 const BUF_SIZE: u32 = 16384;
+const MIN_WAIT_GRANULARITY: Duration = Duration::from_millis(1);
 
 #[derive(Clone)]
 struct ReadData {
-    dir: PathBuf,          // directory that is being watched
-    file: Option<PathBuf>, // if a file is being watched, this is its full path
+    dir: PathBuf, // directory that is being watched
+    /// The watched files in `dir`, if files are watched rather than the directory
+    files: Option<WatchedFiles>,
     complete_sem: HANDLE,
     is_recursive: bool,
+    report_self_events: bool,
+    /// See [Config::with_precise_create_kinds]
+    precise_create_kinds: bool,
+    rename_pair_window: Option<Duration>,
+    /// Changes to be reported by the operating system, see [`notify_filter`]
+    notify_filter: u32,
+    pending_rename: PendingRename,
+    /// Serial number of the watched volume, if events are read with the extended reader, see
+    /// [`ReaderKind`]
+    volume_serial: Option<u32>,
+    /// Passes created and removed subdirectories to the server, if a non-recursive watch offers
+    /// them to the [`SubdirFilter`]
+    subdir_tx: Option<Sender<Action>>,
 }
 
+/// The files watched through the shared watch of their directory, see [`SharedDirWatch`]
+type WatchedFiles = Arc<Mutex<Vec<WatchedFile>>>;
+
+#[derive(Clone)]
+struct WatchedFile {
+    /// Full path of the file, the target if a symbolic link is watched
+    file: PathBuf,
+    /// The watched path, reported for the changes of `file`
+    root: PathBuf,
+}
+
+/// Old name of a rename waiting for its new name, shared across the completions of a watch
+type PendingRename = Arc<Mutex<Option<(PathBuf, Instant)>>>;
+
 struct ReadDirectoryRequest {
     event_handler: Arc<Mutex<dyn EventHandler>>,
     buffer: [u8; BUF_SIZE as usize],
@@ -56,8 +101,26 @@ struct ReadDirectoryRequest {
 enum Action {
     Watch(PathBuf, RecursiveMode),
     Unwatch(PathBuf),
-    Stop,
+    UnwatchPrefix(PathBuf, BoundSender<Vec<PathBuf>>),
+    WatchedPaths(BoundSender<Vec<(PathBuf, RecursiveMode)>>),
+    /// With a sender, the sender is notified once the server stopped, see [`Watcher::stop`]
+    Stop(Option<BoundSender<()>>),
     Configure(Config, BoundSender<Result<bool>>),
+    /// A directory was created in a directory watched non-recursively, see [`SubdirFilter`]
+    WatchSubdir(PathBuf),
+    /// A path was removed from a directory watched non-recursively, which may have been a
+    /// watched subdirectory
+    SubdirRemoved(PathBuf),
+}
+
+/// Decides whether a directory created in a non-recursively watched directory gets a watch, see
+/// [`ReadDirectoryChangesWatcher::with_subdir_filter`]
+type SubdirFilter = Box<dyn FnMut(&Path) -> bool + Send>;
+
+/// The user's callbacks invoked on the server thread
+struct Callbacks {
+    lifecycle: Lifecycle,
+    subdir_filter: Option<SubdirFilter>,
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
@@ -66,9 +129,34 @@ pub enum MetaEvent {
     WatcherAwakened,
 }
 
-struct WatchState {
+/// A directory opened to read its changes
+struct DirWatch {
     dir_handle: HANDLE,
     complete_sem: HANDLE,
+    pending_rename: PendingRename,
+}
+
+struct WatchState {
+    is_recursive: bool,
+    /// Whether the watch was added for a subdirectory accepted by the [`SubdirFilter`] rather
+    /// than explicitly
+    is_subdir: bool,
+    dir: WatchDir,
+}
+
+enum WatchDir {
+    /// The watched directory
+    Own(DirWatch),
+    /// The directory holding the watched file, whose watch is in
+    /// [`ReadDirectoryChangesServer::shared_dirs`]
+    Shared(PathBuf),
+}
+
+/// The watch of a directory shared by all watched files in it, so that watching many files in one
+/// directory opens it only once.
+struct SharedDirWatch {
+    watch: DirWatch,
+    files: WatchedFiles,
 }
 
 struct ReadDirectoryChangesServer {
@@ -77,7 +165,36 @@ struct ReadDirectoryChangesServer {
     meta_tx: Sender<MetaEvent>,
     cmd_tx: Sender<Result<PathBuf>>,
     watches: HashMap<PathBuf, WatchState>,
+    /// The watches of the directories holding watched files
+    shared_dirs: HashMap<PathBuf, SharedDirWatch>,
     wakeup_sem: HANDLE,
+    config: Config,
+    reader_kind: ReaderKind,
+    lifecycle: Lifecycle,
+    subdir_filter: Option<SubdirFilter>,
+    /// Sender of the server's own actions, for the watches offering new subdirectories
+    subdir_tx: Option<Sender<Action>>,
+    /// Passes the events to the event handler, if enabled
+    buffer: Option<Dispatcher>,
+}
+
+/// The function used to read events
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ReaderKind {
+    /// `ReadDirectoryChangesW`
+    Standard,
+    /// `ReadDirectoryChangesExW`, reporting file IDs and timestamps
+    Extended,
+}
+
+impl ReaderKind {
+    fn from_config(config: &Config) -> Self {
+        if config.event_file_ids() && !config.standard_windows_reader() {
+            ReaderKind::Extended
+        } else {
+            ReaderKind::Standard
+        }
+    }
 }
 
 impl ReadDirectoryChangesServer {
@@ -86,72 +203,148 @@ impl ReadDirectoryChangesServer {
         meta_tx: Sender<MetaEvent>,
         cmd_tx: Sender<Result<PathBuf>>,
         wakeup_sem: HANDLE,
+        config: Config,
+        callbacks: Callbacks,
+        buffer: Option<Dispatcher>,
     ) -> Sender<Action> {
         let (action_tx, action_rx) = unbounded();
+        let subdir_tx = callbacks.subdir_filter.is_some().then(|| action_tx.clone());
         // it is, in fact, ok to send the semaphore across threads
         let sem_temp = wakeup_sem as u64;
         let _ = thread::Builder::new()
             .name("notify-rs windows loop".to_string())
             .spawn(move || {
                 let wakeup_sem = sem_temp as HANDLE;
+                let panic_handler = event_handler.clone();
                 let server = ReadDirectoryChangesServer {
                     rx: action_rx,
                     event_handler,
                     meta_tx,
                     cmd_tx,
                     watches: HashMap::new(),
+                    shared_dirs: HashMap::new(),
                     wakeup_sem,
+                    reader_kind: ReaderKind::from_config(&config),
+                    config,
+                    lifecycle: callbacks.lifecycle,
+                    subdir_filter: callbacks.subdir_filter,
+                    subdir_tx,
+                    buffer,
                 };
-                server.run();
+                // the events are read by callbacks on this thread, a panic in them aborts
+                if let Err(error) = Error::catch_panic(|| server.run()) {
+                    emit_event(&panic_handler, Err(error));
+                }
             });
         action_tx
     }
 
     fn run(mut self) {
-        loop {
+        self.lifecycle.thread_started();
+        let stopped_tx = loop {
             // process all available actions first
-            let mut stopped = false;
+            let mut stopped = None;
 
             while let Ok(action) = self.rx.try_recv() {
                 match action {
                     Action::Watch(path, recursive_mode) => {
-                        let res = self.add_watch(path, recursive_mode.is_recursive());
+                        // an explicit watch replaces the watch of an accepted subdirectory
+                        if self.watches.get(&path).is_some_and(|ws| ws.is_subdir) {
+                            self.remove_watch(path.clone());
+                        }
+                        let res = self.add_watch(path, recursive_mode.is_recursive(), false);
                         let _ = self.cmd_tx.send(res);
                     }
                     Action::Unwatch(path) => self.remove_watch(path),
-                    Action::Stop => {
-                        stopped = true;
-                        for ws in self.watches.values() {
-                            stop_watch(ws, &self.meta_tx);
+                    Action::UnwatchPrefix(prefix, tx) => {
+                        let _ = tx.send(self.remove_watch_prefix(&prefix));
+                    }
+                    Action::WatchedPaths(tx) => {
+                        let watched_paths = self
+                            .watches
+                            .iter()
+                            .filter(|(_, ws)| !ws.is_subdir)
+                            .map(|(path, ws)| {
+                                (path.clone(), RecursiveMode::from_bool(ws.is_recursive))
+                            })
+                            .collect();
+                        let _ = tx.send(watched_paths);
+                    }
+                    Action::Stop(tx) => {
+                        stopped = Some(tx);
+                        for (path, watch) in self.dir_watches() {
+                            stop_watch(watch, &self.meta_tx);
+                            flush_pending_rename(&watch.pending_rename, None, &self.event_handler);
+                            self.lifecycle.watch_removed(path);
                         }
                         break;
                     }
                     Action::Configure(config, tx) => {
                         self.configure_raw_mode(config, tx);
                     }
+                    Action::WatchSubdir(path) => self.watch_subdir(path),
+                    Action::SubdirRemoved(path) => self.remove_subdir_watches(&path),
                 }
             }
 
-            if stopped {
-                break;
+            if let Some(tx) = stopped {
+                break tx;
             }
 
             unsafe {
                 // wait with alertable flag so that the completion routine fires
-                let waitres = WaitForSingleObjectEx(self.wakeup_sem, 100, 1);
+                let waitres = WaitForSingleObjectEx(self.wakeup_sem, self.wait_timeout_ms(), 1);
                 if waitres == WAIT_OBJECT_0 {
                     let _ = self.meta_tx.send(MetaEvent::WatcherAwakened);
                 }
             }
-        }
+
+            if let Some(window) = self.config.rename_pair_window() {
+                for (_, watch) in self.dir_watches() {
+                    flush_pending_rename(&watch.pending_rename, Some(window), &self.event_handler);
+                }
+            }
+        };
 
         // we have to clean this up, since the watcher may be long gone
         unsafe {
             CloseHandle(self.wakeup_sem);
         }
+
+        // pass on the events read so far before the watcher gets notified
+        if let Some(buffer) = self.buffer.take() {
+            buffer.close();
+        }
+        self.lifecycle.thread_exited();
+        // the watcher must not wake the server anymore once it is notified
+        drop(self);
+        if let Some(tx) = stopped_tx {
+            let _ = tx.send(());
+        }
     }
 
-    fn add_watch(&mut self, path: PathBuf, is_recursive: bool) -> Result<PathBuf> {
+    /// The open directories along with their paths.
+    fn dir_watches(&self) -> impl Iterator<Item = (&PathBuf, &DirWatch)> {
+        let own = self.watches.iter().filter_map(|(path, ws)| match &ws.dir {
+            WatchDir::Own(watch) => Some((path, watch)),
+            WatchDir::Shared(_) => None,
+        });
+        let shared = self
+            .shared_dirs
+            .iter()
+            .map(|(dir, shared)| (dir, &shared.watch));
+        own.chain(shared)
+    }
+
+    fn wait_timeout_ms(&self) -> u32 {
+        self.config
+            .wait_granularity()
+            .as_millis()
+            .try_into()
+            .unwrap_or(INFINITE - 1)
+    }
+
+    fn add_watch(&mut self, path: PathBuf, is_recursive: bool, is_subdir: bool) -> Result<PathBuf> {
         // path must exist and be either a file or directory
         if !path.is_dir() && !path.is_file() {
             return Err(
@@ -160,15 +353,47 @@ impl ReadDirectoryChangesServer {
             );
         }
 
-        let (watching_file, dir_target) = {
-            if path.is_dir() {
-                (false, path.clone())
-            } else {
-                // emulate file watching by watching the parent directory
-                (true, path.parent().unwrap().to_path_buf())
-            }
+        let watching_file = !path.is_dir();
+        // Directories behind a junction or symbolic link are opened through it, so the changes
+        // are reported relative to the watched path. The changes of a file behind a symbolic
+        // link are reported in the directory holding its target, which is watched instead.
+        let link = watching_file && is_reparse_point(&path);
+        let file = if link {
+            fs::canonicalize(&path).map_err(|e| Error::io(e).add_path(path.clone()))?
+        } else {
+            path.clone()
+        };
+        let dir_target = if watching_file {
+            // emulate file watching by watching the parent directory
+            file.parent().unwrap().to_path_buf()
+        } else {
+            path.clone()
         };
 
+        if watching_file {
+            // watching the file again replaces its entry
+            self.remove_watch(path.clone());
+            let watched_file = WatchedFile {
+                file: file.clone(),
+                root: path.clone(),
+            };
+            match self.shared_dirs.get(&dir_target) {
+                Some(shared) if !shared.files.lock().unwrap().is_empty() => {
+                    shared.files.lock().unwrap().push(watched_file);
+                    let ws = WatchState {
+                        is_recursive,
+                        is_subdir,
+                        dir: WatchDir::Shared(dir_target),
+                    };
+                    self.watches.insert(path.clone(), ws);
+                    return Ok(path);
+                }
+                // the directory was removed along with the files watched in it
+                Some(_) => self.remove_shared_dir(&dir_target),
+                None => {}
+            }
+        }
+
         let encoded_path: Vec<u16> = dir_target
             .as_os_str()
             .encode_wide()
@@ -187,6 +412,10 @@ impl ReadDirectoryChangesServer {
             );
 
             if handle == INVALID_HANDLE_VALUE {
+                let err = Error::io_watch(std::io::Error::last_os_error());
+                if let ErrorKind::TooManyOpenFiles = err.kind {
+                    return Err(err.add_path(path));
+                }
                 return Err(if watching_file {
                     Error::generic(
                         "You attempted to watch a single file, but parent \
@@ -199,11 +428,12 @@ impl ReadDirectoryChangesServer {
                 });
             }
         }
-        let wf = if watching_file {
-            Some(path.clone())
-        } else {
-            None
-        };
+        let files = watching_file.then(|| {
+            Arc::new(Mutex::new(vec![WatchedFile {
+                file,
+                root: path.clone(),
+            }]))
+        });
         // every watcher gets its own semaphore to signal completion
         let semaphore = unsafe { CreateSemaphoreW(ptr::null_mut(), 0, 1, ptr::null_mut()) };
         if semaphore == ptr::null_mut() || semaphore == INVALID_HANDLE_VALUE {
@@ -212,25 +442,155 @@ impl ReadDirectoryChangesServer {
             }
             return Err(Error::generic("Failed to create semaphore for watch.").add_path(path));
         }
+        let pending_rename = PendingRename::default();
         let rd = ReadData {
-            dir: dir_target,
-            file: wf,
+            dir: dir_target.clone(),
+            files: files.clone(),
             complete_sem: semaphore,
             is_recursive,
+            // an accepted subdirectory is not a watched path of its own
+            report_self_events: self.config.report_self_events() && !is_subdir,
+            precise_create_kinds: self.config.precise_create_kinds(),
+            rename_pair_window: self.config.rename_pair_window(),
+            notify_filter: notify_filter(&self.config),
+            pending_rename: pending_rename.clone(),
+            volume_serial: match self.reader_kind {
+                ReaderKind::Extended => volume_serial(handle),
+                ReaderKind::Standard => None,
+            },
+            subdir_tx: if is_recursive || watching_file {
+                None
+            } else {
+                self.subdir_tx.clone()
+            },
         };
-        let ws = WatchState {
+        let watch = DirWatch {
             dir_handle: handle,
             complete_sem: semaphore,
+            pending_rename,
         };
-        self.watches.insert(path.clone(), ws);
         start_read(&rd, self.event_handler.clone(), handle);
+        let dir = match files {
+            Some(files) => {
+                self.lifecycle.watch_added(&dir_target);
+                let shared = SharedDirWatch { watch, files };
+                self.shared_dirs.insert(dir_target.clone(), shared);
+                WatchDir::Shared(dir_target)
+            }
+            None => {
+                self.lifecycle.watch_added(&path);
+                WatchDir::Own(watch)
+            }
+        };
+        let ws = WatchState {
+            is_recursive,
+            is_subdir,
+            dir,
+        };
+        self.watches.insert(path.clone(), ws);
         Ok(path)
     }
 
+    /// Watches the directory created at `path` non-recursively, if the [`SubdirFilter`] accepts
+    /// it.
+    fn watch_subdir(&mut self, path: PathBuf) {
+        if self.watches.contains_key(&path) {
+            return;
+        }
+        let Some(subdir_filter) = &mut self.subdir_filter else {
+            return;
+        };
+        if !subdir_filter(&path) {
+            return;
+        }
+        if let Err(error) = self.add_watch(path.clone(), false, true) {
+            emit_event(&self.event_handler, Err(error));
+            return;
+        }
+
+        // entries created before the watch was added were missed, report them now
+        let Ok(entries) = fs::read_dir(&path) else {
+            return;
+        };
+        let mut events = Vec::new();
+        let mut subdirs = Vec::new();
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let kind = match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => CreateKind::Folder,
+                Ok(file_type) if file_type.is_file() => CreateKind::File,
+                _ => CreateKind::Any,
+            };
+            if kind == CreateKind::Folder {
+                subdirs.push(entry.path());
+            }
+            events.push(Ok(
+                Event::new(EventKind::Create(kind)).add_path(entry.path())
+            ));
+        }
+        emit_events(&self.event_handler, events);
+        for subdir in subdirs {
+            self.watch_subdir(subdir);
+        }
+    }
+
+    /// Removes the watches of accepted subdirectories at or below `dir`.
+    fn remove_subdir_watches(&mut self, dir: &Path) {
+        let subdirs: Vec<PathBuf> = self
+            .watches
+            .iter()
+            .filter(|(path, ws)| ws.is_subdir && path.starts_with(dir))
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in subdirs {
+            self.remove_watch(path);
+        }
+    }
+
     fn remove_watch(&mut self, path: PathBuf) {
-        if let Some(ws) = self.watches.remove(&path) {
-            stop_watch(&ws, &self.meta_tx);
+        match self.watches.remove(&path).map(|ws| ws.dir) {
+            Some(WatchDir::Own(watch)) => {
+                stop_watch(&watch, &self.meta_tx);
+                flush_pending_rename(&watch.pending_rename, None, &self.event_handler);
+                self.lifecycle.watch_removed(&path);
+                // the subdirectories were only watched along with the directory
+                self.remove_subdir_watches(&path);
+            }
+            Some(WatchDir::Shared(dir)) => {
+                let is_unused = self.shared_dirs.get(&dir).is_some_and(|shared| {
+                    let mut files = shared.files.lock().unwrap();
+                    files.retain(|watched_file| watched_file.root != path);
+                    files.is_empty()
+                });
+                if is_unused {
+                    self.remove_shared_dir(&dir);
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn remove_shared_dir(&mut self, dir: &Path) {
+        if let Some(shared) = self.shared_dirs.remove(dir) {
+            stop_watch(&shared.watch, &self.meta_tx);
+            flush_pending_rename(&shared.watch.pending_rename, None, &self.event_handler);
+            self.lifecycle.watch_removed(dir);
+        }
+    }
+
+    /// Removes all watches below `prefix`, returning their paths.
+    fn remove_watch_prefix(&mut self, prefix: &Path) -> Vec<PathBuf> {
+        let mut removed: Vec<PathBuf> = self
+            .watches
+            .iter()
+            .filter(|(path, ws)| !ws.is_subdir && path.starts_with(prefix))
+            .map(|(path, _)| path.clone())
+            .collect();
+        removed.sort();
+        for path in &removed {
+            self.remove_watch(path.clone());
         }
+        self.remove_subdir_watches(prefix);
+        removed
     }
 
     fn configure_raw_mode(&mut self, _config: Config, tx: BoundSender<Result<bool>>) {
@@ -239,21 +599,87 @@ impl ReadDirectoryChangesServer {
     }
 }
 
-fn stop_watch(ws: &WatchState, meta_tx: &Sender<MetaEvent>) {
+fn stop_watch(watch: &DirWatch, meta_tx: &Sender<MetaEvent>) {
     unsafe {
-        let cio = CancelIo(ws.dir_handle);
-        let ch = CloseHandle(ws.dir_handle);
+        let cio = CancelIo(watch.dir_handle);
+        let ch = CloseHandle(watch.dir_handle);
         // have to wait for it, otherwise we leak the memory allocated for there read request
         if cio != 0 && ch != 0 {
-            while WaitForSingleObjectEx(ws.complete_sem, INFINITE, 1) != WAIT_OBJECT_0 {
+            while WaitForSingleObjectEx(watch.complete_sem, INFINITE, 1) != WAIT_OBJECT_0 {
                 // drain the apc queue, fix for https://github.com/notify-rs/notify/issues/287#issuecomment-801465550
             }
         }
-        CloseHandle(ws.complete_sem);
+        CloseHandle(watch.complete_sem);
     }
     let _ = meta_tx.send(MetaEvent::SingleWatchComplete);
 }
 
+/// The user's event handler, shared with the watcher
+struct SharedHandler(Arc<Mutex<dyn EventHandler>>);
+
+impl EventHandler for SharedHandler {
+    fn handle_event(&mut self, event: Result<Event>) {
+        emit_event(&self.0, event);
+    }
+
+    fn handle_events(&mut self, events: Vec<Result<Event>>) {
+        emit_events(&self.0, events);
+    }
+}
+
+fn emit_event(event_handler: &Mutex<dyn EventHandler>, res: Result<Event>) {
+    if let Ok(mut guard) = event_handler.lock() {
+        let f: &mut dyn EventHandler = &mut *guard;
+        f.handle_event(res);
+    }
+}
+
+fn emit_events(event_handler: &Mutex<dyn EventHandler>, events: Vec<Result<Event>>) {
+    if events.is_empty() {
+        return;
+    }
+    if let Ok(mut guard) = event_handler.lock() {
+        let f: &mut dyn EventHandler = &mut *guard;
+        f.handle_events(events);
+    }
+}
+
+/// Emits a held back old name as `Modify(Name(From))` once `window` has elapsed, or right away
+/// if no window is given.
+fn flush_pending_rename(
+    pending: &Mutex<Option<(PathBuf, Instant)>>,
+    window: Option<Duration>,
+    event_handler: &Mutex<dyn EventHandler>,
+) {
+    let mut pending = pending.lock().unwrap();
+    let expired = match (&*pending, window) {
+        (None, _) => false,
+        (Some(_), None) => true,
+        (Some((_, since)), Some(window)) => since.elapsed() >= window,
+    };
+    if expired {
+        let (path, _) = pending.take().unwrap();
+        let kind = EventKind::Modify(ModifyKind::Name(RenameMode::From));
+        emit_event(event_handler, Ok(Event::new(kind).add_path(path)));
+    }
+}
+
+/// Returns the changes to request with `ReadDirectoryChangesW`.
+///
+/// Name changes are always requested, they report creations, removals and renames. The other
+/// changes are only reported as `Modify` events, see [Config::with_event_kinds].
+fn notify_filter(config: &Config) -> u32 {
+    let mut flags = FILE_NOTIFY_CHANGE_FILE_NAME | FILE_NOTIFY_CHANGE_DIR_NAME;
+    if config.allows_kind(EventKindMask::Modify) {
+        flags |= FILE_NOTIFY_CHANGE_ATTRIBUTES
+            | FILE_NOTIFY_CHANGE_SIZE
+            | FILE_NOTIFY_CHANGE_LAST_WRITE
+            | FILE_NOTIFY_CHANGE_CREATION
+            | FILE_NOTIFY_CHANGE_SECURITY;
+    }
+    flags
+}
+
 fn start_read(rd: &ReadData, event_handler: Arc<Mutex<dyn EventHandler>>, handle: HANDLE) {
     let request = Box::new(ReadDirectoryRequest {
         event_handler,
@@ -262,15 +688,9 @@ fn start_read(rd: &ReadData, event_handler: Arc<Mutex<dyn EventHandler>>, handle
         data: rd.clone(),
     });
 
-    let flags = FILE_NOTIFY_CHANGE_FILE_NAME
-        | FILE_NOTIFY_CHANGE_DIR_NAME
-        | FILE_NOTIFY_CHANGE_ATTRIBUTES
-        | FILE_NOTIFY_CHANGE_SIZE
-        | FILE_NOTIFY_CHANGE_LAST_WRITE
-        | FILE_NOTIFY_CHANGE_CREATION
-        | FILE_NOTIFY_CHANGE_SECURITY;
+    let flags = request.data.notify_filter;
 
-    let monitor_subdir = if request.data.file.is_none() && request.data.is_recursive {
+    let monitor_subdir = if request.data.files.is_none() && request.data.is_recursive {
         1
     } else {
         0
@@ -286,16 +706,41 @@ fn start_read(rd: &ReadData, event_handler: Arc<Mutex<dyn EventHandler>>, handle
 
         // This is using an asynchronous call with a completion routine for receiving notifications
         // An I/O completion port would probably be more performant
-        let ret = ReadDirectoryChangesW(
-            handle,
-            request.buffer.as_mut_ptr() as *mut c_void,
-            BUF_SIZE,
-            monitor_subdir,
-            flags,
-            &mut 0u32 as *mut u32, // not used for async reqs
-            overlapped,
-            Some(handle_event),
-        );
+        let mut ret = 0;
+        if request.data.volume_serial.is_some() {
+            ret = ReadDirectoryChangesExW(
+                handle,
+                request.buffer.as_mut_ptr() as *mut c_void,
+                BUF_SIZE,
+                monitor_subdir,
+                flags,
+                &mut 0u32 as *mut u32, // not used for async reqs
+                overlapped,
+                Some(handle_event),
+                ReadDirectoryNotifyExtendedInformation,
+            );
+            if ret == 0
+                && matches!(
+                    GetLastError(),
+                    ERROR_INVALID_FUNCTION | ERROR_INVALID_PARAMETER | ERROR_NOT_SUPPORTED
+                )
+            {
+                // the extended reader is not available, e.g. on network shares
+                request.data.volume_serial = None;
+            }
+        }
+        if request.data.volume_serial.is_none() {
+            ret = ReadDirectoryChangesW(
+                handle,
+                request.buffer.as_mut_ptr() as *mut c_void,
+                BUF_SIZE,
+                monitor_subdir,
+                flags,
+                &mut 0u32 as *mut u32, // not used for async reqs
+                overlapped,
+                Some(handle_event),
+            );
+        }
 
         if ret == 0 {
             // error reading. retransmute request memory to allow drop.
@@ -324,71 +769,152 @@ unsafe extern "system" fn handle_event(
         return;
     }
 
+    if error_code == ERROR_ACCESS_DENIED {
+        // received when the watched dir was deleted, the handle is no longer usable
+        match &request.data.files {
+            Some(files) => {
+                // the shared watch is gone along with the watched files
+                let files = std::mem::take(&mut *files.lock().unwrap());
+                if request.data.report_self_events {
+                    let events = files
+                        .into_iter()
+                        .map(|watched_file| Ok(watch_invalidated(watched_file.root)))
+                        .collect();
+                    emit_events(&request.event_handler, events);
+                }
+            }
+            None if request.data.report_self_events => {
+                let root = request.data.dir.clone();
+                emit_event(&request.event_handler, Ok(watch_invalidated(root)));
+            }
+            None => {}
+        }
+        ReleaseSemaphore(request.data.complete_sem, 1, ptr::null_mut());
+        return;
+    }
+
     // Get the next request queued up as soon as possible
     start_read(&request.data, request.event_handler.clone(), request.handle);
 
+    if let Some(window) = request.data.rename_pair_window {
+        flush_pending_rename(
+            &request.data.pending_rename,
+            Some(window),
+            &request.event_handler,
+        );
+    }
+
     // The FILE_NOTIFY_INFORMATION struct has a variable length due to the variable length
     // string as its last member. Each struct contains an offset for getting the next entry in
     // the buffer.
     let mut cur_offset: *const u8 = request.buffer.as_ptr();
-    // In Wine, FILE_NOTIFY_INFORMATION structs are packed placed in the buffer;
-    // they are aligned to 16bit (WCHAR) boundary instead of 32bit required by FILE_NOTIFY_INFORMATION.
-    // Hence, we need to use `read_unaligned` here to avoid UB.
-    let mut cur_entry = ptr::read_unaligned(cur_offset as *const FILE_NOTIFY_INFORMATION);
+    let volume_serial = request.data.volume_serial;
+    let mut cur_entry = read_entry(cur_offset, volume_serial);
+    // everything in the buffer is emitted at once, see `Batched`
+    let mut batch = Vec::new();
     loop {
-        // filename length is size in bytes, so / 2
-        let len = cur_entry.FileNameLength as usize / 2;
-        let encoded_path: &[u16] = slice::from_raw_parts(
-            cur_offset.offset(std::mem::offset_of!(FILE_NOTIFY_INFORMATION, FileName) as isize)
-                as _,
-            len,
-        );
         // prepend root to get a full path
         let path = request
             .data
             .dir
-            .join(PathBuf::from(OsString::from_wide(encoded_path)));
+            .join(PathBuf::from(OsString::from_wide(cur_entry.file_name)));
+
+        // offer new subdirectories to the subdir filter, a removed path may have been one
+        if let Some(subdir_tx) = &request.data.subdir_tx {
+            match cur_entry.action {
+                FILE_ACTION_ADDED | FILE_ACTION_RENAMED_NEW_NAME if path.is_dir() => {
+                    let _ = subdir_tx.send(Action::WatchSubdir(path.clone()));
+                }
+                FILE_ACTION_REMOVED | FILE_ACTION_RENAMED_OLD_NAME => {
+                    let _ = subdir_tx.send(Action::SubdirRemoved(path.clone()));
+                }
+                _ => {}
+            }
+        }
 
-        // if we are watching a single file, ignore the event unless the path is exactly
-        // the watched file
-        let skip = match request.data.file {
-            None => false,
-            Some(ref watch_path) => *watch_path != path,
+        // if we are watching files, ignore the event unless the path is exactly one of the
+        // watched files, and report it for the watched path, e.g. the link to the file
+        let paths = match &request.data.files {
+            None => vec![path],
+            // the watched file itself was removed or renamed
+            Some(_)
+                if !request.data.report_self_events
+                    && matches!(
+                        cur_entry.action,
+                        FILE_ACTION_REMOVED | FILE_ACTION_RENAMED_OLD_NAME
+                    ) =>
+            {
+                Vec::new()
+            }
+            Some(files) => files
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|watched_file| watched_file.file == path)
+                .map(|watched_file| watched_file.root.clone())
+                .collect(),
         };
 
-        if !skip {
+        for path in paths {
             log::trace!(
                 "Event: path = `{}`, action = {:?}",
                 path.display(),
-                cur_entry.Action
+                cur_entry.action
             );
 
-            let newe = Event::new(EventKind::Any).add_path(path);
+            let mut event_handler = |res: Result<Event>| {
+                batch.push(res.map(|mut event| {
+                    if let Some(file_id) = cur_entry.file_id {
+                        event = event.set_file_id(file_id);
+                    }
+                    if let Some(modified) = cur_entry.modified {
+                        event = event.set_modified(modified);
+                    }
+                    event
+                }))
+            };
 
-            fn emit_event(event_handler: &Mutex<dyn EventHandler>, res: Result<Event>) {
-                if let Ok(mut guard) = event_handler.lock() {
-                    let f: &mut dyn EventHandler = &mut *guard;
-                    f.handle_event(res);
+            if request.data.rename_pair_window.is_some()
+                && matches!(
+                    cur_entry.action,
+                    FILE_ACTION_RENAMED_OLD_NAME | FILE_ACTION_RENAMED_NEW_NAME
+                )
+            {
+                let mut pending = request.data.pending_rename.lock().unwrap();
+                if cur_entry.action == FILE_ACTION_RENAMED_OLD_NAME {
+                    // an earlier old name without a new name is not going to be paired anymore
+                    if let Some((old, _)) = pending.replace((path, Instant::now())) {
+                        let kind = EventKind::Modify(ModifyKind::Name(RenameMode::From));
+                        event_handler(Ok(Event::new(kind).add_path(old)));
+                    }
+                } else if let Some((old, _)) = pending.take() {
+                    let kind = EventKind::Modify(ModifyKind::Name(RenameMode::Both));
+                    event_handler(Ok(Event::new(kind).add_path(old).add_path(path)));
+                } else {
+                    let kind = EventKind::Modify(ModifyKind::Name(RenameMode::To));
+                    event_handler(Ok(Event::new(kind).add_path(path)));
                 }
-            }
-
-            let event_handler = |res| emit_event(&request.event_handler, res);
-
-            if cur_entry.Action == FILE_ACTION_RENAMED_OLD_NAME {
+            } else if cur_entry.action == FILE_ACTION_RENAMED_OLD_NAME {
+                let newe = Event::new(EventKind::Any).add_path(path);
                 let mode = RenameMode::From;
                 let kind = ModifyKind::Name(mode);
                 let kind = EventKind::Modify(kind);
                 let ev = newe.set_kind(kind);
                 event_handler(Ok(ev))
             } else {
-                match cur_entry.Action {
+                let newe = Event::new(EventKind::Any).add_path(path);
+                match cur_entry.action {
                     FILE_ACTION_RENAMED_NEW_NAME => {
                         let kind = EventKind::Modify(ModifyKind::Name(RenameMode::To));
                         let ev = newe.set_kind(kind);
                         event_handler(Ok(ev));
                     }
                     FILE_ACTION_ADDED => {
-                        let kind = EventKind::Create(CreateKind::Any);
+                        let kind = if request.data.precise_create_kinds {
+                            EventKind::Create(create_kind_of(&newe.paths[0]))
+                        } else {
+                            EventKind::Create(CreateKind::Any)
+                        };
                         let ev = newe.set_kind(kind);
                         event_handler(Ok(ev));
                     }
@@ -407,12 +933,85 @@ unsafe extern "system" fn handle_event(
             }
         }
 
-        if cur_entry.NextEntryOffset == 0 {
+        if cur_entry.next_entry_offset == 0 {
             break;
         }
-        cur_offset = cur_offset.offset(cur_entry.NextEntryOffset as isize);
-        cur_entry = ptr::read_unaligned(cur_offset as *const FILE_NOTIFY_INFORMATION);
+        cur_offset = cur_offset.offset(cur_entry.next_entry_offset as isize);
+        cur_entry = read_entry(cur_offset, volume_serial);
+    }
+
+    emit_events(&request.event_handler, batch);
+}
+
+/// An entry of the buffer filled by `ReadDirectoryChangesW` or `ReadDirectoryChangesExW`
+struct NotifyEntry<'a> {
+    next_entry_offset: u32,
+    action: u32,
+    file_name: &'a [u16],
+    file_id: Option<FileId>,
+    modified: Option<SystemTime>,
+}
+
+/// Reads the entry at `offset`, which is an extended entry if the volume serial is known.
+unsafe fn read_entry<'a>(offset: *const u8, volume_serial: Option<u32>) -> NotifyEntry<'a> {
+    if let Some(volume_serial) = volume_serial {
+        return read_extended_entry(offset, volume_serial);
     }
+
+    // In Wine, FILE_NOTIFY_INFORMATION structs are packed placed in the buffer;
+    // they are aligned to 16bit (WCHAR) boundary instead of 32bit required by FILE_NOTIFY_INFORMATION.
+    // Hence, we need to use `read_unaligned` here to avoid UB.
+    let entry = ptr::read_unaligned(offset as *const FILE_NOTIFY_INFORMATION);
+    // filename length is size in bytes, so / 2
+    let file_name = slice::from_raw_parts(
+        offset.add(std::mem::offset_of!(FILE_NOTIFY_INFORMATION, FileName)) as *const u16,
+        entry.FileNameLength as usize / 2,
+    );
+    NotifyEntry {
+        next_entry_offset: entry.NextEntryOffset,
+        action: entry.Action,
+        file_name,
+        file_id: None,
+        modified: None,
+    }
+}
+
+unsafe fn read_extended_entry<'a>(offset: *const u8, volume_serial: u32) -> NotifyEntry<'a> {
+    let entry = ptr::read_unaligned(offset as *const FILE_NOTIFY_EXTENDED_INFORMATION);
+    let file_name = slice::from_raw_parts(
+        offset.add(std::mem::offset_of!(
+            FILE_NOTIFY_EXTENDED_INFORMATION,
+            FileName
+        )) as *const u16,
+        entry.FileNameLength as usize / 2,
+    );
+    NotifyEntry {
+        next_entry_offset: entry.NextEntryOffset,
+        action: entry.Action,
+        file_name,
+        file_id: Some(FileId::new_low_res(volume_serial, entry.FileId as u64)),
+        modified: filetime_to_system_time(entry.LastModificationTime),
+    }
+}
+
+/// Whether `path` itself is a junction, symbolic link or other reparse point.
+fn is_reparse_point(path: &Path) -> bool {
+    fs::symlink_metadata(path)
+        .is_ok_and(|metadata| metadata.file_attributes() & FILE_ATTRIBUTE_REPARSE_POINT != 0)
+}
+
+/// Converts a `FILETIME` value, in 100ns intervals since 1601-01-01, to a `SystemTime`.
+fn filetime_to_system_time(filetime: i64) -> Option<SystemTime> {
+    const UNIX_EPOCH_AS_FILETIME: i64 = 116_444_736_000_000_000;
+    let since_unix_epoch = u64::try_from(filetime.checked_sub(UNIX_EPOCH_AS_FILETIME)?).ok()?;
+    UNIX_EPOCH.checked_add(Duration::from_nanos(since_unix_epoch.checked_mul(100)?))
+}
+
+/// Serial number of the volume holding the directory behind `handle`.
+fn volume_serial(handle: HANDLE) -> Option<u32> {
+    let mut info: BY_HANDLE_FILE_INFORMATION = unsafe { std::mem::zeroed() };
+    let ret = unsafe { GetFileInformationByHandle(handle, &mut info) };
+    (ret != 0).then_some(info.dwVolumeSerialNumber)
 }
 
 /// Watcher implementation based on ReadDirectoryChanges
@@ -421,6 +1020,9 @@ pub struct ReadDirectoryChangesWatcher {
     tx: Sender<Action>,
     cmd_rx: Receiver<Result<PathBuf>>,
     wakeup_sem: HANDLE,
+    gate: PauseGate,
+    leftovers: Leftovers,
+    follow_symlinks: bool,
 }
 
 impl ReadDirectoryChangesWatcher {
@@ -428,6 +1030,111 @@ impl ReadDirectoryChangesWatcher {
         event_handler: Arc<Mutex<dyn EventHandler>>,
         meta_tx: Sender<MetaEvent>,
     ) -> Result<ReadDirectoryChangesWatcher> {
+        let counters = Arc::<Counters>::default();
+        let leftovers = Leftovers::default();
+        let event_handler = Arc::new(Mutex::new(
+            leftovers.handler(counters.handler(SharedHandler(event_handler))),
+        ));
+        let callbacks = Callbacks {
+            lifecycle: Lifecycle::default(),
+            subdir_filter: None,
+        };
+        Self::create_with_config(
+            event_handler,
+            meta_tx,
+            Config::default(),
+            callbacks,
+            leftovers,
+            counters,
+        )
+    }
+
+    /// Create a new watcher, invoking the `lifecycle` callbacks for the operations of its thread.
+    pub fn with_lifecycle<F: EventHandler>(
+        event_handler: F,
+        config: Config,
+        lifecycle: Lifecycle,
+    ) -> Result<Self> {
+        let callbacks = Callbacks {
+            lifecycle,
+            subdir_filter: None,
+        };
+        Self::with_callbacks(event_handler, config, callbacks)
+    }
+
+    /// Create a new watcher, calling `subdir_filter` with each directory created in a
+    /// non-recursively watched directory, and invoking the `lifecycle` callbacks for the
+    /// operations of its thread, see [`with_lifecycle`](Self::with_lifecycle).
+    ///
+    /// Directories for which `subdir_filter` returns `true` are watched non-recursively as well,
+    /// so their contents are reported and their own new subdirectories are offered in turn. This
+    /// watches selected parts of a large tree without watching the whole subtree. Entries created
+    /// in an accepted directory before its watch was added are reported as `Create` events once
+    /// it is watched, so they may be reported twice. The watches of accepted directories are
+    /// removed along with the directory or its parent's watch, and are not listed by
+    /// [`Watcher::watched_paths`].
+    ///
+    /// `subdir_filter` is called on the watcher's thread.
+    pub fn with_subdir_filter<F, S>(
+        event_handler: F,
+        config: Config,
+        lifecycle: Lifecycle,
+        subdir_filter: S,
+    ) -> Result<Self>
+    where
+        F: EventHandler,
+        S: FnMut(&Path) -> bool + Send + 'static,
+    {
+        let callbacks = Callbacks {
+            lifecycle,
+            subdir_filter: Some(Box::new(subdir_filter)),
+        };
+        Self::with_callbacks(event_handler, config, callbacks)
+    }
+
+    fn with_callbacks<F: EventHandler>(
+        event_handler: F,
+        config: Config,
+        callbacks: Callbacks,
+    ) -> Result<Self> {
+        // create dummy channel for meta event
+        // TODO: determine the original purpose of this - can we remove it?
+        let (meta_tx, _) = unbounded();
+        let counters = Arc::<Counters>::default();
+        let mut leftovers = Leftovers::default();
+        let dispatch = dispatch::from_config(counters.handler(event_handler), &config)?;
+        leftovers.flush_on_take(dispatch.flush());
+        let settle = settle::from_config(leftovers.handler(dispatch), &config)?;
+        leftovers.flush_on_take(settle.flush());
+        let event_handler = Arc::new(Mutex::new(AttributeHandler::new(
+            settle,
+            &config,
+            Self::kind(),
+        )));
+        Self::create_with_config(
+            event_handler,
+            meta_tx,
+            config,
+            callbacks,
+            leftovers,
+            counters,
+        )
+    }
+
+    fn create_with_config(
+        event_handler: Arc<Mutex<dyn EventHandler>>,
+        meta_tx: Sender<MetaEvent>,
+        config: Config,
+        callbacks: Callbacks,
+        leftovers: Leftovers,
+        counters: Arc<Counters>,
+    ) -> Result<ReadDirectoryChangesWatcher> {
+        if config.wait_granularity() < MIN_WAIT_GRANULARITY
+            || config.event_buffer_capacity() == Some(0)
+        {
+            return Err(Error::invalid_config(&config));
+        }
+
         let (cmd_tx, cmd_rx) = unbounded();
 
         let wakeup_sem = unsafe { CreateSemaphoreW(ptr::null_mut(), 0, 1, ptr::null_mut()) };
@@ -435,17 +1142,42 @@ impl ReadDirectoryChangesWatcher {
             return Err(Error::generic("Failed to create wakeup semaphore."));
         }
 
-        let action_tx =
-            ReadDirectoryChangesServer::start(event_handler, meta_tx, cmd_tx, wakeup_sem);
+        let gate = PauseGate::new(Box::new(SharedHandler(event_handler)), &config, counters);
+        let mut event_handler: Arc<Mutex<dyn EventHandler>> = Arc::new(Mutex::new(gate.clone()));
+        let buffer = match config.event_buffer_capacity() {
+            Some(capacity) => {
+                let buffer = Dispatcher::start(
+                    "notify-rs windows buffer",
+                    Some(capacity),
+                    Overflow::Rescan,
+                    SharedHandler(event_handler),
+                )?;
+                event_handler = Arc::new(Mutex::new(buffer.queue()));
+                Some(buffer)
+            }
+            None => None,
+        };
+        let action_tx = ReadDirectoryChangesServer::start(
+            event_handler,
+            meta_tx,
+            cmd_tx,
+            wakeup_sem,
+            config,
+            callbacks,
+            buffer,
+        );
 
         Ok(ReadDirectoryChangesWatcher {
             tx: action_tx,
             cmd_rx,
             wakeup_sem,
+            gate,
+            leftovers,
+            follow_symlinks: config.follow_symlinks(),
         })
     }
 
-    fn wakeup_server(&mut self) {
+    fn wakeup_server(&self) {
         // breaks the server out of its wait state.  right now this is really just an optimization,
         // so that if you add a watch you don't block for 100ms in watch() while the
         // server sleeps.
@@ -466,7 +1198,11 @@ impl ReadDirectoryChangesWatcher {
             .cmd_rx
             .recv()
             .map_err(|_| Error::generic("Error receiving from command channel"))?
-            .map_err(|e| Error::generic(&format!("Error in watcher: {:?}", e)))?;
+            .map_err(|e| match e.kind {
+                // keep the error kind, so running out of handles can be told apart
+                ErrorKind::TooManyOpenFiles => e,
+                _ => Error::generic(&format!("Error in watcher: {:?}", e)),
+            })?;
 
         if pb.as_path() != ack_pb.as_path() {
             Err(Error::generic(&format!(
@@ -509,31 +1245,96 @@ impl ReadDirectoryChangesWatcher {
         self.wakeup_server();
         res
     }
+
+    fn watched_paths_inner(&self) -> Result<Vec<(PathBuf, RecursiveMode)>> {
+        let (tx, rx) = bounded(1);
+        self.tx
+            .send(Action::WatchedPaths(tx))
+            .map_err(|_| Error::generic("Error sending to internal channel"))?;
+        self.wakeup_server();
+        Ok(rx.recv()?)
+    }
+
+    fn unwatch_prefix_inner(&mut self, prefix: &Path) -> Result<Vec<PathBuf>> {
+        let pb = if prefix.is_absolute() {
+            prefix.to_owned()
+        } else {
+            let p = env::current_dir().map_err(Error::io)?;
+            p.join(prefix)
+        };
+        let (tx, rx) = bounded(1);
+        self.tx
+            .send(Action::UnwatchPrefix(pb, tx))
+            .map_err(|_| Error::generic("Error sending to internal channel"))?;
+        self.wakeup_server();
+        Ok(rx.recv()?)
+    }
 }
 
 impl Watcher for ReadDirectoryChangesWatcher {
-    fn new<F: EventHandler>(event_handler: F, _config: Config) -> Result<Self> {
-        // create dummy channel for meta event
-        // TODO: determine the original purpose of this - can we remove it?
-        let (meta_tx, _) = unbounded();
-        let event_handler = Arc::new(Mutex::new(event_handler));
-        Self::create(event_handler, meta_tx)
+    fn new<F: EventHandler>(event_handler: F, config: Config) -> Result<Self> {
+        Self::with_lifecycle(event_handler, config, Lifecycle::default())
     }
 
     fn watch(&mut self, path: &Path, recursive_mode: RecursiveMode) -> Result<()> {
-        self.watch_inner(path, recursive_mode)
+        self.watch_inner(path, recursive_mode)?;
+        self.gate.root_watched(path);
+        Ok(())
     }
 
     fn unwatch(&mut self, path: &Path) -> Result<()> {
+        self.gate.root_unwatched(path, false);
         self.unwatch_inner(path)
     }
 
+    fn watched_paths(&self) -> Result<Vec<(PathBuf, RecursiveMode)>> {
+        self.watched_paths_inner()
+    }
+
+    fn unwatch_prefix(&mut self, prefix: &Path) -> Result<Vec<PathBuf>> {
+        self.gate.root_unwatched(prefix, true);
+        self.unwatch_prefix_inner(prefix)
+    }
+
     fn configure(&mut self, config: Config) -> Result<bool> {
         let (tx, rx) = bounded(1);
         self.tx.send(Action::Configure(config, tx))?;
         rx.recv()?
     }
 
+    fn pause(&mut self) -> Result<()> {
+        self.gate.pause();
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        self.gate.resume();
+        Ok(())
+    }
+
+    fn resync(&mut self) -> Result<()> {
+        let roots = self.watched_paths()?;
+        resync::resync(&roots, self.follow_symlinks, &mut self.gate);
+        Ok(())
+    }
+
+    fn stats(&self) -> crate::WatcherStats {
+        let watches = self.watched_paths().map_or(0, |paths| paths.len());
+        self.gate.stats(watches)
+    }
+
+    fn stop(self) -> Vec<Result<Event>> {
+        self.leftovers.collect();
+        self.gate.resume();
+
+        let (tx, rx) = bounded(1);
+        if self.tx.send(Action::Stop(Some(tx))).is_ok() {
+            self.wakeup_server();
+            let _ = rx.recv();
+        }
+        self.leftovers.take()
+    }
+
     fn kind() -> crate::WatcherKind {
         WatcherKind::ReadDirectoryChangesWatcher
     }
@@ -541,9 +1342,11 @@ impl Watcher for ReadDirectoryChangesWatcher {
 
 impl Drop for ReadDirectoryChangesWatcher {
     fn drop(&mut self) {
-        let _ = self.tx.send(Action::Stop);
-        // better wake it up
-        self.wakeup_server();
+        // the server is already gone if the watcher was stopped
+        if self.tx.send(Action::Stop(None)).is_ok() {
+            // better wake it up
+            self.wakeup_server();
+        }
     }
 }
 
@@ -552,3 +1355,326 @@ impl Drop for ReadDirectoryChangesWatcher {
 unsafe impl Send for ReadDirectoryChangesWatcher {}
 // Because all public methods are `&mut self` it's also perfectly safe to share references.
 unsafe impl Sync for ReadDirectoryChangesWatcher {}
+
+#[test]
+fn wait_granularity_below_minimum_is_rejected() {
+    let config = Config::default().with_wait_granularity(Duration::ZERO);
+    let res = ReadDirectoryChangesWatcher::new(|_| {}, config);
+    assert!(matches!(
+        res,
+        Err(Error {
+            kind: crate::ErrorKind::InvalidConfig(_),
+            ..
+        })
+    ));
+}
+
+/// Measures the add-watch latency when the server isn't woken up, e.g. because the wakeup raced
+/// with its wait, for the default and a fine wait granularity.
+///
+/// Run with `cargo test -p notify --lib add_watch_latency -- --ignored --nocapture`.
+#[test]
+#[ignore = "benchmark"]
+fn add_watch_latency_follows_the_wait_granularity() {
+    let dir = tempfile::tempdir().unwrap();
+    let latency = |granularity: Duration| {
+        let config = Config::default().with_wait_granularity(granularity);
+        let watcher = ReadDirectoryChangesWatcher::new(|_| {}, config).unwrap();
+        let rounds = 20;
+        let start = Instant::now();
+        for _ in 0..rounds {
+            // the server only takes the actions once its wait timed out
+            let path = dir.path().to_path_buf();
+            watcher
+                .tx
+                .send(Action::Watch(path.clone(), RecursiveMode::NonRecursive))
+                .unwrap();
+            watcher.cmd_rx.recv().unwrap().unwrap();
+            watcher.tx.send(Action::Unwatch(path)).unwrap();
+        }
+        start.elapsed() / rounds
+    };
+
+    let default = latency(Duration::from_millis(100));
+    let fine = latency(Duration::from_millis(10));
+    println!("add-watch latency: {default:?} with 100ms granularity, {fine:?} with 10ms");
+    assert!(fine < default / 2);
+}
+
+#[test]
+fn empty_event_buffer_is_rejected() {
+    let config = Config::default().with_event_buffer_capacity(0);
+    let res = ReadDirectoryChangesWatcher::new(|_| {}, config);
+    assert!(matches!(
+        res,
+        Err(Error {
+            kind: crate::ErrorKind::InvalidConfig(_),
+            ..
+        })
+    ));
+}
+
+#[test]
+fn rename_pair_window_reports_both_names() {
+    let dir = tempfile::tempdir().unwrap();
+    let from = dir.path().join("from");
+    let to = dir.path().join("to");
+    std::fs::write(&from, b"").unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let config = Config::default().with_rename_pair_window(Duration::from_millis(500));
+    let mut watcher = ReadDirectoryChangesWatcher::new(tx, config).unwrap();
+    watcher
+        .watch(dir.path(), RecursiveMode::NonRecursive)
+        .unwrap();
+
+    std::fs::rename(&from, &to).unwrap();
+
+    let event = rx
+        .iter()
+        .map(|res| res.unwrap())
+        .find(|event| matches!(event.kind, EventKind::Modify(ModifyKind::Name(_))))
+        .unwrap();
+    assert_eq!(
+        event.kind,
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both))
+    );
+    assert_eq!(event.paths, vec![from, to]);
+}
+
+#[test]
+fn event_file_ids_match_for_rename() {
+    let dir = tempfile::tempdir().unwrap();
+    let from = dir.path().join("from");
+    let to = dir.path().join("to");
+    std::fs::write(&from, b"").unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let config = Config::default().with_event_file_ids(true);
+    let mut watcher = ReadDirectoryChangesWatcher::new(tx, config).unwrap();
+    watcher
+        .watch(dir.path(), RecursiveMode::NonRecursive)
+        .unwrap();
+
+    std::fs::rename(&from, to).unwrap();
+
+    let renames: Vec<_> = rx
+        .iter()
+        .map(|res| res.unwrap())
+        .filter(|event| matches!(event.kind, EventKind::Modify(ModifyKind::Name(_))))
+        .take(2)
+        .collect();
+    assert!(renames[0].file_id().is_some());
+    assert!(renames[0].modified().is_some());
+    assert_eq!(renames[0].file_id(), renames[1].file_id());
+}
+
+#[test]
+fn standard_windows_reader_reports_no_file_ids() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let config = Config::default()
+        .with_event_file_ids(true)
+        .with_standard_windows_reader(true);
+    let mut watcher = ReadDirectoryChangesWatcher::new(tx, config).unwrap();
+    watcher
+        .watch(dir.path(), RecursiveMode::NonRecursive)
+        .unwrap();
+
+    std::fs::write(dir.path().join("file"), b"").unwrap();
+
+    let event = rx.recv_timeout(Duration::from_secs(5)).unwrap().unwrap();
+    assert_eq!(event.file_id(), None);
+    assert_eq!(event.modified(), None);
+}
+
+#[test]
+fn linked_file_is_reported_at_the_link() {
+    let dir = tempfile::tempdir().unwrap();
+    let target_dir = dir.path().join("target");
+    fs::create_dir(&target_dir).unwrap();
+    let target = target_dir.join("file");
+    fs::write(&target, b"").unwrap();
+    let link = dir.path().join("link");
+    if std::os::windows::fs::symlink_file(&target, &link).is_err() {
+        // creating symbolic links requires developer mode or elevated privileges
+        return;
+    }
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = ReadDirectoryChangesWatcher::new(tx, Config::default()).unwrap();
+    watcher.watch(&link, RecursiveMode::NonRecursive).unwrap();
+
+    fs::write(&target, b"Lorem ipsum").unwrap();
+
+    let event = rx
+        .recv_timeout(Duration::from_secs(5))
+        .expect("no event for the linked file")
+        .unwrap();
+    assert_eq!(event.paths, [link]);
+}
+
+#[test]
+fn changes_behind_a_junction_are_reported_at_the_junction() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("target");
+    fs::create_dir(&target).unwrap();
+    let junction = dir.path().join("junction");
+    // unlike symbolic links, junctions can be created without privileges
+    let status = std::process::Command::new("cmd")
+        .arg("/C")
+        .arg("mklink")
+        .arg("/J")
+        .arg(&junction)
+        .arg(&target)
+        .output()
+        .unwrap()
+        .status;
+    assert!(status.success(), "mklink /J failed");
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = ReadDirectoryChangesWatcher::new(tx, Config::default()).unwrap();
+    watcher.watch(&junction, RecursiveMode::Recursive).unwrap();
+
+    fs::write(target.join("file"), b"Lorem ipsum").unwrap();
+
+    let event = rx
+        .recv_timeout(Duration::from_secs(5))
+        .expect("no event behind the junction")
+        .unwrap();
+    assert_eq!(event.paths, [junction.join("file")]);
+}
+
+#[test]
+fn files_in_one_directory_share_a_watch() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let dir = tempfile::tempdir().unwrap();
+    let files: Vec<_> = (0..100)
+        .map(|i| dir.path().join(format!("file{i}")))
+        .collect();
+    for file in &files {
+        fs::write(file, b"").unwrap();
+    }
+
+    let watches = Arc::new(AtomicUsize::new(0));
+    let added = watches.clone();
+    let removed = watches.clone();
+    let lifecycle = Lifecycle::new()
+        .with_on_watch_added(move |_| {
+            added.fetch_add(1, Ordering::Relaxed);
+        })
+        .with_on_watch_removed(move |_| {
+            removed.fetch_sub(1, Ordering::Relaxed);
+        });
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher =
+        ReadDirectoryChangesWatcher::with_lifecycle(tx, Config::default(), lifecycle).unwrap();
+    for file in &files {
+        watcher.watch(file, RecursiveMode::NonRecursive).unwrap();
+    }
+    assert_eq!(watches.load(Ordering::Relaxed), 1);
+    assert_eq!(watcher.watched_paths().unwrap().len(), 100);
+
+    fs::write(&files[42], b"Lorem ipsum").unwrap();
+    let event = rx
+        .recv_timeout(Duration::from_secs(5))
+        .expect("no event for the watched file")
+        .unwrap();
+    assert_eq!(event.paths, [files[42].clone()]);
+
+    // unwatching is asynchronous, listing the watched paths waits for it
+    for file in &files[1..] {
+        watcher.unwatch(file).unwrap();
+    }
+    assert_eq!(watcher.watched_paths().unwrap().len(), 1);
+    assert_eq!(watches.load(Ordering::Relaxed), 1);
+    watcher.unwatch(&files[0]).unwrap();
+    assert!(watcher.watched_paths().unwrap().is_empty());
+    assert_eq!(watches.load(Ordering::Relaxed), 0);
+}
+
+#[test]
+fn accepted_subdirectories_of_a_non_recursive_watch_are_watched() {
+    let dir = tempfile::tempdir().unwrap();
+    let accepted = dir.path().join("accepted");
+    let skipped = dir.path().join("skipped");
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = ReadDirectoryChangesWatcher::with_subdir_filter(
+        tx,
+        Config::default(),
+        Lifecycle::default(),
+        |dir| !dir.ends_with("skipped"),
+    )
+    .unwrap();
+    watcher
+        .watch(dir.path(), RecursiveMode::NonRecursive)
+        .unwrap();
+
+    let mut events =
+        std::iter::from_fn(|| rx.recv_timeout(Duration::from_secs(1)).ok()).map(|res| res.unwrap());
+    for subdir in [&skipped, &accepted] {
+        fs::create_dir(subdir).unwrap();
+        let event = events.next().expect("no event for the subdirectory");
+        assert_eq!(event.paths, [subdir.clone()]);
+    }
+    // listing the watched paths waits for the subdirectories to be watched
+    assert_eq!(watcher.watched_paths().unwrap().len(), 1);
+
+    fs::write(skipped.join("file"), b"").unwrap();
+    fs::write(accepted.join("file"), b"").unwrap();
+    let event = events
+        .next()
+        .expect("no event in the accepted subdirectory");
+    assert!(event.kind.is_create(), "{event:?}");
+    assert_eq!(event.paths, [accepted.join("file")]);
+}
+
+#[test]
+fn entries_created_before_a_subdirectory_is_watched_are_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let accepted = dir.path().join("accepted");
+    let (filter_tx, filter_rx) = std::sync::mpsc::channel::<()>();
+    let filter_rx = Mutex::new(filter_rx);
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = ReadDirectoryChangesWatcher::with_subdir_filter(
+        tx,
+        Config::default(),
+        Lifecycle::default(),
+        move |_| {
+            // hold the watch back until the subdirectory has contents
+            let _ = filter_rx.lock().unwrap().recv();
+            true
+        },
+    )
+    .unwrap();
+    watcher
+        .watch(dir.path(), RecursiveMode::NonRecursive)
+        .unwrap();
+
+    fs::create_dir(&accepted).unwrap();
+    fs::create_dir(accepted.join("nested")).unwrap();
+    fs::write(accepted.join("file"), b"").unwrap();
+    thread::sleep(Duration::from_millis(200));
+    drop(filter_tx);
+
+    let mut paths: Vec<_> = std::iter::from_fn(|| rx.recv_timeout(Duration::from_secs(1)).ok())
+        .map(|res| res.unwrap())
+        .filter(|event| event.kind.is_create())
+        .flat_map(|event| event.paths)
+        .collect();
+    paths.sort();
+    paths.dedup();
+    assert_eq!(
+        paths,
+        [
+            accepted.clone(),
+            accepted.join("file"),
+            accepted.join("nested")
+        ]
+    );
+}
@@ -0,0 +1,234 @@
+//! Event handler merging the creation and writing of a file
+//!
+//! Writing a new file produces a `Create` event, `Modify(Data)` events for the writes and, on
+//! Linux, an `Access(Close(Write))` event once the writer closes the file. The [`FileWritten`]
+//! handler collapses such a sequence into a single `Create` event delivered at close time, which
+//! then means the file is new and fully written.
+
+use crate::event::{AccessKind, AccessMode, ModifyKind};
+use crate::timed::{Hold, Timed};
+use crate::{Event, EventHandler, EventKind, Result};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// [`EventHandler`] holding back `Create` events of files until the file is closed after
+/// writing.
+///
+/// Relies on the `Access(Close(Write))` events of the
+/// [INotifyWatcher](crate::INotifyWatcher). Once a held back file is closed, its `Create` event
+/// is delivered and the data modifications and accesses in between are dropped. If the file is
+/// not closed within the window, or is removed or renamed first, the held back events are
+/// delivered unchanged. Other events are forwarded right away, so events may be reordered.
+/// Held back events are delivered when the handler is dropped.
+///
+/// The events are delivered from a background thread.
+///
+/// ```no_run
+/// # use notify::{FileWritten, RecursiveMode, Result, Watcher};
+/// # use std::path::Path;
+/// # use std::time::Duration;
+/// # fn main() -> Result<()> {
+/// let handler = FileWritten::new(
+///     |event: Result<notify::Event>| println!("{event:?}"),
+///     Duration::from_secs(2),
+/// )?;
+/// let mut watcher = notify::recommended_watcher(handler)?;
+/// watcher.watch(Path::new("."), RecursiveMode::Recursive)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct FileWritten {
+    timed: Timed,
+    window: Duration,
+}
+
+impl FileWritten {
+    /// Create a new [`FileWritten`] delivering to `event_handler`, waiting at most `window`
+    /// for a created file to be closed.
+    pub fn new<F: EventHandler>(mut event_handler: F, window: Duration) -> Result<Self> {
+        let held = Creations {
+            held: HashMap::new(),
+            window,
+        };
+        let timed = Timed::start("notify-rs written loop", held, move |events: Vec<_>| {
+            event_handler.handle_events(events)
+        })?;
+        Ok(Self { timed, window })
+    }
+}
+
+impl fmt::Debug for FileWritten {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FileWritten")
+            .field("window", &self.window)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EventHandler for FileWritten {
+    fn handle_event(&mut self, event: Result<Event>) {
+        self.timed.send(event);
+    }
+}
+
+/// The events of a created file that wasn't closed yet.
+struct Held {
+    events: Vec<Event>,
+    due: Instant,
+}
+
+/// The created files that weren't closed yet.
+struct Creations {
+    held: HashMap<PathBuf, Held>,
+    window: Duration,
+}
+
+impl Hold for Creations {
+    fn push(&mut self, event: Result<Event>) -> Vec<Result<Event>> {
+        match event {
+            Ok(event) => hold(&mut self.held, event, self.window)
+                .into_iter()
+                .map(Ok)
+                .collect(),
+            Err(error) => vec![Err(error)],
+        }
+    }
+
+    fn due(&self) -> Option<Instant> {
+        self.held.values().map(|held| held.due).min()
+    }
+
+    fn take_due(&mut self, now: Instant) -> Vec<Result<Event>> {
+        let mut expired = Vec::new();
+        self.held.retain(|_, held| {
+            if held.due > now {
+                return true;
+            }
+            expired.push((held.due, std::mem::take(&mut held.events)));
+            false
+        });
+        expired.sort_by_key(|(due, _)| *due);
+        expired
+            .into_iter()
+            .flat_map(|(_, events)| events)
+            .map(Ok)
+            .collect()
+    }
+
+    fn take_all(&mut self) -> Vec<Result<Event>> {
+        let mut rest: Vec<_> = self.held.drain().map(|(_, held)| held).collect();
+        rest.sort_by_key(|held| held.due);
+        rest.into_iter()
+            .flat_map(|held| held.events)
+            .map(Ok)
+            .collect()
+    }
+}
+
+/// Holds back `event` if it is a creation of a file or belongs to a held back creation, returns
+/// the events to be forwarded.
+fn hold(held: &mut HashMap<PathBuf, Held>, event: Event, window: Duration) -> Vec<Event> {
+    if let Some(path) = event.paths.first().filter(|path| held.contains_key(*path)) {
+        match event.kind {
+            EventKind::Access(AccessKind::Close(AccessMode::Write)) => {
+                let mut creation = held.remove(path).unwrap();
+                creation.events.truncate(1);
+                return creation.events;
+            }
+            EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Any) | EventKind::Access(_)
+                if event.paths.len() == 1 =>
+            {
+                held.get_mut(path).unwrap().events.push(event);
+                return Vec::new();
+            }
+            _ => {
+                let mut events = held.remove(path).unwrap().events;
+                events.push(event);
+                return events;
+            }
+        }
+    }
+
+    if let (EventKind::Create(_), [path]) = (event.kind, event.paths.as_slice()) {
+        if !path.is_dir() {
+            held.insert(
+                path.clone(),
+                Held {
+                    events: vec![event],
+                    due: Instant::now() + window,
+                },
+            );
+            return Vec::new();
+        }
+    }
+    vec![event]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{CreateKind, DataChange, RemoveKind};
+    use std::path::Path;
+
+    fn event(kind: EventKind, path: &Path) -> Result<Event> {
+        Ok(Event::new(kind).add_path(path.to_path_buf()))
+    }
+
+    #[test]
+    fn creation_and_writes_are_merged_at_close() {
+        let dir = tempfile::tempdir().unwrap();
+        let written = dir.path().join("written");
+        let removed = dir.path().join("removed");
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut handler = FileWritten::new(tx, Duration::from_secs(5)).unwrap();
+
+        let data = EventKind::Modify(ModifyKind::Data(DataChange::Any));
+        handler.handle_event(event(EventKind::Create(CreateKind::File), &written));
+        handler.handle_event(event(EventKind::Create(CreateKind::File), &removed));
+        handler.handle_event(event(data, &written));
+        handler.handle_event(event(data, &written));
+        handler.handle_event(event(EventKind::Remove(RemoveKind::File), &removed));
+        handler.handle_event(event(
+            EventKind::Access(AccessKind::Close(AccessMode::Write)),
+            &written,
+        ));
+
+        let received: Vec<_> = std::iter::from_fn(|| rx.recv_timeout(Duration::from_secs(1)).ok())
+            .take(3)
+            .map(|event| event.unwrap())
+            .map(|event| (event.kind, event.paths))
+            .collect();
+        assert_eq!(
+            received,
+            [
+                (EventKind::Create(CreateKind::File), vec![removed.clone()]),
+                (EventKind::Remove(RemoveKind::File), vec![removed]),
+                (EventKind::Create(CreateKind::File), vec![written]),
+            ]
+        );
+
+        drop(handler);
+        assert!(rx.recv().is_err());
+    }
+
+    #[test]
+    fn unclosed_creations_are_released_after_the_window() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("open");
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut handler = FileWritten::new(tx, Duration::from_millis(100)).unwrap();
+
+        handler.handle_event(event(EventKind::Create(CreateKind::File), &path));
+        handler.handle_event(event(
+            EventKind::Modify(ModifyKind::Data(DataChange::Any)),
+            &path,
+        ));
+
+        let first = rx.recv_timeout(Duration::from_secs(1)).unwrap().unwrap();
+        assert_eq!(first.kind, EventKind::Create(CreateKind::File));
+        let second = rx.recv_timeout(Duration::from_secs(1)).unwrap().unwrap();
+        assert!(second.kind.is_modify());
+    }
+}